@@ -0,0 +1,479 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Inference of auto trait implementations
+//!
+//! An auto trait is a trait with an `impl Trait for ..` item. A struct or enum implements
+//! such a trait if it has a positive impl of the trait whose bounds are satisfied. If it
+//! has neither a positive nor a negative impl, it implements the trait if the types of
+//! all of its fields do. Fields whose type is a type parameter make the implementation
+//! conditional on a bound on that parameter unless the parameter is already bounded by
+//! the trait.
+//!
+//! Arrays and slices are treated like the type they contain and raw pointers are
+//! treated the same way unless there is a negative impl of the trait for raw pointers.
+//! References follow the impls of `Send` and `Sync` in the core library, see
+//! `Infer::reference`.
+//! Recursive types are assumed to implement the trait while their own implementation is
+//! being computed. Results that depend on this assumption for another type are not
+//! stored and are computed again when they are needed.
+
+use std::{mem};
+use std::rc::{Arc};
+use std::vec::{Vec};
+use std::iter::{IteratorExt};
+use tree::{self, Walker, ItemData, Crate, Item, Type, DefId, Generics, TyParamBound,
+           WherePredicate, PathParameters, ResolvedPath, StructField, VariantKind,
+           AutoImpl, Impl, Ref};
+
+/// Computes the auto trait implementations of all structs and enums.
+pub fn run(krate: &Crate) {
+    let mut traits = AutoTraits { traits: Vec::new(), no_pointers: Vec::new() };
+    traits.walk_crate(krate);
+    if traits.traits.len() == 0 {
+        return;
+    }
+    (Infer { ctx: &traits, stack: Vec::new(), cycle: !0 }).walk_crate(krate);
+}
+
+struct AutoTraits {
+    /// The traits with an `impl Trait for ..` item.
+    traits: Vec<Arc<ItemData>>,
+    /// The traits with a negative impl for raw pointers.
+    no_pointers: Vec<DefId>,
+}
+
+impl AutoTraits {
+    /// Returns the auto trait with the given name.
+    fn named(&self, name: &[u8]) -> Option<&Arc<ItemData>> {
+        let pos = self.traits.find(|t| match t.name {
+            Some(ref n) => &n[..] == name,
+            _ => false,
+        });
+        pos.map(|i| &self.traits[i])
+    }
+}
+
+impl Walker for AutoTraits {
+    fn walk_item_data(&mut self, val: &Arc<ItemData>) {
+        match val.inner {
+            Item::DefaultImpl(ref d) => {
+                if let Some(t) = resolved_item(&d.trait_) {
+                    if !self.traits.iter().any(|a| a.node == t.node) {
+                        self.traits.push(t);
+                    }
+                }
+            },
+            Item::Impl(ref i) if i.negative.unwrap_or(false) => {
+                if let (&Type::Pointer(_), &Some(ref t)) = (&i.for_, &i.trait_) {
+                    if let Some(t) = resolved_item(t) {
+                        self.no_pointers.push(t.node);
+                    }
+                }
+            },
+            _ => { },
+        }
+        tree::walk_item_data(self, val);
+    }
+}
+
+/// The implementation of an auto trait collected from the fields of a type.
+struct State {
+    negative: bool,
+    unknown: bool,
+    /// The type parameters that have to implement the trait.
+    bounds: Vec<Vec<u8>>,
+}
+
+impl State {
+    fn into_impl(self) -> AutoImpl {
+        if self.negative {
+            AutoImpl::Negative
+        } else if self.unknown {
+            AutoImpl::Unknown
+        } else {
+            AutoImpl::Bounded(self.bounds)
+        }
+    }
+}
+
+struct Infer<'a> {
+    ctx: &'a AutoTraits,
+    /// The (type, trait) pairs whose implementation is currently being computed.
+    stack: Vec<(DefId, DefId)>,
+    /// The lowest position in `stack` whose implementation was assumed by the current
+    /// computation, or `!0`.
+    cycle: usize,
+}
+
+impl<'a> Walker for Infer<'a> {
+    fn walk_item_data(&mut self, val: &Arc<ItemData>) {
+        match val.inner {
+            Item::Struct(_) | Item::Enum(_) => {
+                let ctx = self.ctx;
+                for trait_ in &ctx.traits {
+                    self.ensure(val, trait_);
+                }
+            },
+            _ => { },
+        }
+        tree::walk_item_data(self, val);
+    }
+}
+
+impl<'a> Infer<'a> {
+    /// Computes the implementation of an auto trait for an item if necessary.
+    ///
+    /// [return_value]
+    /// Returns `None` if the implementation is currently being computed.
+    fn ensure(&mut self, item: &ItemData, trait_: &Arc<ItemData>) -> Option<AutoImpl> {
+        for &(ref t, ref auto) in &*item.auto_traits.borrow() {
+            if t.node == trait_.node {
+                return Some(copy(auto));
+            }
+        }
+        if let Some(pos) = self.stack.find(|&(i, t)| i == item.node && t == trait_.node) {
+            if pos < self.cycle {
+                self.cycle = pos;
+            }
+            return None;
+        }
+
+        let depth = self.stack.len();
+        let outer = mem::replace(&mut self.cycle, !0);
+        self.stack.push((item.node, trait_.node));
+        let auto = self.infer(item, trait_);
+        self.stack.pop();
+        let cycle = mem::replace(&mut self.cycle, outer);
+
+        // The result assumes that a type further up the stack implements the trait.
+        if cycle < depth {
+            if cycle < self.cycle {
+                self.cycle = cycle;
+            }
+            return Some(auto);
+        }
+
+        item.auto_traits.borrow_mut().push((trait_.add_ref(), copy(&auto)));
+        Some(auto)
+    }
+
+    fn infer(&mut self, item: &ItemData, trait_: &Arc<ItemData>) -> AutoImpl {
+        for impl_item in &*item.impls.borrow() {
+            if let Item::Impl(ref i) = impl_item.inner {
+                let implements = match i.trait_ {
+                    Some(ref t) => match resolved_item(t) {
+                        Some(t) => t.node == trait_.node,
+                        _ => false,
+                    },
+                    _ => false,
+                };
+                if implements {
+                    return match i.negative.unwrap_or(false) {
+                        true => AutoImpl::Negative,
+                        false => positive_impl(item, i, trait_),
+                    };
+                }
+            }
+        }
+
+        let mut state = State { negative: false, unknown: false, bounds: Vec::new() };
+        match item.inner {
+            Item::Struct(ref s) => {
+                state.unknown |= s.private_fields;
+                self.fields(&mut state, &s.fields, trait_, &s.generics);
+            },
+            Item::Enum(ref e) => {
+                for variant in &e.variants {
+                    let variant = match variant.inner {
+                        Item::Variant(ref v) => v,
+                        _ => continue,
+                    };
+                    match variant.kind {
+                        VariantKind::CLike => { },
+                        VariantKind::Tuple(ref types) => {
+                            for ty in types {
+                                self.ty(&mut state, ty, trait_, &e.generics);
+                            }
+                        },
+                        VariantKind::Struct(ref s) => {
+                            state.unknown |= s.private_fields;
+                            self.fields(&mut state, &s.fields, trait_, &e.generics);
+                        },
+                    }
+                }
+            },
+            _ => state.unknown = true,
+        }
+        state.into_impl()
+    }
+
+    fn fields(&mut self, state: &mut State, fields: &[Arc<ItemData>],
+              trait_: &Arc<ItemData>, generics: &Generics) {
+        for field in fields {
+            match field.inner {
+                Item::StructField(StructField::Typed(ref ty)) => {
+                    self.ty(state, ty, trait_, generics);
+                },
+                _ => state.unknown = true,
+            }
+        }
+    }
+
+    /// Adds the requirements for a type to implement an auto trait.
+    ///
+    /// [argument, generics]
+    /// The generics of the item that contains the type.
+    fn ty(&mut self, state: &mut State, ty: &Type, trait_: &Arc<ItemData>,
+          generics: &Generics) {
+        match *ty {
+            Type::Generic(ref g) => param(state, &g.name, trait_, generics),
+            Type::ResolvedPath(ref r) if r.is_generic => {
+                match r.path.segments.last() {
+                    Some(s) => param(state, &s.name, trait_, generics),
+                    _ => state.unknown = true,
+                }
+            },
+            Type::ResolvedPath(ref r) => self.path(state, r, trait_, generics),
+            Type::Primitive(_) | Type::Bottom | Type::BareFunction(_) => { },
+            Type::Tuple(ref t) => {
+                for ty in &t.fields {
+                    self.ty(state, ty, trait_, generics);
+                }
+            },
+            Type::Slice(ref s) => self.ty(state, &s.ty, trait_, generics),
+            Type::Array(ref a) => self.ty(state, &a.ty, trait_, generics),
+            Type::Ref(ref r) => self.reference(state, r, trait_, generics),
+            Type::Pointer(ref p) => {
+                if self.ctx.no_pointers.iter().any(|&t| t == trait_.node) {
+                    state.negative = true;
+                } else {
+                    self.ty(state, &p.ty, trait_, generics);
+                }
+            },
+            Type::UfcsPath(_) | Type::Infer | Type::HkltBound(_) => state.unknown = true,
+        }
+    }
+
+    /// Adds the requirements for a reference to implement an auto trait.
+    ///
+    /// = Remarks
+    ///
+    /// `&T` and `&mut T` are `Sync` if `T` is `Sync`, and `&mut T` is `Send` if `T` is
+    /// `Send`. `&T` is only `Send` if `T` is `Sync`. The implementations of other auto
+    /// traits for references are unknown.
+    fn reference(&mut self, state: &mut State, r: &Ref, trait_: &Arc<ItemData>,
+                 generics: &Generics) {
+        let ctx = self.ctx;
+        match trait_.name.as_ref().map(|n| &n[..]) {
+            Some(b"Sync") => self.ty(state, &r.ty, trait_, generics),
+            Some(b"Send") if r.mutable => self.ty(state, &r.ty, trait_, generics),
+            Some(b"Send") => match ctx.named(b"Sync") {
+                Some(sync) => {
+                    let mut inner = State {
+                        negative: false,
+                        unknown: false,
+                        bounds: Vec::new(),
+                    };
+                    self.ty(&mut inner, &r.ty, sync, generics);
+                    // Bounds on Sync cannot be expressed in the implementation of Send.
+                    state.negative |= inner.negative;
+                    state.unknown |= inner.unknown || inner.bounds.len() > 0;
+                },
+                _ => state.unknown = true,
+            },
+            _ => state.unknown = true,
+        }
+    }
+
+    fn path(&mut self, state: &mut State, path: &ResolvedPath, trait_: &Arc<ItemData>,
+            generics: &Generics) {
+        let item = match *path.item.borrow() {
+            Some(ref i) => i.add_ref(),
+            _ => {
+                state.unknown = true;
+                return;
+            },
+        };
+
+        match item.inner {
+            Item::Struct(_) | Item::Enum(_) => { },
+            Item::Primitive(_) => return,
+            Item::Typedef(ref t) if t.generics.type_params.len() == 0 => {
+                self.ty(state, &t.type_, trait_, generics);
+                return;
+            },
+            _ => {
+                state.unknown = true;
+                return;
+            },
+        }
+
+        let auto = match self.ensure(&item, trait_) {
+            Some(auto) => auto,
+            _ => return,
+        };
+
+        match auto {
+            AutoImpl::Explicit => { },
+            AutoImpl::Negative => state.negative = true,
+            AutoImpl::Unknown => state.unknown = true,
+            AutoImpl::Bounded(ref params) => {
+                for param in params {
+                    if let Some(arg) = type_arg(&item, path, param) {
+                        self.ty(state, arg, trait_, generics);
+                    }
+                }
+            },
+        }
+    }
+}
+
+/// Returns the implementation of an auto trait provided by a positive impl.
+///
+/// = Remarks
+///
+/// Type parameters of the impl that are bounded by the trait make the implementation
+/// conditional on the type parameters of the type they are passed to. If such a
+/// parameter is not directly an argument of the type, the implementation is unknown.
+fn positive_impl(item: &ItemData, impl_: &Impl, trait_: &ItemData) -> AutoImpl {
+    let generics = match item.inner {
+        Item::Struct(ref s) => &s.generics,
+        Item::Enum(ref e) => &e.generics,
+        _ => return AutoImpl::Explicit,
+    };
+    let args: &[Type] = match impl_.for_ {
+        Type::ResolvedPath(ref r) => match r.path.segments.last() {
+            Some(s) => match s.params {
+                PathParameters::AngleBracketed(ref a) => &a.ty_params[..],
+                _ => &[],
+            },
+            _ => &[],
+        },
+        _ => &[],
+    };
+
+    let mut bounds = Vec::new();
+    for p in &impl_.generics.type_params {
+        if !bounded(&p.name, trait_, &impl_.generics) {
+            continue;
+        }
+        let pos = match args.find(|a| generic_name(a) == Some(&p.name[..])) {
+            Some(pos) if pos < generics.type_params.len() => pos,
+            _ => return AutoImpl::Unknown,
+        };
+        match generics.type_params[pos].name.try_to() {
+            Ok(name) => bounds.push(name),
+            _ => return AutoImpl::Unknown,
+        }
+    }
+    match bounds.len() {
+        0 => AutoImpl::Explicit,
+        _ => AutoImpl::Bounded(bounds),
+    }
+}
+
+/// Copies a stored implementation.
+fn copy(auto: &AutoImpl) -> AutoImpl {
+    match *auto {
+        AutoImpl::Explicit => AutoImpl::Explicit,
+        AutoImpl::Negative => AutoImpl::Negative,
+        AutoImpl::Unknown => AutoImpl::Unknown,
+        AutoImpl::Bounded(ref params) => {
+            let mut vec = Vec::new();
+            for param in params {
+                match param.try_to() {
+                    Ok(param) => vec.push(param),
+                    _ => return AutoImpl::Unknown,
+                }
+            }
+            AutoImpl::Bounded(vec)
+        },
+    }
+}
+
+/// Returns the name of a type parameter.
+fn generic_name(ty: &Type) -> Option<&[u8]> {
+    match *ty {
+        Type::Generic(ref g) => Some(&g.name[..]),
+        Type::ResolvedPath(ref r) if r.is_generic => match r.path.segments.last() {
+            Some(s) => Some(&s.name[..]),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Returns whether a type parameter is bounded by a trait.
+fn bounded(name: &[u8], trait_: &ItemData, generics: &Generics) -> bool {
+    for p in &generics.type_params {
+        if &p.name[..] == name && has_bound(&p.bounds, trait_) {
+            return true;
+        }
+    }
+    for pred in &generics.where_predicates {
+        if let WherePredicate::Bound(ref b) = *pred {
+            if generic_name(&b.ty) == Some(name) && has_bound(&b.bounds, trait_) {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+/// Adds a bound on a type parameter unless the parameter is already bounded by the trait.
+fn param(state: &mut State, name: &[u8], trait_: &ItemData, generics: &Generics) {
+    if bounded(name, trait_, generics) {
+        return;
+    }
+    if state.bounds.iter().any(|b| &b[..] == name) {
+        return;
+    }
+    match name.try_to() {
+        Ok(name) => state.bounds.push(name),
+        _ => state.unknown = true,
+    }
+}
+
+fn has_bound(bounds: &[TyParamBound], trait_: &ItemData) -> bool {
+    bounds.iter().any(|b| match *b {
+        TyParamBound::Trait(ref t) => match resolved_item(&t.trait_.trait_) {
+            Some(i) => i.node == trait_.node,
+            _ => false,
+        },
+        _ => false,
+    })
+}
+
+/// Returns the argument of a path that corresponds to a type parameter of a struct or
+/// enum.
+fn type_arg<'a>(item: &ItemData, path: &'a ResolvedPath,
+                param: &[u8]) -> Option<&'a Type> {
+    let generics = match item.inner {
+        Item::Struct(ref s) => &s.generics,
+        Item::Enum(ref e) => &e.generics,
+        _ => return None,
+    };
+    let pos = match generics.type_params.find(|p| &p.name[..] == param) {
+        Some(pos) => pos,
+        _ => return None,
+    };
+    let segment = match path.path.segments.last() {
+        Some(s) => s,
+        _ => return None,
+    };
+    match segment.params {
+        PathParameters::AngleBracketed(ref a) if pos < a.ty_params.len() => {
+            Some(&a.ty_params[pos])
+        },
+        _ => None,
+    }
+}
+
+fn resolved_item(ty: &Type) -> Option<Arc<ItemData>> {
+    match *ty {
+        Type::ResolvedPath(ref r) => r.item.borrow().as_ref().map(|i| i.add_ref()),
+        _ => None,
+    }
+}
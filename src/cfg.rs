@@ -0,0 +1,154 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! `#[cfg]` expressions
+//!
+//! The attributes are already split into words, lists, and name-value pairs by the
+//! parser so we only have to map them onto a small boolean AST.
+
+use std::vec::{Vec};
+use std::bx::{Box};
+use std::string::{ByteStr};
+
+use tree::{Attribute};
+
+pub enum Cfg {
+    /// `#[cfg(unix)]`
+    Name(Vec<u8>),
+    /// `#[cfg(target_arch = "x86_64")]`
+    NameValue(Vec<u8>, Vec<u8>),
+    Not(Box<Cfg>),
+    All(Vec<Cfg>),
+    Any(Vec<Cfg>),
+}
+
+/// Collects all `#[cfg]` attributes of an item into one expression.
+///
+/// = Remarks
+///
+/// Malformed attributes and attributes with unknown predicates are ignored with a
+/// warning.
+pub fn from_attributes(attrs: &[Attribute]) -> Result<Option<Cfg>> {
+    let mut all = Vec::new();
+    for attr in attrs {
+        if let Attribute::List(ref name, ref args) = *attr {
+            if name.as_str() == "cfg" {
+                if args.len() != 1 {
+                    warning!("Ignoring cfg attribute with {} arguments", args.len());
+                    continue;
+                }
+                if let Some(cfg) = try!(cfg(&args[0])) {
+                    try!(all.reserve(1));
+                    all.push(cfg);
+                }
+            }
+        }
+    }
+    match all.len() {
+        0 => Ok(None),
+        1 => Ok(all.pop()),
+        _ => Ok(Some(Cfg::All(all))),
+    }
+}
+
+/// Returns `None` if the expression is malformed or contains an unknown predicate.
+fn cfg(attr: &Attribute) -> Result<Option<Cfg>> {
+    match *attr {
+        Attribute::Word(ref name) => Ok(Some(Cfg::Name(try!(name.try_to())))),
+        Attribute::NameValue(ref name, ref val) => {
+            Ok(Some(Cfg::NameValue(try!(name.try_to()), try!(val.try_to()))))
+        },
+        Attribute::List(ref name, ref args) => {
+            let mut vec = try!(Vec::with_capacity(args.len()));
+            for arg in args {
+                match try!(cfg(arg)) {
+                    Some(c) => vec.push(c),
+                    _ => return Ok(None),
+                }
+            }
+            let bytes: &[u8] = name.as_ref();
+            match bytes {
+                b"all" => Ok(Some(Cfg::All(vec))),
+                b"any" => Ok(Some(Cfg::Any(vec))),
+                b"not" => {
+                    if vec.len() != 1 {
+                        warning!("Ignoring cfg with not() with {} arguments", vec.len());
+                        return Ok(None);
+                    }
+                    Ok(Some(Cfg::Not(try!(Box::new()).set(vec.pop().unwrap()))))
+                },
+                _ => {
+                    let name: &ByteStr = name.as_ref();
+                    warning!("Ignoring cfg with unknown predicate {:?}", name);
+                    Ok(None)
+                },
+            }
+        },
+    }
+}
+
+impl Cfg {
+    /// Appends a human readable description of the expression, e.g., `x86_64 or
+    /// aarch64`.
+    pub fn describe(&self, dst: &mut Vec<u8>) -> Result {
+        self.describe_(dst, false)
+    }
+
+    fn describe_(&self, dst: &mut Vec<u8>, nested: bool) -> Result {
+        match *self {
+            Cfg::Name(ref name) => try!(dst.push_all(name)),
+            Cfg::NameValue(ref name, ref val) => {
+                let bytes: &[u8] = name.as_ref();
+                match bytes {
+                    b"target_arch" | b"target_os" | b"target_env" | b"target_family" => {
+                        try!(dst.push_all(val));
+                    },
+                    b"target_pointer_width" => {
+                        try!(dst.push_all(val));
+                        try!(dst.push_all(b"-bit targets"));
+                    },
+                    b"target_endian" => {
+                        try!(dst.push_all(val));
+                        try!(dst.push_all(b"-endian targets"));
+                    },
+                    b"feature" => {
+                        try!(dst.push_all(b"crate feature "));
+                        try!(dst.push_all(val));
+                    },
+                    _ => {
+                        try!(dst.push_all(name));
+                        try!(dst.push_all(b" = "));
+                        try!(dst.push_all(val));
+                    },
+                }
+            },
+            Cfg::Not(ref c) => {
+                try!(dst.push_all(b"not "));
+                try!(c.describe_(dst, true));
+            },
+            Cfg::All(ref cs) => try!(describe_list(dst, cs, b" and ", nested)),
+            Cfg::Any(ref cs) => try!(describe_list(dst, cs, b" or ", nested)),
+        }
+        Ok(())
+    }
+}
+
+fn describe_list(dst: &mut Vec<u8>, cs: &[Cfg], sep: &[u8], nested: bool) -> Result {
+    let parens = nested && cs.len() > 1;
+    if parens {
+        try!(dst.push_all(b"("));
+    }
+    let mut first = true;
+    for c in cs {
+        if !first {
+            try!(dst.push_all(sep));
+        }
+        first = false;
+        try!(c.describe_(dst, true));
+    }
+    if parens {
+        try!(dst.push_all(b")"));
+    }
+    Ok(())
+}
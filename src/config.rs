@@ -0,0 +1,207 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Project configuration
+//!
+//! The configuration is read from `lrs_doc.conf` if it exists. Each line is one of
+//!
+//! * `key = value`, a setting, see `Config::set`,
+//! * `:name: value`, a variable that is available in all doc comments,
+//! * empty, or a comment starting with `#`.
+//!
+//! Arguments of the form `key=value` on the command line override the file.
+
+use std::io::{Read};
+use std::file::{self, File};
+use std::file::mode::{MODE_DIRECTORY};
+use std::vec::{Vec};
+use std::string::{ByteStr};
+use std::util::{memchr};
+
+use html::{Sections};
+use markup::{self, Env, Syntax};
+
+/// The default configuration file.
+pub const FILE: &'static str = "lrs_doc.conf";
+
+/// The default url of man pages.
+pub const MAN_URL: &'static str =
+    "http://man7.org/linux/man-pages/man{section}/{name}.{section}.html";
+
+pub struct Config {
+    /// The rustdoc Json file.
+    pub input: Vec<u8>,
+    /// The directory the output is written to.
+    pub output: Vec<u8>,
+    /// The first component of all paths.
+    pub crate_name: Vec<u8>,
+    /// The url of `link:man:name(section)` links. `{name}` and `{section}` are replaced.
+    pub man_url: Vec<u8>,
+    pub sections: Sections,
+    /// The syntax of doc comments that don't select one.
+    pub syntax: Syntax,
+    /// The directory that included files are relative to.
+    pub doc_root: Option<Vec<u8>>,
+    /// Variables that are available in all doc comments.
+    pub vars: Vec<(Vec<u8>, Vec<u8>)>,
+    /// The width at which syntax blocks are wrapped.
+    pub width: usize,
+}
+
+impl Config {
+    /// Creates the default configuration.
+    pub fn new() -> Result<Config> {
+        Ok(Config {
+            input: try!(b"doc.json".try_to()),
+            output: try!(b"doc".try_to()),
+            crate_name: try!(b"lrs".try_to()),
+            man_url: try!(MAN_URL.as_bytes().try_to()),
+            sections: try!(Sections::new()),
+            syntax: Syntax::Lrs,
+            doc_root: None,
+            vars: Vec::new(),
+            width: 80,
+        })
+    }
+
+    /// Reads a configuration file.
+    ///
+    /// [argument, required]
+    /// Whether it's an error if the file does not exist.
+    pub fn load(&mut self, path: &[u8], required: bool) -> Result {
+        let path: &ByteStr = path.as_ref();
+        let mut file = match File::open_read(path) {
+            Ok(f) => f,
+            Err(e) => {
+                if required {
+                    error!("Could not open {:?} ({:?})", path, e);
+                }
+                return Ok(());
+            },
+        };
+        let mut content = Vec::new();
+        try!(file.read_to_eof(&mut content));
+
+        for (no, line) in content.split(|&c| c == b'\n').enumerate() {
+            let line = markup::trim(line);
+            if line.len() == 0 || line[0] == b'#' {
+                continue;
+            }
+            if let Some(end) = markup::var_def_line(line) {
+                try!(self.vars.reserve(1));
+                let val = markup::trim(&line[end+2..]);
+                self.vars.push((try!(line[1..end].try_to()), try!(val.try_to())));
+                continue;
+            }
+            let pos = match memchr(line, b'=') {
+                Some(p) => p,
+                _ => error!("{:?}:{}: Expected key = value", path, no + 1),
+            };
+            let key: &ByteStr = markup::trim(&line[..pos]).as_ref();
+            if !try!(self.set(key.as_ref(), markup::trim(&line[pos+1..]))) {
+                error!("{:?}:{}: Unknown setting {:?}", path, no + 1, key);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Changes a setting. Returns `false` if the key is unknown.
+    ///
+    /// = Remarks
+    ///
+    /// The keys are
+    ///
+    /// * `input`, `output`, `crate`, `man_url`, `doc_root`: See the fields of the same
+    ///   name.
+    /// * `sections`, `informative`: Comma separated lists of section names, see
+    ///   `Sections`.
+    /// * `warn_sections`: `true` or `false`.
+    /// * `markup`: `lrs`, `markdown`, or `detect`.
+    /// * `width`: A number of columns, see `width`.
+    pub fn set(&mut self, key: &[u8], val: &[u8]) -> Result<bool> {
+        match key {
+            b"input" => self.input = try!(val.try_to()),
+            b"output" => self.output = try!(val.try_to()),
+            b"crate" => self.crate_name = try!(val.try_to()),
+            b"man_url" => self.man_url = try!(val.try_to()),
+            b"doc_root" => self.doc_root = Some(try!(val.try_to())),
+            b"sections" => self.sections.order = try!(list(val)),
+            b"informative" => self.sections.informative = try!(list(val)),
+            b"warn_sections" => {
+                self.sections.warn_unknown = match val {
+                    b"true" => true,
+                    b"false" => false,
+                    _ => {
+                        let val: &ByteStr = val.as_ref();
+                        error!("Expected true or false, found {:?}", val);
+                    },
+                };
+            },
+            b"markup" => {
+                self.syntax = match Syntax::from_name(val) {
+                    Some(s) => s,
+                    _ => {
+                        let val: &ByteStr = val.as_ref();
+                        error!("Unknown markup {:?}", val);
+                    },
+                };
+            },
+            b"width" => {
+                self.width = match markup::number(val) {
+                    Some(w) => w,
+                    _ => {
+                        let val: &ByteStr = val.as_ref();
+                        error!("Expected a number, found {:?}", val);
+                    },
+                };
+            },
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    /// Creates the output directory and returns the path of a file in it.
+    pub fn output_file(&self, name: &str) -> Result<Vec<u8>> {
+        let dir: &ByteStr = self.output.as_ref();
+        let _ = file::create_dir(dir, MODE_DIRECTORY);
+        let mut path: Vec<u8> = try!(self.output.try_to());
+        try!(path.push_all(b"/"));
+        try!(path.push_all(name.as_bytes()));
+        Ok(path)
+    }
+
+    /// Returns the settings of the doc comment parser.
+    pub fn env(&self) -> Result<Env> {
+        let root = match self.doc_root {
+            Some(ref r) => Some(try!(r.try_to())),
+            _ => None,
+        };
+        let mut vars = try!(Vec::with_capacity(self.vars.len()));
+        for &(ref name, ref val) in &self.vars {
+            vars.push((try!(name.try_to()), try!(val.try_to())));
+        }
+        Ok(Env {
+            syntax: self.syntax,
+            root: root,
+            vars: vars,
+            crate_name: try!(self.crate_name.try_to()),
+        })
+    }
+}
+
+/// Splits a comma separated list.
+fn list(mut val: &[u8]) -> Result<Vec<Vec<u8>>> {
+    let mut list = Vec::new();
+    while val.len() > 0 {
+        let end = memchr(val, b',').unwrap_or(val.len());
+        try!(list.reserve(1));
+        list.push(try!(markup::trim(&val[..end]).try_to()));
+        val = &val[end..];
+        if val.len() > 0 {
+            val = &val[1..];
+        }
+    }
+    Ok(list)
+}
@@ -0,0 +1,733 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Json output of the processed tree
+//!
+//! The output is written to `lrs_doc.json` in the output directory and has the following
+//! form:
+//!
+//! ----
+//! { "format": "lrs_doc", "version": VERSION, "crate": Item }
+//! ----
+//!
+//! Every item has the fields `id`, `name`, `path`, `parent`, `kind`, `public`, `docs`,
+//! `stability`, `cfg`, `targets`, `impls`, `inherited`, and `inner`. `id` is a string of
+//! the form `krate:index`. `parent`, `impls`, and `inherited` contain ids and resolved
+//! paths in types have a `target` field with the id of the item they point to.
+//! `inherited` is the trait item whose `docs` apply to an undocumented impl item. Every
+//! item appears exactly once in the tree, so consumers have to build their own id map to
+//! follow these references. `docs` contains the parsed markup, not the source of the
+//! comment.
+//!
+//! All objects that represent one of several alternatives have a `kind` field that
+//! determines the remaining fields. `cfg` is `null` or the `#[cfg]` expression of the
+//! item, an object of kind `name`, `name_value`, `not`, `all`, or `any`.
+//!
+//! `VERSION` is increased whenever a field is removed or changes its meaning. Adding
+//! fields is not a breaking change.
+
+use std::file::{File};
+use std::file::flags::{FILE_WRITE_ONLY, FILE_TRUNCATE, FILE_CREATE};
+use std::file::mode::{MODE_FILE};
+use std::rc::{Arc};
+use std::vec::{Vec};
+use std::string::{ByteStr};
+
+use json::{Value, Object};
+use tree::*;
+use markup::{self, Document, Part, BlockData, Block, ListEl, ListKind, Definition,
+             TableCol, Align, TextBlock, Text, TextAttr};
+use passes::{full_path};
+use config::{Config};
+use cfg::{Cfg};
+
+/// The version of the output schema.
+pub const VERSION: i64 = 2;
+
+pub fn create(krate: &Crate, config: &Config) -> Result {
+    let mut root = Vec::new();
+    try!(field(&mut root, "format", try!(string(b"lrs_doc"))));
+    try!(field(&mut root, "version", Value::Integer(VERSION)));
+    try!(field(&mut root, "crate", try!(item_data(&krate.item))));
+
+    let path = try!(config.output_file("lrs_doc.json"));
+    let path: &ByteStr = path.as_ref();
+    let flags = FILE_WRITE_ONLY | FILE_TRUNCATE | FILE_CREATE;
+    let mut file = try!(File::open(path, flags, MODE_FILE));
+    Value::Object(root).write(&mut file)
+}
+
+pub fn field(obj: &mut Object, name: &str, val: Value) -> Result {
+    try!(obj.reserve(1));
+    obj.push((try!(name.as_bytes().try_to()), val));
+    Ok(())
+}
+
+fn string(s: &[u8]) -> Result<Value> {
+    Ok(Value::String(try!(s.try_to())))
+}
+
+fn opt_string(s: &Option<Vec<u8>>) -> Result<Value> {
+    match *s {
+        Some(ref s) => string(s),
+        _ => Ok(Value::Null),
+    }
+}
+
+fn opt<T, F>(val: &Option<T>, f: F) -> Result<Value>
+    where F: FnOnce(&T) -> Result<Value>,
+{
+    match *val {
+        Some(ref v) => f(v),
+        _ => Ok(Value::Null),
+    }
+}
+
+fn array<T, F>(vals: &[T], mut f: F) -> Result<Value>
+    where F: FnMut(&T) -> Result<Value>,
+{
+    let mut array = try!(Vec::with_capacity(vals.len()));
+    for val in vals {
+        array.push(try!(f(val)));
+    }
+    Ok(Value::Array(array))
+}
+
+fn strings(vals: &[Vec<u8>]) -> Result<Value> {
+    array(vals, |s| string(s))
+}
+
+/// An object with only a `kind` field.
+fn kind(kind: &str) -> Result<Object> {
+    let mut obj = Vec::new();
+    try!(field(&mut obj, "kind", try!(string(kind.as_bytes()))));
+    Ok(obj)
+}
+
+fn id(node: DefId) -> Result<Value> {
+    let mut id = Vec::new();
+    try!(write!(&mut id, "{}:{}", node.krate, node.index));
+    Ok(Value::String(id))
+}
+
+fn item_data(item: &Arc<ItemData>) -> Result<Value> {
+    let (kind_, inner) = try!(item_inner(&item.inner));
+
+    let mut obj = Vec::new();
+    try!(field(&mut obj, "id", try!(id(item.node))));
+    try!(field(&mut obj, "name", try!(opt_string(&item.name))));
+    try!(field(&mut obj, "path", Value::String(try!(full_path(item)))));
+    let parent = match *item.parent.borrow() {
+        Some(ref p) => try!(id(p.node)),
+        _ => Value::Null,
+    };
+    try!(field(&mut obj, "parent", parent));
+    try!(field(&mut obj, "kind", try!(string(kind_.as_bytes()))));
+    try!(field(&mut obj, "public", Value::Boolean(item.public)));
+    try!(field(&mut obj, "docs", try!(document(&item.docs))));
+    try!(field(&mut obj, "stability", try!(stability(&item.stability))));
+    try!(field(&mut obj, "cfg", try!(opt(&item.cfg, cfg))));
+    try!(field(&mut obj, "targets", try!(strings(&item.targets))));
+    try!(field(&mut obj, "impls", try!(array(&item.impls.borrow(), |i| id(i.node)))));
+    let inherited = match *item.inherited.borrow() {
+        Some(ref i) => try!(id(i.node)),
+        _ => Value::Null,
+    };
+    try!(field(&mut obj, "inherited", inherited));
+    try!(field(&mut obj, "inner", Value::Object(inner)));
+    Ok(Value::Object(obj))
+}
+
+fn item_datas(items: &[Arc<ItemData>]) -> Result<Value> {
+    array(items, item_data)
+}
+
+fn cfg(expr: &Cfg) -> Result<Value> {
+    let obj = match *expr {
+        Cfg::Name(ref name) => {
+            let mut obj = try!(kind("name"));
+            try!(field(&mut obj, "name", try!(string(name))));
+            obj
+        },
+        Cfg::NameValue(ref name, ref val) => {
+            let mut obj = try!(kind("name_value"));
+            try!(field(&mut obj, "name", try!(string(name))));
+            try!(field(&mut obj, "value", try!(string(val))));
+            obj
+        },
+        Cfg::Not(ref c) => {
+            let mut obj = try!(kind("not"));
+            try!(field(&mut obj, "cfg", try!(cfg(c))));
+            obj
+        },
+        Cfg::All(ref cs) => {
+            let mut obj = try!(kind("all"));
+            try!(field(&mut obj, "cfgs", try!(array(cs, cfg))));
+            obj
+        },
+        Cfg::Any(ref cs) => {
+            let mut obj = try!(kind("any"));
+            try!(field(&mut obj, "cfgs", try!(array(cs, cfg))));
+            obj
+        },
+    };
+    Ok(Value::Object(obj))
+}
+
+fn stability(stab: &Stability) -> Result<Value> {
+    let mut obj = Vec::new();
+    let level = match stab.level {
+        StabilityLevel::Unmarked => Value::Null,
+        StabilityLevel::Stable => try!(string(b"stable")),
+        StabilityLevel::Unstable => try!(string(b"unstable")),
+    };
+    try!(field(&mut obj, "level", level));
+    try!(field(&mut obj, "feature", try!(opt_string(&stab.feature))));
+    try!(field(&mut obj, "since", try!(opt_string(&stab.since))));
+    try!(field(&mut obj, "issue", try!(opt_string(&stab.issue))));
+    let deprecated = try!(opt(&stab.deprecated, |d| {
+        let mut obj = Vec::new();
+        try!(field(&mut obj, "since", try!(opt_string(&d.since))));
+        try!(field(&mut obj, "note", try!(opt_string(&d.note))));
+        Ok(Value::Object(obj))
+    }));
+    try!(field(&mut obj, "deprecated", deprecated));
+    Ok(Value::Object(obj))
+}
+
+/// Returns the kind of an item and the fields specific to this kind.
+fn item_inner(item: &Item) -> Result<(&'static str, Object)> {
+    let mut obj = Vec::new();
+    let kind = match *item {
+        Item::GlobImport(ref g) => {
+            try!(field(&mut obj, "path", try!(path_string(&g.path))));
+            try!(field(&mut obj, "target", try!(opt(&g.node, |n| id(*n)))));
+            "glob_import"
+        },
+        Item::Struct(ref s) => {
+            try!(field(&mut obj, "struct_type", try!(struct_type(&s.struct_type))));
+            try!(field(&mut obj, "generics", try!(generics(&s.generics))));
+            try!(field(&mut obj, "fields", try!(item_datas(&s.fields))));
+            try!(field(&mut obj, "private_fields", Value::Boolean(s.private_fields)));
+            "struct"
+        },
+        Item::Enum(ref e) => {
+            try!(field(&mut obj, "generics", try!(generics(&e.generics))));
+            try!(field(&mut obj, "variants", try!(item_datas(&e.variants))));
+            "enum"
+        },
+        Item::Func(ref f) => { try!(func(&mut obj, f)); "function" },
+        Item::ExternFunc(ref f) => { try!(func(&mut obj, f)); "extern_function" },
+        Item::Module(ref m) => {
+            try!(field(&mut obj, "items", try!(item_datas(&m.items))));
+            "module"
+        },
+        Item::Typedef(ref t) => {
+            try!(field(&mut obj, "type", try!(type_(&t.type_))));
+            try!(field(&mut obj, "generics", try!(generics(&t.generics))));
+            try!(field(&mut obj, "assoc", Value::Boolean(t.is_assoc)));
+            "typedef"
+        },
+        Item::Static(ref s) => { try!(static_(&mut obj, s)); "static" },
+        Item::ExternStatic(ref s) => { try!(static_(&mut obj, s)); "extern_static" },
+        Item::Constant(ref c) => {
+            try!(field(&mut obj, "type", try!(type_(&c.type_))));
+            try!(field(&mut obj, "expr", try!(string(&c.expr))));
+            "constant"
+        },
+        Item::Trait(ref t) => {
+            try!(field(&mut obj, "unsafe", Value::Boolean(t.unsaf)));
+            try!(field(&mut obj, "generics", try!(generics(&t.generics))));
+            try!(field(&mut obj, "bounds", try!(bounds(&t.bounds))));
+            try!(field(&mut obj, "items", try!(item_datas(&t.items))));
+            "trait"
+        },
+        Item::Impl(ref i) => {
+            try!(field(&mut obj, "unsafe", Value::Boolean(i.unsaf)));
+            try!(field(&mut obj, "generics", try!(generics(&i.generics))));
+            try!(field(&mut obj, "trait", try!(opt(&i.trait_, type_))));
+            try!(field(&mut obj, "for", try!(type_(&i.for_))));
+            try!(field(&mut obj, "items", try!(item_datas(&i.items))));
+            try!(field(&mut obj, "derived", Value::Boolean(i.derived)));
+            try!(field(&mut obj, "negative", Value::Boolean(i.negative.unwrap_or(false))));
+            "impl"
+        },
+        Item::MethodDecl(ref m) => { try!(method(&mut obj, m)); "required_method" },
+        Item::Method(ref m) => { try!(method(&mut obj, m)); "method" },
+        Item::StructField(ref f) => {
+            let ty = match *f {
+                StructField::Hidden => Value::Null,
+                StructField::Typed(ref t) => try!(type_(t)),
+            };
+            try!(field(&mut obj, "type", ty));
+            "struct_field"
+        },
+        Item::Variant(ref v) => {
+            let kind = match v.kind {
+                VariantKind::CLike => "c_like",
+                VariantKind::Tuple(ref types) => {
+                    try!(field(&mut obj, "types", try!(array(types, type_))));
+                    "tuple"
+                },
+                VariantKind::Struct(ref s) => {
+                    try!(field(&mut obj, "fields", try!(item_datas(&s.fields))));
+                    try!(field(&mut obj, "private_fields",
+                               Value::Boolean(s.private_fields)));
+                    "struct"
+                },
+            };
+            try!(field(&mut obj, "variant_kind", try!(string(kind.as_bytes()))));
+            "variant"
+        },
+        Item::Macro(ref m) => {
+            try!(field(&mut obj, "source", try!(string(&m.source))));
+            "macro"
+        },
+        Item::Primitive(p) => {
+            try!(field(&mut obj, "primitive", try!(primitive(p))));
+            "primitive"
+        },
+        Item::AssocType(ref a) => {
+            try!(field(&mut obj, "bounds", try!(bounds(&a.bounds))));
+            try!(field(&mut obj, "default", try!(opt(&a.default, type_))));
+            "assoc_type"
+        },
+        Item::DefaultImpl(ref d) => {
+            try!(field(&mut obj, "unsafe", Value::Boolean(d.unsaf)));
+            try!(field(&mut obj, "trait", try!(type_(&d.trait_))));
+            "default_impl"
+        },
+    };
+    Ok((kind, obj))
+}
+
+fn func(obj: &mut Object, f: &Func) -> Result {
+    try!(field(obj, "unsafe", Value::Boolean(f.unsaf)));
+    try!(field(obj, "abi", try!(abi(&f.abi))));
+    try!(field(obj, "generics", try!(generics(&f.generics))));
+    try!(field(obj, "decl", try!(fn_decl(&f.decl))));
+    Ok(())
+}
+
+fn method(obj: &mut Object, m: &Method) -> Result {
+    try!(field(obj, "unsafe", Value::Boolean(m.unsaf)));
+    try!(field(obj, "abi", try!(abi(&m.abi))));
+    try!(field(obj, "generics", try!(generics(&m.generics))));
+    try!(field(obj, "self", try!(self_ty(&m.self_))));
+    try!(field(obj, "decl", try!(fn_decl(&m.decl))));
+    Ok(())
+}
+
+fn static_(obj: &mut Object, s: &Static) -> Result {
+    try!(field(obj, "type", try!(type_(&s.type_))));
+    try!(field(obj, "mutable", Value::Boolean(s.mutable)));
+    try!(field(obj, "expr", try!(string(&s.expr))));
+    Ok(())
+}
+
+fn struct_type(t: &StructType) -> Result<Value> {
+    match *t {
+        StructType::Plain => string(b"plain"),
+        StructType::Tuple => string(b"tuple"),
+        StructType::Unit => string(b"unit"),
+    }
+}
+
+fn abi(abi: &Abi) -> Result<Value> {
+    match *abi {
+        Abi::Rust => string(b"Rust"),
+        Abi::C => string(b"C"),
+        Abi::System => string(b"system"),
+        Abi::RustIntrinsic => string(b"rust-intrinsic"),
+        Abi::RustCall => string(b"rust-call"),
+    }
+}
+
+fn self_ty(s: &SelfTy) -> Result<Value> {
+    let obj = match *s {
+        SelfTy::Static => try!(kind("static")),
+        SelfTy::Value => try!(kind("value")),
+        SelfTy::Borrowed(ref lt, mutable) => {
+            let mut obj = try!(kind("ref"));
+            try!(field(&mut obj, "lifetime", try!(opt_string(lt))));
+            try!(field(&mut obj, "mutable", Value::Boolean(mutable)));
+            obj
+        },
+        SelfTy::Explicit(ref t) => {
+            let mut obj = try!(kind("explicit"));
+            try!(field(&mut obj, "type", try!(type_(t))));
+            obj
+        },
+    };
+    Ok(Value::Object(obj))
+}
+
+fn fn_decl(decl: &FnDecl) -> Result<Value> {
+    let mut obj = Vec::new();
+    let inputs = try!(array(&decl.inputs, |a| {
+        let mut obj = Vec::new();
+        try!(field(&mut obj, "name", try!(string(&a.name))));
+        try!(field(&mut obj, "type", try!(type_(&a.type_))));
+        Ok(Value::Object(obj))
+    }));
+    try!(field(&mut obj, "inputs", inputs));
+    let (output, diverges) = match decl.output {
+        FuncRetTy::Return(ref t) => (try!(type_(t)), false),
+        FuncRetTy::Unit => (Value::Null, false),
+        FuncRetTy::NoReturn => (Value::Null, true),
+    };
+    try!(field(&mut obj, "output", output));
+    try!(field(&mut obj, "diverges", Value::Boolean(diverges)));
+    Ok(Value::Object(obj))
+}
+
+fn generics(g: &Generics) -> Result<Value> {
+    let mut obj = Vec::new();
+    try!(field(&mut obj, "lifetimes", try!(strings(&g.lifetimes))));
+    let params = try!(array(&g.type_params, |p| {
+        let mut obj = Vec::new();
+        try!(field(&mut obj, "name", try!(string(&p.name))));
+        try!(field(&mut obj, "bounds", try!(bounds(&p.bounds))));
+        try!(field(&mut obj, "default", try!(opt(&p.default, type_))));
+        Ok(Value::Object(obj))
+    }));
+    try!(field(&mut obj, "type_params", params));
+    try!(field(&mut obj, "where", try!(array(&g.where_predicates, where_predicate))));
+    Ok(Value::Object(obj))
+}
+
+fn where_predicate(p: &WherePredicate) -> Result<Value> {
+    let obj = match *p {
+        WherePredicate::Bound(ref b) => {
+            let mut obj = try!(kind("bound"));
+            try!(field(&mut obj, "type", try!(type_(&b.ty))));
+            try!(field(&mut obj, "bounds", try!(bounds(&b.bounds))));
+            obj
+        },
+        WherePredicate::Region(ref r) => {
+            let mut obj = try!(kind("region"));
+            try!(field(&mut obj, "lifetime", try!(string(&r.lt))));
+            try!(field(&mut obj, "bounds", try!(strings(&r.bounds))));
+            obj
+        },
+        WherePredicate::Eq(ref e) => {
+            let mut obj = try!(kind("eq"));
+            try!(field(&mut obj, "lhs", try!(type_(&e.lhs))));
+            try!(field(&mut obj, "rhs", try!(type_(&e.rhs))));
+            obj
+        },
+    };
+    Ok(Value::Object(obj))
+}
+
+fn bounds(bounds: &[TyParamBound]) -> Result<Value> {
+    array(bounds, |b| {
+        let obj = match *b {
+            TyParamBound::Lifetime(ref lt) => {
+                let mut obj = try!(kind("lifetime"));
+                try!(field(&mut obj, "name", try!(string(lt))));
+                obj
+            },
+            TyParamBound::Trait(ref t) => {
+                let mut obj = try!(kind("trait"));
+                try!(field(&mut obj, "trait", try!(type_(&t.trait_.trait_))));
+                try!(field(&mut obj, "lifetimes", try!(strings(&t.trait_.lifetimes))));
+                try!(field(&mut obj, "maybe", Value::Boolean(t.maybe)));
+                obj
+            },
+        };
+        Ok(Value::Object(obj))
+    })
+}
+
+/// The path as it was written, e.g., `std::io::Read`.
+fn path_string(path: &Path) -> Result<Value> {
+    let mut s = Vec::new();
+    for (i, seg) in path.segments.iter().enumerate() {
+        if i > 0 || path.global {
+            try!(s.push_all(b"::"));
+        }
+        try!(s.push_all(&seg.name));
+    }
+    Ok(Value::String(s))
+}
+
+fn primitive(p: Primitive) -> Result<Value> {
+    string(primitive_name(p))
+}
+
+/// Returns the name of a primitive type.
+pub fn primitive_name(p: Primitive) -> &'static [u8] {
+    match p {
+        Primitive::Isize      => b"isize",
+        Primitive::I8         => b"i8",
+        Primitive::I16        => b"i16",
+        Primitive::I32        => b"i32",
+        Primitive::I64        => b"i64",
+        Primitive::Usize      => b"usize",
+        Primitive::U8         => b"u8",
+        Primitive::U16        => b"u16",
+        Primitive::U32        => b"u32",
+        Primitive::U64        => b"u64",
+        Primitive::F32        => b"f32",
+        Primitive::F64        => b"f64",
+        Primitive::Char       => b"char",
+        Primitive::Bool       => b"bool",
+        Primitive::Str        => b"str",
+        Primitive::Slice      => b"slice",
+        Primitive::Array      => b"array",
+        Primitive::Tuple      => b"tuple",
+        Primitive::RawPointer => b"pointer",
+    }
+}
+
+fn type_(t: &Type) -> Result<Value> {
+    let obj = match *t {
+        Type::ResolvedPath(ref p) => {
+            let mut obj = try!(kind("path"));
+            try!(field(&mut obj, "path", try!(path_string(&p.path))));
+            let segments = try!(array(&p.path.segments, |seg| {
+                let mut obj = Vec::new();
+                try!(field(&mut obj, "name", try!(string(&seg.name))));
+                try!(field(&mut obj, "params", try!(path_params(&seg.params))));
+                Ok(Value::Object(obj))
+            }));
+            try!(field(&mut obj, "segments", segments));
+            let target = match *p.item.borrow() {
+                Some(ref i) => try!(id(i.node)),
+                _ => Value::Null,
+            };
+            try!(field(&mut obj, "target", target));
+            try!(field(&mut obj, "bounds", try!(opt(&p.params, |b| bounds(b)))));
+            try!(field(&mut obj, "generic", Value::Boolean(p.is_generic)));
+            obj
+        },
+        Type::Generic(ref g) => {
+            let mut obj = try!(kind("generic"));
+            try!(field(&mut obj, "name", try!(string(&g.name))));
+            obj
+        },
+        Type::Primitive(p) => {
+            let mut obj = try!(kind("primitive"));
+            try!(field(&mut obj, "name", try!(primitive(p))));
+            obj
+        },
+        Type::BareFunction(ref f) => {
+            let mut obj = try!(kind("function"));
+            try!(field(&mut obj, "unsafe", Value::Boolean(f.decl.unsaf)));
+            try!(field(&mut obj, "abi", try!(string(&f.decl.abi))));
+            try!(field(&mut obj, "generics", try!(generics(&f.decl.generics))));
+            try!(field(&mut obj, "decl", try!(fn_decl(&f.decl.decl))));
+            obj
+        },
+        Type::Tuple(ref t) => {
+            let mut obj = try!(kind("tuple"));
+            try!(field(&mut obj, "fields", try!(array(&t.fields, type_))));
+            obj
+        },
+        Type::Slice(ref s) => {
+            let mut obj = try!(kind("slice"));
+            try!(field(&mut obj, "type", try!(type_(&s.ty))));
+            obj
+        },
+        Type::Array(ref a) => {
+            let mut obj = try!(kind("array"));
+            try!(field(&mut obj, "type", try!(type_(&a.ty))));
+            try!(field(&mut obj, "len", try!(string(&a.initializer))));
+            obj
+        },
+        Type::Bottom => try!(kind("never")),
+        Type::Pointer(ref p) => {
+            let mut obj = try!(kind("pointer"));
+            try!(field(&mut obj, "mutable", Value::Boolean(p.mutable)));
+            try!(field(&mut obj, "type", try!(type_(&p.ty))));
+            obj
+        },
+        Type::Ref(ref r) => {
+            let mut obj = try!(kind("ref"));
+            try!(field(&mut obj, "lifetime", try!(opt_string(&r.lifetime))));
+            try!(field(&mut obj, "mutable", Value::Boolean(r.mutable)));
+            try!(field(&mut obj, "type", try!(type_(&r.ty))));
+            obj
+        },
+        Type::UfcsPath(ref u) => {
+            let mut obj = try!(kind("qualified_path"));
+            try!(field(&mut obj, "name", try!(string(&u.target))));
+            try!(field(&mut obj, "self", try!(type_(&u.self_ty))));
+            try!(field(&mut obj, "trait", try!(type_(&u.trait_))));
+            obj
+        },
+        Type::Infer => try!(kind("infer")),
+        Type::HkltBound(ref h) => {
+            let mut obj = try!(kind("bounds"));
+            try!(field(&mut obj, "bounds", try!(bounds(&h.bounds))));
+            obj
+        },
+    };
+    Ok(Value::Object(obj))
+}
+
+fn path_params(params: &PathParameters) -> Result<Value> {
+    let obj = match *params {
+        PathParameters::AngleBracketed(ref a) => {
+            let mut obj = try!(kind("angle_bracketed"));
+            try!(field(&mut obj, "lifetimes", try!(strings(&a.lifetimes))));
+            try!(field(&mut obj, "types", try!(array(&a.ty_params, type_))));
+            let bindings = try!(array(&a.bindings, |b| {
+                let mut obj = Vec::new();
+                try!(field(&mut obj, "name", try!(string(&b.name))));
+                try!(field(&mut obj, "type", try!(type_(&b.ty))));
+                Ok(Value::Object(obj))
+            }));
+            try!(field(&mut obj, "bindings", bindings));
+            obj
+        },
+        PathParameters::Parenthesized(ref p) => {
+            let mut obj = try!(kind("parenthesized"));
+            try!(field(&mut obj, "inputs", try!(array(&p.args, type_))));
+            try!(field(&mut obj, "output", try!(opt(&p.return_value, type_))));
+            obj
+        },
+    };
+    Ok(Value::Object(obj))
+}
+
+fn document(doc: &Document) -> Result<Value> {
+    let parts = try!(array(&doc.parts, |part| {
+        let obj = match *part {
+            Part::SectionHeader(level, ref text) => {
+                let mut obj = try!(kind("section"));
+                try!(field(&mut obj, "level", Value::Integer(level as i64)));
+                try!(field(&mut obj, "title", try!(text_block(text))));
+                obj
+            },
+            Part::Block(ref b) => return block_data(b),
+        };
+        Ok(Value::Object(obj))
+    }));
+    let mut obj = Vec::new();
+    try!(field(&mut obj, "parts", parts));
+    Ok(Value::Object(obj))
+}
+
+fn block_data(block: &BlockData) -> Result<Value> {
+    let mut obj = match block.inner {
+        Block::Grouped(ref blocks) => {
+            let mut obj = try!(kind("group"));
+            try!(field(&mut obj, "blocks", try!(array(blocks, block_data))));
+            obj
+        },
+        Block::Code(ref code) => {
+            let mut obj = try!(kind("code"));
+            try!(field(&mut obj, "code", try!(string(code))));
+            obj
+        },
+        Block::List(k, ref els) => {
+            let mut obj = try!(kind("list"));
+            try!(field(&mut obj, "ordered", Value::Boolean(k == ListKind::Ordered)));
+            try!(field(&mut obj, "items", try!(array(els, list_el))));
+            obj
+        },
+        Block::Definitions(ref defs) => {
+            let mut obj = try!(kind("definitions"));
+            let items = try!(array(defs, |d: &Definition| {
+                let mut obj = Vec::new();
+                try!(field(&mut obj, "term", try!(text_block(&d.term))));
+                try!(field(&mut obj, "desc", try!(list_el(&d.desc))));
+                Ok(Value::Object(obj))
+            }));
+            try!(field(&mut obj, "items", items));
+            obj
+        },
+        Block::Table(ref t) => {
+            let mut obj = try!(kind("table"));
+            try!(field(&mut obj, "header", Value::Boolean(t.header)));
+            let align = try!(array(&t.align, |a| {
+                let name = match *a {
+                    Align::Default => "default",
+                    Align::Left => "left",
+                    Align::Center => "center",
+                    Align::Right => "right",
+                };
+                string(name.as_bytes())
+            }));
+            try!(field(&mut obj, "align", align));
+            let rows = try!(array(&t.rows, |row| array(&row.cols, |cell| match cell.inner {
+                TableCol::Simple(ref t) => text_block(t),
+                TableCol::Complex(ref b) => block_data(b),
+            })));
+            try!(field(&mut obj, "rows", rows));
+            let spans = try!(array(&t.rows, |row| array(&row.cols, |cell| {
+                let mut obj = Vec::new();
+                try!(field(&mut obj, "cols", Value::Integer(cell.span.cols as i64)));
+                try!(field(&mut obj, "rows", Value::Integer(cell.span.rows as i64)));
+                Ok(Value::Object(obj))
+            })));
+            try!(field(&mut obj, "spans", spans));
+            obj
+        },
+        Block::Text(ref t) => {
+            let mut obj = try!(kind("text"));
+            try!(field(&mut obj, "text", try!(text_block(t))));
+            obj
+        },
+    };
+    let attributes = try!(array(&block.attributes, |a: &markup::Attribute| {
+        let mut obj = Vec::new();
+        try!(field(&mut obj, "name", try!(string(&a.name))));
+        try!(field(&mut obj, "args", try!(opt_string(&a.args))));
+        Ok(Value::Object(obj))
+    }));
+    try!(field(&mut obj, "attributes", attributes));
+    Ok(Value::Object(obj))
+}
+
+/// Elements with a nested block are objects of kind `item`.
+fn list_el(el: &ListEl) -> Result<Value> {
+    match *el {
+        ListEl::Simple(ref t) => text_block(t),
+        ListEl::Complex(ref b) => block_data(b),
+        ListEl::Nested(ref t, ref b) => {
+            let mut obj = try!(kind("item"));
+            try!(field(&mut obj, "text", try!(text_block(t))));
+            try!(field(&mut obj, "block", try!(block_data(b))));
+            Ok(Value::Object(obj))
+        },
+    }
+}
+
+/// Text blocks are objects of kind `raw`, `nested`, `link`, or `break` to distinguish
+/// them from blocks in list items and table columns.
+fn text_block(text: &TextBlock) -> Result<Value> {
+    let mut obj = match text.inner {
+        Text::Raw(ref s) => {
+            let mut obj = try!(kind("raw"));
+            try!(field(&mut obj, "text", try!(string(s))));
+            obj
+        },
+        Text::Nested(ref parts) => {
+            let mut obj = try!(kind("nested"));
+            try!(field(&mut obj, "parts", try!(array(parts, text_block))));
+            obj
+        },
+        Text::Link(ref target, ref label) => {
+            let mut obj = try!(kind("link"));
+            try!(field(&mut obj, "target", try!(string(target))));
+            try!(field(&mut obj, "label", try!(opt(label, |l| text_block(l)))));
+            obj
+        },
+        Text::LineBreak => try!(kind("break")),
+    };
+    let style = match text.attribute {
+        Some(TextAttr::Raw) => try!(string(b"code")),
+        Some(TextAttr::Bold) => try!(string(b"bold")),
+        Some(TextAttr::Italic) => try!(string(b"italic")),
+        Some(TextAttr::Strikethrough) => try!(string(b"strikethrough")),
+        Some(TextAttr::Superscript) => try!(string(b"superscript")),
+        Some(TextAttr::Subscript) => try!(string(b"subscript")),
+        None => Value::Null,
+    };
+    try!(field(&mut obj, "style", style));
+    Ok(Value::Object(obj))
+}
@@ -0,0 +1,1108 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Formatting of doc comments
+//!
+//! `print` writes a parsed document back as markup source in canonical form:
+//!
+//! * One blank line between blocks.
+//! * Text is wrapped at `WIDTH` columns. `\` continuations are joined.
+//! * Attributes, list elements, and table rows are written one per line.
+//! * Nested blocks are indented by two spaces.
+//!
+//! Variables have already been substituted by the parser and are not written. Doc
+//! comments that define or use variables are therefore not formatted.
+//!
+//! Documents parsed from the lrs markup language can always be printed. Documents
+//! converted from Markdown can contain constructs that have no source form, and
+//! `unsupported` returns the first of them:
+//!
+//! * Code blocks containing a `----` line.
+//! * Line breaks in section headers, definition terms, and SimpleRow columns.
+//! * Definition terms containing `::`.
+//! * Link targets that are empty or contain a space or `[`.
+//! * Text that is followed by another block in a list element, a table cell, or a
+//!   group, since a TextBlock only ends at a blank line.
+//! * Nested blocks of list elements that don't start with a list, blank lines in their
+//!   code blocks, and tables with several rows in them.
+//! * Adjacent lists of the same kind in a list element.
+
+use std::io::{Write, Read};
+use std::file::{File};
+use std::file::flags::{FILE_WRITE_ONLY, FILE_TRUNCATE, FILE_CREATE};
+use std::file::mode::{MODE_FILE};
+use std::rc::{Arc};
+use std::vec::{Vec};
+use std::string::{ByteStr};
+
+use tree::{self, Walker, ItemData, Crate, Item};
+use markup::{self, Document, Part, BlockData, Block, ListEl, ListKind, TableCell,
+             TableCol, Span, TextBlock, Text, TextAttr, Syntax, Env};
+use passes::{full_path};
+use parse::{doc_source};
+use config::{Config};
+
+/// The width of wrapped text, not including indentation.
+const WIDTH: usize = 80;
+
+/// Prints a document as markup source.
+pub fn print(doc: &Document) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    for (i, part) in doc.parts.iter().enumerate() {
+        if i > 0 {
+            try!(out.push_all(b"\n"));
+        }
+        match *part {
+            Part::SectionHeader(level, ref title) => {
+                for _ in 0..level {
+                    try!(out.push_all(b"="));
+                }
+                try!(out.push_all(b" "));
+                try!(text_block(&mut out, title, Ctx { table: false, strict: false }));
+                try!(out.push_all(b"\n"));
+            },
+            Part::Block(ref data) => try!(block(&mut out, data, false)),
+        }
+    }
+    Ok(out)
+}
+
+/// Writes a block.
+///
+/// [argument, nested]
+/// Whether the block is part of an indented list element. Such blocks cannot contain
+/// blank lines.
+fn block(out: &mut Vec<u8>, data: &BlockData, nested: bool) -> Result {
+    for attr in &data.attributes {
+        try!(out.push_all(b"["));
+        try!(out.push_all(&attr.name));
+        if let Some(ref args) = attr.args {
+            try!(out.push_all(b","));
+            try!(out.push_all(args));
+        }
+        try!(out.push_all(b"]\n"));
+    }
+
+    match data.inner {
+        Block::Grouped(ref blocks) => {
+            // The parser groups the blocks of a list element if there is not exactly one.
+            if nested && blocks.len() != 1 && data.attributes.len() == 0 {
+                for b in blocks {
+                    try!(block(out, b, true));
+                }
+                return Ok(());
+            }
+            try!(out.push_all(b"{\n"));
+            for (i, b) in blocks.iter().enumerate() {
+                if i > 0 && !nested {
+                    try!(out.push_all(b"\n"));
+                }
+                try!(block(out, b, nested));
+            }
+            // A TextBlock would contain the GroupEnd.
+            if !nested {
+                try!(out.push_all(b"\n"));
+            }
+            try!(out.push_all(b"}\n"));
+        },
+        Block::Code(ref code) => {
+            try!(out.push_all(b"----\n"));
+            if code.len() > 0 {
+                try!(out.push_all(code));
+                try!(out.push_all(b"\n"));
+            }
+            try!(out.push_all(b"----\n"));
+        },
+        Block::List(kind, ref els) => {
+            let (simple, complex): (&[u8], &[u8]) = match kind {
+                ListKind::Unordered => (b"* ", b"**\n"),
+                ListKind::Ordered => (b". ", b"..\n"),
+            };
+            for el in els {
+                match *el {
+                    ListEl::Complex(ref b) => {
+                        try!(out.push_all(complex));
+                        try!(block(out, b, nested));
+                    },
+                    _ => try!(list_el(out, simple, el)),
+                }
+            }
+        },
+        Block::Definitions(ref defs) => {
+            for def in defs {
+                let mut term = Vec::new();
+                let ctx = Ctx { table: false, strict: false };
+                try!(text_block(&mut term, &def.term, ctx));
+                try!(term.push_all(b"::"));
+                let empty = match def.desc {
+                    ListEl::Simple(ref t) => is_empty(t),
+                    _ => false,
+                };
+                if empty {
+                    try!(out.push_all(&term));
+                    try!(out.push_all(b"\n"));
+                } else {
+                    try!(term.push_all(b" "));
+                    try!(list_el(out, &term, &def.desc));
+                }
+            }
+        },
+        Block::Table(ref table) => {
+            try!(out.push_all(b"|===\n"));
+            for (i, row) in table.rows.iter().enumerate() {
+                if i > 0 {
+                    try!(out.push_all(b"\n"));
+                }
+                let mut in_line = false;
+                for cell in &row.cols {
+                    match cell.inner {
+                        TableCol::Simple(ref t) => {
+                            if cell.span != Span::single() {
+                                if in_line {
+                                    try!(out.push_all(b" "));
+                                }
+                                try!(cell_span(out, cell.span));
+                            }
+                            try!(out.push_all(b"|"));
+                            try!(text_block(out, t, Ctx { table: true, strict: false }));
+                            in_line = true;
+                        },
+                        TableCol::Complex(ref b) => {
+                            if in_line {
+                                try!(out.push_all(b"\n"));
+                                in_line = false;
+                            }
+                            try!(block(out, b, nested));
+                        },
+                    }
+                }
+                if in_line {
+                    try!(out.push_all(b"\n"));
+                }
+            }
+            // The same goes for the TableDelim after a TextBlock in the last row.
+            let last_text = table.rows.last().map(|r| ends_with_text(&r.cols));
+            if !nested && last_text.unwrap_or(false) {
+                try!(out.push_all(b"\n"));
+            }
+            try!(out.push_all(b"|===\n"));
+        },
+        Block::Text(ref t) => {
+            let mut text = Vec::new();
+            try!(text_block(&mut text, t, Ctx { table: false, strict: false }));
+            try!(wrap(out, &text, b"", b"", false));
+        },
+    }
+
+    Ok(())
+}
+
+/// CellSpan <- [0-9]+ '+' / [0-9]* '.' [0-9]+ '+'
+fn cell_span(out: &mut Vec<u8>, span: Span) -> Result {
+    if span.cols > 1 {
+        try!(write!(out, "{}", span.cols));
+    }
+    if span.rows > 1 {
+        try!(write!(out, ".{}", span.rows));
+    }
+    out.push_all(b"+")
+}
+
+/// Writes a simple or nested list element.
+///
+/// [argument, first]
+/// The marker or term that precedes the text of the element.
+fn list_el(out: &mut Vec<u8>, first: &[u8], el: &ListEl) -> Result {
+    let (t, nested) = match *el {
+        ListEl::Simple(ref t) => (t, None),
+        ListEl::Nested(ref t, ref b) => (t, Some(b)),
+        ListEl::Complex(_) => return Ok(()),
+    };
+
+    let mut text = Vec::new();
+    try!(text_block(&mut text, t, Ctx { table: false, strict: false }));
+    try!(wrap(out, &text, first, b"  ", true));
+
+    if let Some(b) = nested {
+        let mut inner = Vec::new();
+        try!(block(&mut inner, b, true));
+        for line in inner.split(|&c| c == b'\n') {
+            if line.len() > 0 {
+                try!(out.push_all(b"  "));
+                try!(out.push_all(line));
+                try!(out.push_all(b"\n"));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns whether the last column of a row is a TextBlock that is not part of a
+/// SimpleRow.
+fn ends_with_text(cols: &[TableCell]) -> bool {
+    match cols.last() {
+        Some(&TableCell { inner: TableCol::Complex(ref b), .. }) => is_text(b),
+        _ => false,
+    }
+}
+
+fn is_text(data: &BlockData) -> bool {
+    match data.inner {
+        Block::Text(_) => true,
+        _ => false,
+    }
+}
+
+fn is_empty(t: &TextBlock) -> bool {
+    match t.inner {
+        Text::Raw(ref s) => t.attribute.is_none() && s.len() == 0,
+        _ => false,
+    }
+}
+
+/// Writes text that can contain line breaks, breaking long lines at spaces.
+///
+/// [argument, first]
+/// The prefix of the first line.
+///
+/// [argument, rest]
+/// The prefix of all other lines.
+///
+/// [argument, list]
+/// Whether the text is part of a list element. Otherwise it's a TextBlock.
+fn wrap(out: &mut Vec<u8>, text: &[u8], first: &[u8], rest: &[u8], list: bool) -> Result {
+    let mut is_first = true;
+    for seg in text.split(|&c| c == b'\n') {
+        let mut start = 0;
+        loop {
+            let end = match line_end(seg, start, is_first && !list, list) {
+                Some(end) => end,
+                _ => seg.len(),
+            };
+            try!(out.push_all(if is_first { first } else { rest }));
+            try!(out.push_all(&seg[start..end]));
+            try!(out.push_all(b"\n"));
+            is_first = false;
+            if end == seg.len() {
+                break;
+            }
+            start = end + 1;
+        }
+    }
+    Ok(())
+}
+
+/// Returns the position of the space at which the line starting at `start` should be
+/// broken.
+///
+/// [argument, first]
+/// Whether this is the first line of a TextBlock.
+///
+/// [argument, list]
+/// Whether the next line is a continuation line of a list element.
+fn line_end(seg: &[u8], start: usize, first: bool, list: bool) -> Option<usize> {
+    if seg.len() - start <= WIDTH {
+        return None;
+    }
+    let mut best = None;
+    for p in start + 1..seg.len() - 1 {
+        if seg[p] != b' ' {
+            continue;
+        }
+        if p - start > WIDTH && best.is_some() {
+            break;
+        }
+        // Lines ending in `\` are joined with the next line and lines ending in ` +` are
+        // followed by a line break.
+        if seg[p - 1] == b'\\' || (p >= 2 && seg[p - 2] == b' ' && seg[p - 1] == b'+') {
+            continue;
+        }
+        if first && markup::starts_block(&seg[start..p]) {
+            continue;
+        }
+        if list && markup::starts_list_el(&seg[p + 1..]) {
+            continue;
+        }
+        best = Some(p);
+        if p - start > WIDTH {
+            break;
+        }
+    }
+    best
+}
+
+#[derive(Copy)]
+struct Ctx {
+    /// Whether the text is in a table column.
+    table: bool,
+    /// Whether the text is inside closed formatting.
+    strict: bool,
+}
+
+fn text_block(out: &mut Vec<u8>, t: &TextBlock, ctx: Ctx) -> Result {
+    let delim: &[u8] = match t.attribute {
+        None => return text(out, &t.inner, ctx),
+        Some(TextAttr::Raw) => {
+            try!(out.push_all(b"`"));
+            if let Text::Raw(ref s) = t.inner {
+                try!(escape(out, s, b'`'));
+            }
+            try!(out.push_all(b"`"));
+            return Ok(());
+        },
+        Some(TextAttr::Bold) => {
+            // The content is unescaped before it is parsed.
+            let mut inner = Vec::new();
+            try!(text(&mut inner, &t.inner, ctx));
+            try!(out.push_all(b"*"));
+            try!(escape(out, &inner, b'*'));
+            try!(out.push_all(b"*"));
+            return Ok(());
+        },
+        Some(TextAttr::Italic) => b"_",
+        Some(TextAttr::Strikethrough) => b"~~",
+        Some(TextAttr::Superscript) => b"^",
+        Some(TextAttr::Subscript) => b"~",
+    };
+    try!(out.push_all(delim));
+    try!(text(out, &t.inner, Ctx { table: ctx.table, strict: true }));
+    try!(out.push_all(delim));
+    Ok(())
+}
+
+/// Escapes `\` and `c`.
+fn escape(out: &mut Vec<u8>, s: &[u8], c: u8) -> Result {
+    for &b in s {
+        if b == b'\\' || b == c {
+            try!(out.push_all(b"\\"));
+        }
+        try!(out.reserve(1));
+        out.push(b);
+    }
+    Ok(())
+}
+
+fn text(out: &mut Vec<u8>, t: &Text, ctx: Ctx) -> Result {
+    match *t {
+        Text::Raw(ref s) => try!(raw(out, s, ctx)),
+        Text::Nested(ref blocks) => {
+            for b in blocks {
+                try!(text_block(out, b, ctx));
+            }
+        },
+        Text::Link(ref target, ref label) => {
+            try!(out.push_all(b"link:"));
+            try!(out.push_all(target));
+            if let Some(ref label) = *label {
+                // The label is unescaped before it is parsed.
+                let mut inner = Vec::new();
+                try!(text_block(&mut inner, label, ctx));
+                try!(out.push_all(b"["));
+                try!(escape(out, &inner, b']'));
+                try!(out.push_all(b"]"));
+            }
+        },
+        Text::LineBreak => try!(out.push_all(b" +\n")),
+    }
+    Ok(())
+}
+
+fn is_word(c: u8) -> bool {
+    match c {
+        b'a'...b'z' | b'A'...b'Z' | b'0'...b'9' => true,
+        _ => false,
+    }
+}
+
+/// Writes unformatted text and escapes everything that could start formatting.
+fn raw(out: &mut Vec<u8>, s: &[u8], ctx: Ctx) -> Result {
+    for (i, &c) in s.iter().enumerate() {
+        let escape = match c {
+            b'\\' | b'`' | b'*' | b'~' | b'^' => true,
+            b'_' => ctx.strict || i == 0 || !is_word(s[i - 1]),
+            b'|' => ctx.table,
+            b'{' => markup::var_ref(&s[i..]).is_some(),
+            b'l' => s[i..].starts_with(b"link:"),
+            _ => false,
+        };
+        if escape {
+            try!(out.push_all(b"\\"));
+        }
+        try!(out.reserve(1));
+        out.push(c);
+    }
+    Ok(())
+}
+
+/// Returns from the enclosing function if a construct is unsupported.
+macro_rules! check {
+    ($e:expr) => { if let Some(msg) = $e { return Some(msg); } };
+}
+
+/// Returns the first construct of a document that `print` cannot write.
+///
+/// = Remarks
+///
+/// See the module documentation for the list of such constructs. None of them is
+/// produced by parsing the lrs markup language, but Markdown can produce all of them.
+pub fn unsupported(doc: &Document) -> Option<&'static str> {
+    for part in &doc.parts {
+        match *part {
+            Part::SectionHeader(_, ref title) => check!(unsupported_text(title, true)),
+            Part::Block(ref data) => check!(unsupported_block(data, false)),
+        }
+    }
+    None
+}
+
+/// [argument, nested]
+/// Whether the block is part of an indented list element.
+fn unsupported_block(data: &BlockData, nested: bool) -> Option<&'static str> {
+    match data.inner {
+        Block::Grouped(ref blocks) => {
+            let braces = !nested || blocks.len() == 1 || data.attributes.len() > 0;
+            for (i, b) in blocks.iter().enumerate() {
+                check!(unsupported_block(b, nested));
+                if !nested {
+                    continue;
+                }
+                if is_text(b) && (braces || i + 1 < blocks.len()) {
+                    return Some("text followed by another block in a list element");
+                }
+                if !braces && i > 0 && same_list(&blocks[i - 1], b) {
+                    return Some("adjacent lists of the same kind in a list element");
+                }
+            }
+        },
+        Block::Code(ref code) => {
+            for line in code.split(|&c| c == b'\n') {
+                if line == &b"----"[..] {
+                    return Some("code block containing a ---- line");
+                }
+                if nested && line.len() == 0 {
+                    return Some("blank line in a code block in a list element");
+                }
+            }
+        },
+        Block::List(_, ref els) => {
+            for (i, el) in els.iter().enumerate() {
+                if let ListEl::Complex(ref b) = *el {
+                    if is_text(b) && i + 1 < els.len() {
+                        return Some("text in a block list element other than the last");
+                    }
+                }
+                check!(unsupported_list_el(el, nested));
+            }
+        },
+        Block::Definitions(ref defs) => {
+            for def in defs {
+                check!(unsupported_text(&def.term, true));
+                if contains_colons(&def.term) {
+                    return Some("definition term containing ::");
+                }
+                check!(unsupported_list_el(&def.desc, nested));
+            }
+        },
+        Block::Table(ref table) => {
+            if nested && table.rows.len() > 1 {
+                return Some("table with several rows in a list element");
+            }
+            for row in &table.rows {
+                for (i, cell) in row.cols.iter().enumerate() {
+                    match cell.inner {
+                        TableCol::Simple(ref t) => check!(unsupported_text(t, true)),
+                        TableCol::Complex(ref b) => {
+                            if is_text(b) && (nested || i + 1 < row.cols.len()) {
+                                return Some("text followed by another table cell");
+                            }
+                            check!(unsupported_block(b, nested));
+                        },
+                    }
+                }
+            }
+        },
+        Block::Text(ref t) => check!(unsupported_text(t, false)),
+    }
+    None
+}
+
+fn unsupported_list_el(el: &ListEl, nested: bool) -> Option<&'static str> {
+    match *el {
+        ListEl::Simple(ref t) => unsupported_text(t, false),
+        ListEl::Complex(ref b) => unsupported_block(b, nested),
+        ListEl::Nested(ref t, ref b) => {
+            check!(unsupported_text(t, false));
+            // Other lines are continuation lines of the text.
+            if !starts_with_list(b) {
+                return Some("list element text followed by a block that is not a list");
+            }
+            unsupported_block(b, true)
+        },
+    }
+}
+
+/// Returns whether the first line of a nested block starts a list element.
+fn starts_with_list(data: &BlockData) -> bool {
+    if data.attributes.len() > 0 {
+        return false;
+    }
+    match data.inner {
+        Block::List(..) | Block::Definitions(_) => true,
+        Block::Grouped(ref blocks) if blocks.len() > 1 => starts_with_list(&blocks[0]),
+        _ => false,
+    }
+}
+
+fn same_list(a: &BlockData, b: &BlockData) -> bool {
+    if b.attributes.len() > 0 {
+        return false;
+    }
+    match (&a.inner, &b.inner) {
+        (&Block::List(k1, _), &Block::List(k2, _)) => k1 == k2,
+        (&Block::Definitions(_), &Block::Definitions(_)) => true,
+        _ => false,
+    }
+}
+
+/// [argument, line]
+/// Whether the text has to fit on one line.
+fn unsupported_text(t: &TextBlock, line: bool) -> Option<&'static str> {
+    match t.inner {
+        Text::Raw(ref s) => {
+            if s.find(|&c| c == b'\n').is_some() {
+                return Some("newline in text");
+            }
+        },
+        Text::Nested(ref blocks) => {
+            for b in blocks {
+                check!(unsupported_text(b, line));
+            }
+        },
+        Text::Link(ref target, ref label) => {
+            if target.len() == 0 || target.find(|&c| c == b' ' || c == b'[').is_some() {
+                return Some("link target that is empty or contains a space or [");
+            }
+            if let Some(ref label) = *label {
+                check!(unsupported_text(label, line));
+            }
+        },
+        Text::LineBreak if line => return Some("line break in a single-line text"),
+        Text::LineBreak => { },
+    }
+    None
+}
+
+fn contains_colons(t: &TextBlock) -> bool {
+    let colons = |s: &[u8]| (1..s.len()).any(|i| s[i - 1] == b':' && s[i] == b':');
+    match t.inner {
+        Text::Raw(ref s) => colons(s),
+        Text::Nested(ref blocks) => blocks.iter().any(contains_colons),
+        Text::Link(ref target, ref label) => {
+            colons(target) || label.as_ref().map(|l| contains_colons(l)).unwrap_or(false)
+        },
+        Text::LineBreak => false,
+    }
+}
+
+/// Returns whether two documents have the same content. Positions are ignored.
+pub fn same_document(a: &Document, b: &Document) -> bool {
+    same_all(&a.parts, &b.parts, same_part)
+}
+
+fn same_all<T, F>(a: &[T], b: &[T], f: F) -> bool
+    where F: Fn(&T, &T) -> bool,
+{
+    if a.len() != b.len() {
+        return false;
+    }
+    for i in 0..a.len() {
+        if !f(&a[i], &b[i]) {
+            return false;
+        }
+    }
+    true
+}
+
+fn same_part(a: &Part, b: &Part) -> bool {
+    match (a, b) {
+        (&Part::SectionHeader(l1, ref t1), &Part::SectionHeader(l2, ref t2)) => {
+            l1 == l2 && same_text_block(t1, t2)
+        },
+        (&Part::Block(ref b1), &Part::Block(ref b2)) => same_block(b1, b2),
+        _ => false,
+    }
+}
+
+fn same_block(a: &BlockData, b: &BlockData) -> bool {
+    let same_attrs = same_all(&a.attributes, &b.attributes, |a1, a2| {
+        let args = match (&a1.args, &a2.args) {
+            (&Some(ref x), &Some(ref y)) => &x[..] == &y[..],
+            (&None, &None) => true,
+            _ => false,
+        };
+        args && &a1.name[..] == &a2.name[..]
+    });
+    if !same_attrs {
+        return false;
+    }
+    match (&a.inner, &b.inner) {
+        (&Block::Grouped(ref x), &Block::Grouped(ref y)) => same_all(x, y, same_block),
+        (&Block::Code(ref x), &Block::Code(ref y)) => &x[..] == &y[..],
+        (&Block::List(k1, ref x), &Block::List(k2, ref y)) => {
+            k1 == k2 && same_all(x, y, same_list_el)
+        },
+        (&Block::Definitions(ref x), &Block::Definitions(ref y)) => {
+            same_all(x, y, |d1, d2| {
+                same_text_block(&d1.term, &d2.term) && same_list_el(&d1.desc, &d2.desc)
+            })
+        },
+        (&Block::Table(ref x), &Block::Table(ref y)) => {
+            if x.header != y.header || !same_all(&x.align, &y.align, |a1, a2| a1 == a2) {
+                return false;
+            }
+            same_all(&x.rows, &y.rows, |r1, r2| {
+                same_all(&r1.cols, &r2.cols, |c1, c2| {
+                    if c1.span != c2.span {
+                        return false;
+                    }
+                    match (&c1.inner, &c2.inner) {
+                        (&TableCol::Simple(ref t1), &TableCol::Simple(ref t2)) => {
+                            same_text_block(t1, t2)
+                        },
+                        (&TableCol::Complex(ref b1), &TableCol::Complex(ref b2)) => {
+                            same_block(b1, b2)
+                        },
+                        _ => false,
+                    }
+                })
+            })
+        },
+        (&Block::Text(ref x), &Block::Text(ref y)) => same_text_block(x, y),
+        _ => false,
+    }
+}
+
+fn same_list_el(a: &ListEl, b: &ListEl) -> bool {
+    match (a, b) {
+        (&ListEl::Simple(ref t1), &ListEl::Simple(ref t2)) => same_text_block(t1, t2),
+        (&ListEl::Complex(ref b1), &ListEl::Complex(ref b2)) => same_block(b1, b2),
+        (&ListEl::Nested(ref t1, ref b1), &ListEl::Nested(ref t2, ref b2)) => {
+            same_text_block(t1, t2) && same_block(b1, b2)
+        },
+        _ => false,
+    }
+}
+
+fn same_text_block(a: &TextBlock, b: &TextBlock) -> bool {
+    if a.attribute != b.attribute {
+        return false;
+    }
+    match (&a.inner, &b.inner) {
+        (&Text::Raw(ref x), &Text::Raw(ref y)) => &x[..] == &y[..],
+        (&Text::Nested(ref x), &Text::Nested(ref y)) => same_all(x, y, same_text_block),
+        (&Text::Link(ref x1, ref l1), &Text::Link(ref x2, ref l2)) => {
+            let labels = match (l1, l2) {
+                (&Some(ref l1), &Some(ref l2)) => same_text_block(l1, l2),
+                (&None, &None) => true,
+                _ => false,
+            };
+            labels && &x1[..] == &x2[..]
+        },
+        (&Text::LineBreak, &Text::LineBreak) => true,
+        _ => false,
+    }
+}
+
+/// The result of formatting one document.
+pub enum Outcome {
+    /// The document is already in canonical form.
+    Unchanged,
+    /// The document can be rewritten to the contained source.
+    Changed(Vec<u8>),
+    /// The printed document does not parse to the same document.
+    Failed,
+    /// The document contains a construct that cannot be printed.
+    Unsupported(&'static str),
+}
+
+/// Formats the source of a document.
+pub fn format(src: &[u8], doc: &Document) -> Result<Outcome> {
+    if let Some(msg) = unsupported(doc) {
+        return Ok(Outcome::Unsupported(msg));
+    }
+    let printed = try!(print(doc));
+    if trim_newlines(&printed) == trim_newlines(src) {
+        return Ok(Outcome::Unchanged);
+    }
+    let reparsed = try!(markup::parse(&printed, &Env::new()));
+    if !same_document(doc, &reparsed) {
+        return Ok(Outcome::Failed);
+    }
+    Ok(Outcome::Changed(printed))
+}
+
+fn trim_newlines(s: &[u8]) -> &[u8] {
+    let mut end = s.len();
+    while end > 0 && s[end - 1] == b'\n' {
+        end -= 1;
+    }
+    &s[..end]
+}
+
+/// Reports the doc comments that are not in canonical form.
+///
+/// [argument, patch]
+/// Whether to write the rewritten comments to `fmt_doc.patch` in the output directory.
+pub fn run(krate: &Crate, patch: bool, config: &Config) -> Result {
+    let mut fmt = FormatDocs {
+        patch: Vec::new(),
+        total: 0,
+        changed: 0,
+        failed: 0,
+    };
+    fmt.walk_crate(krate);
+
+    println!("{} of {} doc comments reformatted, {} could not be formatted", fmt.changed,
+             fmt.total, fmt.failed);
+
+    if patch {
+        let path = try!(config.output_file("fmt_doc.patch"));
+        let path: &ByteStr = path.as_ref();
+        let flags = FILE_WRITE_ONLY | FILE_TRUNCATE | FILE_CREATE;
+        let mut file = try!(File::open(path, flags, MODE_FILE));
+        try!(file.write_all(&fmt.patch));
+    }
+
+    Ok(())
+}
+
+struct FormatDocs {
+    /// The rewritten comments, each preceded by a `--- path` line.
+    patch: Vec<u8>,
+    total: usize,
+    changed: usize,
+    failed: usize,
+}
+
+impl FormatDocs {
+    fn item(&mut self, item: &ItemData) -> Result {
+        // The printer writes the lrs markup language.
+        if item.docs.syntax != Syntax::Lrs {
+            return Ok(());
+        }
+        let src = try!(doc_source(&item.attrs));
+        if src.len() == 0 {
+            return Ok(());
+        }
+        // Printing the document would replace the includes by their content.
+        if src.split(|&c| c == b'\n').any(|l| markup::include_directive(l).is_some()) {
+            return Ok(());
+        }
+        // The same goes for variables, and definitions would be dropped.
+        if markup::uses_vars(&src) {
+            return Ok(());
+        }
+        self.total += 1;
+
+        let printed = match try!(format(&src, &item.docs)) {
+            Outcome::Unchanged => return Ok(()),
+            Outcome::Changed(p) => p,
+            Outcome::Failed => {
+                self.failed += 1;
+                let path = try!(full_path(item));
+                warning!("{}: cannot be reformatted without changing its meaning",
+                         path.as_str());
+                return Ok(());
+            },
+            Outcome::Unsupported(msg) => {
+                self.failed += 1;
+                let path = try!(full_path(item));
+                warning!("{}: cannot be reformatted: {}", path.as_str(), msg);
+                return Ok(());
+            },
+        };
+        self.changed += 1;
+
+        let mut header = try!(full_path(item));
+        if let Some(ref span) = item.span {
+            try!(write!(&mut header, " ({}:{})", span.filename.as_str(), span.loline));
+        }
+        println!("{}: reformatted", header.as_str());
+
+        let prefix: &[u8] = match item.inner {
+            Item::Module(_) => b"//!",
+            _ => b"///",
+        };
+        try!(self.patch.push_all(b"--- "));
+        try!(self.patch.push_all(&header));
+        try!(self.patch.push_all(b"\n"));
+        for line in trim_newlines(&printed).split(|&c| c == b'\n') {
+            try!(self.patch.push_all(prefix));
+            if line.len() > 0 {
+                try!(self.patch.push_all(b" "));
+                try!(self.patch.push_all(line));
+            }
+            try!(self.patch.push_all(b"\n"));
+        }
+
+        Ok(())
+    }
+}
+
+impl Walker for FormatDocs {
+    fn walk_item_data(&mut self, val: &Arc<ItemData>) {
+        tryerr!(self.item(val), "Could not format the documentation");
+        tree::walk_item_data(self, val);
+    }
+}
+
+/// Checks that a file of markup survives formatting.
+///
+/// = Remarks
+///
+/// Prints `ok` if printing the parsed document and parsing it again produces the same
+/// document and if printing is idempotent. Documents containing unsupported constructs
+/// fail.
+pub fn roundtrip(path: &[u8]) {
+    let path: &ByteStr = path.as_ref();
+    let mut file = tryerr!(File::open_read(path), "Could not open {:?}", path);
+    let mut src = Vec::new();
+    tryerr!(file.read_to_eof(&mut src), "Could not read {:?}", path);
+
+    let doc = tryerr!(markup::parse(&src, &Env::new()), "Could not parse {:?}", path);
+    if let Some(msg) = unsupported(&doc) {
+        println!("{:?}: unsupported: {}", path, msg);
+        return;
+    }
+    let printed = tryerr!(print(&doc), "Could not print {:?}", path);
+    let reparsed = tryerr!(markup::parse(&printed, &Env::new()), "Could not parse {:?}",
+                           path);
+    let reprinted = tryerr!(print(&reparsed), "Could not print {:?}", path);
+
+    if !same_document(&doc, &reparsed) {
+        println!("{:?}: changes meaning", path);
+    } else if &printed[..] != &reprinted[..] {
+        println!("{:?}: not idempotent", path);
+    } else {
+        println!("{:?}: ok", path);
+    }
+}
+
+/// Prints a file of markup in canonical form.
+///
+/// = Remarks
+///
+/// The syntax of the file is detected. The name of the syntax and the diagnostics are
+/// printed before the document. Markdown is printed as the lrs markup it is converted
+/// to. If the document contains a construct that cannot be printed, the construct is
+/// printed instead. Returns whether there are errors.
+pub fn convert(path: &[u8], env: &Env) -> bool {
+    let path: &ByteStr = path.as_ref();
+    let mut file = tryerr!(File::open_read(path), "Could not open {:?}", path);
+    let mut src = Vec::new();
+    tryerr!(file.read_to_eof(&mut src), "Could not read {:?}", path);
+
+    let syntax = markup::detect(&src);
+    let doc = tryerr!(markup::parse_with(&src, syntax, env), "Could not parse {:?}",
+                      path);
+
+    let name = match syntax {
+        Syntax::Markdown => "markdown",
+        _ => "lrs",
+    };
+    println!("--- {:?}: {}", path, name);
+    for diag in &doc.diagnostics {
+        let kind = match diag.error {
+            true => "error",
+            false => "warning",
+        };
+        println!("line {}, column {}: {}: {}", diag.line, diag.column, kind, diag.msg);
+    }
+    match unsupported(&doc) {
+        Some(msg) => println!("unsupported: {}", msg),
+        _ => {
+            let printed = tryerr!(print(&doc), "Could not print {:?}", path);
+            let printed: &ByteStr = trim_newlines(&printed).as_ref();
+            println!("{}", printed);
+        },
+    }
+    doc.diagnostics.iter().any(|d| d.error)
+}
+
+/// A xorshift generator for the documents of `random`.
+struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    /// Returns a number less than `n`.
+    fn below(&mut self, n: usize) -> usize {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        (self.state % n as u64) as usize
+    }
+}
+
+/// The words of generated text. None of them can start a block at the start of a line.
+const WORDS: &'static [&'static [u8]] = &[
+    b"word", b"text", b"a", b"element", b"the", b"of", b"*bold*", b"*two words*",
+    b"`raw`", b"`a+b`", b"_italic_", b"a_b", b"~~struck~~", b"x^2^", b"H~2~O",
+    b"\\*escaped\\*", b"link:lrs::vec::Vec[vector]", b"link:lrs::string",
+    b"averylongwordwithoutanyspacesthatcannotbebrokenatallbythewrappingofthetext",
+];
+
+fn random_text(out: &mut Vec<u8>, rng: &mut Rng, max: usize) -> Result {
+    let words = 1 + rng.below(max);
+    for i in 0..words {
+        if i > 0 {
+            try!(out.push_all(b" "));
+        }
+        try!(out.push_all(WORDS[rng.below(WORDS.len())]));
+    }
+    out.push_all(b"\n")
+}
+
+/// Writes a random block.
+///
+/// [argument, depth]
+/// The number of enclosing GroupedBlocks.
+fn random_block(out: &mut Vec<u8>, rng: &mut Rng, depth: usize) -> Result {
+    match rng.below(8) {
+        0 | 1 => try!(random_text(out, rng, 40)),
+        2 => {
+            try!(out.push_all(b"[note]\n"));
+            try!(random_text(out, rng, 10));
+        },
+        3 => {
+            let (simple, complex): (&[u8], &[u8]) = match rng.below(2) {
+                0 => (b"* ", b"**\n"),
+                _ => (b". ", b"..\n"),
+            };
+            let els = 1 + rng.below(3);
+            for i in 0..els {
+                // A BlockListEl is only generated last since its TextBlock would
+                // contain the following elements.
+                if i == els - 1 && rng.below(4) == 0 {
+                    try!(out.push_all(complex));
+                    try!(random_text(out, rng, 10));
+                    continue;
+                }
+                try!(out.push_all(simple));
+                try!(random_text(out, rng, 30));
+                if rng.below(3) == 0 {
+                    for _ in 0..1 + rng.below(2) {
+                        try!(out.push_all(b"  "));
+                        try!(out.push_all(simple));
+                        try!(random_text(out, rng, 10));
+                    }
+                }
+            }
+        },
+        4 => {
+            for _ in 0..1 + rng.below(3) {
+                try!(out.push_all(b"term"));
+                try!(out.push_all(WORDS[rng.below(3)]));
+                try!(out.push_all(b":: "));
+                try!(random_text(out, rng, 20));
+            }
+        },
+        5 => {
+            try!(out.push_all(b"----\n"));
+            for _ in 0..rng.below(3) {
+                try!(random_text(out, rng, 10));
+            }
+            try!(out.push_all(b"----\n"));
+        },
+        6 => {
+            try!(out.push_all(b"|===\n"));
+            for i in 0..1 + rng.below(3) {
+                if i > 0 {
+                    try!(out.push_all(b"\n"));
+                }
+                for _ in 0..2 {
+                    try!(out.push_all(b"|"));
+                    try!(out.push_all(WORDS[rng.below(WORDS.len())]));
+                }
+                try!(out.push_all(b"\n"));
+            }
+            try!(out.push_all(b"|===\n"));
+        },
+        _ => {
+            if depth > 1 {
+                return random_text(out, rng, 10);
+            }
+            try!(out.push_all(b"{\n"));
+            for i in 0..1 + rng.below(3) {
+                if i > 0 {
+                    try!(out.push_all(b"\n"));
+                }
+                try!(random_block(out, rng, depth + 1));
+            }
+            try!(out.push_all(b"\n}\n"));
+        },
+    }
+    Ok(())
+}
+
+fn random_document(rng: &mut Rng) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    for i in 0..1 + rng.below(6) {
+        if i > 0 {
+            try!(out.push_all(b"\n"));
+        }
+        if rng.below(5) == 0 {
+            for _ in 0..1 + rng.below(2) {
+                try!(out.push_all(b"="));
+            }
+            try!(out.push_all(b" "));
+            try!(random_text(&mut out, rng, 5));
+        } else {
+            try!(random_block(&mut out, rng, 0));
+        }
+    }
+    Ok(out)
+}
+
+/// Checks that generated documents survive formatting.
+///
+/// [argument, count]
+/// The number of documents to generate.
+///
+/// = Remarks
+///
+/// The documents are the same in every run. Each document that does not survive
+/// formatting is printed, followed by a summary line.
+pub fn random(count: usize) {
+    let mut rng = Rng { state: 0x2545_f491_4f6c_dd1d };
+    let mut failed = 0;
+    for i in 0..count {
+        let src = tryerr!(random_document(&mut rng), "Out of memory");
+        let doc = tryerr!(markup::parse(&src, &Env::new()), "Could not parse document {}",
+                          i);
+        if let Some(msg) = unsupported(&doc) {
+            failed += 1;
+            println!("document {}: unsupported: {}", i, msg);
+            println!("{}", src.as_str());
+            continue;
+        }
+        let printed = tryerr!(print(&doc), "Could not print document {}", i);
+        let reparsed = tryerr!(markup::parse(&printed, &Env::new()),
+                               "Could not parse document {}", i);
+        let reprinted = tryerr!(print(&reparsed), "Could not print document {}", i);
+
+        let msg = if !same_document(&doc, &reparsed) {
+            "changes meaning"
+        } else if &printed[..] != &reprinted[..] {
+            "not idempotent"
+        } else {
+            continue;
+        };
+        failed += 1;
+        println!("document {}: {}", i, msg);
+        println!("{}", src.as_str());
+    }
+    println!("{} random documents: {} failed", count, failed);
+}
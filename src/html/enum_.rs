@@ -4,7 +4,9 @@
 
 use std::io::{Write};
 
-use html::{Formatter, markup, angle_generics, where_predicates, write_raw_type, path};
+use html::{Formatter, markup, write_syntax, path};
+use config::{Config};
+use layout::{self, Docs};
 use tree::*;
 
 impl Formatter {
@@ -14,18 +16,21 @@ impl Formatter {
         try!(self.head(&mut file, "Enum "));
         try!(self.h1(&mut file, "Enum "));
 
-        try!(markup::short(&mut file, &item.docs.parts));
+        try!(self.banners(&mut file, item));
 
-        try!(syntax(&mut file, item, enum_));
+        try!(markup::short(&mut file, &item.docs.parts, &self.config));
+
+        try!(syntax(&mut file, item, enum_, &self.config));
+        try!(self.generics(&mut file, &enum_.generics, &item.docs));
         try!(self.enum_variants(&mut file, item, enum_));
         try!(self.type_static_methods(&mut file, item));
         try!(self.type_methods(&mut file, item));
+        try!(self.type_deref_methods(&mut file, item));
         try!(self.type_trait_impls(&mut file, item));
+        try!(self.type_auto_traits(&mut file, item));
+        try!(self.type_uses(&mut file, item));
 
-        try!(markup::description(&mut file, &item.docs.parts));
-        try!(markup::remarks(&mut file, &item.docs.parts));
-        try!(markup::examples(&mut file, &item.docs.parts));
-        try!(markup::see_also(&mut file, &item.docs.parts));
+        try!(self.sections(&mut file, &item.docs.parts));
 
         try!(self.foot(&mut file));
 
@@ -81,7 +86,7 @@ impl Formatter {
                     </td>\
                     <td>\
                     "));
-            try!(markup::short(file, &item.docs.parts));
+            try!(markup::short(file, &item.docs.parts, &self.config));
             try!(file.write_all(b"\
                     </td>\
                 </tr>\
@@ -99,65 +104,20 @@ impl Formatter {
     }
 }
 
-fn syntax<W: Write>(file: &mut W, item: &ItemData, enum_: &Enum) -> Result {
-    let mut variants: Vec<_> = Vec::new();
+fn syntax<W: Write>(file: &mut W, item: &ItemData, enum_: &Enum,
+                    conf: &Config) -> Result {
+    let mut variants: Vec<(&[u8], _)> = Vec::new();
 
     for item in &enum_.variants {
         if let Item::Variant(ref v) = item.inner {
             try!(variants.reserve(1));
-            variants.push((item, v));
-        }
-    }
-
-    variants.sort_by(|&(i1, _), &(i2, _)| i1.name.as_ref().unwrap()
-                                     .cmp(i2.name.as_ref().unwrap()));
-
-    try!(file.write_all(b"\
-        <h2>Syntax</h2>\
-        <pre>\
-            enum \
-        "));
-    try!(file.write_all(item.name.as_ref().unwrap().as_ref()));
-
-    let mut have_where_predicates = enum_.generics.where_predicates.len() > 0;
-    have_where_predicates |= try!(angle_generics(file, &enum_.generics));
-
-    if have_where_predicates {
-        try!(file.write_all(b"\n"));
-        try!(where_predicates(file, &enum_.generics, "    "));
-        try!(file.write_all(b"\n{\n"));
-    } else {
-        try!(file.write_all(b" {\n"));
-    }
-
-    for &(item, variant) in &variants {
-        try!(file.write_all(b"    "));
-        try!(file.write_all(item.name.as_ref().unwrap().as_ref()));
-        match variant.kind {
-            VariantKind::CLike => { },
-            VariantKind::Tuple(ref ts) => {
-                try!(file.write_all(b"("));
-                let mut first = true;
-                for t in ts {
-                    if !first {
-                        try!(file.write_all(b", "));
-                    }
-                    first = false;
-                    try!(write_raw_type(file, t));
-                }
-                try!(file.write_all(b")"));
-            },
-            VariantKind::Struct(_) => {
-                try!(file.write_all(b"Struct variant documentation not implemented"));
-            },
+            variants.push((&item.name.as_ref().unwrap()[..], v));
         }
-        try!(file.write_all(b",\n"));
     }
 
-    try!(file.write_all(b"\
-            }\
-        </pre>\
-        "));
+    variants.sort_by(|&(n1, _), &(n2, _)| n1.cmp(n2));
 
-    Ok(())
+    let mut doc = Docs::new();
+    try!(layout::enum_(&mut doc, item.name.as_ref().unwrap(), enum_, &variants));
+    write_syntax(file, doc, conf)
 }
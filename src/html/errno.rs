@@ -0,0 +1,95 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use std::io::{Write};
+use std::cmp::{Ordering};
+
+use html::{Formatter, markup};
+use tree::*;
+
+impl Formatter {
+    /// Writes a paragraph with a link to the errno index.
+    pub fn errno_index_link<W: Write>(&self, file: &mut W) -> Result {
+        try!(file.write_all(b"<p><a href=\"./"));
+        try!(markup::raw(file, &self.config.crate_name));
+        try!(file.write_all(b"::errno_index.html\">Errno index</a></p>"));
+        Ok(())
+    }
+
+    /// Writes a page that lists the functions that can return each errno.
+    pub fn errno_index(&mut self) -> Result {
+        self.errnos.sort_by(|a, b| {
+            match a.0.cmp(&b.0) {
+                Ordering::Equal => a.1.cmp(&b.1),
+                o => o,
+            }
+        });
+
+        let mut file: Vec<_> = Vec::new();
+
+        self.path.push(try!("errno_index".try_to()));
+
+        try!(self.head(&mut file, ""));
+        try!(file.write_all(b"<h1>Errno index</h1>"));
+
+        try!(file.write_all(b"\
+            <table>\
+                <thead>\
+                    <tr>\
+                        <th>Errno</th>\
+                        <th>Returned by</th>\
+                    </tr>\
+                </thead>\
+                <tbody>\
+                    "));
+
+        let mut prev: Option<&(Vec<u8>, Vec<u8>)> = None;
+        for entry in &self.errnos {
+            let same_errno = match prev {
+                Some(p) if p.0 == entry.0 => {
+                    if p.1 == entry.1 {
+                        continue;
+                    }
+                    true
+                },
+                _ => false,
+            };
+            if same_errno {
+                try!(file.write_all(b", "));
+            } else {
+                if prev.is_some() {
+                    try!(file.write_all(b"</td></tr>"));
+                }
+                try!(file.write_all(b"<tr><td><a href=\"./"));
+                try!(markup::raw(&mut file, &self.config.crate_name));
+                try!(file.write_all(b"::error::"));
+                try!(markup::raw(&mut file, &entry.0));
+                try!(file.write_all(b".html\">"));
+                try!(markup::raw(&mut file, &entry.0));
+                try!(file.write_all(b"</a></td><td>"));
+            }
+            try!(file.write_all(b"<a href=\"./"));
+            try!(markup::raw(&mut file, &entry.1));
+            try!(file.write_all(b".html\">"));
+            try!(markup::raw(&mut file, &entry.1));
+            try!(file.write_all(b"</a>"));
+            prev = Some(entry);
+        }
+        if prev.is_some() {
+            try!(file.write_all(b"</td></tr>"));
+        }
+
+        try!(file.write_all(b"\
+                </tbody>\
+            </table>\
+            "));
+
+        try!(self.foot(&mut file));
+
+        try!(try!(self.file()).write_all(&file));
+
+        self.path.pop();
+        Ok(())
+    }
+}
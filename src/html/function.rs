@@ -4,8 +4,10 @@
 
 use std::io::{Write};
 
-use html::{Formatter, write_abi, angle_generics, fn_in_out, where_predicates, markup};
+use html::{Formatter, write_syntax, markup, path};
 use markup::{Document};
+use config::{Config};
+use layout::{self, Docs};
 use tree::*;
 
 impl Formatter {
@@ -15,56 +17,91 @@ impl Formatter {
         try!(self.head(&mut file, "Function "));
         try!(self.h1(&mut file, "Function "));
 
-        try!(markup::short(&mut file, &item.docs.parts));
+        try!(self.banners(&mut file, item));
 
-        try!(syntax(&mut file, func, self.path.last().as_ref().unwrap()));
-        try!(args(&mut file, &func.decl, &item.docs));
-        try!(return_value(&mut file, &func.decl, &item.docs));
+        try!(markup::short(&mut file, &item.docs.parts, &self.config));
 
-        try!(markup::description(&mut file, &item.docs.parts));
-        try!(markup::remarks(&mut file, &item.docs.parts));
-        try!(markup::examples(&mut file, &item.docs.parts));
-        try!(markup::see_also(&mut file, &item.docs.parts));
+        try!(syntax(&mut file, func, self.path.last().as_ref().unwrap(), &self.config));
+        try!(self.generics(&mut file, &func.generics, &item.docs));
+        try!(args(&mut file, &func.decl, &item.docs, &self.config));
+        try!(return_value(&mut file, &func.decl, &item.docs, &self.config));
+        try!(self.errors(&mut file, &item.docs));
+
+        try!(self.sections(&mut file, &item.docs.parts));
 
         try!(self.foot(&mut file));
 
         try!(try!(self.file()).write_all(&file));
         Ok(())
     }
-}
 
-fn syntax<W: Write>(file: &mut W, func: &Func, name: &Vec<u8>) -> Result {
-    try!(file.write_all(b"\
-        <h2>Syntax</h2>\
-        <pre>\
-        "));
-    if func.unsaf {
-        try!(file.write_all(b"unsafe "));
-    }
-    if try!(write_abi(file, &func.abi)) {
-        try!(file.write_all(b" "));
-    }
-    try!(file.write_all(b"fn "));
-    try!(file.write_all(name));
+    /// Writes the errno values documented with `[errno, NAME]` and records them for the
+    /// errno index.
+    pub fn errors<W: Write>(&mut self, mut file: &mut W, docs: &Document) -> Result {
+        let errnos = try!(markup::errnos(&docs.parts));
+        if errnos.len() == 0 {
+            return Ok(());
+        }
 
-    let mut have_where_predicates = func.generics.where_predicates.len() > 0;
-    have_where_predicates |= try!(angle_generics(file, &func.generics));
+        try!(file.write_all(b"\
+            <h2>Errors</h2>\
+            <table>\
+                <thead>\
+                    <tr>\
+                        <th>Name</th>\
+                        <th>Description</th>\
+                    </tr>\
+                </thead>\
+                <tbody>\
+                    "));
+
+        let title = try!(path::title(&self.path));
+        for &(name, data) in &errnos {
+            try!(file.write_all(b"\
+                <tr>\
+                    <td>\
+                        <a href=\"./\
+                    "));
+            try!(markup::raw(file, &self.config.crate_name));
+            try!(file.write_all(b"::error::"));
+            try!(markup::raw(file, name.as_ref()));
+            try!(file.write_all(b".html\">"));
+            try!(markup::raw(file, name.as_ref()));
+            try!(file.write_all(b"\
+                        </a>\
+                    </td>\
+                    <td>\
+                    "));
+            try!(markup::block_data(file, data, true, &self.config));
+            try!(file.write_all(b"\
+                    </td>\
+                </tr>\
+                "));
 
-    try!(fn_in_out(file, &SelfTy::Static, &func.decl));
+            try!(self.errnos.reserve(1));
+            self.errnos.push((try!(name.try_to()), try!(title[..].try_to())));
+        }
 
-    if have_where_predicates {
-        try!(file.write_all(b"\n"));
-        try!(where_predicates(file, &func.generics, "    "));
-    }
+        try!(file.write_all(b"\
+                </tbody>\
+            </table>\
+            "));
+        try!(self.errno_index_link(file));
 
-    try!(file.write_all(b"\
-        </pre>\
-        "));
+        Ok(())
+    }
+}
 
-    Ok(())
+fn syntax<W: Write>(file: &mut W, func: &Func, name: &Vec<u8>,
+                    conf: &Config) -> Result {
+    let mut doc = Docs::new();
+    try!(layout::fn_sig(&mut doc, func.unsaf, &func.abi, name, &func.generics,
+                        &SelfTy::Static, &func.decl));
+    write_syntax(file, doc, conf)
 }
 
-pub fn args<W: Write>(mut file: &mut W, decl: &FnDecl, docs: &Document) -> Result {
+pub fn args<W: Write>(mut file: &mut W, decl: &FnDecl, docs: &Document,
+                      conf: &Config) -> Result {
     if decl.inputs.len() == 0 {
         return Ok(());
     }
@@ -91,7 +128,7 @@ pub fn args<W: Write>(mut file: &mut W, decl: &FnDecl, docs: &Document) -> Resul
                 </td>\
                 <td>\
                 "));
-        try!(markup::arg_desc(file, &docs.parts, arg.name.as_str()));
+        try!(markup::arg_desc(file, &docs.parts, arg.name.as_str(), conf));
         try!(file.write_all(b"\
                 </td>\
             <tr>\
@@ -107,7 +144,7 @@ pub fn args<W: Write>(mut file: &mut W, decl: &FnDecl, docs: &Document) -> Resul
 }
 
 pub fn return_value<W: Write>(mut file: &mut W, decl: &FnDecl,
-                              docs: &Document) -> Result {
+                              docs: &Document, conf: &Config) -> Result {
     if let FuncRetTy::Unit = decl.output {
         return Ok(());
     }
@@ -125,7 +162,7 @@ pub fn return_value<W: Write>(mut file: &mut W, decl: &FnDecl,
             try!(file.write_all(b"This function does not return."));
         },
         FuncRetTy::Return(_) => {
-            try!(markup::return_value(file, &docs.parts));
+            try!(markup::return_value(file, &docs.parts, conf));
         },
         FuncRetTy::Unit => { },
     };
@@ -0,0 +1,121 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use std::io::{Write};
+
+use html::{Formatter, markup, write_ty_param_bounds, write_raw_type};
+use markup::{Document};
+use tree::*;
+
+impl Formatter {
+    /// Writes the table of lifetimes and type parameters.
+    ///
+    /// = Remarks
+    ///
+    /// The bounds of a parameter are the bounds in the angle brackets followed by the
+    /// bounds of the where predicates that constrain the parameter itself. The
+    /// descriptions are taken from `[lifetime, 'a]` and `[type_param, T]` blocks.
+    pub fn generics<W: Write>(&self, file: &mut W, generics: &Generics,
+                              docs: &Document) -> Result {
+        if generics.lifetimes.len() + generics.type_params.len() == 0 {
+            return Ok(());
+        }
+
+        try!(file.write_all(b"\
+            <h2>Type parameters</h2>\
+            <table>\
+                <thead>\
+                    <tr>\
+                        <th>Name</th>\
+                        <th>Bounds</th>\
+                        <th>Default</th>\
+                        <th>Description</th>\
+                    </tr>\
+                </thead>\
+                <tbody>\
+                    "));
+
+        for lt in &generics.lifetimes {
+            try!(file.write_all(b"<tr><td><code>"));
+            try!(markup::raw(file, lt));
+            try!(file.write_all(b"</code></td><td><code>"));
+            try!(lifetime_bounds(file, generics, lt));
+            try!(file.write_all(b"</code></td><td></td><td>"));
+            try!(markup::param_desc(file, &docs.parts, "lifetime", lt, &self.config));
+            try!(file.write_all(b"</td></tr>"));
+        }
+
+        for param in &generics.type_params {
+            try!(file.write_all(b"<tr><td><code>"));
+            try!(markup::raw(file, &param.name));
+            try!(file.write_all(b"</code></td><td><code>"));
+            try!(type_param_bounds(file, generics, param));
+            try!(file.write_all(b"</code></td><td><code>"));
+            if let Some(ref t) = param.default {
+                try!(write_raw_type(file, t));
+            }
+            try!(file.write_all(b"</code></td><td>"));
+            try!(markup::param_desc(file, &docs.parts, "type_param", &param.name,
+                                    &self.config));
+            try!(file.write_all(b"</td></tr>"));
+        }
+
+        try!(file.write_all(b"\
+                </tbody>\
+            </table>\
+            "));
+
+        Ok(())
+    }
+}
+
+fn lifetime_bounds<W: Write>(file: &mut W, generics: &Generics, lt: &[u8]) -> Result {
+    let mut first = true;
+    for pred in &generics.where_predicates {
+        if let WherePredicate::Region(ref r) = *pred {
+            if &r.lt[..] != lt {
+                continue;
+            }
+            for bound in &r.bounds {
+                if !first {
+                    try!(file.write_all(b" + "));
+                }
+                first = false;
+                try!(file.write_all(bound.as_ref()));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn type_param_bounds<W: Write>(file: &mut W, generics: &Generics,
+                               param: &TyParam) -> Result {
+    let mut first = param.bounds.len() == 0;
+    try!(write_ty_param_bounds(file, &param.bounds));
+    for pred in &generics.where_predicates {
+        if let WherePredicate::Bound(ref b) = *pred {
+            match generic_name(&b.ty) {
+                Some(name) if name == &param.name[..] && b.bounds.len() > 0 => { },
+                _ => continue,
+            }
+            if !first {
+                try!(file.write_all(b" + "));
+            }
+            first = false;
+            try!(write_ty_param_bounds(file, &b.bounds));
+        }
+    }
+    Ok(())
+}
+
+/// Returns the name of a type that is a type parameter.
+fn generic_name(ty: &Type) -> Option<&[u8]> {
+    match *ty {
+        Type::Generic(ref g) => Some(&g.name),
+        Type::ResolvedPath(ref p) if p.is_generic && p.path.segments.len() == 1 => {
+            Some(&p.path.segments[0].name)
+        },
+        _ => None,
+    }
+}
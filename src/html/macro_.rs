@@ -14,14 +14,13 @@ impl Formatter {
         try!(self.head(&mut file, "Macro "));
         try!(self.h1(&mut file, "Macro "));
 
-        try!(markup::short(&mut file, &item.docs.parts));
+        try!(self.banners(&mut file, item));
+
+        try!(markup::short(&mut file, &item.docs.parts, &self.config));
 
         try!(syntax(&mut file, item, macro_));
 
-        try!(markup::description(&mut file, &item.docs.parts));
-        try!(markup::remarks(&mut file, &item.docs.parts));
-        try!(markup::examples(&mut file, &item.docs.parts));
-        try!(markup::see_also(&mut file, &item.docs.parts));
+        try!(self.sections(&mut file, &item.docs.parts));
 
         try!(self.foot(&mut file));
 
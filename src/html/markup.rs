@@ -2,35 +2,83 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
-use std::io::{Write};
+use std::io::{Write, Read};
+use std::file::{File};
 use std::string::{ByteStr};
 use std::util::{memchr};
 use std::bx::{Box};
+use std::vec::{Vec};
 
+use markup::{self, Env};
 use markup::*;
+use config::{Config};
+
+/// Prints the html of a file of markup.
+///
+/// = Remarks
+///
+/// The diagnostics are printed before the html. To make the output readable, a line
+/// break is inserted between adjacent tags. Returns whether there are errors.
+pub fn render(path: &[u8], env: &Env, conf: &Config) -> bool {
+    let path: &ByteStr = path.as_ref();
+    let mut file = tryerr!(File::open_read(path), "Could not open {:?}", path);
+    let mut src = Vec::new();
+    tryerr!(file.read_to_eof(&mut src), "Could not read {:?}", path);
+
+    let doc = tryerr!(markup::parse(&src, env), "Could not parse {:?}", path);
+
+    println!("--- {:?}", path);
+    for diag in &doc.diagnostics {
+        let kind = match diag.error {
+            true => "error",
+            false => "warning",
+        };
+        println!("line {}, column {}: {}: {}", diag.line, diag.column, kind, diag.msg);
+    }
+
+    let mut html = Vec::new();
+    tryerr!(all(&mut html, &doc.parts, conf), "Could not render {:?}", path);
+    let mut lines = Vec::new();
+    for (i, &b) in html.iter().enumerate() {
+        if b == b'<' && i > 0 && html[i - 1] == b'>' {
+            tryerr!(lines.push_all(b"\n"), "Out of memory");
+        }
+        tryerr!(lines.reserve(1), "Out of memory");
+        lines.push(b);
+    }
+    let lines: &ByteStr = lines.as_ref();
+    println!("{}", lines);
+
+    doc.diagnostics.iter().any(|d| d.error)
+}
 
-pub fn all<W: Write>(w: &mut W, parts: &[Part]) -> Result {
+pub fn all<W: Write>(w: &mut W, parts: &[Part], conf: &Config) -> Result {
     for part in parts {
         match *part {
-            Part::SectionHeader(n, ref text) => try!(section_header(w, n, text, false)),
-            Part::Block(ref data) => try!(block_data(w, data, false)),
+            Part::SectionHeader(n, ref text) => {
+                try!(section_header(w, n, text, false, conf))
+            },
+            Part::Block(ref data) => try!(block_data(w, data, false, conf)),
         }
     }
     Ok(())
 }
 
-pub fn short<W: Write>(w: &mut W, parts: &[Part]) -> Result {
+pub fn short<W: Write>(w: &mut W, parts: &[Part], conf: &Config) -> Result {
     for part in parts {
         match *part {
             Part::SectionHeader(1, _) => break,
-            Part::SectionHeader(n, ref text) => try!(section_header(w, n, text, false)),
-            Part::Block(ref data) => try!(block_data(w, data, false)),
+            Part::SectionHeader(n, ref text) => {
+                try!(section_header(w, n, text, false, conf))
+            },
+            Part::Block(ref data) => try!(block_data(w, data, false, conf)),
         }
     }
     Ok(())
 }
 
-pub fn field_desc<W: Write>(w: &mut W, parts: &[Part], name: &[u8]) -> Result {
+pub fn field_desc<W: Write>(w: &mut W, parts: &[Part], name: &[u8],
+                            conf: &Config) -> Result {
     for part in parts {
         match *part {
             Part::SectionHeader(1, _) => break,
@@ -40,7 +88,7 @@ pub fn field_desc<W: Write>(w: &mut W, parts: &[Part], name: &[u8]) -> Result {
                     if attr.name.as_str().trim() == "field" {
                         if let Some(ref a) = attr.args {
                             if a.as_str().trim() == name {
-                                try!(block_data(w, data, true));
+                                try!(block_data(w, data, true, conf));
                                 return Ok(());
                             }
                         }
@@ -52,7 +100,34 @@ pub fn field_desc<W: Write>(w: &mut W, parts: &[Part], name: &[u8]) -> Result {
     Ok(())
 }
 
-pub fn return_value<W: Write>(w: &mut W, parts: &[Part]) -> Result {
+/// Writes the description of a lifetime or type parameter.
+///
+/// [argument, kind]
+/// The name of the attribute, `lifetime` or `type_param`.
+pub fn param_desc<W: Write>(w: &mut W, parts: &[Part], kind: &str, name: &[u8],
+                            conf: &Config) -> Result {
+    for part in parts {
+        match *part {
+            Part::SectionHeader(1, _) => break,
+            Part::SectionHeader(_, _) => { },
+            Part::Block(ref data) => {
+                for attr in &data.attributes {
+                    if attr.name.as_str().trim() == kind {
+                        if let Some(ref a) = attr.args {
+                            if a.as_str().trim() == name {
+                                try!(block_data(w, data, true, conf));
+                                return Ok(());
+                            }
+                        }
+                    }
+                }
+            },
+        }
+    }
+    Ok(())
+}
+
+pub fn return_value<W: Write>(w: &mut W, parts: &[Part], conf: &Config) -> Result {
     for part in parts {
         match *part {
             Part::SectionHeader(1, _) => break,
@@ -60,7 +135,7 @@ pub fn return_value<W: Write>(w: &mut W, parts: &[Part]) -> Result {
             Part::Block(ref data) => {
                 for attr in &data.attributes {
                     if attr.name.as_str().trim() == "return_value" {
-                        try!(block_data(w, data, true));
+                        try!(block_data(w, data, true, conf));
                         return Ok(());
                     }
                 }
@@ -87,7 +162,30 @@ pub fn has_return_value(parts: &[Part]) -> bool {
     false
 }
 
-pub fn arg_desc<W: Write>(w: &mut W, parts: &[Part], name: &ByteStr) -> Result {
+/// Returns the `[errno, NAME]` blocks of a document in source order.
+pub fn errnos(parts: &[Part]) -> Result<Vec<(&ByteStr, &BlockData)>> {
+    let mut errnos = Vec::new();
+    for part in parts {
+        match *part {
+            Part::SectionHeader(1, _) => break,
+            Part::SectionHeader(_, _) => { },
+            Part::Block(ref data) => {
+                for attr in &data.attributes {
+                    if attr.name.as_str().trim() == "errno" {
+                        if let Some(ref a) = attr.args {
+                            try!(errnos.reserve(1));
+                            errnos.push((a.as_str().trim(), data));
+                        }
+                    }
+                }
+            },
+        }
+    }
+    Ok(errnos)
+}
+
+pub fn arg_desc<W: Write>(w: &mut W, parts: &[Part], name: &ByteStr,
+                          conf: &Config) -> Result {
     for part in parts {
         match *part {
             Part::SectionHeader(1, _) => break,
@@ -97,7 +195,7 @@ pub fn arg_desc<W: Write>(w: &mut W, parts: &[Part], name: &ByteStr) -> Result {
                     if attr.name.as_str().trim() == "argument" {
                         if let Some(ref a) = attr.args {
                             if a.as_str().trim() == name {
-                                try!(block_data(w, data, true));
+                                try!(block_data(w, data, true, conf));
                                 return Ok(());
                             }
                         }
@@ -109,59 +207,89 @@ pub fn arg_desc<W: Write>(w: &mut W, parts: &[Part], name: &ByteStr) -> Result {
     Ok(())
 }
 
-fn text_block_is(block: &TextBlock, val: &str) -> bool {
+pub fn text_block_is(block: &TextBlock, val: &[u8]) -> bool {
+    let val: &ByteStr = val.as_ref();
     match block.inner {
-        Text::Raw(ref s) if s.as_str() == val => true,
+        Text::Raw(ref s) if s.as_str().trim() == val => true,
         _ => false,
     }
 }
 
-pub fn description<W: Write>(w: &mut W, parts: &[Part]) -> Result {
-    section(w, parts, "Description", false)
-}
+/// Writes all level-1 sections of a document.
+///
+/// [argument, conf]
+/// `conf.sections` determines the order of the sections and which of them are
+/// informative.
+///
+/// = Remarks
+///
+/// The sections named in `conf.sections.order` are written in that order. An entry `*`
+/// stands for all sections that are not named, in source order. Sections that are not
+/// placed by the ordering are written at the end, in source order.
+pub fn sections<W: Write>(w: &mut W, parts: &[Part], conf: &Config) -> Result {
+    let mut headers = Vec::new();
+    for (pos, part) in parts.iter().enumerate() {
+        if let Part::SectionHeader(1, ref n) = *part {
+            try!(headers.reserve(1));
+            headers.push((pos, n));
+        }
+    }
 
-pub fn remarks<W: Write>(w: &mut W, parts: &[Part]) -> Result {
-    section(w, parts, "Remarks", true)
-}
+    let mut done = try!(Vec::with_capacity(headers.len()));
+    for _ in 0..headers.len() {
+        done.push(false);
+    }
 
-pub fn examples<W: Write>(w: &mut W, parts: &[Part]) -> Result {
-    section(w, parts, "Examples", true)
-}
+    let order = &conf.sections;
+    for name in &order.order {
+        for (i, &(pos, n)) in headers.iter().enumerate() {
+            if done[i] {
+                continue;
+            }
+            let matches = match &name[..] {
+                b"*" => !order.is_ordered(n),
+                _ => text_block_is(n, name),
+            };
+            if matches {
+                try!(section(w, parts, pos, order.is_informative(n), conf));
+                done[i] = true;
+            }
+        }
+    }
 
-pub fn see_also<W: Write>(w: &mut W, parts: &[Part]) -> Result {
-    section(w, parts, "See also", false)
+    for (i, &(pos, n)) in headers.iter().enumerate() {
+        if !done[i] {
+            try!(section(w, parts, pos, order.is_informative(n), conf));
+        }
+    }
+
+    Ok(())
 }
 
-pub fn section<W: Write>(w: &mut W, parts: &[Part], name: &str, info: bool) -> Result {
-    let pos = parts.find(|p| {
-        match *p {
-            Part::SectionHeader(1, ref n) if text_block_is(n, name) => true,
-            _ => false,
-        }
-    });
-    let pos = match pos {
-        Some(p) => p,
-        _ => return Ok(()),
-    };
+/// Writes the level-1 section whose header is at `parts[pos]`.
+fn section<W: Write>(w: &mut W, parts: &[Part], pos: usize, info: bool,
+                     conf: &Config) -> Result {
     match parts[pos] {
-        Part::SectionHeader(1, ref n) => try!(section_header(w, 1, n, info)),
+        Part::SectionHeader(1, ref n) => try!(section_header(w, 1, n, info, conf)),
         _ => { },
     }
     for part in &parts[pos+1..] {
         match *part {
             Part::SectionHeader(1, _) => break,
-            Part::SectionHeader(n, ref text) => try!(section_header(w, n, text, false)),
-            Part::Block(ref data) => try!(block_data(w, data, false)),
+            Part::SectionHeader(n, ref text) => {
+                try!(section_header(w, n, text, false, conf))
+            },
+            Part::Block(ref data) => try!(block_data(w, data, false, conf)),
         }
     }
     Ok(())
 }
 
 pub fn section_header<W: Write>(mut w: &mut W, depth: usize, block: &TextBlock,
-                                info: bool) -> Result {
+                                info: bool, conf: &Config) -> Result {
 
     try!(write!(w, "<h{}>", depth + 1));
-    try!(text_block(w, block));
+    try!(text_block(w, block, conf));
     try!(write!(w, "</h{}>", depth + 1));
     if info {
         try!(w.write_all(br#"<p class="info_head">This section is informative.</p>"#));
@@ -169,10 +297,14 @@ pub fn section_header<W: Write>(mut w: &mut W, depth: usize, block: &TextBlock,
     Ok(())
 }
 
-pub fn text_block<W: Write>(mut w: &mut W, block: &TextBlock) -> Result {
+pub fn text_block<W: Write>(mut w: &mut W, block: &TextBlock, conf: &Config) -> Result {
     let attr = match block.attribute {
         Some(TextAttr::Raw) => "code",
         Some(TextAttr::Bold) => "b",
+        Some(TextAttr::Italic) => "i",
+        Some(TextAttr::Strikethrough) => "s",
+        Some(TextAttr::Superscript) => "sup",
+        Some(TextAttr::Subscript) => "sub",
         _ => "",
     };
 
@@ -180,7 +312,7 @@ pub fn text_block<W: Write>(mut w: &mut W, block: &TextBlock) -> Result {
         try!(write!(w, "<{}>", attr));
     }
 
-    try!(text(w, &block.inner));
+    try!(text(w, &block.inner, conf));
 
     if attr.len() > 0 {
         try!(write!(w, "</{}>", attr));
@@ -189,31 +321,30 @@ pub fn text_block<W: Write>(mut w: &mut W, block: &TextBlock) -> Result {
     Ok(())
 }
 
-pub fn text<W: Write>(mut w: &mut W, txt: &Text) -> Result {
+pub fn text<W: Write>(mut w: &mut W, txt: &Text, conf: &Config) -> Result {
     match *txt {
         Text::Raw(ref s) => try!(raw(w, s.as_ref())),
         Text::Nested(ref blocks) => {
             for b in blocks {
-                try!(text_block(w, b));
+                try!(text_block(w, b, conf));
             }
         },
-        Text::Link(ref l, ref txt) => try!(link(w, l, txt)),
+        Text::Link(ref l, ref txt) => try!(link(w, l, txt, conf)),
+        Text::LineBreak => try!(w.write_all(b"<br />")),
     }
     Ok(())
 }
 
 pub fn link<W: Write>(mut w: &mut W, link: &Vec<u8>,
-                      txt: &Option<Box<TextBlock>>) -> Result {
+                      txt: &Option<Box<TextBlock>>, conf: &Config) -> Result {
     try!(w.write_all(b"<a href=\""));
 
     if link.starts_with(b"man:") {
         if let Some(p) = memchr(link.as_ref(), b'(') {
-            try!(write!(w,
-                "http://man7.org/linux/man-pages/man{}/{}.{}.html\">",
-                link.as_str()[p+1..link.len()-1], link.as_str()[4..p],
-                link.as_str()[p+1..link.len()-1]));
+            try!(man_url(w, &conf.man_url, &link[4..p], &link[p+1..link.len()-1]));
+            try!(w.write_all(b"\">"));
             match *txt {
-                Some(ref txt) => { try!(text_block(w, txt)); }
+                Some(ref txt) => { try!(text_block(w, txt, conf)); }
                 _ => { try!(w.write_all(link[4..].as_ref())); }
             }
             try!(w.write_all(b"</a>"));
@@ -221,10 +352,10 @@ pub fn link<W: Write>(mut w: &mut W, link: &Vec<u8>,
         }
     }
 
-    if link.starts_with(b"lrs") {
+    if link.starts_with(&conf.crate_name[..]) {
         try!(write!(w, "./{}.html\">", link.as_str()));
         match *txt {
-            Some(ref txt) => { try!(text_block(w, txt)); }
+            Some(ref txt) => { try!(text_block(w, txt, conf)); }
             _ => { try!(w.write_all(link.as_ref())); }
         }
         try!(w.write_all(b"</a>"));
@@ -233,13 +364,34 @@ pub fn link<W: Write>(mut w: &mut W, link: &Vec<u8>,
 
     try!(write!(w, "{}\">", link.as_str()));
     match *txt {
-        Some(ref txt) => { try!(text_block(w, txt)); }
+        Some(ref txt) => { try!(text_block(w, txt, conf)); }
         _ => { try!(w.write_all(link.as_ref())); }
     }
     try!(w.write_all(b"</a>"));
     Ok(())
 }
 
+/// Writes the url of a man page by replacing `{name}` and `{section}` in the template.
+fn man_url<W: Write>(w: &mut W, mut url: &[u8], name: &[u8],
+                     section: &[u8]) -> Result {
+    while let Some(pos) = memchr(url, b'{') {
+        try!(w.write_all(&url[..pos]));
+        url = &url[pos..];
+        if url.starts_with(b"{name}") {
+            try!(w.write_all(name));
+            url = &url[6..];
+        } else if url.starts_with(b"{section}") {
+            try!(w.write_all(section));
+            url = &url[9..];
+        } else {
+            try!(w.write_all(b"{"));
+            url = &url[1..];
+        }
+    }
+    try!(w.write_all(url));
+    Ok(())
+}
+
 pub fn raw<W: Write>(mut w: &mut W, txt: &[u8]) -> Result {
     for &b in txt {
         match b {
@@ -252,34 +404,88 @@ pub fn raw<W: Write>(mut w: &mut W, txt: &[u8]) -> Result {
     Ok(())
 }
 
+/// How a block attribute affects the rendering of the block.
+enum BlockAttrKind {
+    /// The block is not part of the running text. It is rendered elsewhere, e.g., in the
+    /// arguments table.
+    Hidden,
+    /// The block is wrapped in the opening and closing html.
+    Wrap(&'static str, &'static str),
+    /// The block is shown in a box with the given heading. The class of the box is the
+    /// name of the attribute.
+    Admonition(&'static str),
+}
+
+struct BlockAttr {
+    name: &'static str,
+    kind: BlockAttrKind,
+}
+
+/// The block attributes that affect rendering.
+///
+/// = Remarks
+///
+/// If a block has several wrapping attributes, the ones listed first are the outermost.
+static BLOCK_ATTRIBUTES: &'static [BlockAttr] = &[
+    BlockAttr { name: "hidden",       kind: BlockAttrKind::Hidden },
+    BlockAttr { name: "argument",     kind: BlockAttrKind::Hidden },
+    BlockAttr { name: "return_value", kind: BlockAttrKind::Hidden },
+    BlockAttr { name: "field",        kind: BlockAttrKind::Hidden },
+    BlockAttr { name: "type_param",   kind: BlockAttrKind::Hidden },
+    BlockAttr { name: "lifetime",     kind: BlockAttrKind::Hidden },
+    BlockAttr { name: "errno",        kind: BlockAttrKind::Hidden },
+    BlockAttr {
+        name: "info",
+        kind: BlockAttrKind::Wrap(concat!(r#"<div class="informative">"#,
+                                          r#"<p class="info_head">"#,
+                                          "This block is informative.</p>"),
+                                  "</div>"),
+    },
+    BlockAttr { name: "note",    kind: BlockAttrKind::Admonition("Note")    },
+    BlockAttr { name: "tip",     kind: BlockAttrKind::Admonition("Tip")     },
+    BlockAttr { name: "warning", kind: BlockAttrKind::Admonition("Warning") },
+    BlockAttr { name: "caution", kind: BlockAttrKind::Admonition("Caution") },
+    BlockAttr { name: "safety",  kind: BlockAttrKind::Admonition("Safety")  },
+    BlockAttr {
+        name: "quote",
+        kind: BlockAttrKind::Wrap("<blockquote>", "</blockquote>"),
+    },
+];
+
+fn has_attribute(data: &BlockData, name: &str) -> bool {
+    data.attributes.find(|a| a.name.as_str().trim() == name).is_some()
+}
+
 pub fn block_data<W: Write>(mut w: &mut W, data: &BlockData,
-                            show_hidden: bool) -> Result {
+                            show_hidden: bool, conf: &Config) -> Result {
     if !show_hidden {
-        for attr in &data.attributes {
-            let name = attr.name.as_str().trim();
-            for &aname in &["hidden", "argument", "return_value", "field"][..] {
-                if name == aname {
+        for attr in BLOCK_ATTRIBUTES {
+            if let BlockAttrKind::Hidden = attr.kind {
+                if has_attribute(data, attr.name) {
                     return Ok(());
                 }
             }
         }
     }
 
-    let is_info = data.attributes.find(|a| a.name.as_str().trim() == "info").is_some();
-    if is_info {
-        try!(w.write_all(br#"<div class="informative">"#));
-        try!(w.write_all(br#"<p class="info_head">This block is informative.</p>"#));
-    }
-
-    let is_quote = data.attributes.find(|a| a.name.as_str().trim() == "quote").is_some();
-    if is_quote {
-        try!(w.write_all(b"<blockquote>"));
+    for attr in BLOCK_ATTRIBUTES {
+        if !has_attribute(data, attr.name) {
+            continue;
+        }
+        match attr.kind {
+            BlockAttrKind::Hidden => { },
+            BlockAttrKind::Wrap(open, _) => try!(w.write_all(open.as_bytes())),
+            BlockAttrKind::Admonition(head) => {
+                try!(write!(w, r#"<div class="admonition {}">"#, attr.name));
+                try!(write!(w, r#"<p class="admonition_head">{}</p>"#, head));
+            },
+        }
     }
 
     match data.inner {
         Block::Grouped(ref blocks) => {
             for data in blocks {
-                try!(block_data(w, data, false));
+                try!(block_data(w, data, false, conf));
             }
         },
         Block::Code(ref c) => {
@@ -287,52 +493,131 @@ pub fn block_data<W: Write>(mut w: &mut W, data: &BlockData,
             try!(raw(w, c.as_ref()));
             try!(w.write_all(b"</pre>"));
         },
-        Block::List(ref l) => {
-            try!(w.write_all(b"<ul>"));
+        Block::List(kind, ref l) => {
+            let tag = match kind {
+                ListKind::Unordered => "ul",
+                ListKind::Ordered => "ol",
+            };
+            try!(write!(w, "<{}>", tag));
             for el in l {
                 try!(w.write_all(b"<li>"));
-                match *el {
-                    ListEl::Simple(ref b) => {
-                        try!(w.write_all(b"<p>"));
-                        try!(text_block(w, b));
-                        try!(w.write_all(b"</p>"));
-                    },
-                    ListEl::Complex(ref d) => try!(block_data(w, d, false)),
-                }
+                try!(list_el(w, el, conf));
                 try!(w.write_all(b"</li>"));
             }
-            try!(w.write_all(b"</ul>"));
+            try!(write!(w, "</{}>", tag));
+        },
+        Block::Definitions(ref l) => {
+            try!(w.write_all(b"<dl>"));
+            for def in l {
+                try!(w.write_all(b"<dt>"));
+                try!(text_block(w, &def.term, conf));
+                try!(w.write_all(b"</dt><dd>"));
+                try!(list_el(w, &def.desc, conf));
+                try!(w.write_all(b"</dd>"));
+            }
+            try!(w.write_all(b"</dl>"));
         },
         Block::Text(ref t) => {
             try!(w.write_all(b"<p>"));
-            try!(text_block(w, t));
+            try!(text_block(w, t, conf));
             try!(w.write_all(b"</p>"));
         },
         Block::Table(ref t) => {
+            let layout = try!(t.layout());
             try!(w.write_all(b"<table>"));
-            for row in t {
-                try!(w.write_all(b"<tr>"));
-                for col in &row.cols {
-                    try!(w.write_all(b"<td>"));
-                    match *col {
-                        TableCol::Simple(ref t) => try!(text_block(w, t)),
-                        TableCol::Complex(ref d) => try!(block_data(w, d, false)),
-                    }
-                    try!(w.write_all(b"</td>"));
+            for (i, row) in t.rows.iter().enumerate() {
+                let head = t.header && i == 0;
+                if head {
+                    try!(w.write_all(b"<thead>"));
+                } else if i == 0 || (t.header && i == 1) {
+                    try!(w.write_all(b"<tbody>"));
+                }
+                try!(table_row(w, t, row, &layout[i].0, head, conf));
+                if head {
+                    try!(w.write_all(b"</thead>"));
                 }
-                try!(w.write_all(b"</tr>"));
+            }
+            if t.rows.len() > 0 && !(t.header && t.rows.len() == 1) {
+                try!(w.write_all(b"</tbody>"));
             }
             try!(w.write_all(b"</table>"));
         },
     };
 
-    if is_quote {
-        try!(w.write_all(b"</blockquote>"));
+    let mut i = BLOCK_ATTRIBUTES.len();
+    while i > 0 {
+        i -= 1;
+        let attr = &BLOCK_ATTRIBUTES[i];
+        if !has_attribute(data, attr.name) {
+            continue;
+        }
+        match attr.kind {
+            BlockAttrKind::Hidden => { },
+            BlockAttrKind::Wrap(_, close) => try!(w.write_all(close.as_bytes())),
+            BlockAttrKind::Admonition(_) => try!(w.write_all(b"</div>")),
+        }
     }
 
-    if is_info {
-        try!(w.write_all(b"</div>"));
+    Ok(())
+}
+
+/// Writes a table row.
+///
+/// [argument, cols]
+/// The column of each cell.
+///
+/// [argument, head]
+/// Whether this is a header row.
+fn table_row<W: Write>(mut w: &mut W, table: &Table, row: &TableRow, cols: &[usize],
+                       head: bool, conf: &Config) -> Result {
+    let tag = if head { "th" } else { "td" };
+    try!(w.write_all(b"<tr>"));
+    for (i, cell) in row.cols.iter().enumerate() {
+        try!(write!(w, "<{}", tag));
+        if cell.span.cols > 1 {
+            try!(write!(w, r#" colspan="{}""#, cell.span.cols));
+        }
+        if cell.span.rows > 1 {
+            try!(write!(w, r#" rowspan="{}""#, cell.span.rows));
+        }
+        let align = match table.align.len() > cols[i] {
+            true => table.align[cols[i]],
+            false => Align::Default,
+        };
+        let align = match align {
+            Align::Default => "",
+            Align::Left => "left",
+            Align::Center => "center",
+            Align::Right => "right",
+        };
+        if align.len() > 0 {
+            try!(write!(w, r#" style="text-align: {}""#, align));
+        }
+        try!(w.write_all(b">"));
+        match cell.inner {
+            TableCol::Simple(ref t) => try!(text_block(w, t, conf)),
+            TableCol::Complex(ref d) => try!(block_data(w, d, false, conf)),
+        }
+        try!(write!(w, "</{}>", tag));
     }
+    try!(w.write_all(b"</tr>"));
+    Ok(())
+}
 
+fn list_el<W: Write>(w: &mut W, el: &ListEl, conf: &Config) -> Result {
+    match *el {
+        ListEl::Simple(ref b) => {
+            try!(w.write_all(b"<p>"));
+            try!(text_block(w, b, conf));
+            try!(w.write_all(b"</p>"));
+        },
+        ListEl::Complex(ref d) => try!(block_data(w, d, false, conf)),
+        ListEl::Nested(ref b, ref d) => {
+            try!(w.write_all(b"<p>"));
+            try!(text_block(w, b, conf));
+            try!(w.write_all(b"</p>"));
+            try!(block_data(w, d, false, conf));
+        },
+    }
     Ok(())
 }
@@ -11,11 +11,17 @@ use std::file::flags::{
 };
 use std::file::mode::{MODE_DIRECTORY, MODE_FILE};
 use std::vec::{Vec};
+use std::string::{ByteStr};
 use std::iter::{IteratorExt};
 
 use tree::*;
+use markup::{Part, TextBlock};
+use config::{Config};
+use layout::{self, Docs, Sink};
+use search;
+use passes::{write_full_path};
 
-mod markup;
+pub mod markup;
 
 mod trait_;
 mod trait_method;
@@ -31,26 +37,87 @@ mod type_;
 mod module;
 mod method;
 mod macro_;
+mod errno;
+mod generics;
+
+/// The default order of the level-1 sections. See `Sections`.
+pub const SECTION_ORDER: &'static [&'static str] = &[
+    "Description", "Remarks", "*", "Examples", "See also",
+];
+
+/// The level-1 sections that are informative by default.
+pub const INFORMATIVE_SECTIONS: &'static [&'static str] = &[
+    "Remarks", "Examples",
+];
+
+/// Configures how the level-1 sections of the documentation are written.
+pub struct Sections {
+    /// The canonical order of the sections. An entry `*` stands for all sections that are
+    /// not named in this list. Sections that are not placed by the ordering are written
+    /// at the end in source order.
+    pub order: Vec<Vec<u8>>,
+    /// The sections that are marked as informative.
+    pub informative: Vec<Vec<u8>>,
+    /// Whether to warn about sections that appear neither in `order` nor in
+    /// `informative`.
+    pub warn_unknown: bool,
+}
+
+impl Sections {
+    /// Creates the default configuration.
+    pub fn new() -> Result<Sections> {
+        Ok(Sections {
+            order: try!(Sections::list(SECTION_ORDER)),
+            informative: try!(Sections::list(INFORMATIVE_SECTIONS)),
+            warn_unknown: false,
+        })
+    }
+
+    fn list(names: &[&str]) -> Result<Vec<Vec<u8>>> {
+        let mut list = try!(Vec::with_capacity(names.len()));
+        for name in names {
+            list.push(try!(name.try_to()));
+        }
+        Ok(list)
+    }
 
-pub fn create(krate: Crate) -> Result {
-    let docs = &krate.item.docs;
+    fn is_ordered(&self, header: &TextBlock) -> bool {
+        self.order.iter().any(|n| markup::text_block_is(header, n))
+    }
+
+    fn is_informative(&self, header: &TextBlock) -> bool {
+        self.informative.iter().any(|n| markup::text_block_is(header, n))
+    }
+}
+
+pub fn create(krate: Crate, config: Config) -> Result {
     let module = match krate.item.inner {
         Item::Module(ref m) => m,
         _ => errexit!("Crate item is not a module"),
     };
 
+    try!(search::write_index(&krate, &config));
+
     let mut parts = try!(Vec::with_capacity(1));
-    parts.push(try!("lrs".try_to()));
+    parts.push(try!(config.crate_name.try_to()));
 
-    let _ = file::create_dir("doc", MODE_DIRECTORY);
-    let dir = try!(File::open("doc", FILE_ONLY_DIRECTORY | FILE_PATH, Mode(0)));
+    let output: &ByteStr = config.output.as_ref();
+    let _ = file::create_dir(output, MODE_DIRECTORY);
+    let dir = try!(File::open(output, FILE_ONLY_DIRECTORY | FILE_PATH, Mode(0)));
 
     let mut formatter = Formatter { 
         path: parts,
         dir: dir,
+        num_targets: krate.item.targets.len(),
+        config: config,
+        errnos: Vec::new(),
     };
 
-    formatter.module(module, docs)
+    try!(formatter.module(&krate.item, module));
+    if formatter.errnos.len() > 0 {
+        try!(formatter.errno_index());
+    }
+    Ok(())
 }
 
 mod path {
@@ -78,6 +145,12 @@ mod path {
 struct Formatter {
     path: Vec<Vec<u8>>,
     dir: File,
+    /// The number of targets that were merged into the crate. 0 if the crate was not
+    /// created by merging multiple targets.
+    num_targets: usize,
+    config: Config,
+    /// (errno, function) pairs collected from `[errno, NAME]` blocks.
+    errnos: Vec<(Vec<u8>, Vec<u8>)>,
 }
 
 impl Formatter {
@@ -134,375 +207,167 @@ impl Formatter {
         try!(file.write_all(b"</h1>"));
         Ok(())
     }
-}
 
-fn write_ty_param_bounds<W: Write>(file: &mut W, bounds: &[TyParamBound]) -> Result {
-    let mut first = true;
-    for bound in bounds {
-        if !first {
-            try!(file.write_all(b" + "));
-        }
-        first = false;
-        try!(write_ty_param_bound(file, bound));
-    }
-    Ok(())
-}
+    /// Writes the stability and availability notes at the top of an item's page.
+    fn banners<W: Write>(&self, file: &mut W, item: &ItemData) -> Result {
+        let stab = &item.stability;
 
-fn write_ty_param_bound<W: Write>(file: &mut W, bound: &TyParamBound) -> Result {
-    match *bound {
-        TyParamBound::Lifetime(ref l) => {
-            try!(file.write_all(l.as_ref()));
-        },
-        TyParamBound::Trait(ref t) => {
-            if t.maybe {
-                try!(file.write_all(b"?"));
+        if let Some(ref dep) = stab.deprecated {
+            try!(file.write_all(b"<div class=\"deprecated\"><p><b>Deprecated"));
+            if let Some(ref since) = dep.since {
+                try!(file.write_all(b" since "));
+                try!(markup::raw(file, since.as_ref()));
             }
-            try!(write_raw_type(file, &t.trait_.trait_));
-            for lt in &t.trait_.lifetimes {
-                try!(file.write_all(b"+"));
-                try!(file.write_all(lt.as_ref()));
+            try!(file.write_all(b"</b>"));
+            if let Some(ref note) = dep.note {
+                try!(file.write_all(b": "));
+                try!(markup::raw(file, note.as_ref()));
             }
-        },
-    }
-    Ok(())
-}
-
-fn write_angle_params<W: Write>(file: &mut W, lts: &[Vec<u8>], types: &[Type],
-                                bindings: &[TypeBinding]) -> Result {
-    let mut first = true;
-    for lt in lts {
-        if !first {
-            try!(file.write_all(b", "));
+            try!(file.write_all(b"</p></div>"));
         }
-        first = false;
-        try!(file.write_all(lt.as_ref()));
-    }
-    for t in types {
-        if !first {
-            try!(file.write_all(b", "));
+
+        if stab.level == StabilityLevel::Unstable {
+            try!(file.write_all(b"<div class=\"unstable\"><p><b>Unstable</b>"));
+            if let Some(ref feature) = stab.feature {
+                try!(file.write_all(b" (feature <code>"));
+                try!(markup::raw(file, feature.as_ref()));
+                try!(file.write_all(b"</code>"));
+                if let Some(ref issue) = stab.issue {
+                    try!(file.write_all(b", issue "));
+                    try!(markup::raw(file, issue.as_ref()));
+                }
+                try!(file.write_all(b")"));
+            }
+            try!(file.write_all(b"</p></div>"));
         }
-        first = false;
-        try!(write_raw_type(file, t));
-    }
-    for b in bindings {
-        if !first {
-            try!(file.write_all(b", "));
+
+        if let Some(ref cfg) = item.cfg {
+            let mut desc = Vec::new();
+            try!(cfg.describe(&mut desc));
+            try!(file.write_all(b"<div class=\"availability\"><p>Available on <b>"));
+            try!(markup::raw(file, desc.as_ref()));
+            try!(file.write_all(b"</b> only.</p></div>"));
         }
-        first = false;
-        try!(file.write_all(b.name.as_ref()));
-        try!(file.write_all(b" = "));
-        try!(write_raw_type(file, &b.ty));
-    }
-    Ok(())
-}
 
-fn write_raw_type<W: Write>(file: &mut W, t: &Type) -> Result {
-    match *t {
-        Type::ResolvedPath(ref p) => {
-            let mut first = !p.path.global;
-            for (i, segment) in p.path.segments.iter().enumerate() {
-                if !first {
-                    try!(file.write_all(b"::"));
-                }
-                first = false;
-                if i == p.path.segments.len() - 1 {
-                    if let Some(ref item) = *p.item.borrow() {
-                        try!(file.write_all(b"<a href=\"./"));
-                        try!(write_full_path(file, item));
-                        try!(file.write_all(b".html\">"));
-                    }
-                    try!(file.write_all(segment.name.as_ref()));
-                    if p.item.borrow().is_some() {
-                        try!(file.write_all(b"</a>"));
-                    }
-                } else {
-                    try!(file.write_all(segment.name.as_ref()));
-                }
-                match segment.params {
-                    PathParameters::AngleBracketed(ref a) => {
-                        if a.lifetimes.len() + a.ty_params.len() + a.bindings.len() > 0 {
-                            try!(file.write_all(b"&lt;"));
-                            try!(write_angle_params(file, &a.lifetimes, &a.ty_params,
-                                                    &a.bindings));
-                            try!(file.write_all(b"&gt;"));
-                        }
-                    }
-                    PathParameters::Parenthesized(ref p) => {
-                        try!(file.write_all(b"("));
-                        try!(write_angle_params(file, &[], &p.args, &[]));
-                        try!(file.write_all(b")"));
-                        if let Some(ref rv) = p.return_value {
-                            try!(file.write_all(b" -> "));
-                            try!(write_raw_type(file, rv));
-                        }
-                    }
+        if item.targets.len() > 0 && item.targets.len() < self.num_targets {
+            try!(file.write_all(b"<div class=\"availability\"><p>Only available on the \
+                                  following targets: "));
+            for (i, target) in item.targets.iter().enumerate() {
+                if i > 0 {
+                    try!(file.write_all(b", "));
                 }
+                try!(file.write_all(b"<code>"));
+                try!(markup::raw(file, target.as_ref()));
+                try!(file.write_all(b"</code>"));
             }
-        },
-        Type::Generic(ref g) => {
-            try!(file.write_all(g.name.as_ref()));
-        },
-        Type::Primitive(p) => {
-            let res = match p {
-                Primitive::Isize      => file.write_all(b"isize"),
-                Primitive::I8         => file.write_all(b"i8"),
-                Primitive::I16        => file.write_all(b"i16"),
-                Primitive::I32        => file.write_all(b"i32"),
-                Primitive::I64        => file.write_all(b"i64"),
-                Primitive::Usize      => file.write_all(b"usize"),
-                Primitive::U8         => file.write_all(b"u8"),
-                Primitive::U16        => file.write_all(b"u16"),
-                Primitive::U32        => file.write_all(b"u32"),
-                Primitive::U64        => file.write_all(b"u64"),
-                Primitive::F32        => file.write_all(b"f32"),
-                Primitive::F64        => file.write_all(b"f64"),
-                Primitive::Char       => file.write_all(b"char"),
-                Primitive::Bool       => file.write_all(b"bool"),
-                Primitive::Str        => file.write_all(b"str"),
-                Primitive::Slice      => file.write_all(b"[..]"),
-                Primitive::Array      => file.write_all(b"[..;..]"),
-                Primitive::Tuple      => file.write_all(b"(..)"),
-                Primitive::RawPointer => file.write_all(b"*"),
-            };
-            try!(res);
-        },
-        Type::BareFunction(_) => {
-        },
-        Type::Tuple(ref ts) => {
-            try!(file.write_all(b"("));
-            if ts.fields.len() == 1 {
-                try!(write_raw_type(file, &ts.fields[0]));
-                try!(file.write_all(b",)"));
-            } else {
-                let mut first = true;
-                for t in &ts.fields {
-                    if !first {
-                        try!(file.write_all(b", "));
+            try!(file.write_all(b".</p></div>"));
+        }
+
+        Ok(())
+    }
+
+    /// Writes the level-1 sections of an item's documentation.
+    fn sections<W: Write>(&self, file: &mut W, parts: &[Part]) -> Result {
+        let sections = &self.config.sections;
+        if sections.warn_unknown {
+            for part in parts {
+                if let Part::SectionHeader(1, ref n) = *part {
+                    if !sections.is_ordered(n) && !sections.is_informative(n) {
+                        let mut name = Vec::new();
+                        try!(markup::text_block(&mut name, n, &self.config));
+                        let name: &ByteStr = name.as_ref();
+                        let path = try!(path::title(&self.path));
+                        let path: &ByteStr = path.as_ref();
+                        warning!("{:?}: Unknown section {:?}", path, name);
                     }
-                    first = false;
-                    try!(write_raw_type(file, t));
                 }
-                try!(file.write_all(b")"));
-            }
-        },
-        Type::Slice(ref s) => {
-            try!(file.write_all(b"["));
-            try!(write_raw_type(file, &s.ty));
-            try!(file.write_all(b"]"));
-        },
-        Type::Array(ref a) => {
-            try!(file.write_all(b"["));
-            try!(write_raw_type(file, &a.ty));
-            try!(file.write_all(b"; "));
-            try!(file.write_all(a.initializer.as_ref()));
-            try!(file.write_all(b"]"));
-        },
-        Type::Bottom => {
-            try!(file.write_all(b"!"));
-        },
-        Type::Pointer(ref p) => {
-            try!(file.write_all(b"*"));
-            if p.mutable {
-                try!(file.write_all(b"mut "));
-            } else {
-                try!(file.write_all(b"const "));
-            }
-            try!(write_raw_type(file, &p.ty));
-        },
-        Type::Ref(ref r) => {
-            try!(file.write_all(b"&amp;"));
-            if let Some(ref lt) = r.lifetime {
-                try!(file.write_all(lt.as_ref()));
-                try!(file.write_all(b" "));
-            }
-            if r.mutable {
-                try!(file.write_all(b"mut "));
             }
-            try!(write_raw_type(file, &r.ty));
-        },
-        Type::UfcsPath(ref u) => {
-            try!(file.write_all(b"&lt;"));
-            try!(write_raw_type(file, &u.self_ty));
-            try!(file.write_all(b" as "));
-            try!(write_raw_type(file, &u.trait_));
-            try!(file.write_all(b"&gt;::"));
-            try!(file.write_all(u.target.as_ref()));
-        },
-        Type::Infer => {
-            try!(file.write_all(b"_"));
-        },
-        Type::HkltBound(_) => {
-        },
+        }
+        markup::sections(file, parts, &self.config)
     }
-    Ok(())
-}
 
-fn fn_in_out<W: Write>(dst: &mut W, slf: &SelfTy, decl: &FnDecl) -> Result {
-    try!(dst.write_all(b"("));
-
-    let have_slf = match *slf {
-        SelfTy::Static => false,
-        SelfTy::Value => {
-            try!(dst.write_all(b"self"));
-            true
-        },
-        SelfTy::Borrowed(ref lt, mutable) => {
-            try!(dst.write_all(b"&"));
-            if let Some(ref s) = *lt {
-                try!(dst.write_all(s.as_ref()));
-                try!(dst.write_all(b" "));
-            }
-            if mutable {
-                try!(dst.write_all(b"mut "));
+    /// Writes the short stability and availability markers used in item tables.
+    fn badges<W: Write>(&self, file: &mut W, item: &ItemData) -> Result {
+        if item.stability.deprecated.is_some() {
+            try!(file.write_all(b" <span class=\"badge deprecated\">Deprecated</span>"));
+        }
+        if item.stability.level == StabilityLevel::Unstable {
+            try!(file.write_all(b" <span class=\"badge unstable\">Unstable</span>"));
+        }
+        if let Some(ref cfg) = item.cfg {
+            let mut desc = Vec::new();
+            try!(cfg.describe(&mut desc));
+            try!(file.write_all(b" <span class=\"badge availability\">"));
+            try!(markup::raw(file, desc.as_ref()));
+            try!(file.write_all(b"</span>"));
+        }
+        if item.targets.len() > 0 && item.targets.len() < self.num_targets {
+            for target in &item.targets {
+                try!(file.write_all(b" <span class=\"badge availability\">"));
+                try!(markup::raw(file, target.as_ref()));
+                try!(file.write_all(b"</span>"));
             }
-            try!(dst.write_all(b"self"));
-            true
-        },
-        SelfTy::Explicit(ref t) => {
-            try!(dst.write(b"self: "));
-            try!(write_raw_type(dst, t));
-            true
-        },
-    };
-
-    let mut first = !have_slf;
-    for arg in &decl.inputs {
-        if !first {
-            try!(dst.write_all(b", "));
         }
-        first = false;
-        try!(dst.write_all(arg.name.as_ref()));
-        try!(dst.write_all(b": "));
-        try!(write_raw_type(dst, &arg.type_));
-    }
-
-    try!(dst.write_all(b")"));
-
-    match decl.output {
-        FuncRetTy::NoReturn => {
-            try!(dst.write_all(b" -> !"));
-        },
-        FuncRetTy::Return(ref t) => {
-            try!(dst.write_all(b" -> "));
-            try!(write_raw_type(dst, t));
-        },
-        FuncRetTy::Unit => { },
+        Ok(())
     }
-
-    Ok(())
 }
 
-fn write_abi<W: Write>(dst: &mut W, abi: &Abi) -> Result<bool> {
-    if let Abi::Rust = *abi {
-        return Ok(false);
-    }
-    match *abi {
-        Abi::Rust => 0,
-        Abi::C => try!(dst.write_all(b"extern")),
-        Abi::System => try!(dst.write_all(b"extern \"system\"")),
-        Abi::RustIntrinsic => try!(dst.write_all(b"extern \"rust-intrinsic\"")),
-        Abi::RustCall => try!(dst.write_all(b"extern \"rust-call\"")),
-    };
-    Ok(true)
+/// Writes laid out syntax as html.
+struct HtmlSink<'a, W: Write+'a> {
+    file: &'a mut W,
 }
 
-fn write_full_path<W: Write>(dst: &mut W, dstitem: &ItemData) -> Result {
-    if let Some(ref parent) = *dstitem.parent.borrow() {
-        try!(write_full_path(dst, parent));
-        try!(dst.write_all(b"::"));
-    } else {
-        try!(dst.write_all(b"lrs"));
-    }
-    if let Some(ref name) = dstitem.name {
-        try!(dst.write_all(name.as_ref()));
+impl<'a, W: Write> Sink for HtmlSink<'a, W> {
+    fn text(&mut self, text: &[u8]) -> Result {
+        markup::raw(self.file, text)
     }
-    Ok(())
-}
 
-fn angle_generics<W: Write>(file: &mut W,  generics: &Generics) -> Result<bool> {
-    if generics.lifetimes.len() + generics.type_params.len() == 0 {
-        return Ok(false);
+    fn link(&mut self, item: &ItemData, text: &[u8]) -> Result {
+        try!(self.file.write_all(b"<a href=\"./"));
+        try!(write_full_path(self.file, item));
+        try!(self.file.write_all(b".html\">"));
+        try!(markup::raw(self.file, text));
+        self.file.write_all(b"</a>")
     }
 
-    let mut have_where_predicates = false;
-
-    try!(file.write_all(b"&lt;"));
-    let mut first = true;
-    for lt in &generics.lifetimes {
-        if !first {
-            try!(file.write_all(b", "));
-        }
-        first = false;
-        try!(file.write_all(lt.as_ref()));
-    }
-    for t in &generics.type_params {
-        if !first {
-            try!(file.write_all(b", "));
-        }
-        first = false;
-        try!(file.write_all(t.name.as_ref()));
-        if let Some(ref t) = t.default {
-            try!(file.write_all(b" = "));
-            try!(write_raw_type(file, t));
+    fn newline(&mut self, indent: usize) -> Result {
+        try!(self.file.write_all(b"\n"));
+        for _ in 0..indent {
+            try!(self.file.write_all(b" "));
         }
-        have_where_predicates |= t.bounds.len() > 0;
+        Ok(())
     }
-    try!(file.write_all(b"&gt;"));
+}
 
-    Ok(have_where_predicates)
+/// Writes a document that has been built with the `layout` module.
+fn write_doc<W: Write>(file: &mut W, doc: Docs, width: usize) -> Result {
+    let mut sink = HtmlSink { file: file };
+    layout::render(&mut sink, &doc.done(), width)
 }
 
-fn where_predicates<W: Write>(file: &mut W, generics: &Generics, prefix: &str) -> Result {
-    let mut first = true;
-    for t in &generics.type_params {
-        if t.bounds.len() == 0 {
-            continue;
-        }
-        if first {
-            try!(file.write_all(prefix.as_bytes()));
-            try!(file.write_all(b"where "));
-        } else {
-            try!(file.write_all(b"\n      "));
-            try!(file.write_all(prefix.as_bytes()));
-        }
-        first = false;
-        try!(write_ty_param_bounds(file, &t.bounds));
-        try!(file.write_all(b","));
-    }
-    for t in &generics.where_predicates {
-        if first {
-            try!(file.write_all(prefix.as_bytes()));
-            try!(file.write_all(b"where "));
-        } else {
-            try!(file.write_all(b"\n      "));
-            try!(file.write_all(prefix.as_bytes()));
-        }
-        first = false;
-        match *t {
-            WherePredicate::Bound(ref b) => {
-                try!(write_raw_type(file, &b.ty));
-                try!(file.write_all(b": "));
-                try!(write_ty_param_bounds(file, &b.bounds));
-            },
-            WherePredicate::Region(ref r) => {
-                try!(file.write_all(r.lt.as_ref()));
-                try!(file.write_all(b": "));
-                let mut first = true;
-                for lt in &r.bounds {
-                    if !first {
-                        try!(file.write_all(b"+"));
-                    }
-                    first = false;
-                    try!(file.write_all(lt.as_ref()));
-                }
-            },
-            WherePredicate::Eq(ref e) => {
-                try!(write_raw_type(file, &e.lhs));
-                try!(file.write_all(b" = "));
-                try!(write_raw_type(file, &e.rhs));
-            },
-        }
-        try!(file.write_all(b","));
-    }
+/// Writes the syntax block of an item.
+fn write_syntax<W: Write>(file: &mut W, doc: Docs, conf: &Config) -> Result {
+    try!(file.write_all(b"\
+        <h2>Syntax</h2>\
+        <pre>\
+        "));
+    try!(write_doc(file, doc, conf.width));
+    try!(file.write_all(b"\
+        </pre>\
+        "));
     Ok(())
 }
+
+fn write_ty_param_bounds<W: Write>(file: &mut W, bounds: &[TyParamBound]) -> Result {
+    let mut doc = Docs::new();
+    try!(layout::bounds(&mut doc, bounds));
+    // Bounds contain no line breaks.
+    write_doc(file, doc, 0)
+}
+
+fn write_raw_type<W: Write>(file: &mut W, t: &Type) -> Result {
+    let mut doc = Docs::new();
+    try!(layout::ty(&mut doc, t));
+    // Types contain no line breaks.
+    write_doc(file, doc, 0)
+}
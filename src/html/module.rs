@@ -5,17 +5,19 @@
 use std::io::{Write};
 
 use html::{path, markup, Formatter};
-use markup::{Document};
 use tree::*;
 
 impl Formatter {
-    pub fn module(&mut self, module: &Module, docs: &Document) -> Result {
+    pub fn module(&mut self, item: &ItemData, module: &Module) -> Result {
+        let docs = &item.docs;
         let mut file: Vec<_> = Vec::new();
 
         try!(self.head(&mut file, "Module "));
         try!(self.h1(&mut file, "Module "));
 
-        try!(markup::short(&mut file, &docs.parts));
+        try!(self.banners(&mut file, item));
+
+        try!(markup::short(&mut file, &docs.parts, &self.config));
 
         try!(self.module_modules(&mut file, module));
         try!(self.module_types(&mut file, module));
@@ -24,10 +26,12 @@ impl Formatter {
         try!(self.module_statics(&mut file, module));
         try!(self.module_macros(&mut file, module));
 
-        try!(markup::description(&mut file, &docs.parts));
-        try!(markup::remarks(&mut file, &docs.parts));
-        try!(markup::examples(&mut file, &docs.parts));
-        try!(markup::see_also(&mut file, &docs.parts));
+        // The functions of all modules have been written at this point.
+        if self.path.len() == 1 && self.errnos.len() > 0 {
+            try!(self.errno_index_link(&mut file));
+        }
+
+        try!(self.sections(&mut file, &docs.parts));
 
         try!(self.foot(&mut file));
 
@@ -68,7 +72,7 @@ impl Formatter {
 
         for &(item, module) in &sub_mods {
             try!(self.path.push(try!(item.name.as_ref().unwrap().try_to())));
-            try!(self.module(module, &item.docs));
+            try!(self.module(item, module));
 
             try!(file.write_all(b"\
                 <tr>\
@@ -78,12 +82,13 @@ impl Formatter {
             try!(file.write_all(try!(path::path(&self.path)).as_ref()));
             try!(file.write_all(b"\">"));
             try!(file.write_all(item.name.as_ref().unwrap().as_ref()));
+            try!(file.write_all(b"</a>"));
+            try!(self.badges(file, item));
             try!(file.write_all(b"\
-                        </a>\
                     </td>\
                     <td>\
                     "));
-            try!(markup::short(file, &item.docs.parts));
+            try!(markup::short(file, &item.docs.parts, &self.config));
             try!(file.write_all(b"\
                     </td>\
                 </tr>\
@@ -153,12 +158,13 @@ impl Formatter {
             try!(file.write_all(try!(path::path(&self.path)).as_ref()));
             try!(file.write_all(b"\">"));
             try!(file.write_all(item.name.as_ref().unwrap().as_ref()));
+            try!(file.write_all(b"</a>"));
+            try!(self.badges(file, item));
             try!(file.write_all(b"\
-                        </a>\
                     </td>\
                     <td>\
                     "));
-            try!(markup::short(file, &item.docs.parts));
+            try!(markup::short(file, &item.docs.parts, &self.config));
             try!(file.write_all(b"\
                     </td>\
                 </tr>\
@@ -217,12 +223,13 @@ impl Formatter {
             try!(file.write_all(try!(path::path(&self.path)).as_ref()));
             try!(file.write_all(b"\">"));
             try!(file.write_all(item.name.as_ref().unwrap().as_ref()));
+            try!(file.write_all(b"</a>"));
+            try!(self.badges(file, item));
             try!(file.write_all(b"\
-                        </a>\
                     </td>\
                     <td>\
                     "));
-            try!(markup::short(file, &item.docs.parts));
+            try!(markup::short(file, &item.docs.parts, &self.config));
             try!(file.write_all(b"\
                     </td>\
                 </tr>\
@@ -281,12 +288,13 @@ impl Formatter {
             try!(file.write_all(try!(path::path(&self.path)).as_ref()));
             try!(file.write_all(b"\">"));
             try!(file.write_all(item.name.as_ref().unwrap().as_ref()));
+            try!(file.write_all(b"</a>"));
+            try!(self.badges(file, item));
             try!(file.write_all(b"\
-                        </a>\
                     </td>\
                     <td>\
                     "));
-            try!(markup::short(file, &item.docs.parts));
+            try!(markup::short(file, &item.docs.parts, &self.config));
             try!(file.write_all(b"\
                     </td>\
                 </tr>\
@@ -345,12 +353,13 @@ impl Formatter {
             try!(file.write_all(try!(path::path(&self.path)).as_ref()));
             try!(file.write_all(b"\">"));
             try!(file.write_all(item.name.as_ref().unwrap().as_ref()));
+            try!(file.write_all(b"</a>"));
+            try!(self.badges(file, item));
             try!(file.write_all(b"\
-                        </a>\
                     </td>\
                     <td>\
                     "));
-            try!(markup::short(file, &item.docs.parts));
+            try!(markup::short(file, &item.docs.parts, &self.config));
             try!(file.write_all(b"\
                     </td>\
                 </tr>\
@@ -412,12 +421,13 @@ impl Formatter {
             try!(file.write_all(try!(path::path(&self.path)).as_ref()));
             try!(file.write_all(b"\">"));
             try!(file.write_all(item.name.as_ref().unwrap().as_ref()));
+            try!(file.write_all(b"</a>"));
+            try!(self.badges(file, item));
             try!(file.write_all(b"\
-                        </a>\
                     </td>\
                     <td>\
                     "));
-            try!(markup::short(file, &item.docs.parts));
+            try!(markup::short(file, &item.docs.parts, &self.config));
             try!(file.write_all(b"\
                     </td>\
                 </tr>\
@@ -4,10 +4,13 @@
 
 use std::io::{Write};
 use std::iter::{IteratorExt};
+use std::rc::{Arc};
 
-use html::{Formatter, where_predicates, angle_generics, write_raw_type};
+use html::{Formatter, write_syntax};
 use html::markup::{self};
 use markup::{Document};
+use config::{Config};
+use layout::{self, Docs};
 use tree::*;
 
 impl Formatter {
@@ -17,18 +20,22 @@ impl Formatter {
         try!(self.head(&mut file, "Struct "));
         try!(self.h1(&mut file, "Struct "));
 
-        try!(markup::short(&mut file, &item.docs.parts));
+        try!(self.banners(&mut file, item));
+
+        try!(markup::short(&mut file, &item.docs.parts, &self.config));
 
         try!(self.struct_syntax(&mut file, strukt));
-        try!(fields(&mut file, strukt, &item.docs));
+        try!(self.generics(&mut file, &strukt.generics, &item.docs));
+        try!(fields(&mut file, &strukt.struct_type, &strukt.fields, &item.docs,
+                    &self.config));
         try!(self.type_static_methods(&mut file, item));
         try!(self.type_methods(&mut file, item));
+        try!(self.type_deref_methods(&mut file, item));
         try!(self.type_trait_impls(&mut file, item));
+        try!(self.type_auto_traits(&mut file, item));
+        try!(self.type_uses(&mut file, item));
 
-        try!(markup::description(&mut file, &item.docs.parts));
-        try!(markup::remarks(&mut file, &item.docs.parts));
-        try!(markup::examples(&mut file, &item.docs.parts));
-        try!(markup::see_also(&mut file, &item.docs.parts));
+        try!(self.sections(&mut file, &item.docs.parts));
 
         try!(self.foot(&mut file));
 
@@ -37,85 +44,21 @@ impl Formatter {
     }
 
     fn struct_syntax<W: Write>(&mut self, file: &mut W, strukt: &Struct) -> Result {
-        try!(file.write_all(b"\
-            <h2>Syntax</h2>\
-            <pre>\
-                struct \
-            "));
-        try!(file.write_all(self.path[self.path.len()-1].as_ref()));
-
-        let mut have_where_predicates = strukt.generics.where_predicates.len() > 0;
-        have_where_predicates |= try!(angle_generics(file, &strukt.generics));
-
-        if strukt.struct_type == StructType::Tuple {
-            try!(file.write_all(b"("));
-            let mut first = true;
-            for item in &strukt.fields {
-                if !first {
-                    try!(file.write_all(b", "));
-                }
-                first = false;
-                let field = match item.inner {
-                    Item::StructField(ref f) => f,
-                    _ => errexit!("struct field is not a StructField"),
-                };
-                match *field {
-                    StructField::Hidden => {
-                        try!(file.write_all(b"/* */"));
-                    },
-                    StructField::Typed(ref t) => {
-                        try!(write_raw_type(file, t));
-                    },
-                }
-            }
-            try!(file.write_all(b")"));
-        }
-
-        if have_where_predicates {
-            try!(file.write_all(b"\n"));
-            try!(where_predicates(file, &strukt.generics, "    "));
-        }
-
-        if strukt.struct_type == StructType::Plain {
-            if have_where_predicates {
-                try!(file.write_all(b"\n{\n"));
-            } else {
-                try!(file.write_all(b" {\n"));
-            }
-            let mut have_hidden = false;
-            for item in &strukt.fields {
-                let field = match item.inner {
-                    Item::StructField(ref f) => f,
-                    _ => errexit!("struct field is not a StructField"),
-                };
-                if let StructField::Typed(ref t) = *field {
-                    try!(file.write_all(b"    "));
-                    try!(file.write_all(item.name.as_ref().unwrap().as_ref()));
-                    try!(file.write_all(b": "));
-                    try!(write_raw_type(file, t));
-                    try!(file.write_all(b",\n"));
-                } else {
-                    have_hidden = true;
-                }
-            }
-            if have_hidden {
-                try!(file.write_all(b"    /* private fields */\n"));
-            }
-            try!(file.write_all(b"}"));
-        }
-
-        try!(file.write_all(b"\
-            </pre>\
-            "));
-
-        Ok(())
+        let mut doc = Docs::new();
+        try!(layout::struct_(&mut doc, &self.path[self.path.len()-1], strukt));
+        write_syntax(file, doc, &self.config)
     }
-
 }
 
-fn fields<W: Write>(mut file: &mut W, strukt: &Struct, docs: &Document) -> Result {
+/// Writes the table of the public fields of a struct or struct variant.
+///
+/// [argument, docs]
+/// The documentation of the struct or variant. It describes tuple fields.
+pub fn fields<W: Write>(mut file: &mut W, struct_type: &StructType,
+                        fields: &[Arc<ItemData>], docs: &Document,
+                        conf: &Config) -> Result {
     let mut have_public_fields = false;
-    for field in &strukt.fields {
+    for field in fields {
         if let Item::StructField(ref f) = field.inner {
             if let StructField::Typed(_) = *f {
                 have_public_fields = true;
@@ -134,7 +77,7 @@ fn fields<W: Write>(mut file: &mut W, strukt: &Struct, docs: &Document) -> Resul
             <thead>\
                 <tr>\
                 "));
-    if strukt.struct_type == StructType::Tuple {
+    if *struct_type == StructType::Tuple {
         try!(file.write_all(b"<th>Position</th>"));
     } else {
         try!(file.write_all(b"<th>Name</th>"));
@@ -146,7 +89,7 @@ fn fields<W: Write>(mut file: &mut W, strukt: &Struct, docs: &Document) -> Resul
             <tbody>\
                 "));
 
-    for (i, item) in strukt.fields.iter().enumerate() {
+    for (i, item) in fields.iter().enumerate() {
         let field = match item.inner {
             Item::StructField(ref f) => f,
             _ => errexit!("struct field is not a StructField"),
@@ -156,17 +99,17 @@ fn fields<W: Write>(mut file: &mut W, strukt: &Struct, docs: &Document) -> Resul
             _ => continue,
         };
         try!(file.write_all(b"<tr><td>"));
-        if strukt.struct_type == StructType::Tuple {
+        if *struct_type == StructType::Tuple {
             try!(write!(file, "{}", i + 1));
         } else {
             try!(file.write_all(item.name.as_ref().unwrap().as_ref()));
         }
         try!(file.write_all(b"</td><td>"));
-        if strukt.struct_type == StructType::Tuple {
+        if *struct_type == StructType::Tuple {
             let field = try!(format!("{}", i + 1));
-            try!(markup::field_desc(file, &docs.parts, &field));
+            try!(markup::field_desc(file, &docs.parts, &field, conf));
         } else {
-            try!(markup::all(file, &item.docs.parts));
+            try!(markup::all(file, &item.docs.parts, conf));
         }
         try!(file.write_all(b"</td></tr>"));
     }
@@ -6,8 +6,10 @@ use std::io::{Write};
 use std::rc::{Arc};
 use std::vec::{Vec};
 
-use html::{self, Formatter, markup, angle_generics, where_predicates, write_ty_param_bounds, write_raw_type, path};
+use html::{Formatter, markup, write_syntax, write_raw_type, path};
+use layout::{self, Docs};
 use tree::*;
+use config::{Config};
 
 impl Formatter {
     pub fn trait_(&mut self, item: &ItemData, trait_: &Trait) -> Result {
@@ -16,12 +18,15 @@ impl Formatter {
         try!(self.head(&mut file, "Trait "));
         try!(self.h1(&mut file, "Trait "));
 
-        try!(markup::short(&mut file, &item.docs.parts));
+        try!(self.banners(&mut file, item));
+
+        try!(markup::short(&mut file, &item.docs.parts, &self.config));
 
         let (mut assocs, mut required, provided) = try!(collect_parts(trait_));
 
         try!(self.trait_syntax(&mut file, trait_, &assocs, &required, &provided));
-        try!(assoc_types(&mut file, &mut assocs));
+        try!(self.generics(&mut file, &trait_.generics, &item.docs));
+        try!(assoc_types(&mut file, &mut assocs, &self.config));
 
         try!(required.push_all(&provided));
         required.sort_by(|&(f1, _), &(f2, _)| f1.name.as_ref().unwrap()
@@ -30,10 +35,7 @@ impl Formatter {
         try!(self.trait_methods(&mut file, &required));
         try!(self.type_trait_impls(&mut file, item));
 
-        try!(markup::description(&mut file, &item.docs.parts));
-        try!(markup::remarks(&mut file, &item.docs.parts));
-        try!(markup::examples(&mut file, &item.docs.parts));
-        try!(markup::see_also(&mut file, &item.docs.parts));
+        try!(self.sections(&mut file, &item.docs.parts));
 
         try!(self.foot(&mut file));
 
@@ -46,78 +48,10 @@ impl Formatter {
                               required: &[(&Arc<ItemData>, &Method)],
                               provided: &[(&Arc<ItemData>, &Method)],
                               ) -> Result {
-        try!(file.write_all(b"\
-            <h2>Syntax</h2>\
-            <pre>\
-            "));
-        if trait_.unsaf {
-            try!(file.write_all(b"unsafe "));
-        }
-        try!(file.write_all(b"trait "));
-        try!(file.write_all(self.path[self.path.len()-1].as_ref()));
-
-        let mut have_where_predicates = trait_.generics.where_predicates.len() > 0;
-        have_where_predicates |= try!(angle_generics(file, &trait_.generics));
-
-        if trait_.bounds.len() > 0 {
-            try!(file.write_all(b" : "));
-            try!(write_ty_param_bounds(file, &trait_.bounds));
-        }
-
-        if have_where_predicates {
-            try!(file.write_all(b"\n"));
-            try!(where_predicates(file, &trait_.generics, "    "));
-        }
-
-        if assocs.len() + required.len() + provided.len() > 0 {
-            if have_where_predicates {
-                try!(file.write_all(b"\n{\n"));
-            } else {
-                try!(file.write_all(b" {\n"));
-            }
-
-            for &(i, a) in assocs {
-                try!(file.write_all(b"    type "));
-                try!(file.write_all(i.name.as_ref().unwrap().as_ref()));
-                if a.bounds.len() > 0 {
-                    try!(file.write_all(b": "));
-                    try!(write_ty_param_bounds(file, &a.bounds));
-                }
-                try!(file.write_all(b";\n"));
-            }
-
-            if required.len() > 0 {
-                if assocs.len() > 0 {
-                    try!(file.write_all(b"\n"));
-                }
-                try!(file.write_all(b"    /* Required methods */\n"));
-            }
-
-            for &(i, m) in required {
-                html::method::method_syntax(file, m, i.name.as_ref().unwrap().as_ref());
-                try!(file.write_all(b"\n"));
-            }
-
-            if provided.len() > 0 {
-                if assocs.len() + required.len() > 0 {
-                    try!(file.write_all(b"\n"));
-                }
-                try!(file.write_all(b"    /* Provided methods */\n"));
-            }
-
-            for &(i, m) in provided {
-                html::method::method_syntax(file, m, i.name.as_ref().unwrap().as_ref());
-                try!(file.write_all(b"\n"));
-            }
-
-            try!(file.write_all(b"}"));
-        }
-
-        try!(file.write_all(b"\
-            </pre>\
-            "));
-
-        Ok(())
+        let mut doc = Docs::new();
+        try!(layout::trait_(&mut doc, &self.path[self.path.len()-1], trait_, assocs,
+                            required, provided));
+        write_syntax(file, doc, &self.config)
     }
 
     fn trait_methods<W: Write>(&mut self, file: &mut W,
@@ -173,7 +107,7 @@ impl Formatter {
                     </td>\
                     <td>\
                     "));
-            try!(markup::short(file, &item.docs.parts));
+            try!(markup::short(file, &item.docs.parts, &self.config));
             try!(file.write_all(b"\
                     </td>\
                 </tr>\
@@ -192,7 +126,8 @@ impl Formatter {
 }
 
 fn assoc_types<W: Write>(file: &mut W,
-                         assocs: &[(&Arc<ItemData>, &AssocType)]) -> Result {
+                         assocs: &[(&Arc<ItemData>, &AssocType)],
+                         conf: &Config) -> Result {
     if assocs.len() == 0 {
         return Ok(());
     }
@@ -219,7 +154,7 @@ fn assoc_types<W: Write>(file: &mut W,
                 </td>\
                 <td>\
                 "));
-        try!(markup::all(file, &a.docs.parts));
+        try!(markup::all(file, &a.docs.parts, conf));
         try!(file.write_all(b"\
                 </td>\
             </tr>\
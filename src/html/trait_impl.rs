@@ -4,9 +4,12 @@
 
 use std::io::{Write};
 use std::rc::{Arc};
+use std::vec::{Vec};
 
-use html::{Formatter, where_predicates, angle_generics, write_raw_type};
+use html::{Formatter, write_syntax, write_doc};
+use passes::{write_full_path};
 use html::markup::{self};
+use layout::{self, Docs};
 use tree::*;
 
 type Input<'a> = (&'a Arc<ItemData>, &'a Impl, Arc<ItemData>, &'a Type);
@@ -28,15 +31,14 @@ impl Formatter {
                     "));
         }
 
-        try!(markup::short(&mut file, &impls[0].2.docs.parts));
+        try!(markup::short(&mut file, &impls[0].2.docs.parts, &self.config));
 
         for &(impl_item, impl_, ref trait_item, trait_) in impls {
             try!(self.trait_impl_syntax(&mut file, impl_item, impl_, trait_item, trait_));
+            try!(self.generics(&mut file, &impl_.generics, &impl_item.docs));
+            try!(self.trait_impl_methods(&mut file, impl_, trait_item));
 
-            try!(markup::description(&mut file, &impl_item.docs.parts));
-            try!(markup::remarks(&mut file, &impl_item.docs.parts));
-            try!(markup::examples(&mut file, &impl_item.docs.parts));
-            try!(markup::see_also(&mut file, &impl_item.docs.parts));
+            try!(self.sections(&mut file, &impl_item.docs.parts));
         }
 
         try!(self.foot(&mut file));
@@ -48,29 +50,6 @@ impl Formatter {
     fn trait_impl_syntax<W: Write>(&mut self, file: &mut W, _item_impl: &Arc<ItemData>,
                                    impl_: &Impl, _trait_item: &Arc<ItemData>,
                                    trait_: &Type) -> Result {
-        try!(file.write_all(b"\
-            <h2>Syntax</h2>\
-            <pre>\
-                impl\
-            "));
-
-        let mut have_where_predicates = impl_.generics.where_predicates.len() > 0;
-        have_where_predicates |= try!(angle_generics(file, &impl_.generics));
-
-        if impl_.negative == Some(true) {
-            try!(file.write_all(b" !"));
-        } else {
-            try!(file.write_all(b" "));
-        }
-        try!(write_raw_type(file, trait_));
-        try!(file.write_all(b" for "));
-        try!(write_raw_type(file, &impl_.for_));
-
-        if have_where_predicates {
-            try!(file.write_all(b"\n"));
-            try!(where_predicates(file, &impl_.generics, "    "));
-        }
-
         let mut assocs: Vec<_> = Vec::new();
 
         for item in &impl_.items {
@@ -79,27 +58,98 @@ impl Formatter {
             }
         }
 
-        if assocs.len() > 0 {
-            if have_where_predicates {
-                try!(file.write_all(b"\n{\n"));
-            } else {
-                try!(file.write_all(b" {\n"));
-            }
-            for &(item, assoc) in &assocs {
-                try!(file.write_all(b"    type "));
-                try!(file.write_all(item.name.as_ref().unwrap().as_ref()));
-                try!(file.write_all(b" = "));
-                try!(write_raw_type(file, &assoc.type_));
-                try!(file.write_all(b";\n"));
+        let mut doc = Docs::new();
+        try!(layout::trait_impl(&mut doc, impl_, trait_, &assocs));
+        write_syntax(file, doc, &self.config)
+    }
+
+    /// Writes the signature and documentation of the methods defined in an impl.
+    ///
+    /// = Remarks
+    ///
+    /// Methods without documentation show the documentation inherited from the trait
+    /// method.
+    /// Methods that replace a provided method of the trait are marked as overriding.
+    fn trait_impl_methods<W: Write>(&mut self, file: &mut W, impl_: &Impl,
+                                    trait_item: &Arc<ItemData>) -> Result {
+        let mut methods: Vec<_> = Vec::new();
+        for item in &impl_.items {
+            if let Item::Method(ref m) = item.inner {
+                try!(methods.reserve(1));
+                methods.push((item, m));
             }
-            try!(file.write_all(b"}"));
         }
 
-        try!(file.write_all(b"\
-            </pre>\
-            "));
+        if methods.len() == 0 {
+            return Ok(());
+        }
+
+        methods.sort_by(|&(i1, _), &(i2, _)| i1.name.as_ref().unwrap()
+                                       .cmp(i2.name.as_ref().unwrap()));
+
+        try!(file.write_all(b"<h2>Methods</h2>"));
+
+        for &(item, method) in &methods {
+            let name = item.name.as_ref().unwrap();
+            let decl = trait_method(trait_item, name);
+            let overrides = match decl {
+                Some(m) => match m.inner {
+                    Item::Method(_) => true,
+                    _ => false,
+                },
+                _ => false,
+            };
+
+            try!(file.write_all(b"<h3>"));
+            try!(markup::raw(file, name));
+            if overrides {
+                try!(file.write_all(b" <span class=\"badge overrides\">\
+                                      Overrides a provided method</span>"));
+            }
+            try!(file.write_all(b"</h3><pre>"));
+            let mut doc = Docs::new();
+            try!(layout::fn_sig(&mut doc, method.unsaf, &method.abi, name,
+                                &method.generics, &method.self_, &method.decl));
+            try!(write_doc(file, doc, self.config.width));
+            try!(file.write_all(b"</pre>"));
+
+            match *item.inherited.borrow() {
+                Some(ref m) => {
+                    try!(file.write_all(b"<div class=\"trait_docs\">"));
+                    try!(markup::all(file, &m.docs.parts, &self.config));
+                    try!(file.write_all(b"<p class=\"inherited\">Documentation inherited \
+                                          from <a href=\"./"));
+                    try!(write_full_path(file, m));
+                    try!(file.write_all(b".html\">"));
+                    try!(markup::raw(file, trait_item.name.as_ref().unwrap()));
+                    try!(file.write_all(b"::"));
+                    try!(markup::raw(file, name));
+                    try!(file.write_all(b"</a>.</p></div>"));
+                },
+                _ => try!(markup::all(file, &item.docs.parts, &self.config)),
+            }
+        }
 
         Ok(())
     }
+}
 
+/// Returns the method of a trait with the given name.
+fn trait_method<'a>(trait_item: &'a ItemData, name: &[u8]) -> Option<&'a Arc<ItemData>> {
+    let trait_ = match trait_item.inner {
+        Item::Trait(ref t) => t,
+        _ => return None,
+    };
+    for item in &trait_.items {
+        match item.inner {
+            Item::Method(_) | Item::MethodDecl(_) => { },
+            _ => continue,
+        }
+        if let Some(ref n) = item.name {
+            if &n[..] == name {
+                return Some(item);
+            }
+        }
+    }
+    None
 }
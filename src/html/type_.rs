@@ -4,8 +4,10 @@
 
 use std::io::{Write};
 use std::iter::{IteratorExt};
+use std::rc::{Arc};
 
 use html::{path, Formatter, write_raw_type};
+use passes::{fn_page, has_page, write_full_path};
 use html::markup::{self};
 use tree::*;
 
@@ -78,7 +80,7 @@ impl Formatter {
                     </td>\
                     <td>\
                     "));
-            try!(markup::short(file, &item.docs.parts));
+            try!(markup::short(file, &item.docs.parts, &self.config));
             try!(file.write_all(b"\
                     </td>\
                 </tr>\
@@ -148,18 +150,7 @@ impl Formatter {
                 <tr>\
                     <td><code class=\"no_break\">\
                     "));
-            match method.self_ {
-                SelfTy::Static => { },
-                SelfTy::Value => { try!(file.write_all(b"self")); }
-                SelfTy::Borrowed(_, mutable) => {
-                    try!(file.write_all(b"&"));
-                    if mutable {
-                        try!(file.write_all(b"mut "));
-                    }
-                    try!(file.write_all(b"self"));
-                },
-                SelfTy::Explicit(ref t) => { try!(write_raw_type(file, t)); }
-            }
+            try!(receiver(file, method));
             try!(file.write_all(b"\
                     </code></td>\
                     <td>\
@@ -173,7 +164,7 @@ impl Formatter {
                     </td>\
                     <td>\
                     "));
-            try!(markup::short(file, &item.docs.parts));
+            try!(markup::short(file, &item.docs.parts, &self.config));
             try!(file.write_all(b"\
                     </td>\
                 </tr>\
@@ -190,6 +181,248 @@ impl Formatter {
         Ok(())
     }
 
+    /// Writes the methods that are available through the `Deref` impl of a type.
+    ///
+    /// = Remarks
+    ///
+    /// Chains of `Deref` impls are followed until a type is reached a second time.
+    /// Methods that have the same name as a method of the type or of an earlier target
+    /// in the chain are not shown because they cannot be called with the method syntax.
+    pub fn type_deref_methods<W: Write>(&mut self, file: &mut W,
+                                        item: &ItemData) -> Result {
+        let mut seen = Vec::new();
+        try!(seen.reserve(1));
+        seen.push(item.node);
+        let mut shadowed = Vec::new();
+        try!(method_names(&mut shadowed, item));
+
+        let mut next = deref_target(item);
+        while let Some((typedef, target)) = next {
+            if seen.iter().any(|&n| n == target.node) {
+                break;
+            }
+            try!(seen.reserve(1));
+            seen.push(target.node);
+            try!(self.deref_methods(file, &typedef, &target, &mut shadowed));
+            next = deref_target(&target);
+        }
+
+        Ok(())
+    }
+
+    /// [argument, shadowed]
+    /// The names of the methods of the earlier types in the chain. The methods of
+    /// `target` are added to it.
+    fn deref_methods<W: Write>(&mut self, file: &mut W, typedef: &ItemData,
+                               target: &ItemData, shadowed: &mut Vec<Vec<u8>>) -> Result {
+        let impls = target.impls.borrow();
+
+        let mut methods: Vec<_> = Vec::new();
+
+        for impl_item in &*impls {
+            if let Item::Impl(ref impl_) = impl_item.inner {
+                if impl_.trait_.is_none() {
+                    for item in &impl_.items {
+                        if let Item::Method(ref method) = item.inner {
+                            match method.self_ {
+                                SelfTy::Static => { },
+                                _ if contains(shadowed, &item.name) => { },
+                                _ => {
+                                    try!(methods.reserve(1));
+                                    methods.push((item, method));
+                                },
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        try!(method_names(shadowed, target));
+
+        if methods.len() == 0 {
+            return Ok(());
+        }
+
+        methods.sort_by(|&(i1, _), &(i2, _)| i1.name.as_ref().unwrap()
+                                       .cmp(i2.name.as_ref().unwrap()));
+
+        try!(file.write_all(b"<h2>Methods from Deref&lt;Target = "));
+        if let Item::Typedef(ref t) = typedef.inner {
+            try!(write_raw_type(file, &t.type_));
+        }
+        try!(file.write_all(b"\
+            &gt;</h2>\
+            <table>\
+                <thead>\
+                    <tr>\
+                        <th>Receiver</th>\
+                        <th>Name</th>\
+                        <th>Description</th>\
+                    </tr>\
+                </thead>\
+                <tbody>\
+                    "));
+
+        for &(item, method) in &methods {
+            try!(file.write_all(b"\
+                <tr>\
+                    <td><code class=\"no_break\">\
+                    "));
+            try!(receiver(file, method));
+            try!(file.write_all(b"\
+                    </code></td>\
+                    <td>\
+                    "));
+            // Methods of primitive types don't have pages.
+            if has_page(item) {
+                try!(file.write_all(b"<a href=\"./"));
+                try!(write_full_path(file, target));
+                try!(file.write_all(b"::"));
+                try!(file.write_all(item.name.as_ref().unwrap().as_ref()));
+                try!(file.write_all(b".html\">"));
+                try!(file.write_all(item.name.as_ref().unwrap().as_ref()));
+                try!(file.write_all(b"</a>"));
+            } else {
+                try!(file.write_all(item.name.as_ref().unwrap().as_ref()));
+            }
+            try!(file.write_all(b"\
+                    </td>\
+                    <td>\
+                    "));
+            try!(markup::short(file, &item.docs.parts, &self.config));
+            try!(file.write_all(b"\
+                    </td>\
+                </tr>\
+                "));
+        }
+
+        try!(file.write_all(b"\
+                </tbody>\
+            </table>\
+            "));
+
+        Ok(())
+    }
+
+    /// Writes the table of auto traits and whether the type implements them.
+    pub fn type_auto_traits<W: Write>(&mut self, file: &mut W,
+                                      item: &ItemData) -> Result {
+        let auto_traits = item.auto_traits.borrow();
+        if auto_traits.len() == 0 {
+            return Ok(());
+        }
+
+        try!(file.write_all(b"\
+            <h2>Auto trait implementations</h2>\
+            <table>\
+                <thead>\
+                    <tr>\
+                        <th>Trait</th>\
+                        <th>Implemented</th>\
+                    </tr>\
+                </thead>\
+                <tbody>\
+                    "));
+
+        for &(ref trait_, ref auto) in &*auto_traits {
+            try!(file.write_all(b"<tr><td><a href=\"./"));
+            try!(write_full_path(file, trait_));
+            try!(file.write_all(b".html\">"));
+            try!(file.write_all(trait_.name.as_ref().unwrap().as_ref()));
+            try!(file.write_all(b"</a></td><td>"));
+            match *auto {
+                AutoImpl::Explicit => try!(file.write_all(b"Yes (explicit impl)")),
+                AutoImpl::Bounded(ref params) if params.len() == 0 => {
+                    try!(file.write_all(b"Yes"));
+                },
+                AutoImpl::Bounded(ref params) => {
+                    try!(file.write_all(b"If <code>"));
+                    for (i, param) in params.iter().enumerate() {
+                        if i > 0 {
+                            try!(file.write_all(b", "));
+                        }
+                        try!(markup::raw(file, param));
+                        try!(file.write_all(b": "));
+                        try!(file.write_all(trait_.name.as_ref().unwrap().as_ref()));
+                    }
+                    try!(file.write_all(b"</code>"));
+                },
+                AutoImpl::Negative => try!(file.write_all(b"No")),
+                AutoImpl::Unknown => try!(file.write_all(b"Unknown")),
+            }
+            try!(file.write_all(b"</td></tr>"));
+        }
+
+        try!(file.write_all(b"\
+                </tbody>\
+            </table>\
+            "));
+
+        Ok(())
+    }
+
+    /// Writes the functions and methods that return or accept the type.
+    pub fn type_uses<W: Write>(&mut self, file: &mut W, item: &ItemData) -> Result {
+        try!(self.uses(file, "Returned by", &item.returned_by.borrow()));
+        self.uses(file, "Accepted by", &item.accepted_by.borrow())
+    }
+
+    fn uses<W: Write>(&mut self, file: &mut W, title: &str,
+                      funcs: &[Arc<ItemData>]) -> Result {
+        if funcs.len() == 0 {
+            return Ok(());
+        }
+
+        let mut pages: Vec<_> = try!(Vec::with_capacity(funcs.len()));
+        for func in funcs {
+            pages.push((try!(fn_page(func)), func));
+        }
+        pages.sort_by(|&(ref p1, _), &(ref p2, _)| p1.cmp(p2));
+
+        try!(file.write_all(b"<h2>"));
+        try!(file.write_all(title.as_bytes()));
+        try!(file.write_all(b"\
+            </h2>\
+            <table>\
+                <thead>\
+                    <tr>\
+                        <th>Function</th>\
+                        <th>Description</th>\
+                    </tr>\
+                </thead>\
+                <tbody>\
+                    "));
+
+        for &(ref page, func) in &pages {
+            try!(file.write_all(b"\
+                <tr>\
+                    <td>\
+                        <a href=\"./\
+                    "));
+            try!(file.write_all(page));
+            try!(file.write_all(b".html\">"));
+            try!(file.write_all(page));
+            try!(file.write_all(b"\
+                        </a>\
+                    </td>\
+                    <td>\
+                    "));
+            try!(markup::short(file, &func.docs.parts, &self.config));
+            try!(file.write_all(b"\
+                    </td>\
+                </tr>\
+                "));
+        }
+
+        try!(file.write_all(b"\
+                </tbody>\
+            </table>\
+            "));
+
+        Ok(())
+    }
+
     pub fn type_trait_impls<W: Write>(&mut self, mut file: &mut W,
                                       item: &ItemData) -> Result {
         let all_impls = item.impls.borrow();
@@ -261,7 +494,7 @@ impl Formatter {
                     </td>\
                     <td>\
                     "));
-            try!(markup::short(file, &trait_item.docs.parts));
+            try!(markup::short(file, &trait_item.docs.parts, &self.config));
             try!(file.write_all(b"\
                     </td>\
                 </tr>\
@@ -280,3 +513,59 @@ impl Formatter {
         Ok(())
     }
 }
+
+fn receiver<W: Write>(file: &mut W, method: &Method) -> Result {
+    match method.self_ {
+        SelfTy::Static => { },
+        SelfTy::Value => { try!(file.write_all(b"self")); }
+        SelfTy::Borrowed(_, mutable) => {
+            try!(file.write_all(b"&"));
+            if mutable {
+                try!(file.write_all(b"mut "));
+            }
+            try!(file.write_all(b"self"));
+        },
+        SelfTy::Explicit(ref t) => { try!(write_raw_type(file, t)); }
+    }
+    Ok(())
+}
+
+/// Adds the names of the inherent methods of an item that take `self` to `names`.
+fn method_names(names: &mut Vec<Vec<u8>>, item: &ItemData) -> Result {
+    for impl_item in &*item.impls.borrow() {
+        if let Item::Impl(ref impl_) = impl_item.inner {
+            if impl_.trait_.is_some() {
+                continue;
+            }
+            for method in &impl_.items {
+                if let Item::Method(ref m) = method.inner {
+                    if let SelfTy::Static = m.self_ {
+                        continue;
+                    }
+                    if let Some(ref name) = method.name {
+                        if !contains(names, &method.name) {
+                            try!(names.reserve(1));
+                            names.push(try!(name.try_to()));
+                        }
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn contains(names: &[Vec<u8>], name: &Option<Vec<u8>>) -> bool {
+    match *name {
+        Some(ref name) => names.iter().any(|n| n[..] == name[..]),
+        _ => false,
+    }
+}
+
+/// Returns the `Target` typedef of the `Deref` impl of an item and the item it points to.
+fn deref_target(item: &ItemData) -> Option<(Arc<ItemData>, Arc<ItemData>)> {
+    match *item.deref.borrow() {
+        Some((ref typedef, ref target)) => Some((typedef.add_ref(), target.add_ref())),
+        _ => None,
+    }
+}
@@ -8,12 +8,16 @@ use html::{Formatter};
 use tree::*;
 
 impl Formatter {
-    pub fn typedef(&mut self, _item: &ItemData, _typedef: &Typedef) -> Result {
+    pub fn typedef(&mut self, item: &ItemData, typedef: &Typedef) -> Result {
         let mut file: Vec<_> = Vec::new();
 
         try!(self.head(&mut file, "Typedef "));
         try!(self.h1(&mut file, "Typedef "));
 
+        try!(self.banners(&mut file, item));
+
+        try!(self.generics(&mut file, &typedef.generics, &item.docs));
+
         try!(self.foot(&mut file));
 
         try!(try!(self.file()).write_all(&file));
@@ -4,9 +4,11 @@
 
 use std::io::{Write};
 
-use html::{Formatter, where_predicates, angle_generics,  write_raw_type};
+use html::{Formatter, write_syntax, struct_};
 use html::markup::{self};
 use tree::*;
+use config::{Config};
+use layout::{self, Docs};
 
 impl Formatter {
     pub fn variant(&mut self, enum_item: &ItemData, enum_: &Enum, item: &ItemData,
@@ -16,15 +18,14 @@ impl Formatter {
         try!(self.head(&mut file, "Variant "));
         try!(self.h1(&mut file, "Variant "));
 
-        try!(markup::short(&mut file, &item.docs.parts));
+        try!(self.banners(&mut file, item));
 
-        try!(syntax(&mut file, enum_item, enum_, item, variant));
-        try!(fields(&mut file, item, variant));
+        try!(markup::short(&mut file, &item.docs.parts, &self.config));
 
-        try!(markup::description(&mut file, &item.docs.parts));
-        try!(markup::remarks(&mut file, &item.docs.parts));
-        try!(markup::examples(&mut file, &item.docs.parts));
-        try!(markup::see_also(&mut file, &item.docs.parts));
+        try!(syntax(&mut file, enum_item, enum_, item, variant, &self.config));
+        try!(fields(&mut file, item, variant, &self.config));
+
+        try!(self.sections(&mut file, &item.docs.parts));
 
         try!(self.foot(&mut file));
 
@@ -34,59 +35,21 @@ impl Formatter {
 }
 
 fn syntax<W: Write>(file: &mut W, enum_item: &ItemData, enum_: &Enum, item: &ItemData,
-                    variant: &Variant) -> Result {
-    try!(file.write_all(b"\
-        <h2>Syntax</h2>\
-        <pre>\
-            enum \
-        "));
-    try!(file.write_all(enum_item.name.as_ref().unwrap().as_ref()));
-
-    let mut have_where_predicates = enum_.generics.where_predicates.len() > 0;
-    have_where_predicates |= try!(angle_generics(file, &enum_.generics));
-
-    if have_where_predicates {
-        try!(file.write_all(b"\n"));
-        try!(where_predicates(file, &enum_.generics, "    "));
-        try!(file.write_all(b"\n{\n"));
-    } else {
-        try!(file.write_all(b" {\n"));
-    }
-
-    try!(file.write_all(b"    "));
-    try!(file.write_all(item.name.as_ref().unwrap().as_ref()));
-    match variant.kind {
-        VariantKind::CLike => { },
-        VariantKind::Tuple(ref ts) => {
-            try!(file.write_all(b"("));
-            let mut first = true;
-            for t in ts {
-                if !first {
-                    try!(file.write_all(b", "));
-                }
-                first = false;
-                try!(write_raw_type(file, t));
-            }
-            try!(file.write_all(b")"));
-        },
-        VariantKind::Struct(_) => {
-            try!(file.write_all(b"Struct variant documentation not implemented"));
-        },
-    }
-
-    try!(file.write_all(b"\
-            \n}\
-        </pre>\
-        "));
-
-    Ok(())
+                    variant: &Variant, conf: &Config) -> Result {
+    let mut doc = Docs::new();
+    try!(layout::enum_(&mut doc, enum_item.name.as_ref().unwrap(), enum_,
+                       &[(&item.name.as_ref().unwrap()[..], variant)]));
+    write_syntax(file, doc, conf)
 }
 
-fn fields<W: Write>(mut file: &mut W, item: &ItemData, variant: &Variant) -> Result {
+fn fields<W: Write>(mut file: &mut W, item: &ItemData, variant: &Variant,
+                    conf: &Config) -> Result {
     let fields = match variant.kind {
         VariantKind::CLike => return Ok(()),
         VariantKind::Tuple(ref f) => f,
-        VariantKind::Struct(_) => return Ok(()),
+        VariantKind::Struct(ref s) => {
+            return struct_::fields(file, &s.struct_type, &s.fields, &item.docs, conf);
+        },
     };
 
     if fields.len() == 0 {
@@ -110,7 +73,7 @@ fn fields<W: Write>(mut file: &mut W, item: &ItemData, variant: &Variant) -> Res
         try!(write!(file, "{}", i + 1));
         try!(file.write_all(b"</td><td>"));
         let field = try!(format!("{}", i + 1));
-        try!(markup::field_desc(file, &item.docs.parts, &field));
+        try!(markup::field_desc(file, &item.docs.parts, &field, conf));
         try!(file.write_all(b"</td></tr>"));
     }
 
@@ -2,25 +2,18 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
-//! Simple Json parser
+//! Simple pull-based Json reader and writer
 //!
-//! Not supported: Floating point numbers
+//! The reader does not build a tree. The consumer asks for the next value in the order
+//! in which it appears in the input, e.g., `object_start`, `key`, `string`, ..., and
+//! the reader checks the syntax as it goes. `value` can be used to build a `Value` tree
+//! for the cases where random access is needed.
+//!
+//! Every error contains the position of the offending token.
 
-use std::error::{Errno, InvalidArgument};
-use std::{mem};
 use std::vec::{Vec};
-use std::io::{BufRead};
-use std::parse::{Parsable};
-
-macro_rules! error {
-    ($fmt:expr) => { error!(concat!($fmt, "{}"), "") };
-    ($fmt:expr, $($arg:tt)*) => {{
-        errln!(concat!("lrs_doc: Error: ", $fmt), $($arg)*);
-        return Err(ERR);
-    }};
-}
-
-const ERR: Errno = InvalidArgument;
+use std::io::{Read, Write};
+use std::string::{ByteStr};
 
 pub type Object = Vec<(Vec<u8>, Value)>;
 pub type Array = Vec<Value>;
@@ -28,183 +21,752 @@ pub type Slice = [Value];
 pub enum Value {
     String(Vec<u8>),
     Integer(i64),
+    Float(f64),
     Object(Object),
     Array(Array),
     Boolean(bool),
     Null,
 }
 
-pub fn parse(mut data: &[u8]) -> Result<Value> {
-    Ok(Value::Object(try!(object(&mut data))))
+impl Value {
+    /// Writes the value as compact Json text.
+    pub fn write<W: Write>(&self, w: &mut W) -> Result {
+        match *self {
+            Value::String(ref s) => try!(write_string(w, s)),
+            Value::Integer(i) => { try!(write!(w, "{}", i)); },
+            Value::Float(f) => { try!(write!(w, "{}", f)); },
+            Value::Boolean(true) => { try!(w.write_all(b"true")); },
+            Value::Boolean(false) => { try!(w.write_all(b"false")); },
+            Value::Null => { try!(w.write_all(b"null")); },
+            Value::Object(ref o) => {
+                try!(w.write_all(b"{"));
+                for (i, field) in o.iter().enumerate() {
+                    if i > 0 {
+                        try!(w.write_all(b","));
+                    }
+                    try!(write_string(w, &field.0));
+                    try!(w.write_all(b":"));
+                    try!(field.1.write(w));
+                }
+                try!(w.write_all(b"}"));
+            },
+            Value::Array(ref a) => {
+                try!(w.write_all(b"["));
+                for (i, val) in a.iter().enumerate() {
+                    if i > 0 {
+                        try!(w.write_all(b","));
+                    }
+                    try!(val.write(w));
+                }
+                try!(w.write_all(b"]"));
+            },
+        }
+        Ok(())
+    }
+}
+
+/// Writes a string with all characters escaped that Json doesn't allow in strings.
+///
+/// Bytes above 0x7f are written as they are.
+fn write_string<W: Write>(w: &mut W, s: &[u8]) -> Result {
+    try!(w.write_all(b"\""));
+    let mut start = 0;
+    for i in 0..s.len() {
+        let esc: &[u8] = match s[i] {
+            b'"' => b"\\\"",
+            b'\\' => b"\\\\",
+            b'\n' => b"\\n",
+            b'\r' => b"\\r",
+            b'\t' => b"\\t",
+            c if c < 0x20 => {
+                try!(w.write_all(&s[start..i]));
+                try!(write!(w, "\\u{:04x}", c));
+                start = i + 1;
+                continue;
+            },
+            _ => continue,
+        };
+        try!(w.write_all(&s[start..i]));
+        try!(w.write_all(esc));
+        start = i + 1;
+    }
+    try!(w.write_all(&s[start..]));
+    try!(w.write_all(b"\""));
+    Ok(())
+}
+
+/// A position in the input.
+#[derive(Copy)]
+pub struct Pos {
+    /// Byte offset starting at 0.
+    pub offset: usize,
+    /// Line starting at 1.
+    pub line: usize,
+    /// Column (in bytes) starting at 1.
+    pub column: usize,
+}
+
+/// The kind of the next value.
+#[derive(Copy, Eq)]
+pub enum Kind {
+    String,
+    Number,
+    Object,
+    Array,
+    Boolean,
+    Null,
+}
+
+#[derive(Copy)]
+pub enum Number {
+    Integer(i64),
+    Float(f64),
 }
 
-fn whitespace(data: &mut &[u8]) {
-    while data.len() > 0 {
-        match data[0] {
-            b' ' | b'\t' | b'\n' | b'\r' => { data.consume(1); },
-            _ => break,
+/// A source of Json values.
+///
+/// = Remarks
+///
+/// Objects are read by calling `object_start` followed by `key` until it returns
+/// `None`. After each key, the value of the key has to be read (or skipped.)
+///
+/// Arrays are read by calling `array_start` followed by `array_next` until it returns
+/// `false`. After each `true`, the next element has to be read (or skipped.)
+pub trait Source {
+    /// Returns the position of the last token, if known.
+    fn pos(&self) -> Option<Pos>;
+
+    /// Returns the kind of the next value without consuming it.
+    fn peek(&mut self) -> Result<Kind>;
+
+    fn object_start(&mut self) -> Result;
+
+    /// Returns the next key of the current object or `None` if the object has ended.
+    fn key(&mut self) -> Result<Option<Vec<u8>>>;
+
+    fn array_start(&mut self) -> Result;
+
+    /// Returns whether the current array has another element.
+    fn array_next(&mut self) -> Result<bool>;
+
+    fn string(&mut self) -> Result<Vec<u8>>;
+    fn number(&mut self) -> Result<Number>;
+    fn boolean(&mut self) -> Result<bool>;
+    fn null(&mut self) -> Result;
+
+    fn integer(&mut self) -> Result<i64> {
+        match try!(self.number()) {
+            Number::Integer(i) => Ok(i),
+            Number::Float(f) => error_in!(self, "Expected an integer, found {}", f),
+        }
+    }
+
+    fn float(&mut self) -> Result<f64> {
+        match try!(self.number()) {
+            Number::Integer(i) => Ok(i as f64),
+            Number::Float(f) => Ok(f),
+        }
+    }
+
+    /// Skips the next value.
+    fn skip(&mut self) -> Result {
+        match try!(self.peek()) {
+            Kind::String => { try!(self.string()); },
+            Kind::Number => { try!(self.number()); },
+            Kind::Boolean => { try!(self.boolean()); },
+            Kind::Null => try!(self.null()),
+            Kind::Object => {
+                try!(self.object_start());
+                while try!(self.key()).is_some() {
+                    try!(self.skip());
+                }
+            },
+            Kind::Array => {
+                try!(self.array_start());
+                while try!(self.array_next()) {
+                    try!(self.skip());
+                }
+            },
         }
+        Ok(())
     }
 }
 
-fn value(data: &mut &[u8]) -> Result<Value> {
-    whitespace(data);
-    if data.len() == 0 { error!("Value has length 0"); }
-    let value = match data[0] {
-        b'"'               => Value::String(try!(string(data))),
-        b'-' | b'0'...b'9' => Value::Integer(try!(integer(data))),
-        b'{'               => Value::Object(try!(object(data))),
-        b'['               => Value::Array(try!(array(data))),
-        b't' | b'f'        => Value::Boolean(try!(boolean(data))),
-        b'n'               => try!(null(data)),
-        _ => error!("Value starts with unknown letter: {:?}", data[0] as char),
+/// Reads the next value into a tree.
+pub fn value<S: Source>(r: &mut S) -> Result<Value> {
+    let value = match try!(r.peek()) {
+        Kind::String => Value::String(try!(r.string())),
+        Kind::Boolean => Value::Boolean(try!(r.boolean())),
+        Kind::Null => { try!(r.null()); Value::Null },
+        Kind::Number => match try!(r.number()) {
+            Number::Integer(i) => Value::Integer(i),
+            Number::Float(f) => Value::Float(f),
+        },
+        Kind::Object => {
+            let mut object = Vec::new();
+            try!(r.object_start());
+            while let Some(key) = try!(r.key()) {
+                let val = try!(value(r));
+                try!(object.reserve(1));
+                object.push((key, val));
+            }
+            Value::Object(object)
+        },
+        Kind::Array => {
+            let mut array = Vec::new();
+            try!(r.array_start());
+            while try!(r.array_next()) {
+                let val = try!(value(r));
+                try!(array.reserve(1));
+                array.push(val);
+            }
+            Value::Array(array)
+        },
     };
     Ok(value)
 }
 
-fn object(data: &mut &[u8]) -> Result<Object> {
-    let mut pairs = Vec::new();
-
-    whitespace(data);
-    if data.len() == 0 || data[0] != b'{' { error!("Object is empty or doesn't start with {{"); }
-    data.consume(1);
-    whitespace(data);
-    if data.len() != 0 && data[0] == b'}' {
-        data.consume(1);
-        return Ok(pairs);
-    }
-
-    loop {
-        let key = try!(string(data));
-        whitespace(data);
-        if data.len() == 0 || data[0] != b':' { error!("Can't find : in object"); }
-        data.consume(1);
-        let value = try!(value(data));
-        try!(pairs.push((key, value)));
-        whitespace(data);
-        if data.len() == 0 { error!("Can't find , or }} in object"); }
-        if data[0] == b'}' { break; }
-        if data[0] == b',' { data.consume(1); }
-    }
-
-    data.consume(1);
-    Ok(pairs)
-}
-
-fn string(data: &mut &[u8]) -> Result<Vec<u8>> {
-    whitespace(data);
-    if data.len() == 0 || data[0] != b'"' { error!("String is empty or doesn't start with \""); }
-    data.consume(1);
-    let mut string = Vec::new();
-    let mut escape = false;
-
-    loop {
-        if data.len() == 0 { error!("Unterminated string"); }
-        if !escape && data[0] == b'"' { break; }
-        try!(string.reserve(1));
-
-        if escape {
-            escape = false;
-
-            match data[0] {
-                b'"' => string.push(b'"'),
-                b'\\' => string.push(b'\\'),
-                b'/' => string.push(b'/'),
-                b'b' => string.push(8),
-                b'f' => string.push(12),
-                b'n' => string.push(10),
-                b'r' => string.push(13),
-                b't' => string.push(b'\t'),
+const BUF_SIZE: usize = 4096;
+
+/// A `Source` that reads Json text from a `Read`.
+pub struct Reader<R: Read> {
+    r: R,
+    buf: [u8; BUF_SIZE],
+    start: usize,
+    end: usize,
+    eof: bool,
+
+    /// The position of the next byte.
+    next: Pos,
+    /// The position of the last token.
+    last: Pos,
+
+    /// One entry for each open object or array. `true` if no element has been read
+    /// yet.
+    first: Vec<bool>,
+}
+
+impl<R: Read> Reader<R> {
+    pub fn new(r: R) -> Reader<R> {
+        let start = Pos { offset: 0, line: 1, column: 1 };
+        Reader {
+            r: r,
+            buf: [0; BUF_SIZE],
+            start: 0,
+            end: 0,
+            eof: false,
+            next: start,
+            last: start,
+            first: Vec::new(),
+        }
+    }
+
+    fn fill(&mut self) -> Result {
+        if self.start == self.end && !self.eof {
+            self.start = 0;
+            self.end = try!(self.r.read(&mut self.buf));
+            self.eof = self.end == 0;
+        }
+        Ok(())
+    }
+
+    fn peek_byte(&mut self) -> Result<Option<u8>> {
+        try!(self.fill());
+        if self.start == self.end {
+            Ok(None)
+        } else {
+            Ok(Some(self.buf[self.start]))
+        }
+    }
+
+    fn next_byte(&mut self) -> Result<u8> {
+        match try!(self.peek_byte()) {
+            Some(b) => {
+                self.start += 1;
+                self.next.offset += 1;
+                if b == b'\n' {
+                    self.next.line += 1;
+                    self.next.column = 1;
+                } else {
+                    self.next.column += 1;
+                }
+                Ok(b)
+            },
+            _ => error_in!(self, "Unexpected end of input"),
+        }
+    }
+
+    fn whitespace(&mut self) -> Result {
+        while let Some(b) = try!(self.peek_byte()) {
+            match b {
+                b' ' | b'\t' | b'\n' | b'\r' => { try!(self.next_byte()); },
+                _ => break,
+            }
+        }
+        self.last = self.next;
+        Ok(())
+    }
+
+    /// Skips whitespace and returns the next byte without consuming it.
+    fn token(&mut self) -> Result<u8> {
+        try!(self.whitespace());
+        match try!(self.peek_byte()) {
+            Some(b) => Ok(b),
+            _ => error_in!(self, "Unexpected end of input"),
+        }
+    }
+
+    fn expect(&mut self, c: u8) -> Result {
+        let b = try!(self.token());
+        if b != c {
+            error_in!(self, "Expected {:?}, found {:?}", c as char, b as char);
+        }
+        try!(self.next_byte());
+        Ok(())
+    }
+
+    fn literal(&mut self, lit: &[u8]) -> Result {
+        try!(self.whitespace());
+        for &c in lit {
+            if try!(self.next_byte()) != c {
+                let lit: &ByteStr = lit.as_ref();
+                error_in!(self, "Invalid literal, expected {:?}", lit);
+            }
+        }
+        Ok(())
+    }
+
+    fn hex4(&mut self) -> Result<u32> {
+        let mut num = 0;
+        for _ in 0..4 {
+            let digit = match try!(self.next_byte()) {
+                b @ b'0'...b'9' => b - b'0',
+                b @ b'a'...b'f' => b - b'a' + 10,
+                b @ b'A'...b'F' => b - b'A' + 10,
+                b => error_in!(self, "Invalid hex digit in unicode escape: {:?}", b as char),
+            };
+            num = num * 16 + digit as u32;
+        }
+        Ok(num)
+    }
+
+    /// Reads the part of a `\u` escape sequence after the `\u`. Surrogate pairs are
+    /// combined into one code point.
+    fn unicode_escape(&mut self) -> Result<char> {
+        let mut num = try!(self.hex4());
+        if num >= 0xDC00 && num <= 0xDFFF {
+            error_in!(self, "Unexpected low surrogate {:x} in unicode escape", num);
+        }
+        if num >= 0xD800 && num <= 0xDBFF {
+            if try!(self.next_byte()) != b'\\' || try!(self.next_byte()) != b'u' {
+                error_in!(self, "High surrogate {:x} is not followed by a low surrogate", num);
+            }
+            let low = try!(self.hex4());
+            if low < 0xDC00 || low > 0xDFFF {
+                error_in!(self, "High surrogate {:x} is followed by {:x}", num, low);
+            }
+            num = 0x10000 + ((num - 0xD800) << 10) + (low - 0xDC00);
+        }
+        match char::from_u32(num) {
+            Some(c) => Ok(c),
+            None => error_in!(self, "Invalid code point {:x} in unicode escape", num),
+        }
+    }
+
+    /// Reads the digits of a number.
+    fn digits(&mut self, dst: &mut Vec<u8>) -> Result<usize> {
+        let mut n = 0;
+        while let Some(b) = try!(self.peek_byte()) {
+            match b {
+                b'0'...b'9' => {
+                    try!(dst.reserve(1));
+                    dst.push(try!(self.next_byte()));
+                    n += 1;
+                },
+                _ => break,
+            }
+        }
+        Ok(n)
+    }
+
+    /// Reads the text of a number and returns whether it has a fraction or exponent.
+    fn number_text(&mut self, num: &mut Vec<u8>) -> Result<bool> {
+        try!(self.whitespace());
+        if try!(self.peek_byte()) == Some(b'-') {
+            try!(num.push_all(b"-"));
+            try!(self.next_byte());
+        }
+        if try!(self.digits(num)) == 0 {
+            error_in!(self, "Number has no digits");
+        }
+        let mut float = false;
+        if try!(self.peek_byte()) == Some(b'.') {
+            float = true;
+            try!(num.push_all(b"."));
+            try!(self.next_byte());
+            if try!(self.digits(num)) == 0 {
+                error_in!(self, "Number has no digits after the decimal point");
+            }
+        }
+        match try!(self.peek_byte()) {
+            Some(b'e') | Some(b'E') => {
+                float = true;
+                try!(self.next_byte());
+                try!(num.push_all(b"e"));
+                match try!(self.peek_byte()) {
+                    Some(b'+') => { try!(self.next_byte()); },
+                    Some(b'-') => { try!(self.next_byte()); try!(num.push_all(b"-")); },
+                    _ => { },
+                }
+                if try!(self.digits(num)) == 0 {
+                    error_in!(self, "Number has no digits in the exponent");
+                }
+            },
+            _ => { },
+        }
+        Ok(float)
+    }
+}
+
+impl<R: Read> Source for Reader<R> {
+    fn pos(&self) -> Option<Pos> {
+        Some(self.last)
+    }
+
+    fn peek(&mut self) -> Result<Kind> {
+        let kind = match try!(self.token()) {
+            b'"'               => Kind::String,
+            b'-' | b'0'...b'9' => Kind::Number,
+            b'{'               => Kind::Object,
+            b'['               => Kind::Array,
+            b't' | b'f'        => Kind::Boolean,
+            b'n'               => Kind::Null,
+            b => error_in!(self, "Value starts with unknown letter: {:?}", b as char),
+        };
+        Ok(kind)
+    }
+
+    fn object_start(&mut self) -> Result {
+        try!(self.expect(b'{'));
+        try!(self.first.reserve(1));
+        self.first.push(true);
+        Ok(())
+    }
+
+    fn key(&mut self) -> Result<Option<Vec<u8>>> {
+        let first = match self.first.last() {
+            Some(&f) => f,
+            _ => error_in!(self, "Key outside of object"),
+        };
+        let b = try!(self.token());
+        if b == b'}' {
+            try!(self.next_byte());
+            self.first.pop();
+            return Ok(None);
+        }
+        if !first {
+            if b != b',' {
+                error_in!(self, "Expected , or }} in object, found {:?}", b as char);
+            }
+            try!(self.next_byte());
+        }
+        *self.first.last_mut().unwrap() = false;
+        let key = try!(self.string());
+        try!(self.expect(b':'));
+        Ok(Some(key))
+    }
+
+    fn array_start(&mut self) -> Result {
+        try!(self.expect(b'['));
+        try!(self.first.reserve(1));
+        self.first.push(true);
+        Ok(())
+    }
+
+    fn array_next(&mut self) -> Result<bool> {
+        let first = match self.first.last() {
+            Some(&f) => f,
+            _ => error_in!(self, "Element outside of array"),
+        };
+        let b = try!(self.token());
+        if b == b']' {
+            try!(self.next_byte());
+            self.first.pop();
+            return Ok(false);
+        }
+        if !first {
+            if b != b',' {
+                error_in!(self, "Expected , or ] in array, found {:?}", b as char);
+            }
+            try!(self.next_byte());
+        }
+        *self.first.last_mut().unwrap() = false;
+        Ok(true)
+    }
+
+    fn string(&mut self) -> Result<Vec<u8>> {
+        try!(self.expect(b'"'));
+        let mut string = Vec::new();
+
+        loop {
+            let b = try!(self.next_byte());
+            if b == b'"' {
+                break;
+            }
+            if b != b'\\' {
+                try!(string.reserve(1));
+                string.push(b);
+                continue;
+            }
+            let c = match try!(self.next_byte()) {
+                b'"' => b'"',
+                b'\\' => b'\\',
+                b'/' => b'/',
+                b'b' => 8,
+                b'f' => 12,
+                b'n' => 10,
+                b'r' => 13,
+                b't' => b'\t',
                 b'u' => {
-                    data.consume(1);
-                    if data.len() < 4 { error!("Unicode escape sequence too short"); }
-                    let mut num = *b"0x0000";
-                    mem::copy(&mut num[2..], &data[..4]);
-                    data.consume(4);
-
-                    let c = match char::from_u32(try!(num.parse())) {
-                        Some(c) => c,
-                        None => error!("Unicode escape sequence invalid"),
-                    };
+                    let c = try!(self.unicode_escape());
                     let encoded = c.to_utf8();
                     let len = c.len();
-                    try!(string.reserve(len));
-                    string.push_all(&encoded[..len]);
-
+                    try!(string.push_all(&encoded[..len]));
                     continue;
                 },
-                _ => error!("Unknown escape character: {:?}", data[0] as char),
+                e => error_in!(self, "Unknown escape character: {:?}", e as char),
             };
-        } else if data[0] == b'\\' {
-            escape = true;
-        } else {
-            string.push(data[0]);
+            try!(string.reserve(1));
+            string.push(c);
         }
 
-        data.consume(1);
+        Ok(string)
+    }
+
+    fn number(&mut self) -> Result<Number> {
+        let mut num = Vec::new();
+        if try!(self.number_text(&mut num)) {
+            return Ok(Number::Float(parse_float(&num)));
+        }
+        match i64::parse_bytes(&num) {
+            Ok(n) => Ok(Number::Integer(n)),
+            _ => Ok(Number::Float(parse_float(&num))),
+        }
     }
 
-    data.consume(1);
-    Ok(string)
+    fn boolean(&mut self) -> Result<bool> {
+        if try!(self.token()) == b't' {
+            try!(self.literal(b"true"));
+            Ok(true)
+        } else {
+            try!(self.literal(b"false"));
+            Ok(false)
+        }
+    }
+
+    fn null(&mut self) -> Result {
+        self.literal(b"null")
+    }
 }
 
-fn integer(data: &mut &[u8]) -> Result<i64> {
-    whitespace(data);
-    let (num, len) = try!(i64::parse_bytes_init(*data));
-    data.consume(len);
-    Ok(num)
+/// Converts the text of a valid Json number into a float.
+///
+/// = Remarks
+///
+/// The result is correctly rounded if the digits fit into 53 bits and the exponent is at
+/// most 22 in magnitude. Otherwise it can be off by a few units in the last place.
+fn parse_float(num: &[u8]) -> f64 {
+    let mut i = 0;
+    let negative = num[0] == b'-';
+    if negative {
+        i += 1;
+    }
+    let mut mantissa = 0.0;
+    let mut exp: i64 = 0;
+    while i < num.len() && num[i] >= b'0' && num[i] <= b'9' {
+        mantissa = mantissa * 10.0 + (num[i] - b'0') as f64;
+        i += 1;
+    }
+    if i < num.len() && num[i] == b'.' {
+        i += 1;
+        while i < num.len() && num[i] >= b'0' && num[i] <= b'9' {
+            mantissa = mantissa * 10.0 + (num[i] - b'0') as f64;
+            exp -= 1;
+            i += 1;
+        }
+    }
+    if i < num.len() && num[i] == b'e' {
+        i += 1;
+        let exp_negative = num[i] == b'-';
+        if exp_negative {
+            i += 1;
+        }
+        let mut e: i64 = 0;
+        while i < num.len() {
+            e = e.saturating_mul(10).saturating_add((num[i] - b'0') as i64);
+            i += 1;
+        }
+        exp = if exp_negative { exp.saturating_sub(e) } else { exp.saturating_add(e) };
+    }
+
+    // Beyond these exponents every non-zero mantissa overflows or underflows.
+    let val = if mantissa == 0.0 {
+        0.0
+    } else if exp > MAX_EXP {
+        1.0 / 0.0
+    } else if exp >= 0 {
+        mantissa * pow10(exp as u32)
+    } else if exp >= -308 {
+        mantissa / pow10(-exp as u32)
+    } else if exp >= -MAX_EXP {
+        // 10^-exp itself would overflow.
+        mantissa / 1e308 / pow10((-exp - 308) as u32)
+    } else {
+        0.0
+    };
+    if negative { -val } else { val }
 }
 
-fn array(data: &mut &[u8]) -> Result<Array> {
-    let mut array = Vec::new();
+/// The largest decimal exponent `parse_float` scales by.
+const MAX_EXP: i64 = 800;
 
-    whitespace(data);
-    if data.len() == 0 || data[0] != b'[' { error!("Array doesn't start with ["); }
-    data.consume(1);
-    whitespace(data);
-    if data.len() != 0 && data[0] == b']' {
-        data.consume(1);
-        return Ok(array);
+/// Computes `10^n` by repeated squaring. The result is exact for `n <= 22`.
+fn pow10(mut n: u32) -> f64 {
+    let mut res = 1.0;
+    let mut base = 10.0;
+    while n > 0 {
+        if n & 1 == 1 {
+            res *= base;
+        }
+        base *= base;
+        n >>= 1;
     }
+    res
+}
+
+enum Frame<'a> {
+    Object(&'a Object, usize),
+    Array(&'a Array, usize),
+}
+
+/// A `Source` that reads from an existing `Value` tree.
+pub struct ValueReader<'a> {
+    next: Option<&'a Value>,
+    frames: Vec<Frame<'a>>,
+}
 
-    loop {
-        let value = try!(value(data));
-        try!(array.reserve(1));
-        array.push(value);
-        whitespace(data);
-        if data.len() == 0 { error!("Can't find , or ] in array"); }
-        if data[0] == b']' { break; }
-        if data[0] == b',' { data.consume(1); }
+impl<'a> ValueReader<'a> {
+    pub fn new(val: &'a Value) -> ValueReader<'a> {
+        ValueReader {
+            next: Some(val),
+            frames: Vec::new(),
+        }
     }
 
-    data.consume(1);
-    Ok(array)
+    fn take(&mut self) -> Result<&'a Value> {
+        match self.next.take() {
+            Some(v) => Ok(v),
+            _ => error_in!(self, "No value to read"),
+        }
+    }
 }
 
-fn boolean(data: &mut &[u8]) -> Result<bool> {
-    const TRUE: &'static [u8] = b"true";
-    const FALSE: &'static [u8] = b"false";
+impl<'a> Source for ValueReader<'a> {
+    fn pos(&self) -> Option<Pos> {
+        None
+    }
+
+    fn peek(&mut self) -> Result<Kind> {
+        let kind = match self.next {
+            Some(&Value::String(_)) => Kind::String,
+            Some(&Value::Integer(_)) | Some(&Value::Float(_)) => Kind::Number,
+            Some(&Value::Object(_)) => Kind::Object,
+            Some(&Value::Array(_)) => Kind::Array,
+            Some(&Value::Boolean(_)) => Kind::Boolean,
+            Some(&Value::Null) => Kind::Null,
+            None => error_in!(self, "No value to read"),
+        };
+        Ok(kind)
+    }
+
+    fn object_start(&mut self) -> Result {
+        match *try!(self.take()) {
+            Value::Object(ref o) => {
+                try!(self.frames.reserve(1));
+                self.frames.push(Frame::Object(o, 0));
+                Ok(())
+            },
+            _ => error_in!(self, "Expected an object"),
+        }
+    }
+
+    fn key(&mut self) -> Result<Option<Vec<u8>>> {
+        let (obj, pos) = match self.frames.last() {
+            Some(&Frame::Object(o, pos)) => (o, pos),
+            _ => error_in!(self, "Key outside of object"),
+        };
+        if pos == obj.len() {
+            self.frames.pop();
+            return Ok(None);
+        }
+        let last = self.frames.len() - 1;
+        self.frames[last] = Frame::Object(obj, pos + 1);
+        self.next = Some(&obj[pos].1);
+        Ok(Some(try!(obj[pos].0.try_to())))
+    }
+
+    fn array_start(&mut self) -> Result {
+        match *try!(self.take()) {
+            Value::Array(ref a) => {
+                try!(self.frames.reserve(1));
+                self.frames.push(Frame::Array(a, 0));
+                Ok(())
+            },
+            _ => error_in!(self, "Expected an array"),
+        }
+    }
 
-    whitespace(data);
-    if data.len() >= TRUE.len() && &data[..TRUE.len()] == TRUE {
-        data.consume(TRUE.len());
+    fn array_next(&mut self) -> Result<bool> {
+        let (array, pos) = match self.frames.last() {
+            Some(&Frame::Array(a, pos)) => (a, pos),
+            _ => error_in!(self, "Element outside of array"),
+        };
+        if pos == array.len() {
+            self.frames.pop();
+            return Ok(false);
+        }
+        let last = self.frames.len() - 1;
+        self.frames[last] = Frame::Array(array, pos + 1);
+        self.next = Some(&array[pos]);
         Ok(true)
-    } else if data.len() >= FALSE.len() && &data[..FALSE.len()] == FALSE {
-        data.consume(FALSE.len());
-        Ok(false)
-    } else {
-        error!("Can't parse boolean");
     }
-}
 
-fn null(data: &mut &[u8]) -> Result<Value> {
-    const NULL: &'static [u8] = b"null";
+    fn string(&mut self) -> Result<Vec<u8>> {
+        match *try!(self.take()) {
+            Value::String(ref s) => s.try_to(),
+            _ => error_in!(self, "Expected a string"),
+        }
+    }
 
-    whitespace(data);
-    if data.len() >= NULL.len() && &data[..NULL.len()] == NULL {
-        data.consume(NULL.len());
-        Ok(Value::Null)
-    } else {
-        error!("Can't parse null");
+    fn number(&mut self) -> Result<Number> {
+        match *try!(self.take()) {
+            Value::Integer(i) => Ok(Number::Integer(i)),
+            Value::Float(f) => Ok(Number::Float(f)),
+            _ => error_in!(self, "Expected a number"),
+        }
+    }
+
+    fn boolean(&mut self) -> Result<bool> {
+        match *try!(self.take()) {
+            Value::Boolean(b) => Ok(b),
+            _ => error_in!(self, "Expected a boolean"),
+        }
+    }
+
+    fn null(&mut self) -> Result {
+        match *try!(self.take()) {
+            Value::Null => Ok(()),
+            _ => error_in!(self, "Expected null"),
+        }
     }
 }
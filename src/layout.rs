@@ -0,0 +1,825 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Layout of syntax blocks
+//!
+//! Signatures are built as documents in a small intermediate representation and laid out
+//! with the algorithm from Wadler's "A prettier printer". A group is printed on one line
+//! if it fits into the remaining width together with the text that follows it on the
+//! same line. Otherwise the line breaks in the group become newlines followed by the
+//! indentation of the enclosing nests.
+//!
+//! The laid out document is written to a `Sink`. Backends implement `Sink` to add their
+//! own escaping and links; `TextSink` writes plain text.
+
+use std::io::{Write};
+use std::rc::{Arc};
+use std::vec::{Vec};
+use std::bx::{Box};
+
+use tree::*;
+use export::{primitive_name};
+
+/// A document.
+pub enum Doc {
+    /// Static text without line breaks.
+    Lit(&'static [u8]),
+    /// Text without line breaks.
+    Text(Vec<u8>),
+    /// Text that refers to an item.
+    Link(Arc<ItemData>, Vec<u8>),
+    /// A space if the enclosing group is flat, a line break otherwise.
+    Line,
+    /// Nothing if the enclosing group is flat, a line break otherwise.
+    SoftLine,
+    /// A line break. The enclosing groups are never flat.
+    HardLine,
+    /// Increases the indentation of the line breaks in a document.
+    Nest(usize, Box<Doc>),
+    /// A document that is printed on one line if it fits.
+    Group(Box<Doc>),
+    Concat(Vec<Doc>),
+}
+
+/// A sequence of documents that is being built.
+pub struct Docs {
+    docs: Vec<Doc>,
+}
+
+impl Docs {
+    pub fn new() -> Docs {
+        Docs { docs: Vec::new() }
+    }
+
+    pub fn add(&mut self, doc: Doc) -> Result {
+        try!(self.docs.reserve(1));
+        self.docs.push(doc);
+        Ok(())
+    }
+
+    pub fn lit(&mut self, text: &'static [u8]) -> Result {
+        self.add(Doc::Lit(text))
+    }
+
+    pub fn text(&mut self, text: &[u8]) -> Result {
+        self.add(Doc::Text(try!(text.try_to())))
+    }
+
+    pub fn link(&mut self, item: &Arc<ItemData>, text: &[u8]) -> Result {
+        self.add(Doc::Link(item.add_ref(), try!(text.try_to())))
+    }
+
+    pub fn line(&mut self) -> Result {
+        self.add(Doc::Line)
+    }
+
+    pub fn soft_line(&mut self) -> Result {
+        self.add(Doc::SoftLine)
+    }
+
+    pub fn hard_line(&mut self) -> Result {
+        self.add(Doc::HardLine)
+    }
+
+    pub fn nest(&mut self, indent: usize, docs: Docs) -> Result {
+        self.add(Doc::Nest(indent, try!(Box::new()).set(docs.done())))
+    }
+
+    pub fn group(&mut self, docs: Docs) -> Result {
+        self.add(Doc::Group(try!(Box::new()).set(docs.done())))
+    }
+
+    pub fn done(self) -> Doc {
+        Doc::Concat(self.docs)
+    }
+}
+
+/// The output of the layout.
+pub trait Sink {
+    /// Writes text that does not contain line breaks.
+    fn text(&mut self, text: &[u8]) -> Result;
+    /// Writes text that refers to an item.
+    fn link(&mut self, item: &ItemData, text: &[u8]) -> Result;
+    /// Starts a new line with the given indentation.
+    fn newline(&mut self, indent: usize) -> Result;
+}
+
+/// A sink that writes plain text.
+pub struct TextSink<'a, W: Write+'a> {
+    pub file: &'a mut W,
+}
+
+impl<'a, W: Write> Sink for TextSink<'a, W> {
+    fn text(&mut self, text: &[u8]) -> Result {
+        self.file.write_all(text)
+    }
+
+    fn link(&mut self, _item: &ItemData, text: &[u8]) -> Result {
+        self.file.write_all(text)
+    }
+
+    fn newline(&mut self, indent: usize) -> Result {
+        try!(self.file.write_all(b"\n"));
+        for _ in 0..indent {
+            try!(self.file.write_all(b" "));
+        }
+        Ok(())
+    }
+}
+
+/// Lays out a document.
+///
+/// [argument, width]
+/// The width that groups have to fit into to be printed on one line.
+pub fn render<S: Sink>(sink: &mut S, doc: &Doc, width: usize) -> Result {
+    // (indentation, flat, document) triples in reverse order.
+    let mut stack: Vec<(usize, bool, &Doc)> = Vec::new();
+    try!(stack.reserve(1));
+    stack.push((0, false, doc));
+    let mut col = 0;
+
+    while let Some((indent, flat, doc)) = stack.pop() {
+        match *doc {
+            Doc::Lit(text) => {
+                try!(sink.text(text));
+                col += text.len();
+            },
+            Doc::Text(ref text) => {
+                try!(sink.text(text));
+                col += text.len();
+            },
+            Doc::Link(ref item, ref text) => {
+                try!(sink.link(item, text));
+                col += text.len();
+            },
+            Doc::Line if flat => {
+                try!(sink.text(b" "));
+                col += 1;
+            },
+            Doc::SoftLine if flat => { },
+            Doc::Line | Doc::SoftLine | Doc::HardLine => {
+                try!(sink.newline(indent));
+                col = indent;
+            },
+            Doc::Nest(n, ref doc) => {
+                try!(stack.reserve(1));
+                stack.push((indent + n, flat, &**doc));
+            },
+            Doc::Group(ref doc) => {
+                let rest = if col < width { width - col } else { 0 };
+                let flat = flat || try!(fits(rest, doc, &stack));
+                try!(stack.reserve(1));
+                stack.push((indent, flat, &**doc));
+            },
+            Doc::Concat(ref docs) => {
+                try!(stack.reserve(docs.len()));
+                let mut i = docs.len();
+                while i > 0 {
+                    i -= 1;
+                    stack.push((indent, flat, &docs[i]));
+                }
+            },
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns whether a flat document fits into the remaining width.
+///
+/// [argument, next]
+/// The documents that follow the document. They are included up to the next line break.
+fn fits(mut rest: usize, doc: &Doc, next: &[(usize, bool, &Doc)]) -> Result<bool> {
+    let mut stack: Vec<(bool, &Doc)> = Vec::new();
+    try!(stack.reserve(1));
+    stack.push((true, doc));
+    let mut pos = next.len();
+
+    loop {
+        let (flat, doc) = match stack.pop() {
+            Some(d) => d,
+            _ if pos > 0 => {
+                pos -= 1;
+                (next[pos].1, next[pos].2)
+            },
+            _ => return Ok(true),
+        };
+        let len = match *doc {
+            Doc::Lit(text) => text.len(),
+            Doc::Text(ref text) | Doc::Link(_, ref text) => text.len(),
+            Doc::Line if flat => 1,
+            Doc::SoftLine if flat => 0,
+            Doc::HardLine if flat => return Ok(false),
+            Doc::Line | Doc::SoftLine | Doc::HardLine => return Ok(true),
+            Doc::Nest(_, ref doc) | Doc::Group(ref doc) => {
+                try!(stack.reserve(1));
+                stack.push((flat, &**doc));
+                0
+            },
+            Doc::Concat(ref docs) => {
+                try!(stack.reserve(docs.len()));
+                let mut i = docs.len();
+                while i > 0 {
+                    i -= 1;
+                    stack.push((flat, &docs[i]));
+                }
+                0
+            },
+        };
+        if len > rest {
+            return Ok(false);
+        }
+        rest -= len;
+    }
+}
+
+/// Appends a list whose elements are separated by commas and line breaks.
+///
+/// = Remarks
+///
+/// The list is enclosed in `open` and `close`. If it doesn't fit on one line, every
+/// element is placed on its own line and indented.
+fn list<T, F>(d: &mut Docs, open: &'static [u8], close: &'static [u8], elements: &[T],
+              mut f: F) -> Result
+    where F: FnMut(&mut Docs, &T) -> Result,
+{
+    if elements.len() == 0 {
+        try!(d.lit(open));
+        return d.lit(close);
+    }
+
+    let mut inner = Docs::new();
+    try!(inner.soft_line());
+    for (i, el) in elements.iter().enumerate() {
+        if i > 0 {
+            try!(inner.lit(b","));
+            try!(inner.line());
+        }
+        try!(f(&mut inner, el));
+    }
+
+    let mut group = Docs::new();
+    try!(group.lit(open));
+    try!(group.nest(4, inner));
+    try!(group.soft_line());
+    try!(group.lit(close));
+    d.group(group)
+}
+
+/// Appends a block of items that are enclosed in braces.
+///
+/// [argument, after_where]
+/// Whether the block follows a where clause. The opening brace is then placed on its
+/// own line.
+///
+/// [argument, body]
+/// The items of the block. Each item has to start with a line break.
+fn block(d: &mut Docs, after_where: bool, body: Docs) -> Result {
+    if after_where {
+        try!(d.hard_line());
+        try!(d.lit(b"{"));
+    } else {
+        try!(d.lit(b" {"));
+    }
+    try!(d.nest(4, body));
+    try!(d.hard_line());
+    d.lit(b"}")
+}
+
+/// Appends a type.
+pub fn ty(d: &mut Docs, t: &Type) -> Result {
+    match *t {
+        Type::ResolvedPath(ref p) => {
+            let mut first = !p.path.global;
+            for (i, segment) in p.path.segments.iter().enumerate() {
+                if !first {
+                    try!(d.lit(b"::"));
+                }
+                first = false;
+                let last = i == p.path.segments.len() - 1;
+                match *p.item.borrow() {
+                    Some(ref item) if last => try!(d.link(item, &segment.name)),
+                    _ => try!(d.text(&segment.name)),
+                }
+                match segment.params {
+                    PathParameters::AngleBracketed(ref a) => {
+                        if a.lifetimes.len() + a.ty_params.len() + a.bindings.len() > 0 {
+                            try!(d.lit(b"<"));
+                            try!(angle_params(d, &a.lifetimes, &a.ty_params,
+                                              &a.bindings));
+                            try!(d.lit(b">"));
+                        }
+                    },
+                    PathParameters::Parenthesized(ref p) => {
+                        try!(d.lit(b"("));
+                        try!(angle_params(d, &[], &p.args, &[]));
+                        try!(d.lit(b")"));
+                        if let Some(ref rv) = p.return_value {
+                            try!(d.lit(b" -> "));
+                            try!(ty(d, rv));
+                        }
+                    },
+                }
+            }
+        },
+        Type::Generic(ref g) => try!(d.text(&g.name)),
+        Type::Primitive(p) => try!(d.lit(primitive_name(p))),
+        Type::BareFunction(_) => { },
+        Type::Tuple(ref ts) => {
+            try!(d.lit(b"("));
+            for (i, t) in ts.fields.iter().enumerate() {
+                if i > 0 {
+                    try!(d.lit(b", "));
+                }
+                try!(ty(d, t));
+            }
+            if ts.fields.len() == 1 {
+                try!(d.lit(b","));
+            }
+            try!(d.lit(b")"));
+        },
+        Type::Slice(ref s) => {
+            try!(d.lit(b"["));
+            try!(ty(d, &s.ty));
+            try!(d.lit(b"]"));
+        },
+        Type::Array(ref a) => {
+            try!(d.lit(b"["));
+            try!(ty(d, &a.ty));
+            try!(d.lit(b"; "));
+            try!(d.text(&a.initializer));
+            try!(d.lit(b"]"));
+        },
+        Type::Bottom => try!(d.lit(b"!")),
+        Type::Pointer(ref p) => {
+            match p.mutable {
+                true => try!(d.lit(b"*mut ")),
+                false => try!(d.lit(b"*const ")),
+            }
+            try!(ty(d, &p.ty));
+        },
+        Type::Ref(ref r) => {
+            try!(d.lit(b"&"));
+            if let Some(ref lt) = r.lifetime {
+                try!(d.text(lt));
+                try!(d.lit(b" "));
+            }
+            if r.mutable {
+                try!(d.lit(b"mut "));
+            }
+            try!(ty(d, &r.ty));
+        },
+        Type::UfcsPath(ref u) => {
+            try!(d.lit(b"<"));
+            try!(ty(d, &u.self_ty));
+            try!(d.lit(b" as "));
+            try!(ty(d, &u.trait_));
+            try!(d.lit(b">::"));
+            try!(d.text(&u.target));
+        },
+        Type::Infer => try!(d.lit(b"_")),
+        Type::HkltBound(_) => { },
+    }
+    Ok(())
+}
+
+fn angle_params(d: &mut Docs, lts: &[Vec<u8>], types: &[Type],
+                bindings: &[TypeBinding]) -> Result {
+    let mut first = true;
+    for lt in lts {
+        if !first {
+            try!(d.lit(b", "));
+        }
+        first = false;
+        try!(d.text(lt));
+    }
+    for t in types {
+        if !first {
+            try!(d.lit(b", "));
+        }
+        first = false;
+        try!(ty(d, t));
+    }
+    for b in bindings {
+        if !first {
+            try!(d.lit(b", "));
+        }
+        first = false;
+        try!(d.text(&b.name));
+        try!(d.lit(b" = "));
+        try!(ty(d, &b.ty));
+    }
+    Ok(())
+}
+
+/// Appends bounds separated by `+`.
+pub fn bounds(d: &mut Docs, bounds: &[TyParamBound]) -> Result {
+    for (i, bound) in bounds.iter().enumerate() {
+        if i > 0 {
+            try!(d.lit(b" + "));
+        }
+        match *bound {
+            TyParamBound::Lifetime(ref l) => try!(d.text(l)),
+            TyParamBound::Trait(ref t) => {
+                if t.maybe {
+                    try!(d.lit(b"?"));
+                }
+                try!(ty(d, &t.trait_.trait_));
+                for lt in &t.trait_.lifetimes {
+                    try!(d.lit(b"+"));
+                    try!(d.text(lt));
+                }
+            },
+        }
+    }
+    Ok(())
+}
+
+/// Appends the lifetimes and type parameters of an item.
+///
+/// = Remarks
+///
+/// The bounds of the type parameters are part of the where clause.
+pub fn generics(d: &mut Docs, generics: &Generics) -> Result {
+    let num = generics.lifetimes.len() + generics.type_params.len();
+    if num == 0 {
+        return Ok(());
+    }
+
+    let mut params = try!(Vec::with_capacity(num));
+    for lt in &generics.lifetimes {
+        params.push((Some(lt), None));
+    }
+    for param in &generics.type_params {
+        params.push((None, Some(param)));
+    }
+
+    list(d, b"<", b">", &params, |d, &(lt, param)| {
+        if let Some(lt) = lt {
+            try!(d.text(lt));
+        }
+        if let Some(param) = param {
+            try!(d.text(&param.name));
+            if let Some(ref t) = param.default {
+                try!(d.lit(b" = "));
+                try!(ty(d, t));
+            }
+        }
+        Ok(())
+    })
+}
+
+/// Appends the where clause of an item on a new line.
+///
+/// [return_value]
+/// Returns whether the item has a where clause.
+pub fn where_clause(d: &mut Docs, generics: &Generics) -> Result<bool> {
+    let mut preds = Docs::new();
+    let mut first = true;
+    for param in &generics.type_params {
+        if param.bounds.len() == 0 {
+            continue;
+        }
+        if !first {
+            try!(preds.line());
+        }
+        first = false;
+        try!(preds.text(&param.name));
+        try!(preds.lit(b": "));
+        try!(bounds(&mut preds, &param.bounds));
+        try!(preds.lit(b","));
+    }
+    for pred in &generics.where_predicates {
+        if !first {
+            try!(preds.line());
+        }
+        first = false;
+        match *pred {
+            WherePredicate::Bound(ref b) => {
+                try!(ty(&mut preds, &b.ty));
+                try!(preds.lit(b": "));
+                try!(bounds(&mut preds, &b.bounds));
+            },
+            WherePredicate::Region(ref r) => {
+                try!(preds.text(&r.lt));
+                try!(preds.lit(b": "));
+                for (i, lt) in r.bounds.iter().enumerate() {
+                    if i > 0 {
+                        try!(preds.lit(b"+"));
+                    }
+                    try!(preds.text(lt));
+                }
+            },
+            WherePredicate::Eq(ref e) => {
+                try!(ty(&mut preds, &e.lhs));
+                try!(preds.lit(b" = "));
+                try!(ty(&mut preds, &e.rhs));
+            },
+        }
+        try!(preds.lit(b","));
+    }
+    if first {
+        return Ok(false);
+    }
+
+    let mut clause = Docs::new();
+    try!(clause.hard_line());
+    try!(clause.lit(b"where "));
+    let mut group = Docs::new();
+    try!(group.group(preds));
+    try!(clause.nest(6, group));
+    try!(d.nest(4, clause));
+    Ok(true)
+}
+
+fn abi(d: &mut Docs, abi: &Abi) -> Result {
+    match *abi {
+        Abi::Rust => Ok(()),
+        Abi::C => d.lit(b"extern "),
+        Abi::System => d.lit(b"extern \"system\" "),
+        Abi::RustIntrinsic => d.lit(b"extern \"rust-intrinsic\" "),
+        Abi::RustCall => d.lit(b"extern \"rust-call\" "),
+    }
+}
+
+/// Appends the signature of a function or method.
+pub fn fn_sig(d: &mut Docs, unsaf: bool, fn_abi: &Abi, name: &[u8], gen: &Generics,
+              self_: &SelfTy, decl: &FnDecl) -> Result {
+    if unsaf {
+        try!(d.lit(b"unsafe "));
+    }
+    try!(abi(d, fn_abi));
+    try!(d.lit(b"fn "));
+    try!(d.text(name));
+    try!(generics(d, gen));
+
+    let has_self = match *self_ {
+        SelfTy::Static => false,
+        _ => true,
+    };
+    let mut args = try!(Vec::with_capacity(decl.inputs.len() + 1));
+    if has_self {
+        args.push(None);
+    }
+    for arg in &decl.inputs {
+        args.push(Some(arg));
+    }
+    try!(list(d, b"(", b")", &args, |d, &arg| {
+        match arg {
+            Some(arg) => {
+                try!(d.text(&arg.name));
+                try!(d.lit(b": "));
+                ty(d, &arg.type_)
+            },
+            _ => self_arg(d, self_),
+        }
+    }));
+
+    match decl.output {
+        FuncRetTy::NoReturn => try!(d.lit(b" -> !")),
+        FuncRetTy::Return(ref t) => {
+            try!(d.lit(b" -> "));
+            try!(ty(d, t));
+        },
+        FuncRetTy::Unit => { },
+    }
+
+    try!(where_clause(d, gen));
+    Ok(())
+}
+
+fn self_arg(d: &mut Docs, self_: &SelfTy) -> Result {
+    match *self_ {
+        SelfTy::Static => Ok(()),
+        SelfTy::Value => d.lit(b"self"),
+        SelfTy::Borrowed(ref lt, mutable) => {
+            try!(d.lit(b"&"));
+            if let Some(ref lt) = *lt {
+                try!(d.text(lt));
+                try!(d.lit(b" "));
+            }
+            if mutable {
+                try!(d.lit(b"mut "));
+            }
+            d.lit(b"self")
+        },
+        SelfTy::Explicit(ref t) => {
+            try!(d.lit(b"self: "));
+            ty(d, t)
+        },
+    }
+}
+
+/// Appends the signature of a function or method item.
+///
+/// [return_value]
+/// Returns `false` if the item is neither a function nor a method.
+pub fn item_fn_sig(d: &mut Docs, item: &ItemData) -> Result<bool> {
+    let name = match item.name {
+        Some(ref n) => &n[..],
+        _ => return Ok(false),
+    };
+    match item.inner {
+        Item::Func(ref f) | Item::ExternFunc(ref f) => {
+            try!(fn_sig(d, f.unsaf, &f.abi, name, &f.generics, &SelfTy::Static, &f.decl));
+        },
+        Item::Method(ref m) | Item::MethodDecl(ref m) => {
+            try!(fn_sig(d, m.unsaf, &m.abi, name, &m.generics, &m.self_, &m.decl));
+        },
+        _ => return Ok(false),
+    }
+    Ok(true)
+}
+
+/// Appends the fields of a tuple struct or tuple variant.
+fn tuple_fields(d: &mut Docs, fields: &[Arc<ItemData>]) -> Result {
+    list(d, b"(", b")", fields, |d, item| {
+        match item.inner {
+            Item::StructField(StructField::Typed(ref t)) => ty(d, t),
+            _ => d.lit(b"/* */"),
+        }
+    })
+}
+
+/// Appends the fields of a struct or struct variant.
+fn named_fields(fields: &[Arc<ItemData>], private_fields: bool) -> Result<Docs> {
+    let mut body = Docs::new();
+    let mut have_hidden = private_fields;
+    for item in fields {
+        match item.inner {
+            Item::StructField(StructField::Typed(ref t)) => {
+                try!(body.hard_line());
+                try!(body.text(item.name.as_ref().unwrap()));
+                try!(body.lit(b": "));
+                try!(ty(&mut body, t));
+                try!(body.lit(b","));
+            },
+            _ => have_hidden = true,
+        }
+    }
+    if have_hidden {
+        try!(body.hard_line());
+        try!(body.lit(b"/* private fields */"));
+    }
+    Ok(body)
+}
+
+/// Appends the definition of a struct.
+pub fn struct_(d: &mut Docs, name: &[u8], strukt: &Struct) -> Result {
+    try!(d.lit(b"struct "));
+    try!(d.text(name));
+    try!(generics(d, &strukt.generics));
+    if strukt.struct_type == StructType::Tuple {
+        try!(tuple_fields(d, &strukt.fields));
+    }
+    let has_where = try!(where_clause(d, &strukt.generics));
+    if strukt.struct_type == StructType::Plain {
+        try!(block(d, has_where, try!(named_fields(&strukt.fields, false))));
+    }
+    Ok(())
+}
+
+/// Appends the definition of an enum with the given named variants.
+pub fn enum_(d: &mut Docs, name: &[u8], enum_: &Enum,
+             variants: &[(&[u8], &Variant)]) -> Result {
+    try!(d.lit(b"enum "));
+    try!(d.text(name));
+    try!(generics(d, &enum_.generics));
+    let has_where = try!(where_clause(d, &enum_.generics));
+
+    let mut body = Docs::new();
+    for &(name, variant) in variants {
+        try!(body.hard_line());
+        try!(body.text(name));
+        match variant.kind {
+            VariantKind::CLike => { },
+            VariantKind::Tuple(ref ts) => {
+                try!(list(&mut body, b"(", b")", ts, |d, t| ty(d, t)));
+            },
+            VariantKind::Struct(ref s) => {
+                if s.struct_type == StructType::Tuple {
+                    try!(tuple_fields(&mut body, &s.fields));
+                } else {
+                    try!(block(&mut body, false,
+                               try!(named_fields(&s.fields, s.private_fields))));
+                }
+            },
+        }
+        try!(body.lit(b","));
+    }
+    block(d, has_where, body)
+}
+
+/// Appends the definition of a trait.
+pub fn trait_(d: &mut Docs, name: &[u8], trait_: &Trait,
+              assocs: &[(&Arc<ItemData>, &AssocType)],
+              required: &[(&Arc<ItemData>, &Method)],
+              provided: &[(&Arc<ItemData>, &Method)]) -> Result {
+    if trait_.unsaf {
+        try!(d.lit(b"unsafe "));
+    }
+    try!(d.lit(b"trait "));
+    try!(d.text(name));
+    try!(generics(d, &trait_.generics));
+    if trait_.bounds.len() > 0 {
+        try!(d.lit(b" : "));
+        try!(bounds(d, &trait_.bounds));
+    }
+    let has_where = try!(where_clause(d, &trait_.generics));
+
+    if assocs.len() + required.len() + provided.len() == 0 {
+        return Ok(());
+    }
+
+    let mut body = Docs::new();
+    for &(item, assoc) in assocs {
+        try!(body.hard_line());
+        try!(body.lit(b"type "));
+        try!(body.text(item.name.as_ref().unwrap()));
+        if assoc.bounds.len() > 0 {
+            try!(body.lit(b": "));
+            try!(bounds(&mut body, &assoc.bounds));
+        }
+        try!(body.lit(b";"));
+    }
+    let mut first = assocs.len() == 0;
+    try!(trait_methods(&mut body, &mut first, b"/* Required methods */", required));
+    try!(trait_methods(&mut body, &mut first, b"/* Provided methods */", provided));
+    block(d, has_where, body)
+}
+
+/// Appends a section of the methods in a trait definition.
+///
+/// [argument, first]
+/// Whether nothing precedes the section in the body of the trait.
+fn trait_methods(body: &mut Docs, first: &mut bool, comment: &'static [u8],
+                 methods: &[(&Arc<ItemData>, &Method)]) -> Result {
+    if methods.len() == 0 {
+        return Ok(());
+    }
+    if !*first {
+        try!(body.hard_line());
+    }
+    *first = false;
+    try!(body.hard_line());
+    try!(body.lit(comment));
+    for &(item, m) in methods {
+        try!(body.hard_line());
+        try!(fn_sig(body, m.unsaf, &m.abi, item.name.as_ref().unwrap(), &m.generics,
+                    &m.self_, &m.decl));
+        try!(body.lit(b";"));
+    }
+    Ok(())
+}
+
+/// Appends the header of an impl.
+///
+/// [argument, trait_]
+/// The implemented trait or `None` for an inherent impl.
+///
+/// [return_value]
+/// Returns whether the impl has a where clause.
+pub fn impl_header(d: &mut Docs, impl_: &Impl, trait_: Option<&Type>) -> Result<bool> {
+    try!(d.lit(b"impl"));
+    try!(generics(d, &impl_.generics));
+    try!(d.lit(b" "));
+    if let Some(trait_) = trait_ {
+        if impl_.negative == Some(true) {
+            try!(d.lit(b"!"));
+        }
+        try!(ty(d, trait_));
+        try!(d.lit(b" for "));
+    }
+    try!(ty(d, &impl_.for_));
+    where_clause(d, &impl_.generics)
+}
+
+/// Appends a trait impl with the given associated types.
+pub fn trait_impl(d: &mut Docs, impl_: &Impl, trait_: &Type,
+                  assocs: &[(&Arc<ItemData>, &Typedef)]) -> Result {
+    let has_where = try!(impl_header(d, impl_, Some(trait_)));
+    if assocs.len() == 0 {
+        return Ok(());
+    }
+
+    let mut body = Docs::new();
+    for &(item, assoc) in assocs {
+        try!(body.hard_line());
+        try!(body.lit(b"type "));
+        try!(body.text(item.name.as_ref().unwrap()));
+        try!(body.lit(b" = "));
+        try!(ty(&mut body, &assoc.type_));
+        try!(body.lit(b";"));
+    }
+    block(d, has_where, body)
+}
+
+/// Appends a method in the block of its inherent impl.
+pub fn impl_method(d: &mut Docs, impl_: &Impl, name: &[u8], m: &Method) -> Result {
+    let has_where = try!(impl_header(d, impl_, None));
+    let mut body = Docs::new();
+    try!(body.hard_line());
+    try!(fn_sig(&mut body, m.unsaf, &m.abi, name, &m.generics, &m.self_, &m.decl));
+    block(d, has_where, body)
+}
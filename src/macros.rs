@@ -27,3 +27,33 @@ macro_rules! warning {
         errln!(concat!("lrs_doc: Warning: ", $fmt), $($arg)*);
     }};
 }
+
+/// Print an error with an optional `json::Pos`.
+macro_rules! error_at {
+    ($pos:expr, $fmt:expr, $($arg:tt)*) => {{
+        match $pos {
+            Some(p) => errln!(concat!("lrs_doc: Error: {}:{} (byte {}): ", $fmt), p.line,
+                              p.column, p.offset, $($arg)*),
+            None => errln!(concat!("lrs_doc: Error: ", $fmt), $($arg)*),
+        }
+    }};
+}
+
+/// Print an error and return `InvalidArgument`.
+macro_rules! error {
+    ($fmt:expr) => { error!(concat!($fmt, "{}"), "") };
+    ($fmt:expr, $($arg:tt)*) => {{
+        errln!(concat!("lrs_doc: Error: ", $fmt), $($arg)*);
+        return Err(::std::error::InvalidArgument);
+    }};
+}
+
+/// Print an error at the current position of the reader `$r` and return
+/// `InvalidArgument`.
+macro_rules! error_in {
+    ($r:expr, $fmt:expr) => { error_in!($r, concat!($fmt, "{}"), "") };
+    ($r:expr, $fmt:expr, $($arg:tt)*) => {{
+        error_at!($r.pos(), $fmt, $($arg)*);
+        return Err(::std::error::InvalidArgument);
+    }};
+}
@@ -7,6 +7,9 @@
 #![feature(default_type_parameter_fallback)]
 
 use std::file::{File};
+use std::string::{ByteStr};
+use std::util::{memchr};
+use std::{env};
 
 #[macro_use] mod macros;
 mod json;
@@ -15,14 +18,241 @@ mod parse;
 mod hashmap;
 mod html;
 mod markup;
+mod markdown;
 mod passes;
+mod auto_trait;
+mod cfg;
+mod merge;
+mod export;
+mod fmt_doc;
+mod config;
+mod search;
+mod layout;
+
+enum Mode {
+    /// Generate the html documentation.
+    Html,
+    /// List deprecated items used in public signatures.
+    Deprecated,
+    /// Write the processed tree as Json.
+    Json,
+    /// Report doc comments that are not in canonical form.
+    FmtDoc,
+    /// List the functions whose signatures match a query.
+    Search,
+}
 
 fn main() {
-    let mut vec: Vec<_> = Vec::new();
-    let file = tryerr!(File::open_read("doc.json"), "Could not open doc.json");
-    tryerr!(vec.read_to_eof(file), "Could not read doc.json");
-    let json = tryerr!(json::parse(&vec), "Could not parse JSON");
-    let krate = tryerr!(parse::parse(&json), "Could not parse AST");
-    passes::run(&krate);
-    tryerr!(html::create(krate), "Could not create html");
+    let mut mode = Mode::Html;
+    // (target, path) pairs given as `target:name=path`.
+    let mut targets = Vec::new();
+    // Paths given to `check`.
+    let mut checks = Vec::new();
+    let mut checking = false;
+    // Paths given to `roundtrip`, or `random=count` to check generated documents.
+    let mut roundtrips = Vec::new();
+    let mut roundtripping = false;
+    // Paths given to `convert`.
+    let mut conversions = Vec::new();
+    let mut converting = false;
+    // Paths given to `render`.
+    let mut renders = Vec::new();
+    let mut rendering = false;
+    // Whether `fmt-doc` writes a patch.
+    let mut patch = false;
+    // The query given to `search --sig`.
+    let mut sig_query = None;
+    let mut reading_sig = false;
+
+    // The configuration file is read first so that the arguments override it.
+    let mut config = tryerr!(config::Config::new(), "Out of memory");
+    let mut config_path = None;
+    for arg in env::args().skip(1) {
+        let arg: &[u8] = arg.as_ref();
+        if arg.starts_with(b"config=") {
+            config_path = Some(&arg[b"config=".len()..]);
+        }
+    }
+    match config_path {
+        Some(path) => tryerr!(config.load(path, true),
+                              "Could not load the configuration"),
+        _ => tryerr!(config.load(config::FILE.as_bytes(), false),
+                     "Could not load the configuration"),
+    }
+
+    for arg in env::args().skip(1) {
+        let arg: &[u8] = arg.as_ref();
+        if checking {
+            checks.push(arg);
+            continue;
+        }
+        if roundtripping {
+            roundtrips.push(arg);
+            continue;
+        }
+        if converting {
+            conversions.push(arg);
+            continue;
+        }
+        if rendering {
+            renders.push(arg);
+            continue;
+        }
+        if reading_sig {
+            sig_query = Some(arg);
+            reading_sig = false;
+            continue;
+        }
+        match arg {
+            b"deprecated" => mode = Mode::Deprecated,
+            b"json" => mode = Mode::Json,
+            b"check" => checking = true,
+            b"fmt-doc" => mode = Mode::FmtDoc,
+            b"patch" => patch = true,
+            b"roundtrip" => roundtripping = true,
+            b"convert" => converting = true,
+            b"render" => rendering = true,
+            b"search" => mode = Mode::Search,
+            b"--sig" => reading_sig = true,
+            b"warn_sections" => config.sections.warn_unknown = true,
+            _ if arg.starts_with(b"config=") => { },
+            _ if arg.starts_with(b"target:") => {
+                let target = &arg[b"target:".len()..];
+                let pos = match memchr(target, b'=') {
+                    Some(p) => p,
+                    _ => {
+                        let arg: &ByteStr = arg.as_ref();
+                        errexit!("lrs_doc: Expected target:name=path, found {:?}", arg);
+                    },
+                };
+                targets.push((&target[..pos], &target[pos+1..]));
+            },
+            _ => {
+                let known = match memchr(arg, b'=') {
+                    Some(p) => tryerr!(config.set(&arg[..p], &arg[p+1..]),
+                                       "Invalid argument"),
+                    _ => false,
+                };
+                if !known {
+                    let arg: &ByteStr = arg.as_ref();
+                    errexit!("lrs_doc: Unknown argument {:?}", arg);
+                }
+            },
+        }
+    }
+
+    if checking {
+        for &path in &checks {
+            check(path, &config);
+        }
+        return;
+    }
+
+    if roundtripping {
+        for &path in &roundtrips {
+            if path.starts_with(b"random=") {
+                match markup::number(&path[b"random=".len()..]) {
+                    Some(n) => fmt_doc::random(n),
+                    _ => errexit!("lrs_doc: Expected random=count"),
+                }
+            } else {
+                fmt_doc::roundtrip(path);
+            }
+        }
+        return;
+    }
+
+    let mut env = tryerr!(config.env(), "Out of memory");
+
+    if converting {
+        let mut errors = false;
+        for &path in &conversions {
+            errors |= fmt_doc::convert(path, &env);
+        }
+        if errors {
+            errexit!("lrs_doc: The documents contain errors");
+        }
+        return;
+    }
+
+    if rendering {
+        let mut errors = false;
+        for &path in &renders {
+            errors |= html::markup::render(path, &env, &config);
+        }
+        if errors {
+            errexit!("lrs_doc: The documents contain errors");
+        }
+        return;
+    }
+
+    let krate = if targets.len() > 0 {
+        let mut docs = Vec::new();
+        for &(target, path) in &targets {
+            let json = read_json(path);
+            docs.push((tryerr!(target.try_to(), "Out of memory"), json));
+        }
+        let json = tryerr!(merge::merge(docs), "Could not merge the targets");
+        tryerr!(parse::crate_vars(&mut json::ValueReader::new(&json), &mut env),
+                "Could not parse AST");
+        tryerr!(parse::parse(&mut json::ValueReader::new(&json), env, &config.crate_name),
+                "Could not parse AST")
+    } else {
+        let input: &ByteStr = config.input.as_ref();
+        let file = tryerr!(File::open_read(input), "Could not open {:?}", input);
+        tryerr!(parse::crate_vars(&mut json::Reader::new(file), &mut env),
+                "Could not parse AST");
+        let file = tryerr!(File::open_read(input), "Could not open {:?}", input);
+        tryerr!(parse::parse(&mut json::Reader::new(file), env, &config.crate_name),
+                "Could not parse AST")
+    };
+    let errors = tryerr!(passes::run(&krate), "Could not process the crate");
+    match mode {
+        Mode::Html => tryerr!(html::create(krate, config), "Could not create html"),
+        Mode::Deprecated => passes::deprecated_report(&krate),
+        Mode::Json => tryerr!(export::create(&krate, &config), "Could not create json"),
+        Mode::FmtDoc => tryerr!(fmt_doc::run(&krate, patch, &config),
+                                "Could not format the docs"),
+        Mode::Search => match sig_query {
+            Some(query) => tryerr!(search::run(&krate, query, &config),
+                                   "Could not search"),
+            _ => errexit!("lrs_doc: search requires --sig QUERY"),
+        },
+    }
+    if errors > 0 {
+        errexit!("lrs_doc: {} errors in the documentation", errors);
+    }
+}
+
+/// Parses a file and reports the number of items that were found, how the layout was
+/// read, and what was skipped.
+fn check(path: &[u8], config: &config::Config) {
+    let path: &ByteStr = path.as_ref();
+    let mut env = tryerr!(config.env(), "Out of memory");
+    let file = tryerr!(File::open_read(path), "Could not open {:?}", path);
+    tryerr!(parse::crate_vars(&mut json::Reader::new(file), &mut env),
+            "Could not parse {:?}", path);
+    let file = tryerr!(File::open_read(path), "Could not open {:?}", path);
+    let mut reader = json::Reader::new(file);
+    let parsed = parse::parse_report(&mut reader, env, &config.crate_name);
+    let (krate, report) = tryerr!(parsed, "Could not parse {:?}", path);
+    tryerr!(passes::run(&krate), "Could not process the crate");
+    println!("{:?}: schema {}, {} items", path, report.schema.version,
+             passes::count_items(&krate));
+    for line in &tryerr!(report.schema.mapping(), "Out of memory") {
+        println!("    {}", line);
+    }
+    for kind in &report.kinds {
+        println!("    skipped item kind {:?}", kind.as_str());
+    }
+    for &(obj, ref field) in &report.fields {
+        println!("    skipped field {:?} on {}", field.as_str(), obj);
+    }
+}
+
+fn read_json(path: &[u8]) -> json::Value {
+    let path: &ByteStr = path.as_ref();
+    let file = tryerr!(File::open_read(path), "Could not open {:?}", path);
+    let mut reader = json::Reader::new(file);
+    tryerr!(json::value(&mut reader), "Could not parse JSON in {:?}", path)
 }
@@ -0,0 +1,1162 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Markdown doc comments
+//!
+//! This parses the rustdoc flavor of Markdown into the document structure of the lrs
+//! markup language:
+//!
+//! * `#` headers and setext headers become section headers. Headers in nested
+//!   content become bold text.
+//! * Fenced and indented code blocks become code blocks. Lines starting with `# ` are
+//!   removed from rust code blocks.
+//! * `*`, `-`, and `+` lists become unordered lists and `1.` lists become ordered
+//!   lists.
+//! * Block quotes become blocks with the `quote` attribute.
+//! * Tables become tables with a header row and the alignment of the delimiter row.
+//! * `**`, `__`, `*`, `_`, and `~~` become bold, italic, and strikethrough text.
+//! * Inline links, reference links, autolinks, and images become links. Intra-doc
+//!   links such as ``[`lrs::vec::Vec`]`` become links if the path starts with the crate
+//!   name or `crate`. Other intra-doc links, e.g., ``[`Vec::new`]``, become code.
+//!
+//! Inline html is kept as text.
+
+use std::bx::{Box};
+use std::vec::{Vec};
+
+use markup::{Document, Part, BlockData, Block, Attribute, ListKind, ListEl, Table,
+             TableRow, TableCell, TableCol, Span, Align, TextBlock, Text, TextAttr,
+             Syntax};
+
+#[derive(Copy)]
+struct Line<'a> {
+    /// The line in the document, starting at 1.
+    no: usize,
+    text: &'a [u8],
+}
+
+/// A link reference definition `[label]: target`.
+struct LinkRef {
+    /// The lowercase label.
+    label: Vec<u8>,
+    target: Vec<u8>,
+}
+
+/// Parses a document.
+///
+/// [argument, krate]
+/// The name of the crate. Intra-doc links whose path starts with it become links.
+pub fn parse(input: &[u8], krate: &[u8]) -> Result<Document> {
+    let mut lines = Vec::new();
+    let mut refs = Vec::new();
+    let mut no = 0;
+    // The character and length of the open code fence.
+    let mut fenced = None;
+    for line in input.split(|&c| c == b'\n') {
+        no += 1;
+        match fenced {
+            Some((c, n)) => {
+                if let Some((c2, n2, info)) = fence(line) {
+                    if c2 == c && n2 >= n && info.len() == 0 {
+                        fenced = None;
+                    }
+                }
+            },
+            _ => {
+                if let Some((c, n, _)) = fence(line) {
+                    fenced = Some((c, n));
+                } else if indent(line) < 4 && try!(link_ref(&mut refs, line)) {
+                    // Link reference definitions are not part of the text. Lines in code
+                    // blocks are never definitions.
+                    continue;
+                }
+            },
+        }
+        try!(lines.reserve(1));
+        lines.push(Line { no: no, text: line });
+    }
+    // The trailing newline does not start a new line.
+    if input.len() > 0 && input[input.len() - 1] == b'\n' {
+        lines.pop();
+        no -= 1;
+    }
+
+    let parser = Parser { refs: &refs, krate: krate };
+    let parts = try!(parser.parts(&lines, true));
+
+    Ok(Document {
+        parts: parts,
+        lines: no,
+        diagnostics: Vec::new(),
+        syntax: Syntax::Markdown,
+    })
+}
+
+/// Adds a link reference definition if the line is one.
+fn link_ref(refs: &mut Vec<LinkRef>, line: &[u8]) -> Result<bool> {
+    let line = &line[indent(line)..];
+    if line.len() == 0 || line[0] != b'[' {
+        return Ok(false);
+    }
+    let end = match line.find(|&c| c == b']') {
+        Some(end) if end > 1 => end,
+        _ => return Ok(false),
+    };
+    if end + 1 >= line.len() || line[end + 1] != b':' {
+        return Ok(false);
+    }
+    let rest = &line[end + 2..];
+    let rest = &rest[indent(rest)..];
+    let mut len = 0;
+    while len < rest.len() && rest[len] != b' ' {
+        len += 1;
+    }
+    if len == 0 {
+        return Ok(false);
+    }
+    let mut target = &rest[..len];
+    if target[0] == b'<' && target[len - 1] == b'>' {
+        target = &target[1..len - 1];
+    }
+    try!(refs.reserve(1));
+    refs.push(LinkRef {
+        label: try!(lowercase(&line[1..end])),
+        target: try!(target.try_to()),
+    });
+    Ok(true)
+}
+
+/// The number of leading spaces.
+fn indent(line: &[u8]) -> usize {
+    let mut i = 0;
+    while i < line.len() && line[i] == b' ' {
+        i += 1;
+    }
+    i
+}
+
+fn is_blank(line: &[u8]) -> bool {
+    indent(line) == line.len()
+}
+
+fn trim(s: &[u8]) -> &[u8] {
+    let mut end = s.len();
+    while end > 0 && s[end - 1] == b' ' {
+        end -= 1;
+    }
+    &s[indent(&s[..end])..end]
+}
+
+fn contains(s: &[u8], pat: &[u8]) -> bool {
+    let mut i = 0;
+    while i + pat.len() <= s.len() {
+        if s[i..].starts_with(pat) {
+            return true;
+        }
+        i += 1;
+    }
+    false
+}
+
+fn ends_with(s: &[u8], suffix: &[u8]) -> bool {
+    s.len() >= suffix.len() && &s[s.len() - suffix.len()..] == suffix
+}
+
+fn lowercase(s: &[u8]) -> Result<Vec<u8>> {
+    let mut res: Vec<u8> = try!(s.try_to());
+    for c in &mut res {
+        if b'A' <= *c && *c <= b'Z' {
+            *c += b'a' - b'A';
+        }
+    }
+    Ok(res)
+}
+
+/// Removes up to `n` leading spaces.
+fn dedent(line: &[u8], n: usize) -> &[u8] {
+    let i = indent(line);
+    &line[if i < n { i } else { n }..]
+}
+
+/// ATX headers: `# Title`. Returns the level and the title.
+fn atx_header(line: &[u8]) -> Option<(usize, &[u8])> {
+    let line = &line[indent(line)..];
+    let mut level = 0;
+    while level < line.len() && line[level] == b'#' {
+        level += 1;
+    }
+    if level == 0 || level > 6 || (level < line.len() && line[level] != b' ') {
+        return None;
+    }
+    let mut title = trim(&line[level..]);
+    // Optional closing sequence.
+    let mut end = title.len();
+    while end > 0 && title[end - 1] == b'#' {
+        end -= 1;
+    }
+    if end == 0 || title[end - 1] == b' ' {
+        title = trim(&title[..end]);
+    }
+    Some((level, title))
+}
+
+/// Setext underlines: `===` and `---`. Returns the level.
+fn setext_underline(line: &[u8]) -> Option<usize> {
+    let line = trim(line);
+    if line.len() == 0 || indent(line) > 3 {
+        return None;
+    }
+    let level = match line[0] {
+        b'=' => 1,
+        b'-' => 2,
+        _ => return None,
+    };
+    match line.find(|&c| c != line[0]) {
+        Some(_) => None,
+        _ => Some(level),
+    }
+}
+
+fn thematic_break(line: &[u8]) -> bool {
+    if indent(line) > 3 {
+        return false;
+    }
+    let line = trim(line);
+    if line.len() == 0 || (line[0] != b'-' && line[0] != b'*' && line[0] != b'_') {
+        return false;
+    }
+    let mut n = 0;
+    for &c in line {
+        if c == line[0] {
+            n += 1;
+        } else if c != b' ' {
+            return false;
+        }
+    }
+    n >= 3
+}
+
+/// Code fences. Returns the fence character, the length of the fence, and the info
+/// string.
+fn fence(line: &[u8]) -> Option<(u8, usize, &[u8])> {
+    let i = indent(line);
+    if i > 3 || i == line.len() || (line[i] != b'`' && line[i] != b'~') {
+        return None;
+    }
+    let c = line[i];
+    let mut n = 0;
+    while i + n < line.len() && line[i + n] == c {
+        n += 1;
+    }
+    let info = trim(&line[i + n..]);
+    if n < 3 || (c == b'`' && info.find(|&d| d == b'`').is_some()) {
+        return None;
+    }
+    Some((c, n, info))
+}
+
+/// List item markers. Returns the kind of the list and the column of the content.
+fn list_marker(line: &[u8]) -> Option<(ListKind, usize)> {
+    let i = indent(line);
+    if i > 3 || i == line.len() {
+        return None;
+    }
+    let (kind, end) = match line[i] {
+        b'*' | b'-' | b'+' => (ListKind::Unordered, i + 1),
+        b'0'...b'9' => {
+            let mut j = i;
+            while j < line.len() && j - i < 9 && b'0' <= line[j] && line[j] <= b'9' {
+                j += 1;
+            }
+            if j == line.len() || (line[j] != b'.' && line[j] != b')') {
+                return None;
+            }
+            (ListKind::Ordered, j + 1)
+        },
+        _ => return None,
+    };
+    if end == line.len() {
+        return Some((kind, end + 1));
+    }
+    if line[end] != b' ' {
+        return None;
+    }
+    let spaces = indent(&line[end..]);
+    if spaces > 4 || end + spaces == line.len() {
+        Some((kind, end + 1))
+    } else {
+        Some((kind, end + spaces))
+    }
+}
+
+/// Table delimiter rows: `| --- | :---: |`.
+fn table_delim(line: &[u8]) -> bool {
+    let line = trim(line);
+    let mut dashes = false;
+    for &c in line {
+        match c {
+            b'-' => dashes = true,
+            b'|' | b':' | b' ' => { },
+            _ => return false,
+        }
+    }
+    dashes && line.find(|&c| c == b'|').is_some()
+}
+
+/// Returns the alignment of a column in a table delimiter row.
+fn table_align(cell: &[u8]) -> Align {
+    let left = cell.len() > 0 && cell[0] == b':';
+    let right = cell.len() > 1 && cell[cell.len() - 1] == b':';
+    match (left, right) {
+        (true, true) => Align::Center,
+        (true, false) => Align::Left,
+        (false, true) => Align::Right,
+        (false, false) => Align::Default,
+    }
+}
+
+/// Splits a table row at unescaped `|`.
+fn table_cells(line: &[u8]) -> Result<Vec<&[u8]>> {
+    let mut line = trim(line);
+    if line.len() > 0 && line[0] == b'|' {
+        line = &line[1..];
+    }
+    if line.len() > 0 && line[line.len() - 1] == b'|'
+            && (line.len() < 2 || line[line.len() - 2] != b'\\') {
+        line = &line[..line.len() - 1];
+    }
+    let mut cells = Vec::new();
+    let mut start = 0;
+    let mut i = 0;
+    while i < line.len() {
+        if line[i] == b'\\' {
+            i += 1;
+        } else if line[i] == b'|' {
+            try!(cells.reserve(1));
+            cells.push(trim(&line[start..i]));
+            start = i + 1;
+        }
+        i += 1;
+    }
+    try!(cells.reserve(1));
+    cells.push(trim(&line[start..]));
+    Ok(cells)
+}
+
+/// Returns whether the line starts a block that interrupts a paragraph.
+fn interrupts(line: &[u8]) -> bool {
+    atx_header(line).is_some() || fence(line).is_some() || thematic_break(line)
+        || line[indent(line)..].starts_with(b">")
+        || match list_marker(line) {
+            // An empty item or an ordered list that does not start at 1 cannot interrupt
+            // a paragraph.
+            Some((ListKind::Unordered, col)) => col <= line.len(),
+            Some((ListKind::Ordered, col)) => {
+                col <= line.len() && line[indent(line)..].starts_with(b"1")
+            },
+            None => false,
+        }
+}
+
+/// Returns whether a fenced code block with this info string contains rust code.
+fn is_rust(info: &[u8]) -> bool {
+    if info.len() == 0 {
+        return true;
+    }
+    const ATTRIBUTES: &'static [&'static str] = &["", "rust", "ignore", "should_panic",
+                                                  "no_run", "compile_fail"];
+    for word in info.split(|&c| c == b',' || c == b' ') {
+        if word.starts_with(b"edition") {
+            continue;
+        }
+        if ATTRIBUTES.find(|a| a.as_bytes() == word).is_none() {
+            return false;
+        }
+    }
+    true
+}
+
+fn block(inner: Block) -> BlockData {
+    BlockData { attributes: Vec::new(), inner: inner }
+}
+
+/// Returns the only block or a grouped block.
+fn group(mut blocks: Vec<BlockData>) -> BlockData {
+    if blocks.len() == 1 {
+        blocks.pop().unwrap()
+    } else {
+        block(Block::Grouped(blocks))
+    }
+}
+
+struct Parser<'a> {
+    refs: &'a [LinkRef],
+    krate: &'a [u8],
+}
+
+impl<'a> Parser<'a> {
+    /// Parses a sequence of lines.
+    ///
+    /// [argument, top]
+    /// Whether the lines are the document itself. Otherwise headers become text.
+    fn parts(&self, lines: &[Line], top: bool) -> Result<Vec<Part>> {
+        let mut parts = Vec::new();
+        let mut i = 0;
+        while i < lines.len() {
+            let line = lines[i].text;
+            if is_blank(line) {
+                i += 1;
+                continue;
+            }
+
+            if let Some((level, title)) = atx_header(line) {
+                try!(parts.reserve(1));
+                parts.push(try!(self.header(level, title, top)));
+                i += 1;
+                continue;
+            }
+
+            if thematic_break(line) {
+                i += 1;
+                continue;
+            }
+
+            let (data, next) = if let Some((c, n, info)) = fence(line) {
+                try!(self.fenced(lines, i, c, n, info))
+            } else if indent(line) >= 4 {
+                try!(self.indented(lines, i))
+            } else if line[indent(line)..].starts_with(b">") {
+                try!(self.quote(lines, i))
+            } else if list_marker(line).is_some() {
+                try!(self.list(lines, i))
+            } else if i + 1 < lines.len() && line.find(|&c| c == b'|').is_some()
+                    && table_delim(lines[i + 1].text) {
+                try!(self.table(lines, i))
+            } else {
+                let mut end = i + 1;
+                while end < lines.len() {
+                    let l = lines[end].text;
+                    if is_blank(l) || interrupts(l) || setext_underline(l).is_some() {
+                        break;
+                    }
+                    end += 1;
+                }
+                if end < lines.len() && setext_underline(lines[end].text).is_some() {
+                    let level = setext_underline(lines[end].text).unwrap();
+                    let title = try!(self.paragraph_text(&lines[i..end]));
+                    try!(parts.reserve(1));
+                    parts.push(try!(self.header_text(level, title, top)));
+                    i = end + 1;
+                    continue;
+                }
+                let text = try!(self.paragraph_text(&lines[i..end]));
+                (block(Block::Text(text)), end)
+            };
+
+            try!(parts.reserve(1));
+            parts.push(Part::Block(data));
+            i = next;
+        }
+        Ok(parts)
+    }
+
+    fn blocks(&self, lines: &[Line]) -> Result<Vec<BlockData>> {
+        let parts = try!(self.parts(lines, false));
+        let mut blocks = try!(Vec::with_capacity(parts.len()));
+        for part in parts {
+            if let Part::Block(b) = part {
+                blocks.push(b);
+            }
+        }
+        Ok(blocks)
+    }
+
+    fn header(&self, level: usize, title: &[u8], top: bool) -> Result<Part> {
+        let title = try!(self.inline(title));
+        self.header_text(level, title, top)
+    }
+
+    fn header_text(&self, level: usize, title: TextBlock, top: bool) -> Result<Part> {
+        if top {
+            return Ok(Part::SectionHeader(level, title));
+        }
+        let mut vec = try!(Vec::with_capacity(1));
+        vec.push(title);
+        let bold = TextBlock {
+            attribute: Some(TextAttr::Bold),
+            inner: Text::Nested(vec),
+        };
+        Ok(Part::Block(block(Block::Text(bold))))
+    }
+
+    /// Joins the lines of a paragraph.
+    fn paragraph_text(&self, lines: &[Line]) -> Result<TextBlock> {
+        let mut text = Vec::new();
+        for (i, line) in lines.iter().enumerate() {
+            if i > 0 {
+                try!(text.push_all(b"\n"));
+            }
+            let l = line.text;
+            // Keep trailing spaces on all but the last line for hard line breaks.
+            if i + 1 < lines.len() {
+                try!(text.push_all(&l[indent(l)..]));
+            } else {
+                try!(text.push_all(trim(l)));
+            }
+        }
+        self.inline(&text)
+    }
+
+    fn fenced(&self, lines: &[Line], start: usize, c: u8, n: usize,
+              info: &[u8]) -> Result<(BlockData, usize)> {
+        let fence_indent = indent(lines[start].text);
+        let rust = is_rust(info);
+        let mut code = Vec::new();
+        let mut i = start + 1;
+        while i < lines.len() {
+            let line = lines[i].text;
+            if let Some((c2, n2, info2)) = fence(line) {
+                if c2 == c && n2 >= n && info2.len() == 0 {
+                    i += 1;
+                    break;
+                }
+            }
+            let line = dedent(line, fence_indent);
+            i += 1;
+            if rust {
+                // Hidden lines of examples.
+                if line == &b"#"[..] || line.starts_with(b"# ") {
+                    continue;
+                }
+                if line.starts_with(b"##") {
+                    try!(code.push_all(&line[1..]));
+                    try!(code.push_all(b"\n"));
+                    continue;
+                }
+            }
+            try!(code.push_all(line));
+            try!(code.push_all(b"\n"));
+        }
+        code.pop();
+        Ok((block(Block::Code(code)), i))
+    }
+
+    fn indented(&self, lines: &[Line], start: usize) -> Result<(BlockData, usize)> {
+        let mut end = start;
+        let mut i = start;
+        while i < lines.len() {
+            let line = lines[i].text;
+            if indent(line) >= 4 {
+                end = i + 1;
+            } else if !is_blank(line) {
+                break;
+            }
+            i += 1;
+        }
+        let mut code = Vec::new();
+        for line in &lines[start..end] {
+            try!(code.push_all(dedent(line.text, 4)));
+            try!(code.push_all(b"\n"));
+        }
+        code.pop();
+        Ok((block(Block::Code(code)), end))
+    }
+
+    fn quote(&self, lines: &[Line], start: usize) -> Result<(BlockData, usize)> {
+        let mut inner = Vec::new();
+        let mut i = start;
+        while i < lines.len() {
+            let line = lines[i].text;
+            let rest = &line[indent(line)..];
+            if rest.starts_with(b">") {
+                let rest = &rest[1..];
+                let rest = if rest.starts_with(b" ") { &rest[1..] } else { rest };
+                try!(inner.reserve(1));
+                inner.push(Line { no: lines[i].no, text: rest });
+            } else if !is_blank(line) && !interrupts(line) && inner.len() > 0
+                    && !is_blank(inner[inner.len() - 1].text) {
+                // Lazy continuation of a paragraph.
+                try!(inner.reserve(1));
+                inner.push(lines[i]);
+            } else {
+                break;
+            }
+            i += 1;
+        }
+        let mut data = group(try!(self.blocks(&inner)));
+        try!(data.attributes.reserve(1));
+        data.attributes.push(Attribute {
+            name: try!(b"quote".try_to()),
+            args: None,
+            line: lines[start].no,
+        });
+        Ok((data, i))
+    }
+
+    fn list(&self, lines: &[Line], start: usize) -> Result<(BlockData, usize)> {
+        let (kind, _) = list_marker(lines[start].text).unwrap();
+        let mut els = Vec::new();
+        let mut i = start;
+        while i < lines.len() {
+            let col = match list_marker(lines[i].text) {
+                Some((k, col)) if k == kind => col,
+                _ => break,
+            };
+
+            let mut item = Vec::new();
+            let first = lines[i].text;
+            let first = &first[if col < first.len() { col } else { first.len() }..];
+            try!(item.reserve(1));
+            item.push(Line { no: lines[i].no, text: first });
+            i += 1;
+
+            while i < lines.len() {
+                let line = lines[i].text;
+                if is_blank(line) {
+                    // Blank lines belong to the item if it continues after them.
+                    let mut j = i;
+                    while j < lines.len() && is_blank(lines[j].text) {
+                        j += 1;
+                    }
+                    if j == lines.len() || indent(lines[j].text) < col {
+                        break;
+                    }
+                    try!(item.reserve(1));
+                    item.push(Line { no: lines[i].no, text: b"" });
+                } else if indent(line) >= col {
+                    try!(item.reserve(1));
+                    item.push(Line { no: lines[i].no, text: &line[col..] });
+                } else if !interrupts(line) && !is_blank(item[item.len() - 1].text) {
+                    // Lazy continuation of a paragraph.
+                    try!(item.reserve(1));
+                    item.push(Line { no: lines[i].no, text: &line[indent(line)..] });
+                } else {
+                    break;
+                }
+                i += 1;
+            }
+
+            try!(els.reserve(1));
+            els.push(try!(self.list_el(&item)));
+
+            // Blank lines between items.
+            let mut j = i;
+            while j < lines.len() && is_blank(lines[j].text) {
+                j += 1;
+            }
+            if j == lines.len() {
+                break;
+            }
+            match list_marker(lines[j].text) {
+                Some((k, _)) if k == kind => i = j,
+                _ => break,
+            }
+        }
+        Ok((block(Block::List(kind, els)), i))
+    }
+
+    fn list_el(&self, lines: &[Line]) -> Result<ListEl> {
+        let blocks = try!(self.blocks(lines));
+        if blocks.len() == 0 {
+            let empty = TextBlock { attribute: None, inner: Text::Raw(Vec::new()) };
+            return Ok(ListEl::Simple(empty));
+        }
+        let simple = match blocks[0] {
+            BlockData { inner: Block::Text(_), ref attributes } => attributes.len() == 0,
+            _ => false,
+        };
+        if !simple {
+            return Ok(ListEl::Complex(group(blocks)));
+        }
+        let mut text = None;
+        let mut rest = try!(Vec::with_capacity(blocks.len() - 1));
+        for b in blocks {
+            match b.inner {
+                Block::Text(t) if text.is_none() => text = Some(t),
+                inner => rest.push(BlockData { attributes: b.attributes, inner: inner }),
+            }
+        }
+        let text = text.unwrap();
+        match rest.len() {
+            0 => Ok(ListEl::Simple(text)),
+            _ => Ok(ListEl::Nested(text, group(rest))),
+        }
+    }
+
+    fn table(&self, lines: &[Line], start: usize) -> Result<(BlockData, usize)> {
+        let mut rows = Vec::new();
+        let mut align = Vec::new();
+        let mut i = start;
+        while i < lines.len() {
+            let line = lines[i].text;
+            if is_blank(line) || (i > start + 1 && line.find(|&c| c == b'|').is_none()) {
+                break;
+            }
+            if i == start + 1 {
+                for cell in try!(table_cells(line)) {
+                    try!(align.reserve(1));
+                    align.push(table_align(cell));
+                }
+            } else {
+                let cells = try!(table_cells(line));
+                let mut cols = try!(Vec::with_capacity(cells.len()));
+                for cell in cells {
+                    cols.push(TableCell {
+                        span: Span::single(),
+                        inner: TableCol::Simple(try!(self.inline(cell))),
+                    });
+                }
+                try!(rows.reserve(1));
+                rows.push(TableRow { cols: cols });
+            }
+            i += 1;
+        }
+        let table = Table { header: true, align: align, rows: rows };
+        Ok((block(Block::Table(table)), i))
+    }
+
+    fn inline(&self, text: &[u8]) -> Result<TextBlock> {
+        let mut parser = Inline {
+            refs: self.refs,
+            krate: self.krate,
+            current: None,
+            past: Vec::new(),
+        };
+        try!(parser.parse(text));
+        parser.finish()
+    }
+}
+
+struct Inline<'a> {
+    refs: &'a [LinkRef],
+    krate: &'a [u8],
+    current: Option<Vec<u8>>,
+    past: Vec<TextBlock>,
+}
+
+fn is_punct(c: u8) -> bool {
+    match c {
+        b'!'...b'/' | b':'...b'@' | b'['...b'`' | b'{'...b'~' => true,
+        _ => false,
+    }
+}
+
+fn is_word(c: u8) -> bool {
+    match c {
+        b'a'...b'z' | b'A'...b'Z' | b'0'...b'9' => true,
+        _ => false,
+    }
+}
+
+fn is_space(c: u8) -> bool {
+    c == b' ' || c == b'\n'
+}
+
+/// Returns the length of the run of `c` at the start of `s`.
+fn run(s: &[u8], c: u8) -> usize {
+    let mut n = 0;
+    while n < s.len() && s[n] == c {
+        n += 1;
+    }
+    n
+}
+
+/// Returns the position of the `]` that matches the `[` at the start of `s`.
+fn closing_bracket(s: &[u8]) -> Option<usize> {
+    let mut depth = 0;
+    let mut i = 0;
+    while i < s.len() {
+        match s[i] {
+            b'\\' => i += 1,
+            b'`' => {
+                let n = run(&s[i..], b'`');
+                match code_end(&s[i + n..], n) {
+                    Some(end) => i += n + end + n - 1,
+                    _ => i += n - 1,
+                }
+            },
+            b'[' => depth += 1,
+            b']' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            },
+            _ => { },
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Returns the position of a run of exactly `n` backticks.
+fn code_end(s: &[u8], n: usize) -> Option<usize> {
+    let mut i = 0;
+    while i < s.len() {
+        if s[i] == b'`' {
+            let m = run(&s[i..], b'`');
+            if m == n {
+                return Some(i);
+            }
+            i += m;
+        } else {
+            i += 1;
+        }
+    }
+    None
+}
+
+/// Converts the target of a link. Paths such as `struct@lrs::vec::Vec` or
+/// `lrs::vec::Vec::new()` are reduced to the path.
+fn link_target(target: &[u8]) -> Result<Vec<u8>> {
+    let is_path = target.find(|&c| c == b'/' || c == b'#' || c == b'.').is_none();
+    if !is_path {
+        return target.try_to();
+    }
+    let mut target = target;
+    if let Some(at) = target.find(|&c| c == b'@') {
+        target = &target[at + 1..];
+    }
+    if ends_with(target, b"()") {
+        target = &target[..target.len() - 2];
+    } else if ends_with(target, b"!") {
+        target = &target[..target.len() - 1];
+    }
+    target.try_to()
+}
+
+impl<'a> Inline<'a> {
+    fn parse(&mut self, text: &[u8]) -> Result {
+        let mut i = 0;
+        while i < text.len() {
+            let rest = &text[i..];
+            let n = match rest[0] {
+                b'\\' => try!(self.escape(rest)),
+                b' ' => try!(self.line_break(rest)),
+                b'`' => try!(self.code(rest)),
+                b'*' | b'_' => try!(self.emphasis(text, i)),
+                b'~' => try!(self.strikethrough(rest)),
+                b'[' => try!(self.link(rest)),
+                b'!' if rest.starts_with(b"![") => {
+                    match try!(self.link(&rest[1..])) {
+                        0 => 0,
+                        n => n + 1,
+                    }
+                },
+                b'<' => try!(self.autolink(rest)),
+                _ => 0,
+            };
+            if n > 0 {
+                i += n;
+            } else {
+                try!(self.append_raw(&rest[..1]));
+                i += 1;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<TextBlock> {
+        try!(self.finish_raw());
+        let inner = match self.past.len() {
+            0 => Text::Raw(Vec::new()),
+            1 => return Ok(self.past.pop().unwrap()),
+            _ => Text::Nested(self.past),
+        };
+        Ok(TextBlock { attribute: None, inner: inner })
+    }
+
+    fn append_raw(&mut self, r: &[u8]) -> Result {
+        if self.current.is_none() { self.current = Some(Vec::new()); }
+        try!(self.current.as_mut().unwrap().push_all(r));
+        Ok(())
+    }
+
+    fn finish_raw(&mut self) -> Result {
+        if let Some(c) = self.current.take() {
+            try!(self.past.reserve(1));
+            self.past.push(TextBlock { attribute: None, inner: Text::Raw(c) });
+        }
+        Ok(())
+    }
+
+    fn push(&mut self, block: TextBlock) -> Result {
+        try!(self.finish_raw());
+        try!(self.past.reserve(1));
+        self.past.push(block);
+        Ok(())
+    }
+
+    /// Parses `text` recursively and pushes it with the attribute.
+    fn push_attr(&mut self, text: &[u8], attr: TextAttr) -> Result {
+        let mut inner = Inline {
+            refs: self.refs,
+            krate: self.krate,
+            current: None,
+            past: Vec::new(),
+        };
+        try!(inner.parse(text));
+        let block = try!(inner.finish());
+        let mut block = if block.attribute.is_none() {
+            block
+        } else {
+            let mut vec = try!(Vec::with_capacity(1));
+            vec.push(block);
+            TextBlock { attribute: None, inner: Text::Nested(vec) }
+        };
+        block.attribute = Some(attr);
+        self.push(block)
+    }
+
+    fn escape(&mut self, s: &[u8]) -> Result<usize> {
+        if s.len() < 2 {
+            return Ok(0);
+        }
+        if s[1] == b'\n' {
+            try!(self.push(TextBlock { attribute: None, inner: Text::LineBreak }));
+            return Ok(2);
+        }
+        if !is_punct(s[1]) {
+            return Ok(0);
+        }
+        try!(self.append_raw(&s[1..2]));
+        Ok(2)
+    }
+
+    /// Two or more spaces at the end of a line.
+    fn line_break(&mut self, s: &[u8]) -> Result<usize> {
+        let n = run(s, b' ');
+        if n < s.len() && s[n] == b'\n' {
+            if n >= 2 {
+                try!(self.push(TextBlock { attribute: None, inner: Text::LineBreak }));
+            } else {
+                try!(self.append_raw(b"\n"));
+            }
+            return Ok(n + 1);
+        }
+        Ok(0)
+    }
+
+    fn code(&mut self, s: &[u8]) -> Result<usize> {
+        let n = run(s, b'`');
+        let end = match code_end(&s[n..], n) {
+            Some(end) => end,
+            _ => {
+                try!(self.append_raw(&s[..n]));
+                return Ok(n);
+            },
+        };
+        let mut code = &s[n..n + end];
+        if code.len() > 2 && code[0] == b' ' && code[code.len() - 1] == b' '
+                && !is_blank(code) {
+            code = &code[1..code.len() - 1];
+        }
+        let mut code: Vec<u8> = try!(code.try_to());
+        for c in &mut code {
+            if *c == b'\n' {
+                *c = b' ';
+            }
+        }
+        try!(self.push(TextBlock {
+            attribute: Some(TextAttr::Raw),
+            inner: Text::Raw(code),
+        }));
+        Ok(n + end + n)
+    }
+
+    /// `*` and `_` emphasis. `text[i]` is the delimiter.
+    fn emphasis(&mut self, text: &[u8], i: usize) -> Result<usize> {
+        let c = text[i];
+        let s = &text[i..];
+        let avail = run(s, c);
+        // Intraword `_` does not start emphasis.
+        if c == b'_' && i > 0 && is_word(text[i - 1]) {
+            try!(self.append_raw(&s[..avail]));
+            return Ok(avail);
+        }
+        let n = if avail >= 2 { 2 } else { 1 };
+        if n >= s.len() || is_space(s[n]) {
+            return Ok(0);
+        }
+        // Find a closing run that is not preceded by a space. Longer runs close with
+        // their last characters.
+        let mut j = n;
+        while j < s.len() {
+            if s[j] == b'\\' {
+                j += 2;
+                continue;
+            }
+            if s[j] == b'`' {
+                let m = run(&s[j..], b'`');
+                j += match code_end(&s[j + m..], m) {
+                    Some(end) => m + end + m,
+                    _ => m,
+                };
+                continue;
+            }
+            if s[j] != c {
+                j += 1;
+                continue;
+            }
+            let m = run(&s[j..], c);
+            let after = j + m;
+            let closes = m >= n && !is_space(s[j - 1])
+                && (c != b'_' || after == s.len() || !is_word(s[after]))
+                && (n == 2 || m != 2 || avail > 1);
+            if closes {
+                let end = after - n;
+                let attr = if n == 2 { TextAttr::Bold } else { TextAttr::Italic };
+                try!(self.push_attr(&s[n..end], attr));
+                return Ok(after);
+            }
+            j = after;
+        }
+        Ok(0)
+    }
+
+    fn strikethrough(&mut self, s: &[u8]) -> Result<usize> {
+        if !s.starts_with(b"~~") || s.len() < 3 || is_space(s[2]) {
+            return Ok(0);
+        }
+        let mut j = 2;
+        while j + 1 < s.len() {
+            if s[j] == b'~' && s[j + 1] == b'~' && !is_space(s[j - 1]) {
+                try!(self.push_attr(&s[2..j], TextAttr::Strikethrough));
+                return Ok(j + 2);
+            }
+            j += 1;
+        }
+        Ok(0)
+    }
+
+    /// Inline links `[text](target)`, reference links `[text][label]` and `[label]`, and
+    /// intra-doc links ``[`path`]``.
+    fn link(&mut self, s: &[u8]) -> Result<usize> {
+        let close = match closing_bracket(s) {
+            Some(c) => c,
+            _ => return Ok(0),
+        };
+        let label = &s[1..close];
+        let rest = &s[close + 1..];
+
+        let (target, len) = if rest.starts_with(b"(") {
+            let end = match rest.find(|&c| c == b')') {
+                Some(end) => end,
+                _ => return Ok(0),
+            };
+            let inner = trim(&rest[1..end]);
+            // Drop the title.
+            let target = match inner.find(|&c| c == b' ') {
+                Some(sp) => &inner[..sp],
+                _ => inner,
+            };
+            let target = if target.starts_with(b"<") && ends_with(target, b">") {
+                &target[1..target.len() - 1]
+            } else {
+                target
+            };
+            (try!(link_target(target)), close + 1 + end + 1)
+        } else if rest.starts_with(b"[") {
+            let end = match rest.find(|&c| c == b']') {
+                Some(end) => end,
+                _ => return Ok(0),
+            };
+            let reference = if end == 1 { label } else { &rest[1..end] };
+            match try!(self.reference(reference)) {
+                Some(t) => (t, close + 1 + end + 1),
+                _ => return Ok(0),
+            }
+        } else {
+            match try!(self.reference(label)) {
+                Some(t) => (t, close + 1),
+                _ => return self.intra_doc_link(label, close + 1),
+            }
+        };
+
+        try!(self.push_link(target, label));
+        Ok(len)
+    }
+
+    /// Intra-doc links ``[`path`]`` and `[path::to::item]`.
+    ///
+    /// [argument, len]
+    /// The length of the link in the source.
+    ///
+    /// = Remarks
+    ///
+    /// Only paths that start with the crate name or with `crate` become links. Other
+    /// paths are relative to the scope of the item, which is not known here, and become
+    /// code.
+    fn intra_doc_link(&mut self, label: &[u8], len: usize) -> Result<usize> {
+        let code = label.len() > 2 && label[0] == b'`' && label[label.len() - 1] == b'`';
+        let path = match code {
+            true => &label[1..label.len() - 1],
+            false => label,
+        };
+        let is_path = path.len() > 0
+            && path.find(|&c| c == b' ' || c == b'`').is_none()
+            && (code || contains(path, b"::"));
+        if !is_path {
+            return Ok(0);
+        }
+
+        let first = match path.find(|&c| c == b':') {
+            Some(end) => &path[..end],
+            _ => path,
+        };
+        let target = if self.krate.len() > 0 && first == self.krate {
+            try!(link_target(path))
+        } else if first == &b"crate"[..] && path.len() > first.len() {
+            let mut abs: Vec<u8> = try!(self.krate.try_to());
+            try!(abs.push_all(&path[first.len()..]));
+            try!(link_target(&abs))
+        } else {
+            let code = Text::Raw(try!(path.try_to()));
+            try!(self.push(TextBlock { attribute: Some(TextAttr::Raw), inner: code }));
+            return Ok(len);
+        };
+
+        try!(self.push_link(target, label));
+        Ok(len)
+    }
+
+    /// Pushes a link whose text is the parsed label.
+    fn push_link(&mut self, target: Vec<u8>, label: &[u8]) -> Result {
+        let mut inner = Inline {
+            refs: self.refs,
+            krate: self.krate,
+            current: None,
+            past: Vec::new(),
+        };
+        try!(inner.parse(label));
+        let text = try!(inner.finish());
+        let text = Some(try!(Box::new()).set(text));
+        self.push(TextBlock { attribute: None, inner: Text::Link(target, text) })
+    }
+
+    fn reference(&self, label: &[u8]) -> Result<Option<Vec<u8>>> {
+        let label = try!(lowercase(label));
+        for r in self.refs {
+            if &r.label[..] == &label[..] {
+                return Ok(Some(try!(link_target(&r.target))));
+            }
+        }
+        Ok(None)
+    }
+
+    /// `<http://example.com>`
+    fn autolink(&mut self, s: &[u8]) -> Result<usize> {
+        let end = match s.find(|&c| c == b'>' || c == b' ' || c == b'<' || c == b'\n') {
+            Some(end) if s[end] == b'>' => end,
+            _ => return Ok(0),
+        };
+        let url = &s[1..end];
+        if !contains(url, b"://") {
+            return Ok(0);
+        }
+        let link = Text::Link(try!(url.try_to()), None);
+        try!(self.push(TextBlock { attribute: None, inner: link }));
+        Ok(end + 1)
+    }
+}
@@ -13,14 +13,21 @@
 //!
 //! $             <- '\n'
 //!
-//! SimpleListEl  <- '* ' .* $ ('  ' .* $)*
-//! BlockListEl   <- '**' $ Block
+//! ListMarker    <- '* ' / '. '
+//! ElStart       <- ListMarker / '**' $ / '..' $ / DefTerm
+//! NestedList    <- ('  ' .* $)+
+//! ElText        <- .* $ (!('  ' ElStart) '  ' .* $)* NestedList?
+//! SimpleListEl  <- ListMarker ElText
+//! BlockListEl   <- ('**' / '..') $ Block
 //! ListEl        <- SimpleListEl / BlockListEl
-//! ListBlock     <- ListEl+
+//! DefTerm       <- !' ' (!'::' .)+ '::' (' ' / &$)
+//! DefinitionEl  <- DefTerm ElText
+//! ListBlock     <- ListEl+ / DefinitionEl+
 //!
 //! TableDelim    <- '|===' $
 //! ColumnText    <- (!'|' ('\\\\' / '\\|' / .))*
-//! SimpleRow     <- ('|' ColumnText)+ $
+//! CellSpan      <- [0-9]+ '+' / [0-9]* '.' [0-9]+ '+'
+//! SimpleRow     <- (CellSpan? '|' ColumnText)+ $
 //! Row           <- (SimpleRow / (!$ Block))* $
 //! TableBlock    <- TableDelim ($* !TableDelim Row)* $* TableDelim?
 //!
@@ -44,6 +51,24 @@
 //!
 //! Document      <- ($* (SectionHeader / VarDef / Block))*
 //!
+//! All elements of a ListBlock use the same marker: `*` for unordered lists and `.` for
+//! ordered lists. The NestedList is parsed as a sequence of Blocks after the indentation
+//! has been removed.
+//!
+//! A CellSpan `c+` makes the following column span `c` columns, `.r+` makes it span `r`
+//! rows, and `c.r+` does both. Except at the start of a row, a CellSpan must be
+//! preceded by a space, e.g., `|a 2+|b`. A TableBlock can be preceded by an attribute
+//! `[table, header, cols="<,^,>"]`, see `DocParser::table_options`.
+//!
+//! Before the lines are parsed, each line of the form
+//!
+//! Include       <- 'include::' (!'[' .)+ '[' (!']' .)* ']' $
+//!
+//! is replaced by the lines of the file it names, which can contain includes
+//! themselves. `include::snippet:name[]` names the file `snippets/name` in the doc root.
+//! Since this happens before parsing, includes can also appear in CodeBlocks. See
+//! `include_lines` for the attributes.
+//!
 //! =====================================================================================
 //!
 //! Text structure
@@ -51,13 +76,19 @@
 //!
 //! RawDelim       <- '`'
 //! BoldDelim      <- '*'
+//! ItalicDelim    <- '_'
+//! StrikeDelim    <- '~~'
+//! SupDelim       <- '^'
+//! SubDelim       <- '~'
 //! SubstStart     <- '{'
 //! SubstEnd       <- '}'
 //! LinkEnd        <- ']'
 //! LinkStart      <- 'link:'
 //!
-//! EscapeSequence <- '\\' ('\\' / RawDelim / BoldDelim / SubstStart / LinkEnd /
-//!                         LinkStart)
+//! EscapeSequence <- '\\' ('\\' / RawDelim / BoldDelim / ItalicDelim / SubDelim /
+//!                         SupDelim / '+' / SubstStart / LinkEnd / LinkStart)
+//!
+//! LineBreak      <- ' +' $
 //!
 //! UnnamedLink    <- 'link:' (!' ' !'[' .)+
 //! LinkText       <- (!']' ('\\]' / .))*
@@ -67,19 +98,97 @@
 //! Raw            <- RawDelim (!'`' ('\\`' / '\\\\' / .))* RawDelim?
 //! Bold           <- BoldDelim (!'*' ('\\*' / '\\\\' / .))* BoldDelim?
 //!
-//! Text           <- (EscapeSequenc / Bold / Raw / Link / .)*
+//! Word           <- [a-zA-Z0-9]
+//! Italic         <- !<Word ItalicDelim (!(ItalicDelim !Word) .)+ ItalicDelim !Word
+//! Strike         <- StrikeDelim (!StrikeDelim .)+ StrikeDelim
+//! Sup            <- SupDelim (!SupDelim !' ' .)+ SupDelim
+//! Sub            <- SubDelim (!SubDelim !' ' .)+ SubDelim
 //!
-//! 
-//! This is recursively applied to link/ref text and the content of bold but not raw.
+//! Text           <- (EscapeSequenc / LineBreak / Bold / Raw / Italic / Strike / Sup /
+//!                    Sub / Link / .)*
+//!
+//! `!<Word` means that the preceding character is not a Word character. Unlike Bold and
+//! Raw, the other delimiters are only recognized if the text is closed.
+//!
+//! This is recursively applied to link/ref text and the content of all formatting except
+//! raw.
 
 use std::{mem};
 use std::bx::{Box};
 use std::util::{memchr};
 use std::vec::{Vec};
-use std::io::{BufRead};
+use std::io::{BufRead, Read};
+use std::file::{File};
+use std::string::{ByteStr};
+
+use markdown::{self};
 
 pub struct Document {
     pub parts: Vec<Part>,
+    /// The number of lines in the source of the document.
+    pub lines: usize,
+    pub diagnostics: Vec<Diagnostic>,
+    /// The syntax of the source.
+    pub syntax: Syntax,
+}
+
+/// The syntax of a doc comment.
+#[derive(Copy, Eq)]
+pub enum Syntax {
+    /// The lrs markup language.
+    Lrs,
+    /// rustdoc-style Markdown.
+    Markdown,
+    /// Chosen for each comment by `detect`.
+    Detect,
+}
+
+impl Syntax {
+    /// Parses the value of the `markup` option.
+    pub fn from_name(name: &[u8]) -> Option<Syntax> {
+        match name {
+            b"lrs" => Some(Syntax::Lrs),
+            b"markdown" => Some(Syntax::Markdown),
+            b"detect" => Some(Syntax::Detect),
+            _ => None,
+        }
+    }
+}
+
+/// A problem found while parsing a document.
+pub struct Diagnostic {
+    /// The line in the document, starting at 1.
+    pub line: usize,
+    /// The column in the line, starting at 1.
+    pub column: usize,
+    pub msg: &'static str,
+    /// Whether the problem is an error. Otherwise it's a warning.
+    pub error: bool,
+}
+
+/// Settings shared by all documents.
+pub struct Env {
+    /// The syntax of documents that don't select one.
+    pub syntax: Syntax,
+    /// The directory that included files are relative to.
+    pub root: Option<Vec<u8>>,
+    /// Variables that are available in all documents. Variables defined in a document
+    /// take precedence. Later variables take precedence over earlier ones.
+    pub vars: Vec<(Vec<u8>, Vec<u8>)>,
+    /// The name of the crate. Intra-doc links in Markdown are only linked if they start
+    /// with it.
+    pub crate_name: Vec<u8>,
+}
+
+impl Env {
+    pub fn new() -> Env {
+        Env {
+            syntax: Syntax::Lrs,
+            root: None,
+            vars: Vec::new(),
+            crate_name: Vec::new(),
+        }
+    }
 }
 
 pub enum Part {
@@ -95,23 +204,117 @@ pub struct BlockData {
 pub struct Attribute {
     pub name: Vec<u8>,
     pub args: Option<Vec<u8>>,
+    /// The line of the attribute in the document.
+    pub line: usize,
 }
 
 pub enum Block {
     Grouped(Vec<BlockData>),
     Code(Vec<u8>),
-    List(Vec<ListEl>),
-    Table(Vec<TableRow>),
+    List(ListKind, Vec<ListEl>),
+    Definitions(Vec<Definition>),
+    Table(Table),
     Text(TextBlock),
 }
 
+#[derive(Copy, Eq)]
+pub enum ListKind {
+    Unordered,
+    Ordered,
+}
+
 pub enum ListEl {
     Simple(TextBlock),
     Complex(BlockData),
+    /// A simple element followed by an indented block.
+    Nested(TextBlock, BlockData),
+}
+
+pub struct Definition {
+    pub term: TextBlock,
+    pub desc: ListEl,
+}
+
+pub struct Table {
+    /// Whether the first row is a header row.
+    pub header: bool,
+    /// The alignment of the columns. Columns without an entry use `Align::Default`.
+    pub align: Vec<Align>,
+    pub rows: Vec<TableRow>,
+}
+
+impl Table {
+    /// Places the cells in the grid of columns.
+    ///
+    /// = Remarks
+    ///
+    /// Returns for each row the column of each cell and the number of columns covered
+    /// by the row, including columns covered by cells of earlier rows.
+    pub fn layout(&self) -> Result<Vec<(Vec<usize>, usize)>> {
+        // For each column, the number of rows, starting with the current one, that are
+        // covered by a cell of an earlier row.
+        let mut covered: Vec<usize> = Vec::new();
+        let mut layout = try!(Vec::with_capacity(self.rows.len()));
+        for row in &self.rows {
+            let mut cols = try!(Vec::with_capacity(row.cols.len()));
+            let mut col = 0;
+            for cell in &row.cols {
+                while col < covered.len() && covered[col] > 0 {
+                    col += 1;
+                }
+                cols.push(col);
+                while covered.len() < col + cell.span.cols {
+                    try!(covered.reserve(1));
+                    covered.push(0);
+                }
+                for c in col..col + cell.span.cols {
+                    covered[c] = cell.span.rows;
+                }
+                col += cell.span.cols;
+            }
+            while col < covered.len() && covered[col] > 0 {
+                col += 1;
+            }
+            for i in 0..covered.len() {
+                if covered[i] > 0 {
+                    covered[i] -= 1;
+                }
+            }
+            layout.push((cols, col));
+        }
+        Ok(layout)
+    }
+}
+
+#[derive(Copy, Eq)]
+pub enum Align {
+    Default,
+    Left,
+    Center,
+    Right,
 }
 
 pub struct TableRow {
-    pub cols: Vec<TableCol>,
+    pub cols: Vec<TableCell>,
+}
+
+pub struct TableCell {
+    pub span: Span,
+    pub inner: TableCol,
+}
+
+/// The number of columns and rows covered by a table cell.
+#[derive(Copy, Eq)]
+pub struct Span {
+    pub cols: usize,
+    pub rows: usize,
+}
+
+impl Span {
+    /// The span of a cell that covers one column and one row.
+    pub fn single() -> Span {
+        Span { cols: 1, rows: 1 }
+    }
 }
 
 pub enum TableCol {
@@ -128,58 +331,290 @@ pub enum Text {
     Raw(Vec<u8>),
     Nested(Vec<TextBlock>),
     Link(Vec<u8>, Option<Box<TextBlock>>),
+    LineBreak,
 }
 
+#[derive(Copy, Eq)]
 pub enum TextAttr {
     Raw,
     Bold,
+    Italic,
+    Strikethrough,
+    Superscript,
+    Subscript,
 }
 
-pub fn parse(input: &[u8]) -> Result<Document> {
-    let mut parser = DocParser {
-        r: input,
-        eof: false,
-        next: None,
-
-        parts: Vec::new(),
-        vars: Vec::new(),
-    };
+pub fn parse(input: &[u8], env: &Env) -> Result<Document> {
+    let mut parser = DocParser::new(input, env, 0, 0);
     try!(parser.document());
-    Ok(Document { parts: parser.parts })
+    Ok(Document {
+        parts: parser.parts,
+        lines: parser.read,
+        diagnostics: parser.diags,
+        syntax: Syntax::Lrs,
+    })
+}
+
+/// Parses a document in the given syntax.
+pub fn parse_with(input: &[u8], syntax: Syntax, env: &Env) -> Result<Document> {
+    match syntax {
+        Syntax::Lrs => parse(input, env),
+        Syntax::Markdown => markdown::parse(input, &env.crate_name),
+        Syntax::Detect => parse_with(input, detect(input), env),
+    }
 }
 
+/// Guesses the syntax of a document.
+///
+/// = Remarks
+///
+/// Each line that only makes sense in one of the syntaxes counts for that syntax. Ties
+/// are resolved in favor of the lrs markup language.
+pub fn detect(input: &[u8]) -> Syntax {
+    let mut lrs = 0;
+    let mut md = 0;
+    for line in input.split(|&c| c == b'\n') {
+        if line.starts_with(b"```") || line.starts_with(b"~~~") {
+            md += 1;
+        } else if line.starts_with(b"# ") || line.starts_with(b"## ") {
+            md += 1;
+        } else if line == &b"----"[..] || line == &b"|==="[..] {
+            lrs += 1;
+        } else if line.starts_with(b"= ") || line.starts_with(b"== ") {
+            lrs += 1;
+        } else if line.len() > 1 && line[0] == b'[' && line[line.len() - 1] == b']' {
+            lrs += 1;
+        }
+        let mut i = 0;
+        while i + 1 < line.len() {
+            if line[i..].starts_with(b"](") || line[i..].starts_with(b"[`") {
+                md += 1;
+            } else if line[i..].starts_with(b"link:") {
+                lrs += 1;
+            }
+            i += 1;
+        }
+    }
+    if md > lrs { Syntax::Markdown } else { Syntax::Lrs }
+}
+
+
+
 
 
 
+/// The maximum depth of nested includes.
+const MAX_INCLUDE_DEPTH: usize = 32;
 
+/// The lines of an included file that have not been read yet.
+struct Include {
+    /// The resolved path of the file.
+    path: Vec<u8>,
+    lines: Vec<Vec<u8>>,
+    /// The next line in `lines`.
+    pos: usize,
+    /// The line of the include directive in the document.
+    line: usize,
+}
 
-struct DocParser<R: BufRead> {
+struct DocParser<'a, R: BufRead> {
     r: R,
+    env: &'a Env,
     eof: bool,
     next: Option<Vec<u8>>,
+    includes: Vec<Include>,
+
+    /// The number of lines read from `r`.
+    read: usize,
+    /// The number of the line last returned by `next_line`.
+    line: usize,
+    /// The number of the line in `next`.
+    next_no: usize,
+    /// Added to all positions. Used for nested documents.
+    line_offset: usize,
+    column_offset: usize,
 
     parts: Vec<Part>,
     vars: Vec<(Vec<u8>, Vec<u8>)>,
+    diags: Vec<Diagnostic>,
 }
 
-impl<R: BufRead> DocParser<R> {
+impl<'a, R: BufRead> DocParser<'a, R> {
+    fn new(r: R, env: &'a Env, line_offset: usize,
+           column_offset: usize) -> DocParser<'a, R> {
+        DocParser {
+            r: r,
+            env: env,
+            eof: false,
+            next: None,
+            includes: Vec::new(),
+
+            read: 0,
+            line: 0,
+            next_no: 0,
+            line_offset: line_offset,
+            column_offset: column_offset,
+
+            parts: Vec::new(),
+            vars: Vec::new(),
+            diags: Vec::new(),
+        }
+    }
+
+    fn warn(&mut self, line: usize, column: usize, msg: &'static str) -> Result {
+        self.diagnostic(line, column, msg, false)
+    }
+
+    fn error(&mut self, line: usize, column: usize, msg: &'static str) -> Result {
+        self.diagnostic(line, column, msg, true)
+    }
+
+    fn diagnostic(&mut self, line: usize, column: usize, msg: &'static str,
+                  error: bool) -> Result {
+        try!(self.diags.reserve(1));
+        self.diags.push(Diagnostic {
+            line: self.line_offset + line,
+            column: self.column_offset + column,
+            msg: msg,
+            error: error,
+        });
+        Ok(())
+    }
+
+    /// Parses text that was joined from several lines.
+    ///
+    /// [argument, starts]
+    /// The offset in `text`, the line, and the column of each joined line.
+    ///
+    /// = Remarks
+    ///
+    /// The positions of warnings in text that contains variables are approximate.
+    fn text(&mut self, text: &[u8], starts: &[(usize, usize, usize)]) -> Result<TextBlock> {
+        let mut errs = Vec::new();
+        let block = try!(TextParser::all_in_one(text, &self.vars, &self.env.vars,
+                                                &mut errs));
+        for &(offset, msg) in &errs {
+            let mut start = starts[0];
+            for &s in starts {
+                if s.0 <= offset {
+                    start = s;
+                }
+            }
+            try!(self.warn(start.1, start.2 + offset - start.0, msg));
+        }
+        Ok(block)
+    }
+
     fn peek_line(&mut self) -> Result<&[u8]> {
         if self.next.is_none() {
+            let line = self.line;
             self.next = Some(try!(self.next_line()));
+            self.next_no = self.line;
+            self.line = line;
         }
         Ok(self.next.as_ref().unwrap())
     }
 
     fn next_line(&mut self) -> Result<Vec<u8>> {
         if self.next.is_some() {
+            self.line = self.next_no;
             return Ok(self.next.take().unwrap());
         }
 
+        loop {
+            let line = match try!(self.included_line()) {
+                Some(line) => line,
+                _ => try!(self.read_line()),
+            };
+            match include_directive(&line) {
+                Some((target, attrs)) => try!(self.include(target, attrs)),
+                _ => return Ok(line),
+            }
+        }
+    }
+
+    /// Returns the next line of the innermost included file.
+    fn included_line(&mut self) -> Result<Option<Vec<u8>>> {
+        while self.includes.len() > 0 {
+            {
+                let inc = self.includes.last_mut().unwrap();
+                if inc.pos < inc.lines.len() {
+                    inc.pos += 1;
+                    self.line = inc.line;
+                    return Ok(Some(mem::replace(&mut inc.lines[inc.pos - 1], Vec::new())));
+                }
+            }
+            self.includes.pop();
+        }
+        Ok(None)
+    }
+
+    /// Include <- 'include::' (!'[' .)+ '[' (!']' .)* ']' $
+    ///
+    /// Pushes the lines of an included file. Problems are reported at the line of the
+    /// directive, which is then ignored.
+    fn include(&mut self, target: &[u8], attrs: &[u8]) -> Result {
+        let line = self.line;
+        let env = self.env;
+
+        let path = {
+            let root = match env.root {
+                Some(ref root) => root,
+                _ => return self.error(line, 1, "includes require a doc root"),
+            };
+            let mut path: Vec<_> = try!(root.try_to());
+            try!(path.push_all(b"/"));
+            if target.starts_with(b"snippet:") {
+                try!(path.push_all(b"snippets/"));
+                try!(path.push_all(&target[b"snippet:".len()..]));
+            } else {
+                try!(path.push_all(target));
+            }
+            path
+        };
+
+        if self.includes.find(|i| &i.path[..] == &path[..]).is_some() {
+            return self.error(line, 1, "include cycle");
+        }
+        if self.includes.len() >= MAX_INCLUDE_DEPTH {
+            return self.error(line, 1, "includes are nested too deeply");
+        }
+
+        let mut content = Vec::new();
+        {
+            let path: &ByteStr = path.as_ref();
+            let mut file = match File::open_read(path) {
+                Ok(f) => f,
+                _ => return self.error(line, 1, "included file not found"),
+            };
+            if file.read_to_eof(&mut content).is_err() {
+                return self.error(line, 1, "included file cannot be read");
+            }
+        }
+
+        let lines = match try!(include_lines(&content, attrs)) {
+            Ok(lines) => lines,
+            Err(msg) => return self.error(line, 1, msg),
+        };
+
+        try!(self.includes.reserve(1));
+        self.includes.push(Include {
+            path: path,
+            lines: lines,
+            pos: 0,
+            line: line,
+        });
+        Ok(())
+    }
+
+    fn read_line(&mut self) -> Result<Vec<u8>> {
         let mut buf = Vec::new();
+        self.line = self.read + 1;
 
         loop {
             let n = try!(self.r.copy_until(&mut buf, b'\n'));
             if n > 0 {
+                self.read += 1;
                 if buf[buf.len() - 1] == b'\n' {
                     buf.pop();
                 }
@@ -238,7 +673,8 @@ impl<R: BufRead> DocParser<R> {
             len
         };
         let line = try!(self.next_line());
-        let text = try!(TextParser::all_in_one(&line[len+1..], &self.vars));
+        let pos = self.line;
+        let text = try!(self.text(&line[len+1..], &[(0, pos, len + 2)]));
         try!(self.parts.reserve(1));
         self.parts.push(Part::SectionHeader(len, text));
         Ok(true)
@@ -247,20 +683,9 @@ impl<R: BufRead> DocParser<R> {
     /// VarName <- [a-zA-Z_]+
     /// VarDef  <- ':' VarName ': ' .* $
     fn var_def(&mut self) -> Result<bool> {
-        let end = {
-            let line = try!(self.peek_line());
-            if line.len() < 3 || line[0] != b':' { return Ok(false); }
-            let mut i = 1;
-            while i < line.len() {
-                match line[i] {
-                    b'a'...b'z' | b'A'...b'Z' | b'_' => { },
-                    b':' => break,
-                    _ => return Ok(false),
-                }
-                i += 1;
-            }
-            if i + 1 >= line.len() || line[i+1] != b' ' { return Ok(false); }
-            i
+        let end = match var_def_line(try!(self.peek_line())) {
+            Some(end) => end,
+            _ => return Ok(false),
         };
 
         let line = try!(self.next_line());
@@ -277,7 +702,7 @@ impl<R: BufRead> DocParser<R> {
 
            try!(self.grouped_block())
         || try!(self.code_block())
-        || try!(self.table_block())
+        || try!(self.table_block(&attributes))
         || try!(self.list_block())
         || try!(self.text_block())
         ;
@@ -316,6 +741,8 @@ impl<R: BufRead> DocParser<R> {
     /// [arg, hurr_durr_im_an_arg]
     /// Description of the hurr durr arg that is not shown in the output.
     fn attribute(&mut self) -> Result<Option<Attribute>> {
+        try!(self.peek_line());
+        let pos = self.line_offset + self.next_no;
         let mut line = try!(self.peek_line());
 
         if line.len() == 0 || line[0] != b'[' || line[line.len()-1] != b']' {
@@ -323,13 +750,13 @@ impl<R: BufRead> DocParser<R> {
         }
         line = &line[1..line.len()-1];
 
-        if let Some(pos) = memchr(line, b',') {
-            let name = try!(line[..pos].try_to());
-            let args = try!(line[pos+1..].try_to());
-            Ok(Some(Attribute { name: name, args: Some(args) }))
+        if let Some(comma) = memchr(line, b',') {
+            let name = try!(line[..comma].try_to());
+            let args = try!(line[comma+1..].try_to());
+            Ok(Some(Attribute { name: name, args: Some(args), line: pos }))
         } else {
             let name = try!(line.try_to());
-            Ok(Some(Attribute { name: name, args: None }))
+            Ok(Some(Attribute { name: name, args: None, line: pos }))
         }
     }
 
@@ -351,13 +778,18 @@ impl<R: BufRead> DocParser<R> {
         }
 
         // Discard GroupStart
-        self.next_line();
+        try!(self.next_line());
+        let start = self.line;
 
         let mut blocks = Vec::new();
         loop {
             try!(self.blank_lines());
 
-            if self.eof || try!(self.peek_line()) == &b"}"[..] {
+            if try!(self.peek_line()) == &b"}"[..] {
+                break;
+            }
+            if self.eof {
+                try!(self.warn(start, 1, "unterminated group"));
                 break;
             }
 
@@ -386,18 +818,25 @@ impl<R: BufRead> DocParser<R> {
         
         // Discard CodeDelim
         try!(self.next_line());
+        let start = self.line;
 
         let mut code = Vec::new();
+        let mut closed = false;
 
         while !self.eof {
             let line = try!(self.next_line());
             if &line == &b"----"[..] {
+                closed = true;
                 break;
             }
             try!(code.push_all(&line));
             try!(code.push_all(b"\n"));
         }
 
+        if !closed {
+            try!(self.warn(start, 1, "unterminated code block"));
+        }
+
         // Pop the last \n
         code.pop();
 
@@ -413,39 +852,61 @@ impl<R: BufRead> DocParser<R> {
 
     /// TableDelim <- '|===' $
     /// ColumnText <- (!'|' ('\\\\' / '\\|' / .))*
-    /// SimpleRow  <- ('|' ColumnText)+ $
+    /// CellSpan   <- [0-9]+ '+' / [0-9]* '.' [0-9]+ '+'
+    /// SimpleRow  <- (CellSpan? '|' ColumnText)+ $
     /// Row        <- (SimpleRow / (!$ Block))* $
     /// TableBlock <- TableDelim ($* !TableDelim Row)* $* TableDelim?
-    fn table_block(&mut self) -> Result<bool> {
+    ///
+    /// [table, header]
+    /// |===
+    /// |Flag |Meaning
+    ///
+    /// |`O_RDONLY` 2.1+|Read only
+    /// |`O_RDWR`
+    /// |===
+    fn table_block(&mut self, attributes: &[Attribute]) -> Result<bool> {
         if try!(self.peek_line()) != "|===" {
             return Ok(false);
         }
 
         // Discard TableDelim
         try!(self.next_line());
+        let start = self.line;
+
+        let (header, align) = try!(self.table_options(attributes, start));
 
         let mut rows = Vec::new();
+        // The line where each row starts.
+        let mut lines = Vec::new();
 
         'table: loop {
             try!(self.blank_lines());
 
-            if self.eof || try!(self.peek_line()) == "|===" {
+            if try!(self.peek_line()) == "|===" {
+                break 'table;
+            }
+            if self.eof {
+                try!(self.warn(start, 1, "unterminated table"));
                 break 'table;
             }
 
             let mut cols = Vec::new();
+            try!(lines.reserve(1));
+            lines.push(self.next_no);
 
             'row: loop {
                 if try!(self.peek_line()) == "" {
                     break 'row;
                 }
 
-                if try!(self.peek_line())[0] == b'|' {
+                if let Some(first) = row_start(try!(self.peek_line())) {
                     let line = try!(self.next_line());
+                    let pos = self.line;
 
                     // simple row
-                    let mut col_start = 1;
-                    let mut i = 1;
+                    let mut span = cell_span(&line[..first]).unwrap_or(Span::single());
+                    let mut col_start = first + 1;
+                    let mut i = first + 1;
                     while i < line.len() {
                         // pass escaped \\ and | to the next level
                         if i + 1 < line.len() && line[i] == b'\\' {
@@ -455,25 +916,37 @@ impl<R: BufRead> DocParser<R> {
                             }
                         }
                         if line[i] == b'|' {
-                            let text = try!(TextParser::all_in_one(&line[col_start..i],
-                                                                   &self.vars));
-                            col_start = i + 1;
+                            // The span of the next column ends the text of this one.
+                            let (end, next) = match span_suffix(&line[col_start..i]) {
+                                Some((len, next)) => (col_start + len, next),
+                                _ => (i, Span::single()),
+                            };
+                            let text = try!(self.text(&line[col_start..end],
+                                                      &[(0, pos, col_start + 1)]));
                             try!(cols.reserve(1));
-                            cols.push(TableCol::Simple(text));
+                            cols.push(TableCell {
+                                span: span,
+                                inner: TableCol::Simple(text),
+                            });
+                            span = next;
+                            col_start = i + 1;
                         }
                         i += 1;
                     }
                     // The last column
-                    let text = try!(TextParser::all_in_one(&line[col_start..i],
-                                                           &self.vars));
+                    let text = try!(self.text(&line[col_start..i],
+                                              &[(0, pos, col_start + 1)]));
                     try!(cols.reserve(1));
-                    cols.push(TableCol::Simple(text));
+                    cols.push(TableCell { span: span, inner: TableCol::Simple(text) });
                 } else {
                     // block row
                     try!(self.block());
                     let block = self.pop_block();
                     try!(cols.reserve(1));
-                    cols.push(TableCol::Complex(block));
+                    cols.push(TableCell {
+                        span: Span::single(),
+                        inner: TableCol::Complex(block),
+                    });
                 }
             }
 
@@ -484,9 +957,12 @@ impl<R: BufRead> DocParser<R> {
         // Discard trailing TableDelim if any
         try!(self.next_line());
 
+        let table = Table { header: header, align: align, rows: rows };
+        try!(self.check_table(&table, &lines));
+
         let block = BlockData {
             attributes: Vec::new(),
-            inner: Block::Table(rows),
+            inner: Block::Table(table),
         };
 
         try!(self.parts.reserve(1));
@@ -494,35 +970,103 @@ impl<R: BufRead> DocParser<R> {
         Ok(true)
     }
 
-    /// SimpleListEl <- '* ' .* $ ('  ' .* $)*
-    /// BlockListEl  <- '**' $ Block
+    /// Reads the options of a table from a `table` attribute.
+    ///
+    /// [argument, line]
+    /// The line of the TableDelim. Problems are reported there.
+    ///
+    /// = Remarks
+    ///
+    /// The arguments of the attribute are separated by commas outside of quotes:
+    ///
+    /// `header`:: The first row is a header row.
+    /// `cols="a,b,..."`:: The alignment of the columns: `<` for left, `^` for centered,
+    ///   and `>` for right alignment. An empty entry keeps the default alignment.
+    fn table_options(&mut self, attributes: &[Attribute],
+                     line: usize) -> Result<(bool, Vec<Align>)> {
+        let mut header = false;
+        let mut align = Vec::new();
+        for attr in attributes {
+            if attr.name.as_str().trim() != "table" {
+                continue;
+            }
+            let args = match attr.args {
+                Some(ref args) => args,
+                _ => continue,
+            };
+            for arg in try!(attribute_args(args)) {
+                if arg == &b"header"[..] {
+                    header = true;
+                } else if arg.starts_with(b"cols=") {
+                    let mut val = &arg[b"cols=".len()..];
+                    if val.len() > 1 && val[0] == b'"' && val[val.len() - 1] == b'"' {
+                        val = &val[1..val.len() - 1];
+                    }
+                    align.clear();
+                    for col in val.split(|&c| c == b',') {
+                        let a = match trim(col) {
+                            b"" => Align::Default,
+                            b"<" => Align::Left,
+                            b"^" => Align::Center,
+                            b">" => Align::Right,
+                            _ => {
+                                try!(self.warn(line, 1, "unknown column alignment"));
+                                Align::Default
+                            },
+                        };
+                        try!(align.reserve(1));
+                        align.push(a);
+                    }
+                } else {
+                    try!(self.warn(line, 1, "unknown table option"));
+                }
+            }
+        }
+        Ok((header, align))
+    }
+
+    /// Warns about rows that cover a different number of columns than the first row or
+    /// the `cols` option.
+    fn check_table(&mut self, table: &Table, lines: &[usize]) -> Result {
+        let mut width = table.align.len();
+        for (i, &(_, w)) in try!(table.layout()).iter().enumerate() {
+            if width == 0 {
+                width = w;
+            } else if w != width {
+                try!(self.warn(lines[i], 1, "row has an inconsistent number of columns"));
+            }
+        }
+        Ok(())
+    }
+
+    /// SimpleListEl <- ListMarker ElText
+    /// BlockListEl  <- ('**' / '..') $ Block
     /// ListEl       <- SimpleListEl / BlockListEl
-    /// ListBlock    <- ListEl+
+    /// DefinitionEl <- DefTerm ElText
+    /// ListBlock    <- ListEl+ / DefinitionEl+
+    ///
+    /// * unordered
+    /// . ordered
+    /// term:: definition
     fn list_block(&mut self) -> Result<bool> {
+        let kind = match el_start(try!(self.peek_line())) {
+            Some(ElStart::Simple(k)) => k,
+            Some(ElStart::Complex(k)) => k,
+            Some(ElStart::Definition(_)) => return self.definition_list(),
+            None => return Ok(false),
+        };
+
         let mut list = Vec::new();
 
         loop {
-            let simple = {
-                let line = try!(self.peek_line());
-                if line.len() < 2 { break; }
-                if line == &b"**"[..] {
-                    false
-                } else if &line[..2] == &b"* "[..] {
-                    true
-                } else {
-                    break;
-                }
+            let simple = match el_start(try!(self.peek_line())) {
+                Some(ElStart::Simple(k)) if k == kind => true,
+                Some(ElStart::Complex(k)) if k == kind => false,
+                _ => break,
             };
             let line = try!(self.next_line());
             let el = if simple {
-                let mut buf: Vec<_> = try!(line[2..].try_to());
-                while try!(self.peek_line()).starts_with(b"  ") {
-                    let line = try!(self.next_line());
-                    try!(buf.push_all(b" "));
-                    try!(buf.push_all(&line[2..]));
-                }
-                let text = try!(TextParser::all_in_one(&buf, &self.vars));
-                ListEl::Simple(text)
+                try!(self.list_el(&line[2..], 3))
             } else {
                 try!(self.block());
                 ListEl::Complex(self.pop_block())
@@ -531,33 +1075,130 @@ impl<R: BufRead> DocParser<R> {
             list.push(el);
         }
 
-        if list.len() == 0 {
-            return Ok(false);
+        let block = BlockData { attributes: Vec::new(), inner: Block::List(kind, list) };
+        try!(self.parts.reserve(1));
+        self.parts.push(Part::Block(block));
+        Ok(true)
+    }
+
+    /// DefinitionEl <- DefTerm ElText
+    fn definition_list(&mut self) -> Result<bool> {
+        let mut list = Vec::new();
+
+        loop {
+            let len = match el_start(try!(self.peek_line())) {
+                Some(ElStart::Definition(len)) => len,
+                _ => break,
+            };
+            let line = try!(self.next_line());
+            let pos = self.line;
+            let term = try!(self.text(&line[..len], &[(0, pos, 1)]));
+            let desc = if line.len() > len + 2 {
+                try!(self.list_el(&line[len+3..], len + 4))
+            } else {
+                try!(self.list_el(&[], len + 3))
+            };
+            try!(list.reserve(1));
+            list.push(Definition { term: term, desc: desc });
         }
 
-        let block = BlockData { attributes: Vec::new(), inner: Block::List(list) };
+        let block = BlockData { attributes: Vec::new(), inner: Block::Definitions(list) };
         try!(self.parts.reserve(1));
         self.parts.push(Part::Block(block));
         Ok(true)
     }
 
+    /// NestedList <- ('  ' .* $)+
+    /// ElText     <- .* $ (!('  ' ElStart) '  ' .* $)* NestedList?
+    ///
+    /// [argument, column]
+    /// The column of `first`, which is the rest of the current line.
+    fn list_el(&mut self, first: &[u8], column: usize) -> Result<ListEl> {
+        let mut buf: Vec<_> = try!(first.try_to());
+        let mut starts = try!(Vec::with_capacity(1));
+        starts.push((0, self.line, column));
+        loop {
+            {
+                let line = try!(self.peek_line());
+                if !line.starts_with(b"  ") || el_start(&line[2..]).is_some() {
+                    break;
+                }
+            }
+            let line = try!(self.next_line());
+            if buf.len() > 0 {
+                try!(line_sep(&mut buf));
+            }
+            try!(starts.reserve(1));
+            starts.push((buf.len(), self.line, 3));
+            try!(buf.push_all(&line[2..]));
+        }
+        let text = try!(self.text(&buf, &starts));
+
+        try!(self.peek_line());
+        let first_nested = self.next_no;
+        let mut nested = Vec::new();
+        while try!(self.peek_line()).starts_with(b"  ") {
+            let line = try!(self.next_line());
+            try!(nested.push_all(&line[2..]));
+            try!(nested.push_all(b"\n"));
+        }
+        if nested.len() == 0 {
+            return Ok(ListEl::Simple(text));
+        }
+
+        let mut parser = DocParser::new(&nested[..], self.env,
+                                        self.line_offset + first_nested - 1,
+                                        self.column_offset + 2);
+        // The nested blocks see the variables of the enclosing document.
+        mem::swap(&mut parser.vars, &mut self.vars);
+        let res = parser.document();
+        mem::swap(&mut parser.vars, &mut self.vars);
+        try!(res);
+
+        try!(self.diags.reserve(parser.diags.len()));
+        for diag in parser.diags {
+            self.diags.push(diag);
+        }
+
+        let mut blocks = Vec::new();
+        for part in parser.parts {
+            if let Part::Block(b) = part {
+                try!(blocks.reserve(1));
+                blocks.push(b);
+            }
+        }
+        let block = if blocks.len() == 1 {
+            blocks.pop().unwrap()
+        } else {
+            BlockData { attributes: Vec::new(), inner: Block::Grouped(blocks) }
+        };
+        Ok(ListEl::Nested(text, block))
+    }
+
     /// TextBlock <- (.+ $)* $?
     fn text_block(&mut self) -> Result<bool> {
         let mut text: Vec<_> = Vec::new();
+        let mut starts = Vec::new();
 
         loop {
             let line = try!(self.next_line());
             if line.len() == 0 {
                 break;
             }
+            try!(starts.reserve(1));
+            starts.push((text.len(), self.line, 1));
             try!(text.push_all(&line));
-            try!(text.push_all(b" "));
+            try!(line_sep(&mut text));
         }
 
-        // Pop the last space
+        // Pop the last separator
         text.pop();
 
-        let text = try!(TextParser::all_in_one(&text, &self.vars));
+        if starts.len() == 0 {
+            try!(starts.reserve(1));
+            starts.push((0, self.line, 1));
+        }
+        let text = try!(self.text(&text, &starts));
 
         let block = BlockData {
             attributes: Vec::new(),
@@ -571,19 +1212,440 @@ impl<R: BufRead> DocParser<R> {
     }
 }
 
+/// Appends the separator between two lines of text. Lines that end with ` +` are
+/// followed by a line break.
+fn line_sep(buf: &mut Vec<u8>) -> Result {
+    if buf.ends_with(b" +") {
+        buf.push_all(b"\n")
+    } else {
+        buf.push_all(b" ")
+    }
+}
+
+fn is_word(c: u8) -> bool {
+    match c {
+        b'a'...b'z' | b'A'...b'Z' | b'0'...b'9' => true,
+        _ => false,
+    }
+}
+
+/// How the delimiters of closed formatting are matched.
+#[derive(Copy, Eq)]
+enum Delim {
+    /// The delimiters can appear anywhere.
+    Free,
+    /// The delimiters must not be adjacent to Word characters on the outside.
+    Word,
+    /// The content must not contain spaces.
+    NoSpace,
+}
+
+/// The kind of list element at the start of a line.
+#[derive(Copy)]
+enum ElStart {
+    /// `* ` or `. `
+    Simple(ListKind),
+    /// `**` or `..`
+    Complex(ListKind),
+    /// `term::`, contains the length of the term.
+    Definition(usize),
+}
+
+/// Returns the target and the attributes of an include directive.
+pub fn include_directive(line: &[u8]) -> Option<(&[u8], &[u8])> {
+    if !line.starts_with(b"include::") || line.len() == 0 || line[line.len() - 1] != b']' {
+        return None;
+    }
+    let rest = &line[b"include::".len()..line.len() - 1];
+    match memchr(rest, b'[') {
+        Some(p) if p > 0 => Some((&rest[..p], &rest[p+1..])),
+        _ => None,
+    }
+}
+
+/// Selects the lines of an included file.
+///
+/// [argument, attrs]
+/// The comma separated attributes of the include directive:
+///
+/// `lines=a..b;c..d`:: Only the lines in the ranges are included. Lines are counted from
+///   1 and the ends are inclusive. A missing end means the end of the file.
+/// `tag=name`:: Only the lines between `tag::name[]` and `end::name[]` are included.
+///
+/// = Remarks
+///
+/// Lines that contain tag markers are never included. The inner result contains a
+/// message if the attributes are invalid.
+fn include_lines(content: &[u8],
+                 attrs: &[u8]) -> Result<Result<Vec<Vec<u8>>, &'static str>> {
+    let mut ranges = Vec::new();
+    let mut tag = None;
+    for attr in attrs.split(|&c| c == b',') {
+        if attr.starts_with(b"lines=") {
+            for range in attr[b"lines=".len()..].split(|&c| c == b';') {
+                let (lo, hi) = match memchr(range, b'.') {
+                    Some(p) if range[p..].starts_with(b"..") => {
+                        (&range[..p], &range[p+2..])
+                    },
+                    _ => (range, range),
+                };
+                let lo = match number(lo) {
+                    Some(lo) => lo,
+                    _ => return Ok(Err("invalid line range in include")),
+                };
+                let hi = match number(hi) {
+                    Some(hi) => hi,
+                    _ if hi.len() == 0 => !0,
+                    _ => return Ok(Err("invalid line range in include")),
+                };
+                try!(ranges.reserve(1));
+                ranges.push((lo, hi));
+            }
+        } else if attr.starts_with(b"tag=") {
+            tag = Some(&attr[b"tag=".len()..]);
+        } else if attr.len() > 0 {
+            return Ok(Err("unknown include attribute"));
+        }
+    }
+
+    let (start, end) = match tag {
+        Some(tag) => {
+            let mut start: Vec<_> = try!(b"tag::".try_to());
+            try!(start.push_all(tag));
+            try!(start.push_all(b"[]"));
+            let mut end: Vec<_> = try!(b"end::".try_to());
+            try!(end.push_all(tag));
+            try!(end.push_all(b"[]"));
+            (Some(start), Some(end))
+        },
+        _ => (None, None),
+    };
+
+    let mut lines = Vec::new();
+    let mut in_tag = tag.is_none();
+    let mut found = tag.is_none();
+    let mut cont: Option<Vec<u8>> = None;
+    let mut no = 0;
+    for line in content.split(|&c| c == b'\n') {
+        no += 1;
+        if let Some(ref start) = start {
+            if contains(line, start) {
+                in_tag = true;
+                found = true;
+            }
+        }
+        if let Some(ref end) = end {
+            if contains(line, end) {
+                in_tag = false;
+            }
+        }
+        if !in_tag || contains(line, b"tag::") || contains(line, b"end::") {
+            continue;
+        }
+        if ranges.len() > 0 && ranges.find(|r| r.0 <= no && no <= r.1).is_none() {
+            continue;
+        }
+        // Joined lines as in the document itself.
+        let mut full = match cont.take() {
+            Some(c) => c,
+            _ => Vec::new(),
+        };
+        try!(full.push_all(line));
+        if full.len() > 0 && full[full.len() - 1] == b'\\' {
+            full.pop();
+            cont = Some(full);
+            continue;
+        }
+        try!(lines.reserve(1));
+        lines.push(full);
+    }
+    if let Some(c) = cont {
+        try!(lines.reserve(1));
+        lines.push(c);
+    }
+    // The trailing newline does not start a new line.
+    if content.len() > 0 && content[content.len() - 1] == b'\n' {
+        if lines.len() > 0 && lines[lines.len() - 1].len() == 0 {
+            lines.pop();
+        }
+    }
+
+    if !found {
+        return Ok(Err("tag not found in included file"));
+    }
+    Ok(Ok(lines))
+}
+
+/// Parses a decimal number with at most nine digits.
+pub fn number(s: &[u8]) -> Option<usize> {
+    if s.len() == 0 || s.len() > 9 {
+        return None;
+    }
+    let mut n = 0;
+    for &c in s {
+        match c {
+            b'0'...b'9' => n = 10 * n + (c - b'0') as usize,
+            _ => return None,
+        }
+    }
+    Some(n)
+}
+
+fn contains(s: &[u8], pat: &[u8]) -> bool {
+    let mut i = 0;
+    while i + pat.len() <= s.len() {
+        if s[i..].starts_with(pat) {
+            return true;
+        }
+        i += 1;
+    }
+    false
+}
+
+/// CellSpan <- [0-9]+ '+' / [0-9]* '.' [0-9]+ '+'
+fn cell_span(s: &[u8]) -> Option<Span> {
+    if s.len() < 2 || s[s.len() - 1] != b'+' {
+        return None;
+    }
+    let s = &s[..s.len() - 1];
+    let (cols, rows) = match memchr(s, b'.') {
+        Some(0) => (Some(1), number(&s[1..])),
+        Some(p) => (number(&s[..p]), number(&s[p+1..])),
+        _ => (number(s), Some(1)),
+    };
+    match (cols, rows) {
+        (Some(c), Some(r)) if c > 0 && r > 0 => Some(Span { cols: c, rows: r }),
+        _ => None,
+    }
+}
+
+/// Returns the position of the first `|` if a line starts a SimpleRow.
+fn row_start(line: &[u8]) -> Option<usize> {
+    match memchr(line, b'|') {
+        Some(0) => Some(0),
+        Some(p) if cell_span(&line[..p]).is_some() => Some(p),
+        _ => None,
+    }
+}
+
+/// Returns the length of the text before a CellSpan at the end of a column.
+fn span_suffix(text: &[u8]) -> Option<(usize, Span)> {
+    let mut i = text.len();
+    while i > 0 && text[i - 1] != b' ' {
+        i -= 1;
+    }
+    if i == 0 {
+        return None;
+    }
+    cell_span(&text[i..]).map(|span| (i - 1, span))
+}
+
+/// Splits the arguments of an attribute at commas outside of double quotes.
+fn attribute_args(args: &[u8]) -> Result<Vec<&[u8]>> {
+    let mut list = Vec::new();
+    let mut quoted = false;
+    let mut start = 0;
+    for i in 0..args.len() + 1 {
+        if i < args.len() && args[i] == b'"' {
+            quoted = !quoted;
+        }
+        if i == args.len() || (args[i] == b',' && !quoted) {
+            let arg = trim(&args[start..i]);
+            if arg.len() > 0 {
+                try!(list.reserve(1));
+                list.push(arg);
+            }
+            start = i + 1;
+        }
+    }
+    Ok(list)
+}
+
+/// Removes leading and trailing spaces and tabs.
+pub fn trim(s: &[u8]) -> &[u8] {
+    let mut start = 0;
+    let mut end = s.len();
+    while start < end && (s[start] == b' ' || s[start] == b'\t') {
+        start += 1;
+    }
+    while end > start && (s[end - 1] == b' ' || s[end - 1] == b'\t') {
+        end -= 1;
+    }
+    &s[start..end]
+}
+
+/// VarDef <- ':' VarName ': ' .* $
+///
+/// Returns the position of the `:` after the name.
+pub fn var_def_line(line: &[u8]) -> Option<usize> {
+    if line.len() < 3 || line[0] != b':' { return None; }
+    let mut i = 1;
+    while i < line.len() {
+        match line[i] {
+            b'a'...b'z' | b'A'...b'Z' | b'_' => { },
+            b':' => break,
+            _ => return None,
+        }
+        i += 1;
+    }
+    if i + 1 >= line.len() || line[i+1] != b' ' { return None; }
+    Some(i)
+}
+
+/// Collects the variables defined at the top level of a document.
+pub fn var_defs(input: &[u8], vars: &mut Vec<(Vec<u8>, Vec<u8>)>) -> Result {
+    let mut in_code = false;
+    for line in input.split(|&c| c == b'\n') {
+        if line == &b"----"[..] {
+            in_code = !in_code;
+        }
+        if in_code {
+            continue;
+        }
+        if let Some(end) = var_def_line(line) {
+            try!(vars.reserve(1));
+            vars.push((try!(line[1..end].try_to()), try!(line[end+2..].try_to())));
+        }
+    }
+    Ok(())
+}
+
+/// VarRef <- '{' VarName '}'
+///
+/// Returns the length of the name if `s` starts with a variable reference.
+pub fn var_ref(s: &[u8]) -> Option<usize> {
+    if s.len() == 0 || s[0] != b'{' { return None; }
+    let mut i = 1;
+    while i < s.len() {
+        match s[i] {
+            b'a'...b'z' | b'A'...b'Z' | b'_' => { },
+            b'}' if i > 1 => return Some(i - 1),
+            _ => return None,
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Returns whether a document defines or references variables outside of code blocks.
+///
+/// = Remarks
+///
+/// References are found the way they are substituted, whether or not the variable is
+/// defined.
+pub fn uses_vars(input: &[u8]) -> bool {
+    let mut in_code = false;
+    for line in input.split(|&c| c == b'\n') {
+        if line == &b"----"[..] {
+            in_code = !in_code;
+        }
+        if in_code {
+            continue;
+        }
+        if var_def_line(line).is_some() {
+            return true;
+        }
+        let mut i = 0;
+        while i < line.len() {
+            // \\ and \{ are not substituted.
+            if line[i] == b'\\' && i + 1 < line.len()
+                    && (line[i+1] == b'\\' || line[i+1] == b'{') {
+                i += 2;
+                continue;
+            }
+            if var_ref(&line[i..]).is_some() {
+                return true;
+            }
+            i += 1;
+        }
+    }
+    false
+}
+
+/// Returns whether a line starts a list element.
+pub fn starts_list_el(line: &[u8]) -> bool {
+    el_start(line).is_some()
+}
+
+/// Returns whether a line is not parsed as the first line of a TextBlock.
+pub fn starts_block(line: &[u8]) -> bool {
+    if line == &b"{"[..] || line == &b"----"[..] || line == &b"|==="[..] {
+        return true;
+    }
+    if line.len() > 1 && line[0] == b'[' && line[line.len() - 1] == b']' {
+        return true;
+    }
+    if starts_list_el(line) {
+        return true;
+    }
+    // SectionHeader
+    let mut i = 0;
+    while i < line.len() && line[i] == b'=' {
+        i += 1;
+    }
+    if i > 0 && i < line.len() && line[i] == b' ' {
+        return true;
+    }
+    var_def_line(line).is_some()
+}
+
+fn el_start(line: &[u8]) -> Option<ElStart> {
+    if line == &b"**"[..] {
+        Some(ElStart::Complex(ListKind::Unordered))
+    } else if line == &b".."[..] {
+        Some(ElStart::Complex(ListKind::Ordered))
+    } else if line.starts_with(b"* ") {
+        Some(ElStart::Simple(ListKind::Unordered))
+    } else if line.starts_with(b". ") {
+        Some(ElStart::Simple(ListKind::Ordered))
+    } else {
+        def_term(line).map(|len| ElStart::Definition(len))
+    }
+}
+
+/// DefTerm <- !' ' (!'::' .)+ '::' (' ' / &$)
+fn def_term(line: &[u8]) -> Option<usize> {
+    if line.len() == 0 || line[0] == b' ' {
+        return None;
+    }
+    let mut i = 1;
+    while i + 1 < line.len() {
+        if line[i] == b':' && line[i+1] == b':' {
+            if i + 2 == line.len() || line[i+2] == b' ' {
+                return Some(i);
+            }
+            return None;
+        }
+        i += 1;
+    }
+    None
+}
+
 struct TextParser<'a> {
     text: &'a [u8],
     current: Option<Vec<u8>>,
     past: Vec<TextBlock>,
+
+    /// The offset of the start of `text` in the text passed to `all_in_one`.
+    base: usize,
+    /// The initial length of `text`.
+    len: usize,
+    /// (offset, message) pairs of the problems found while parsing.
+    errs: &'a mut Vec<(usize, &'static str)>,
 }
 
 impl<'a> TextParser<'a> {
-    fn all_in_one(text: &[u8], vars: &[(Vec<u8>, Vec<u8>)]) -> Result<TextBlock> {
-        let vec = try!(TextParser::subst(text, vars));
-        TextParser::parse(&vec)
+    fn all_in_one(text: &[u8], vars: &[(Vec<u8>, Vec<u8>)],
+                  globals: &[(Vec<u8>, Vec<u8>)],
+                  errs: &mut Vec<(usize, &'static str)>) -> Result<TextBlock> {
+        let vec = try!(TextParser::subst(text, vars, globals));
+        TextParser::parse(&vec, 0, errs)
     }
 
-    fn subst(text: &[u8], vars: &[(Vec<u8>, Vec<u8>)]) -> Result<Vec<u8>> {
+    /// Substitutes the variables of the document, `vars`, and the global variables,
+    /// `globals`.
+    fn subst(text: &[u8], vars: &[(Vec<u8>, Vec<u8>)],
+             globals: &[(Vec<u8>, Vec<u8>)]) -> Result<Vec<u8>> {
         let mut text: Vec<_> = try!(text.try_to());
         let mut next = try!(Vec::with_capacity(text.len()));
         loop {
@@ -604,25 +1666,25 @@ impl<'a> TextParser<'a> {
                 }
 
                 // Check if we've found a variable
-                if text[i] == b'{' {
-                    let mut j = i + 1;
-                    let mut looks_like_var = false;
-                    while j < text.len() {
-                        match text[j] {
-                            b'a'...b'z' | b'A'...b'Z' | b'_' => { },
-                            b'}' => { looks_like_var = j > i + 1; break; }
-                            _ => break,
+                if let Some(len) = var_ref(&text[i..]) {
+                    let j = i + 1 + len;
+                    for &(ref var, ref sub) in vars {
+                        if var == &text[i+1..j] {
+                            did_substitute = true;
+                            try!(next.push_all(sub));
+                            i = j + 1;
+                            continue 'outer;
                         }
-                        j += 1;
                     }
-                    if looks_like_var {
-                        for &(ref var, ref sub) in vars {
-                            if var == &text[i+1..j] {
-                                did_substitute = true;
-                                try!(next.push_all(sub));
-                                i = j + 1;
-                                continue 'outer;
-                            }
+                    let mut k = globals.len();
+                    while k > 0 {
+                        k -= 1;
+                        let (ref var, ref sub) = globals[k];
+                        if var == &text[i+1..j] {
+                            did_substitute = true;
+                            try!(next.push_all(sub));
+                            i = j + 1;
+                            continue 'outer;
                         }
                     }
                 }
@@ -644,17 +1706,27 @@ impl<'a> TextParser<'a> {
         Ok(text)
     }
 
-    fn parse(text: &[u8]) -> Result<TextBlock> {
+    fn parse(text: &[u8], base: usize,
+             errs: &mut Vec<(usize, &'static str)>) -> Result<TextBlock> {
         let mut text = TextParser {
             text: &text,
             current: None,
             past: Vec::new(),
+
+            base: base,
+            len: text.len(),
+            errs: errs,
         };
 
         while text.text.len() > 0 {
             let res =    try!(text.escape_sequence())
+                      || try!(text.line_break())
                       || try!(text.bold())
                       || try!(text.raw())
+                      || try!(text.italic())
+                      || try!(text.strikethrough())
+                      || try!(text.superscript())
+                      || try!(text.subscript())
                       || try!(text.link())
                       ;
             if !res {
@@ -691,6 +1763,14 @@ impl<'a> TextParser<'a> {
             1
         } else if self.text[1] == b'*' {
             1
+        } else if self.text[1] == b'_' {
+            1
+        } else if self.text[1] == b'~' {
+            1
+        } else if self.text[1] == b'^' {
+            1
+        } else if self.text[1] == b'+' {
+            1
         } else if self.text[1] == b'{' {
             1
         } else if self.text[1] == b']' {
@@ -707,6 +1787,17 @@ impl<'a> TextParser<'a> {
         Ok(true)
     }
 
+    /// The offset of the unparsed text.
+    fn offset(&self) -> usize {
+        self.base + self.len - self.text.len()
+    }
+
+    fn warn(&mut self, offset: usize, msg: &'static str) -> Result {
+        try!(self.errs.reserve(1));
+        self.errs.push((offset, msg));
+        Ok(())
+    }
+
     fn append_raw(&mut self, r: &[u8]) -> Result {
         if self.current.is_none() { self.current = Some(Vec::new()); }
         try!(self.current.as_mut().unwrap().push_all(r));
@@ -745,13 +1836,75 @@ impl<'a> TextParser<'a> {
             text.push(self.text[i]);
             i += 1;
         }
+        let offset = self.offset();
+        if i == self.text.len() {
+            let msg = match attr {
+                TextAttr::Bold => "unterminated bold text",
+                _ => "unterminated raw text",
+            };
+            try!(self.warn(offset, msg));
+        }
         self.text.consume(i + 1);
         self.finish_raw();
         let block = if cont {
-            try!(TextParser::parse(&text))
+            try!(TextParser::parse(&text, offset + 1, &mut *self.errs))
         } else {
             TextBlock { attribute: None, inner: Text::Raw(text) }
         };
+        self.push_attr(block, attr)
+    }
+
+    fn italic(&mut self) -> Result<bool> {
+        self.closed(b"_", TextAttr::Italic, Delim::Word)
+    }
+
+    fn strikethrough(&mut self) -> Result<bool> {
+        self.closed(b"~~", TextAttr::Strikethrough, Delim::Free)
+    }
+
+    fn superscript(&mut self) -> Result<bool> {
+        self.closed(b"^", TextAttr::Superscript, Delim::NoSpace)
+    }
+
+    fn subscript(&mut self) -> Result<bool> {
+        self.closed(b"~", TextAttr::Subscript, Delim::NoSpace)
+    }
+
+    /// Formatting that is only recognized if the closing delimiter exists. The content
+    /// is parsed recursively.
+    fn closed(&mut self, delim: &[u8], attr: TextAttr, mode: Delim) -> Result<bool> {
+        if !self.text.starts_with(delim) { return Ok(false); }
+        if mode == Delim::Word {
+            if let Some(ref c) = self.current {
+                if c.len() > 0 && is_word(c[c.len() - 1]) { return Ok(false); }
+            }
+        }
+        let start = delim.len();
+        let mut i = start;
+        loop {
+            if i >= self.text.len() { return Ok(false); }
+            if i > start && self.text[i..].starts_with(delim) {
+                let end = i + delim.len();
+                if mode != Delim::Word || end == self.text.len() || !is_word(self.text[end]) {
+                    break;
+                }
+            }
+            if mode == Delim::NoSpace && self.text[i] == b' ' { return Ok(false); }
+            // Escape sequences are handled by the recursive parser.
+            if self.text[i] == b'\\' && i + 1 < self.text.len() {
+                i += 1;
+            }
+            i += 1;
+        }
+        let offset = self.offset() + start;
+        let text = self.text;
+        let block = try!(TextParser::parse(&text[start..i], offset, &mut *self.errs));
+        self.text.consume(i + delim.len());
+        try!(self.finish_raw());
+        self.push_attr(block, attr)
+    }
+
+    fn push_attr(&mut self, block: TextBlock, attr: TextAttr) -> Result<bool> {
         let mut block = if block.attribute.is_none() {
             block
         } else {
@@ -765,6 +1918,16 @@ impl<'a> TextParser<'a> {
         Ok(true)
     }
 
+    /// LineBreak <- ' +' $
+    fn line_break(&mut self) -> Result<bool> {
+        if !self.text.starts_with(b" +\n") { return Ok(false); }
+        self.text.consume(3);
+        try!(self.finish_raw());
+        try!(self.past.reserve(1));
+        self.past.push(TextBlock { attribute: None, inner: Text::LineBreak });
+        Ok(true)
+    }
+
     fn link(&mut self) -> Result<bool> {
         if !self.text.starts_with(b"link:") { return Ok(false); }
         let mut i = b"link:".len();
@@ -794,14 +1957,21 @@ impl<'a> TextParser<'a> {
             }
             if j < self.text.len() {
                 link_text = Some(link_text_);
+            } else {
+                let offset = self.offset() + i;
+                try!(self.warn(offset, "unterminated link text"));
             }
         }
 
         self.finish_raw();
 
+        let offset = self.offset() + i + 1;
         let link = try!(self.text[b"link:".len()..i].try_to());
         let link_text = match link_text {
-            Some(t) => Some(try!(Box::new()).set(try!(TextParser::parse(&t)))),
+            Some(t) => {
+                let text = try!(TextParser::parse(&t, offset, &mut *self.errs));
+                Some(try!(Box::new()).set(text))
+            },
             _ => None,
         };
 
@@ -0,0 +1,446 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Merging of `doc.json` files generated for different targets
+//!
+//! The merge happens on the Json level before the AST is built. Items are identified
+//! by their name and kind (and their trait and self type for impls). Every item of the
+//! merged document gets an additional `lrs_doc_targets` field that lists the targets
+//! the item was found on.
+//!
+//! The DefIds of the local crate are only unique within one document. Before a document
+//! is merged, its items that also exist in the merged document get the DefIds of those
+//! items and all other local DefIds are moved past the largest DefId of the merged
+//! document.
+
+use std::{mem};
+use std::vec::{Vec};
+use std::string::{ByteStr};
+
+use json::{Value, Array};
+
+/// The name of the field that holds the targets of an item.
+pub const TARGETS: &'static str = "lrs_doc_targets";
+
+/// Merges the documents of several targets into the first one.
+///
+/// [argument, docs]
+/// Pairs of target names and the corresponding documents.
+pub fn merge(mut docs: Vec<(Vec<u8>, Value)>) -> Result<Value> {
+    if docs.len() == 0 {
+        error!("no documents to merge");
+    }
+
+    let mut others = try!(Vec::with_capacity(docs.len() - 1));
+    while docs.len() > 1 {
+        others.push(docs.pop().unwrap());
+    }
+    let (target, mut base) = docs.pop().unwrap();
+
+    try!(annotate(try!(root(&mut base)), &target));
+
+    while let Some((target, mut doc)) = others.pop() {
+        try!(renumber(&mut base, &mut doc));
+        let src = try!(root(&mut doc));
+        let dst = try!(root(&mut base));
+        try!(merge_item(dst, src, &target));
+    }
+
+    Ok(base)
+}
+
+fn root(doc: &mut Value) -> Result<&mut Value> {
+    let krate = match field_mut(doc, "crate") {
+        Some(k) => k,
+        _ => error!("document has no crate"),
+    };
+    match field_mut(krate, "module") {
+        Some(m) => Ok(m),
+        _ => error!("crate has no module"),
+    }
+}
+
+/// Replaces the local DefIds of `doc` so that they don't collide with those of `base`.
+fn renumber(base: &mut Value, doc: &mut Value) -> Result {
+    let offset = max_index(base) + 1;
+    let mut ids = Vec::new();
+    try!(match_ids(try!(root(base)), try!(root(doc)), &mut ids));
+    remap(doc, &ids, offset);
+    Ok(())
+}
+
+/// Collects the (src, dst) index pairs of the items in `src` that are merged into items
+/// of `dst`.
+fn match_ids(dst: &Value, src: &Value, ids: &mut Vec<(i64, i64)>) -> Result {
+    if let (Some(d), Some(s)) = (field(dst, "def_id"), field(src, "def_id")) {
+        if let (Some(d), Some(s)) = (local_index(d), local_index(s)) {
+            try!(ids.reserve(1));
+            ids.push((s, d));
+        }
+    }
+
+    let (dst_children, src_children) = match (children(dst), children(src)) {
+        (Some(d), Some(s)) => (d, s),
+        _ => return Ok(()),
+    };
+    for child in src_children {
+        if let Some(pos) = dst_children.find(|d| same_item(d, child)) {
+            try!(match_ids(&dst_children[pos], child, ids));
+        }
+    }
+    Ok(())
+}
+
+/// Returns the index of a DefId of the local crate.
+fn local_index(val: &Value) -> Option<i64> {
+    match field(val, "krate") {
+        Some(&Value::Integer(0)) => { },
+        _ => return None,
+    }
+    match field(val, "index") {
+        Some(index) => match field(index, "_field0") {
+            Some(&Value::Integer(i)) => Some(i),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn is_def_id(val: &Value) -> bool {
+    field_pos(val, "krate").is_some() && field_pos(val, "index").is_some()
+}
+
+/// Returns the largest index of a local DefId in a value or `0`.
+fn max_index(val: &Value) -> i64 {
+    if let Some(i) = local_index(val) {
+        return i;
+    }
+    let mut max = 0;
+    match *val {
+        Value::Array(ref a) => {
+            for v in a {
+                let i = max_index(v);
+                if i > max {
+                    max = i;
+                }
+            }
+        },
+        Value::Object(ref o) => {
+            for &(_, ref v) in o {
+                let i = max_index(v);
+                if i > max {
+                    max = i;
+                }
+            }
+        },
+        _ => { },
+    }
+    max
+}
+
+/// Replaces the local DefIds in a value.
+///
+/// [argument, ids]
+/// (old, new) index pairs. Indices that are not in this list are increased by
+/// `offset`.
+fn remap(val: &mut Value, ids: &[(i64, i64)], offset: i64) {
+    let old = local_index(val);
+    if let Some(i) = old {
+        let new = match ids.find(|&(s, _)| s == i) {
+            Some(pos) => ids[pos].1,
+            _ => i + offset,
+        };
+        if let Some(index) = field_mut(val, "index") {
+            if let Some(field0) = field_mut(index, "_field0") {
+                *field0 = Value::Integer(new);
+            }
+        }
+        return;
+    }
+    match *val {
+        Value::Array(ref mut a) => {
+            for v in a {
+                remap(v, ids, offset);
+            }
+        },
+        Value::Object(ref mut o) => {
+            for &mut (_, ref mut v) in o {
+                remap(v, ids, offset);
+            }
+        },
+        _ => { },
+    }
+}
+
+/// Adds `target` to `item` and all of its children.
+fn annotate(item: &mut Value, target: &[u8]) -> Result {
+    try!(add_target(item, target));
+    if let Some(children) = children_mut(item) {
+        for child in children {
+            try!(annotate(child, target));
+        }
+    }
+    Ok(())
+}
+
+/// Merges an item of `target` into the matching item of the merged document.
+///
+/// = Remarks
+///
+/// Only the children of the item are merged. If the item itself differs, e.g., in its
+/// signature or documentation, the version of the merged document is kept and a warning
+/// is printed.
+fn merge_item(dst: &mut Value, src: &mut Value, target: &[u8]) -> Result {
+    try!(add_target(dst, target));
+
+    if !same_definition(dst, src) {
+        let target: &ByteStr = target.as_ref();
+        match field(src, "name") {
+            Some(&Value::String(ref name)) => {
+                let name: &ByteStr = name.as_ref();
+                warning!("{:?} differs on target {:?}, only one version is documented",
+                         name, target);
+            },
+            _ => warning!("An unnamed item differs on target {:?}, only one version is \
+                           documented", target),
+        }
+    }
+
+    let src_children = match children_mut(src) {
+        Some(c) => mem::replace(c, Vec::new()),
+        _ => return Ok(()),
+    };
+    let dst_children = match children_mut(dst) {
+        Some(c) => c,
+        _ => return Ok(()),
+    };
+
+    for mut child in src_children {
+        match dst_children.find(|d| same_item(d, &child)) {
+            Some(pos) => try!(merge_item(&mut dst_children[pos], &mut child, target)),
+            _ => {
+                try!(annotate(&mut child, target));
+                try!(dst_children.reserve(1));
+                dst_children.push(child);
+            },
+        }
+    }
+
+    Ok(())
+}
+
+fn add_target(item: &mut Value, target: &[u8]) -> Result {
+    if let Some(&mut Value::Array(ref mut targets)) = field_mut(item, TARGETS) {
+        let present = targets.find(|t| match *t {
+            Value::String(ref s) => &s[..] == target,
+            _ => false,
+        });
+        if present.is_none() {
+            try!(targets.reserve(1));
+            targets.push(Value::String(try!(target.try_to())));
+        }
+        return Ok(());
+    }
+    match *item {
+        Value::Object(ref mut o) => {
+            let mut targets = try!(Vec::with_capacity(1));
+            targets.push(Value::String(try!(target.try_to())));
+            try!(o.reserve(1));
+            o.push((try!(TARGETS.as_bytes().try_to()), Value::Array(targets)));
+            Ok(())
+        },
+        _ => error!("tried to add a target to a non-object"),
+    }
+}
+
+/// The names of the fields that hold the nested items, fields, or variants of an item.
+const CHILDREN: [&'static str; 3] = ["items", "fields", "variants"];
+
+/// Returns the nested items, fields, or variants of an item.
+fn children(item: &Value) -> Option<&Array> {
+    let inner = match field(item, "inner") {
+        Some(i) => i,
+        _ => return None,
+    };
+    let fields = match field(inner, "fields") {
+        Some(&Value::Array(ref f)) if f.len() > 0 => &f[0],
+        _ => return None,
+    };
+    match CHILDREN.find(|n| field_pos(fields, n).is_some()) {
+        Some(i) => match field(fields, CHILDREN[i]) {
+            Some(&Value::Array(ref a)) => Some(a),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn children_mut(item: &mut Value) -> Option<&mut Array> {
+    let inner = match field_mut(item, "inner") {
+        Some(i) => i,
+        _ => return None,
+    };
+    let fields = match field_mut(inner, "fields") {
+        Some(&mut Value::Array(ref mut f)) if f.len() > 0 => &mut f[0],
+        _ => return None,
+    };
+    match CHILDREN.find(|n| field_pos(fields, n).is_some()) {
+        Some(i) => match field_mut(fields, CHILDREN[i]) {
+            Some(&mut Value::Array(ref mut a)) => Some(a),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn same_item(a: &Value, b: &Value) -> bool {
+    if !same_field(a, b, "name") {
+        return false;
+    }
+    let (a, b) = match (field(a, "inner"), field(b, "inner")) {
+        (Some(a), Some(b)) => (a, b),
+        _ => return false,
+    };
+    if !same_field(a, b, "variant") {
+        return false;
+    }
+    match field(a, "variant") {
+        Some(&Value::String(ref v)) if v.as_str() == "ImplItem" => { },
+        _ => return true,
+    }
+    match (field(a, "fields"), field(b, "fields")) {
+        (Some(&Value::Array(ref a)), Some(&Value::Array(ref b))) => {
+            a.len() > 0 && b.len() > 0 && same_field(&a[0], &b[0], "trait_")
+                && same_field(&a[0], &b[0], "for_")
+        },
+        _ => false,
+    }
+}
+
+/// Returns whether two matched items have the same attributes, visibility, and
+/// definition. Their children are not compared.
+fn same_definition(a: &Value, b: &Value) -> bool {
+    if !same_field(a, b, "attrs") || !same_field(a, b, "visibility") {
+        return false;
+    }
+    let (a, b) = match (field(a, "inner"), field(b, "inner")) {
+        (Some(a), Some(b)) => (a, b),
+        (None, None) => return true,
+        _ => return false,
+    };
+    if !same_field(a, b, "variant") {
+        return false;
+    }
+    match (field(a, "fields"), field(b, "fields")) {
+        (Some(&Value::Array(ref a)), Some(&Value::Array(ref b))) => {
+            if a.len() != b.len() {
+                return false;
+            }
+            for i in 0..a.len() {
+                if !same_except(&a[i], &b[i], &CHILDREN) {
+                    return false;
+                }
+            }
+            true
+        },
+        (a, b) => a.is_none() && b.is_none(),
+    }
+}
+
+/// Structural equality of two values that ignores the listed fields of objects.
+fn same_except(a: &Value, b: &Value, ignored: &[&str]) -> bool {
+    match (a, b) {
+        (&Value::Object(ref a), &Value::Object(ref b)) => {
+            if a.len() != b.len() {
+                return false;
+            }
+            for i in 0..a.len() {
+                if a[i].0 != b[i].0 {
+                    return false;
+                }
+                let ignore = ignored.find(|n| a[i].0.as_str() == *n).is_some();
+                if !ignore && !same(&a[i].1, &b[i].1) {
+                    return false;
+                }
+            }
+            true
+        },
+        _ => same(a, b),
+    }
+}
+
+fn same_field(a: &Value, b: &Value, name: &str) -> bool {
+    match (field(a, name), field(b, name)) {
+        (Some(a), Some(b)) => same(a, b),
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+/// Structural equality of two values. Object fields have to be in the same order.
+///
+/// = Remarks
+///
+/// DefIds are considered equal because they differ between targets. Types are therefore
+/// compared by their paths.
+fn same(a: &Value, b: &Value) -> bool {
+    if is_def_id(a) && is_def_id(b) {
+        return true;
+    }
+    match (a, b) {
+        (&Value::String(ref a), &Value::String(ref b)) => a == b,
+        (&Value::Integer(a), &Value::Integer(b)) => a == b,
+        (&Value::Float(a), &Value::Float(b)) => a == b,
+        (&Value::Boolean(a), &Value::Boolean(b)) => a == b,
+        (&Value::Null, &Value::Null) => true,
+        (&Value::Array(ref a), &Value::Array(ref b)) => {
+            if a.len() != b.len() {
+                return false;
+            }
+            for i in 0..a.len() {
+                if !same(&a[i], &b[i]) {
+                    return false;
+                }
+            }
+            true
+        },
+        (&Value::Object(ref a), &Value::Object(ref b)) => {
+            if a.len() != b.len() {
+                return false;
+            }
+            for i in 0..a.len() {
+                if a[i].0 != b[i].0 || !same(&a[i].1, &b[i].1) {
+                    return false;
+                }
+            }
+            true
+        },
+        _ => false,
+    }
+}
+
+fn field_pos(obj: &Value, name: &str) -> Option<usize> {
+    match *obj {
+        Value::Object(ref o) => o.find(|f| f.0.as_str() == name),
+        _ => None,
+    }
+}
+
+fn field<'a>(obj: &'a Value, name: &str) -> Option<&'a Value> {
+    match (field_pos(obj, name), obj) {
+        (Some(pos), &Value::Object(ref o)) => Some(&o[pos].1),
+        _ => None,
+    }
+}
+
+fn field_mut<'a>(obj: &'a mut Value, name: &str) -> Option<&'a mut Value> {
+    let pos = match field_pos(obj, name) {
+        Some(p) => p,
+        _ => return None,
+    };
+    match *obj {
+        Value::Object(ref mut o) => Some(&mut o[pos].1),
+        _ => None,
+    }
+}
@@ -3,88 +3,424 @@
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
 //! Parser for the rustdoc JSON output
-
-use std::error::{self};
+//!
+//! The parser consumes a `json::Source` directly without building a Json tree first.
+//!
+//! The layout of the input depends on the version of rustdoc that created it. The
+//! `schema` field of the input selects one of the `SCHEMAS`, which describe how each
+//! known layout differs from the current one. Unknown fields and items of unknown kinds
+//! produce warnings and are skipped.
+
+use std::{mem};
 use std::vec::{Vec};
 use std::bx::{Box};
 use std::rc::{Arc};
 use std::share::{RefCell};
 use std::string::{ByteStr};
 
-use json::{Value};
-use json::Slice as JSlice;
+use json::{Source, Kind, Number, Pos};
 use tree::*;
-use markup::{self};
+use markup::{self, Syntax, Env};
+use cfg::{self};
+use merge::{TARGETS};
+
+/// A rustdoc Json layout.
+pub struct Schema {
+    pub version: &'static str,
+    /// Whether imports and `extern crate` are wrapped in a `ViewItemItem`.
+    view_items: bool,
+    /// Whether `TypedefItem` has a second field that marks associated types.
+    assoc_typedefs: bool,
+    /// Fields that we don't use, as (object, field) pairs. These are skipped without a
+    /// warning.
+    ignored: &'static [(&'static str, &'static str)],
+}
+
+const IGNORED: &'static [(&'static str, &'static str)] = &[
+    ("input", "plugins"),
+    ("crate", "name"),
+    ("crate", "src"),
+    ("crate", "externs"),
+    ("crate", "primitives"),
+    ("item", "stability"),
+    ("Module", "is_crate"),
+    ("Enum", "variants_stripped"),
+];
+
+impl Schema {
+    /// Describes how the layout is read in terms of the current one.
+    pub fn mapping(&self) -> Result<Vec<&'static str>> {
+        let mut mapping = Vec::new();
+        if self.view_items {
+            try!(mapping.reserve(1));
+            mapping.push("ViewItemItem is read as ImportItem or ExternCrateItem");
+        }
+        if !self.assoc_typedefs {
+            try!(mapping.reserve(1));
+            mapping.push("TypedefItem is read as not associated");
+        }
+        Ok(mapping)
+    }
+}
 
-macro_rules! error {
-    ($fmt:expr) => { error!(concat!($fmt, "{}"), "") };
-    ($fmt:expr, $($arg:tt)*) => {{
-        errln!(concat!("lrs_doc: Error: ", $fmt), $($arg)*);
-        return Err(error::InvalidArgument);
-    }};
+/// The parts of an input that were not understood.
+pub struct Report {
+    /// The layout the input was read with.
+    pub schema: &'static Schema,
+    /// The unknown item kinds. Items of these kinds were skipped.
+    pub kinds: Vec<Vec<u8>>,
+    /// The unknown fields as (object, field) pairs. Ignored fields are not included.
+    pub fields: Vec<(&'static str, Vec<u8>)>,
 }
 
-pub const SCHEMA: &'static [u8] = b"0.8.3";
+/// The supported layouts. The first one is the current one and is used if the schema
+/// of the input is unknown.
+pub const SCHEMAS: &'static [Schema] = &[
+    Schema { version: "0.8.3", view_items: false, assoc_typedefs: true, ignored: IGNORED },
+    Schema { version: "0.8.2", view_items: true, assoc_typedefs: false, ignored: IGNORED },
+];
 
-pub fn parse(json: &Value) -> Result<Crate> {
-    let mut fields = [("schema", None), ("crate", None)];
-    try!(collect_object(json, &mut fields, "input"));
+/// A `Source` together with the state of the parser.
+trait Input: Source {
+    fn schema(&self) -> &'static Schema;
 
-    let schema = try!(collect_string(fields[0].1.unwrap(), "input", "schema"));
-    if schema != SCHEMA {
-        let s: &ByteStr = SCHEMA.as_ref();
-        warning!("Unexpected schema. Expected {:?} found {:?}", s, schema);
-    }
+    /// Warns about an unknown field unless it's ignored by the schema or we've already
+    /// warned about it on this kind of object.
+    fn unknown_field(&mut self, obj: &'static str, field: &[u8]) -> Result;
+
+    /// Warns about an item of an unknown kind, which is then skipped.
+    fn unknown_kind(&mut self, kind: &[u8]) -> Result;
+
+    /// The syntax of the doc comments of the current item.
+    fn syntax(&self) -> Syntax;
 
-    krate(fields[1].1.unwrap())
+    /// Sets the syntax and returns the previous one.
+    fn set_syntax(&mut self, syntax: Syntax) -> Syntax;
+
+    fn env(&self) -> &Env;
+
+    fn env_mut(&mut self) -> &mut Env;
+
+    /// Returns `true` when it's called for the first time, i.e., for the crate module.
+    fn first_item(&mut self) -> bool;
+
+    /// The name given to the crate module.
+    fn crate_name(&self) -> &[u8];
 }
 
-fn krate(json: &Value) -> Result<Crate> {
-    let mut fields = [("module", None)];
-    try!(collect_object(json, &mut fields, "crate"));
+struct Parser<'a, S: Source + 'a> {
+    r: &'a mut S,
+    schema: &'static Schema,
+    /// The (object, field) pairs we've already warned about.
+    warned: Vec<(&'static str, Vec<u8>)>,
+    /// The unknown item kinds we've seen.
+    kinds: Vec<Vec<u8>>,
+    syntax: Syntax,
+    env: Env,
+    /// Whether no item has been parsed yet.
+    first: bool,
+    crate_name: &'a [u8],
+}
 
-    let module = try!(item_data(fields[0].1.unwrap()));
-    Ok(Crate { item: module })
+impl<'a, S: Source> Source for Parser<'a, S> {
+    fn pos(&self) -> Option<Pos> { self.r.pos() }
+    fn peek(&mut self) -> Result<Kind> { self.r.peek() }
+    fn object_start(&mut self) -> Result { self.r.object_start() }
+    fn key(&mut self) -> Result<Option<Vec<u8>>> { self.r.key() }
+    fn array_start(&mut self) -> Result { self.r.array_start() }
+    fn array_next(&mut self) -> Result<bool> { self.r.array_next() }
+    fn string(&mut self) -> Result<Vec<u8>> { self.r.string() }
+    fn number(&mut self) -> Result<Number> { self.r.number() }
+    fn boolean(&mut self) -> Result<bool> { self.r.boolean() }
+    fn null(&mut self) -> Result { self.r.null() }
 }
 
-fn item_datas(json: &Value) -> Result<Vec<Arc<ItemData>>> {
-    let array = try!(collect_array(json, "?", "items"));
-    let mut vec = try!(Vec::with_capacity(array.len()));
-    for l in array {
-        vec.push(try!(item_data(l)));
+impl<'a, S: Source> Input for Parser<'a, S> {
+    fn schema(&self) -> &'static Schema {
+        self.schema
+    }
+
+    fn unknown_field(&mut self, obj: &'static str, field: &[u8]) -> Result {
+        for &(o, f) in self.schema.ignored {
+            if o == obj && f.as_bytes() == field {
+                return Ok(());
+            }
+        }
+        for &(o, ref f) in &self.warned {
+            if o == obj && &f[..] == field {
+                return Ok(());
+            }
+        }
+        let name: &ByteStr = field.as_ref();
+        match self.pos() {
+            Some(p) => warning!("{}:{}: Unknown field {:?} on {}", p.line, p.column, name,
+                                obj),
+            None => warning!("Unknown field {:?} on {}", name, obj),
+        }
+        try!(self.warned.reserve(1));
+        self.warned.push((obj, try!(field.try_to())));
+        Ok(())
+    }
+
+    fn unknown_kind(&mut self, kind: &[u8]) -> Result {
+        let v: &ByteStr = kind.as_ref();
+        match self.pos() {
+            Some(p) => warning!("{}:{}: Skipping item of unknown kind {:?}", p.line,
+                                p.column, v),
+            None => warning!("Skipping item of unknown kind {:?}", v),
+        }
+        if self.kinds.find(|k| &k[..] == kind).is_none() {
+            try!(self.kinds.reserve(1));
+            self.kinds.push(try!(kind.try_to()));
+        }
+        Ok(())
+    }
+
+    fn syntax(&self) -> Syntax {
+        self.syntax
+    }
+
+    fn set_syntax(&mut self, syntax: Syntax) -> Syntax {
+        mem::replace(&mut self.syntax, syntax)
+    }
+
+    fn env(&self) -> &Env {
+        &self.env
+    }
+
+    fn env_mut(&mut self) -> &mut Env {
+        &mut self.env
+    }
+
+    fn first_item(&mut self) -> bool {
+        mem::replace(&mut self.first, false)
+    }
+
+    fn crate_name(&self) -> &[u8] {
+        self.crate_name
     }
-    Ok(vec)
 }
 
-fn item_data(json: &Value) -> Result<Arc<ItemData>> {
-    let mut fields = [("name", None), ("attrs", None), ("inner", None),
-                      ("visibility", None), ("def_id", None)];
-    try!(collect_object(json, &mut fields, "item"));
+/// Collects the variables defined in the documentation of the crate module.
+///
+/// = Remarks
+///
+/// The variables are added to `env.vars`, which makes them available in all doc comments
+/// when `env` is passed to `parse`. This is a separate pass over the input because the
+/// items can come before the attributes of the crate module. Everything else is skipped.
+pub fn crate_vars<S: Source>(r: &mut S, env: &mut Env) -> Result {
+    let mut parser = Parser {
+        r: r,
+        schema: &SCHEMAS[0],
+        warned: Vec::new(),
+        kinds: Vec::new(),
+        syntax: env.syntax,
+        env: Env::new(),
+        first: true,
+        crate_name: &[],
+    };
+    try!(collect_object(&mut parser, "input", |r, key| {
+        match key {
+            b"crate" => try!(collect_object(r, "crate", |r, key| {
+                match key {
+                    b"module" => try!(collect_object(r, "item", |r, key| {
+                        match key {
+                            b"attrs" => {
+                                let src = try!(doc_source(&try!(attributes(r))));
+                                try!(markup::var_defs(&src, &mut r.env_mut().vars));
+                            },
+                            _ => try!(r.skip()),
+                        }
+                        Ok(true)
+                    })),
+                    _ => try!(r.skip()),
+                }
+                Ok(true)
+            })),
+            _ => try!(r.skip()),
+        }
+        Ok(true)
+    }));
 
-    let name = match *fields[0].1.unwrap() {
-        Value::String(ref s) => {
-            Some(s.try_to().unwrap())
-        },
-        _ => None,
+    let vars = mem::replace(&mut parser.env.vars, Vec::new());
+    try!(env.vars.reserve(vars.len()));
+    for var in vars {
+        env.vars.push(var);
+    }
+    Ok(())
+}
+
+/// Parses a crate.
+///
+/// [argument, env]
+/// The settings of the doc comments. `env.syntax` is used for doc comments that don't
+/// select a syntax with `#[doc(markup = "...")]`. `env.vars` are available in all doc
+/// comments and should include the variables collected by `crate_vars`.
+///
+/// [argument, crate_name]
+/// The name of the crate module. This is the first component of all paths.
+pub fn parse<S: Source>(r: &mut S, env: Env, crate_name: &[u8]) -> Result<Crate> {
+    let (krate, _) = try!(parse_report(r, env, crate_name));
+    Ok(krate)
+}
+
+/// Parses a crate and reports what was skipped.
+///
+/// = Remarks
+///
+/// See `parse` for the arguments.
+pub fn parse_report<S: Source>(r: &mut S, env: Env,
+                               crate_name: &[u8]) -> Result<(Crate, Report)> {
+    let mut parser = Parser {
+        r: r,
+        schema: &SCHEMAS[0],
+        warned: Vec::new(),
+        kinds: Vec::new(),
+        syntax: env.syntax,
+        env: env,
+        first: true,
+        crate_name: crate_name,
+    };
+    let parsed = {
+        let r = &mut parser;
+
+        let mut schema_seen = false;
+        let mut krate_ = None;
+        try!(collect_object(r, "input", |r, key| {
+            match key {
+                b"schema" => {
+                    let version = try!(collect_string(r, "input", "schema"));
+                    if krate_.is_some() {
+                        warning!("Schema appears after the crate and is ignored");
+                    }
+                    match SCHEMAS.find(|s| s.version.as_bytes() == &version[..]) {
+                        Some(i) => r.schema = &SCHEMAS[i],
+                        _ => warning!("Unknown schema {:?}. Using the layout of {}",
+                                      version.as_str(), SCHEMAS[0].version),
+                    }
+                    schema_seen = true;
+                },
+                b"crate" => krate_ = Some(try!(krate(r))),
+                _ => return Ok(false),
+            }
+            Ok(true)
+        }));
+
+        if !schema_seen {
+            warning!("Input has no schema. Using the layout of {}", SCHEMAS[0].version);
+        }
+
+        try!(required(r, krate_, "input", "crate"))
     };
-    let attrs  = try!(attributes(fields[1].1.unwrap()));
-    let inner  = try!(item(fields[2].1.unwrap()));
-    let public = match *fields[3].1.unwrap() {
-        Value::Null => true,
-        _ => try!(visibility(fields[3].1.unwrap())),
+
+    let report = Report {
+        schema: parser.schema,
+        kinds: parser.kinds,
+        fields: parser.warned,
     };
-    let node   = try!(def_id(fields[4].1.unwrap()));
+    Ok((parsed, report))
+}
 
-    let mut doc: Vec<_> = Vec::new();
-    for attr in &attrs {
-        if let Attribute::NameValue(ref n, ref v) = *attr {
-            if n.as_str() == "doc" {
-                try!(doc.push_all(v.as_ref()));
-                try!(doc.push_all(b"\n"));
-            }
+fn krate<S: Input>(r: &mut S) -> Result<Crate> {
+    let mut module = None;
+    try!(collect_object(r, "crate", |r, key| {
+        match key {
+            b"module" => module = Some(try!(item_data(r))),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }));
+
+    match try!(required(r, module, "crate", "module")) {
+        Some(item) => Ok(Crate { item: item }),
+        _ => error_in!(r, "the crate module has an unknown kind"),
+    }
+}
+
+fn item_datas<S: Input>(r: &mut S) -> Result<Vec<Arc<ItemData>>> {
+    let items = try!(collect_array(r, "?", "items", item_data));
+    let mut vec = try!(Vec::with_capacity(items.len()));
+    for item in items {
+        if let Some(item) = item {
+            vec.push(item);
+        }
+    }
+    Ok(vec)
+}
+
+/// Returns `None` if the item has a kind that we don't know or don't care about.
+fn item_data<S: Input>(r: &mut S) -> Result<Option<Arc<ItemData>>> {
+    let mut name = None;
+    let mut attrs = None;
+    let mut inner = None;
+    let mut public = None;
+    let mut node = None;
+    let mut source = None;
+    let mut targets = Vec::new();
+    // The syntax of the enclosing item. Set if the item selects its own syntax.
+    let mut outer_syntax = None;
+    let crate_module = r.first_item();
+    try!(collect_object(r, "item", |r, key| {
+        match key {
+            b"name" => {
+                name = Some(match try!(r.peek()) {
+                    Kind::String => Some(try!(r.string())),
+                    _ => { try!(r.skip()); None },
+                });
+            },
+            b"attrs" => {
+                let a = try!(attributes(r));
+                // Nested items inherit the syntax. This relies on the attributes coming
+                // before the inner item.
+                if let Some(syntax) = doc_syntax(&a) {
+                    outer_syntax = Some(r.set_syntax(syntax));
+                }
+                attrs = Some(a);
+            },
+            b"inner" => inner = Some(try!(item(r))),
+            b"visibility" => {
+                public = Some(match try!(skip_null(r)) {
+                    true => true,
+                    false => try!(visibility(r)),
+                });
+            },
+            b"def_id" => node = Some(try!(def_id(r))),
+            b"source" => source = Some(try!(span(r))),
+            k if k == TARGETS.as_bytes() => {
+                targets = try!(collect_array(r, "item", TARGETS, |r| {
+                    collect_string(r, "item", TARGETS)
+                }));
+            },
+            _ => return Ok(false),
         }
+        Ok(true)
+    }));
+
+    // The syntax selected by the item applies to its children but not to its siblings.
+    // It is restored before any of the early returns below.
+    let syntax = r.syntax();
+    if let Some(outer) = outer_syntax {
+        r.set_syntax(outer);
     }
-    let docs = try!(markup::parse(&doc));
+
+    let name   = try!(required(r, name, "item", "name"));
+    let attrs  = try!(required(r, attrs, "item", "attrs"));
+    let inner  = match try!(required(r, inner, "item", "inner")) {
+        Some(inner) => inner,
+        _ => return Ok(None),
+    };
+    let public = try!(required(r, public, "item", "visibility"));
+    let node   = try!(required(r, node, "item", "def_id"));
+    let stability = try!(stability(&attrs));
+    let cfg = try!(cfg::from_attributes(&attrs));
+
+    // The crate module is named after the crate, whatever its name in the input.
+    let name = match crate_module {
+        true => Some(try!(r.crate_name().try_to())),
+        false => name,
+    };
+
+    let docs = try!(markup::parse_with(&try!(doc_source(&attrs)), syntax, r.env()));
 
     let item = try!(Arc::new()).set(ItemData {
         name: name,
@@ -95,313 +431,566 @@ fn item_data(json: &Value) -> Result<Arc<ItemData>> {
         node: node,
         parent: RefCell::new(None),
         impls: RefCell::new(Vec::new()),
+        inherited: RefCell::new(None),
+        deref: RefCell::new(None),
+        auto_traits: RefCell::new(Vec::new()),
+        returned_by: RefCell::new(Vec::new()),
+        accepted_by: RefCell::new(Vec::new()),
+        stability: stability,
+        cfg: cfg,
+        targets: targets,
+        span: source,
     });
 
-    Ok(item)
+    Ok(Some(item))
+}
+
+/// Returns the source of the documentation in the attributes of an item.
+pub fn doc_source(attrs: &[Attribute]) -> Result<Vec<u8>> {
+    let mut doc: Vec<_> = Vec::new();
+    for attr in attrs {
+        if let Attribute::NameValue(ref n, ref v) = *attr {
+            if n.as_str() == "doc" {
+                try!(doc.push_all(v.as_ref()));
+                try!(doc.push_all(b"\n"));
+            }
+        }
+    }
+    Ok(doc)
 }
 
-fn attributes(json: &Value) -> Result<Vec<Attribute>> {
-    let array = try!(collect_array(json, "?", "attributes"));
-    let mut attributes = try!(Vec::with_capacity(array.len()));
-    for val in array {
-        attributes.push(try!(attribute(val)));
+/// Returns the syntax selected with `#[doc(markup = "...")]`.
+fn doc_syntax(attrs: &[Attribute]) -> Option<Syntax> {
+    for attr in attrs {
+        if let Attribute::List(ref n, ref list) = *attr {
+            if n.as_str() != "doc" {
+                continue;
+            }
+            for attr in list {
+                if let Attribute::NameValue(ref n, ref v) = *attr {
+                    if n.as_str() == "markup" {
+                        let syntax = Syntax::from_name(v);
+                        if syntax.is_none() {
+                            warning!("Unknown markup {:?}", v.as_str());
+                        }
+                        return syntax;
+                    }
+                }
+            }
+        }
     }
-    Ok(attributes)
+    None
+}
+
+fn attributes<S: Input>(r: &mut S) -> Result<Vec<Attribute>> {
+    collect_array(r, "?", "attributes", attribute)
 }
 
-fn attribute(json: &Value) -> Result<Attribute> {
-    let (variant, fields) = try!(collect_enum(json, "Attribute"));
+fn attribute<S: Input>(r: &mut S) -> Result<Attribute> {
+    let (variant, mut fields) = try!(collect_enum(r, "Attribute"));
 
     let bytes: &[u8] = variant.as_ref();
-    match bytes {
+    let attr = match bytes {
         b"Word" => {
-            if fields.len() != 1 { error!("word attribute has unexpected structure") }
-            let string = try!(collect_string(&fields[0], "word attribute", "fields[0]"));
-            let string = try!(string.try_to());
-            Ok(Attribute::Word(string))
+            try!(fields.next(r));
+            let string = try!(collect_string(r, "word attribute", "fields[0]"));
+            Attribute::Word(string)
         },
         b"List" => {
-            if fields.len() != 2 { error!("list attribute has unexpected structure") }
-            let one = try!(collect_string(&fields[0], "list attribute", "fields[0]"));
-            let two = try!(attributes(&fields[1]));
-            let one = try!(one.try_to());
-            Ok(Attribute::List(one, two))
+            try!(fields.next(r));
+            let one = try!(collect_string(r, "list attribute", "fields[0]"));
+            try!(fields.next(r));
+            let two = try!(attributes(r));
+            Attribute::List(one, two)
         },
         b"NameValue" => {
-            if fields.len() != 2 { error!("list attribute has unexpected structure") }
-            let one = try!(collect_string(&fields[0], "namevalue attribute", "fields[0]"));
-            let two = try!(collect_string(&fields[1], "namevalue attribute", "fields[1]"));
-            let one = try!(one.try_to());
-            let two = try!(two.try_to());
-            Ok(Attribute::NameValue(one, two))
+            try!(fields.next(r));
+            let one = try!(collect_string(r, "namevalue attribute", "fields[0]"));
+            try!(fields.next(r));
+            let two = try!(collect_string(r, "namevalue attribute", "fields[1]"));
+            Attribute::NameValue(one, two)
         },
-        _ => error!("unexpected attribute variant {:?}", variant),
+        _ => error_in!(r, "unexpected attribute variant {:?}", variant),
+    };
+    try!(fields.end(r));
+    Ok(attr)
+}
+
+/// Collects the stability attributes of an item.
+///
+/// Understands `#[stable(since)]`, `#[unstable(feature, issue)]`, `#[deprecated]`,
+/// `#[deprecated = "note"]`, `#[deprecated(since, note)]`, `#[rustc_deprecated(since,
+/// reason)]` and lrs's `#[feature_gate(feature, issue)]`, which is treated like
+/// `#[unstable]`.
+fn stability(attrs: &[Attribute]) -> Result<Stability> {
+    let mut stab = Stability {
+        level: StabilityLevel::Unmarked,
+        feature: None,
+        since: None,
+        issue: None,
+        deprecated: None,
+    };
+
+    for attr in attrs {
+        match *attr {
+            Attribute::Word(ref name) => {
+                if name.as_str() == "deprecated" {
+                    stab.deprecated = Some(Deprecation { since: None, note: None });
+                }
+            },
+            Attribute::NameValue(ref name, ref val) => {
+                if name.as_str() == "deprecated" {
+                    stab.deprecated = Some(Deprecation {
+                        since: None,
+                        note: Some(try!(val.try_to())),
+                    });
+                }
+            },
+            Attribute::List(ref name, ref args) => {
+                let bytes: &[u8] = name.as_ref();
+                match bytes {
+                    b"stable" => {
+                        stab.level = StabilityLevel::Stable;
+                        stab.since = try!(attr_value(args, "since"));
+                    },
+                    b"unstable" | b"feature_gate" => {
+                        stab.level = StabilityLevel::Unstable;
+                        stab.feature = try!(attr_value(args, "feature"));
+                        stab.issue = try!(attr_value(args, "issue"));
+                    },
+                    b"deprecated" | b"rustc_deprecated" => {
+                        let mut note = try!(attr_value(args, "note"));
+                        if note.is_none() {
+                            note = try!(attr_value(args, "reason"));
+                        }
+                        stab.deprecated = Some(Deprecation {
+                            since: try!(attr_value(args, "since")),
+                            note: note,
+                        });
+                    },
+                    _ => { },
+                }
+            },
+        }
+    }
+
+    Ok(stab)
+}
+
+/// Returns the value of the `name = "value"` entry in an attribute list.
+fn attr_value(args: &[Attribute], name: &str) -> Result<Option<Vec<u8>>> {
+    for arg in args {
+        if let Attribute::NameValue(ref n, ref v) = *arg {
+            if n.as_str() == name {
+                return Ok(Some(try!(v.try_to())));
+            }
+        }
     }
+    Ok(None)
 }
 
-fn visibility(json: &Value) -> Result<bool> {
-    let s = try!(collect_string(json, "?", "visibility"));
+fn visibility<S: Input>(r: &mut S) -> Result<bool> {
+    let s = try!(collect_string(r, "?", "visibility"));
 
     let bytes: &[u8] = s.as_ref();
     match bytes {
         b"Public" => Ok(true),
         b"Inherited" => Ok(false),
-        _ => error!("visibility contains unexpected value: {:?}", s),
+        _ => error_in!(r, "visibility contains unexpected value: {:?}", s),
     }
 }
 
-fn def_id(json: &Value) -> Result<DefId> {
-    let mut fields = [("index", None), ("krate", None)];
-    try!(collect_object(json, &mut fields, "def_id"));
+fn span<S: Input>(r: &mut S) -> Result<Span> {
+    let mut filename = None;
+    let mut loline = None;
+    let mut locol = None;
+    let mut hiline = None;
+    let mut hicol = None;
+    try!(collect_object(r, "span", |r, key| {
+        match key {
+            b"filename" => filename = Some(try!(collect_string(r, "span", "filename"))),
+            b"loline" => loline = Some(try!(collect_int(r, "span", "loline"))),
+            b"locol" => locol = Some(try!(collect_int(r, "span", "locol"))),
+            b"hiline" => hiline = Some(try!(collect_int(r, "span", "hiline"))),
+            b"hicol" => hicol = Some(try!(collect_int(r, "span", "hicol"))),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }));
+
+    Ok(Span {
+        filename: try!(required(r, filename, "span", "filename")),
+        loline: try!(required(r, loline, "span", "loline")) as usize,
+        locol: try!(required(r, locol, "span", "locol")) as usize,
+        hiline: try!(required(r, hiline, "span", "hiline")) as usize,
+        hicol: try!(required(r, hicol, "span", "hicol")) as usize,
+    })
+}
 
-    let mut index_fields = [("_field0", None)];
-    try!(collect_object(fields[0].1.unwrap(), &mut index_fields, "index"));
+fn def_id<S: Input>(r: &mut S) -> Result<DefId> {
+    let mut index = None;
+    let mut krate = None;
+    try!(collect_object(r, "def_id", |r, key| {
+        match key {
+            b"index" => {
+                let mut field0 = None;
+                try!(collect_object(r, "index", |r, key| {
+                    match key {
+                        b"_field0" => field0 = Some(try!(collect_int(r, "DefId", "index"))),
+                        _ => return Ok(false),
+                    }
+                    Ok(true)
+                }));
+                index = Some(try!(required(r, field0, "index", "_field0")));
+            },
+            b"krate" => krate = Some(try!(collect_int(r, "DefId", "krate"))),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }));
 
-    let index = try!(collect_int(index_fields[0].1.unwrap(), "DefId", "index"));
-    let krate = try!(collect_int(fields[1].1.unwrap(), "DefId", "krate"));
+    let index = try!(required(r, index, "def_id", "index"));
+    let krate = try!(required(r, krate, "def_id", "krate"));
     Ok(DefId { index: index as u64, krate: krate as u64 })
 }
 
-fn item(json: &Value) -> Result<Item> {
-    let (variant, fields) = try!(collect_enum(json, "ItemEnum"));
+/// Returns `None` if the item has a kind that we don't know or don't care about.
+fn item<S: Input>(r: &mut S) -> Result<Option<Item>> {
+    let (variant, mut fields) = try!(collect_enum(r, "ItemEnum"));
 
     let bytes: &[u8] = variant.as_ref();
-    match bytes {
-        b"ImportItem"          => item_import(fields),
-        b"StructItem"          => item_struct(fields),
-        b"EnumItem"            => item_enum(fields),
-        b"FunctionItem"        => item_func(fields),
-        b"ModuleItem"          => item_module(fields),
-        b"TypedefItem"         => item_typedef(fields),
-        b"StaticItem"          => item_static(fields),
-        b"ConstantItem"        => item_constant(fields),
-        b"TraitItem"           => item_trait(fields),
-        b"ImplItem"            => item_impl(fields),
-        b"TyMethodItem"        => item_method_decl(fields),
-        b"MethodItem"          => item_method(fields),
-        b"StructFieldItem"     => item_struct_field(fields),
-        b"VariantItem"         => item_variant(fields),
-        b"ForeignFunctionItem" => item_extern_func(fields),
-        b"ForeignStaticItem"   => item_extern_static(fields),
-        b"MacroItem"           => item_macro(fields),
-        b"PrimitiveItem"       => item_primitive(fields),
-        b"AssociatedTypeItem"  => item_assoc_type(fields),
-        b"DefaultImplItem"     => item_default_impl(fields),
-        _ => error!("Unexpected item: {:?}", variant),
-    }
+    let item = match bytes {
+        b"ImportItem"          => return item_import(r, fields),
+        b"ViewItemItem" if r.schema().view_items => return item_view(r, fields),
+        b"ExternCrateItem"     => {
+            try!(fields.skip(r));
+            return Ok(None);
+        },
+        b"StructItem"          => try!(item_struct(r, &mut fields)),
+        b"EnumItem"            => try!(item_enum(r, &mut fields)),
+        b"FunctionItem"        => try!(item_func(r, &mut fields)),
+        b"ModuleItem"          => try!(item_module(r, &mut fields)),
+        b"TypedefItem"         => try!(item_typedef(r, &mut fields)),
+        b"StaticItem"          => try!(item_static(r, &mut fields)),
+        b"ConstantItem"        => try!(item_constant(r, &mut fields)),
+        b"TraitItem"           => try!(item_trait(r, &mut fields)),
+        b"ImplItem"            => try!(item_impl(r, &mut fields)),
+        b"TyMethodItem"        => try!(item_method_decl(r, &mut fields)),
+        b"MethodItem"          => try!(item_method(r, &mut fields)),
+        b"StructFieldItem"     => try!(item_struct_field(r, &mut fields)),
+        b"VariantItem"         => try!(item_variant(r, &mut fields)),
+        b"ForeignFunctionItem" => try!(item_extern_func(r, &mut fields)),
+        b"ForeignStaticItem"   => try!(item_extern_static(r, &mut fields)),
+        b"MacroItem"           => try!(item_macro(r, &mut fields)),
+        b"PrimitiveItem"       => try!(item_primitive(r, &mut fields)),
+        b"AssociatedTypeItem"  => try!(item_assoc_type(r, &mut fields)),
+        b"DefaultImplItem"     => try!(item_default_impl(r, &mut fields)),
+        _ => {
+            try!(r.unknown_kind(bytes));
+            try!(fields.skip(r));
+            return Ok(None);
+        },
+    };
+    try!(fields.end(r));
+    Ok(Some(item))
 }
 
-fn item_import(fields: &JSlice) -> Result<Item> {
-    if fields.len() != 1 { error!("import item with {} fields", fields.len()); }
-    let (variant, fields) = try!(collect_enum(&fields[0], "Import"));
-    if variant.as_str() != "GlobImport" { error!("unexpected import variant: {:?}", variant); }
-    if fields.len() != 1 { error!("glob import with {} fields", fields.len()); }
-    let source = try!(glob_import(&fields[0]));
-    Ok(Item::GlobImport(source))
+/// Reads an `ImportItem`. Only glob imports are kept.
+fn item_import<S: Input>(r: &mut S, mut fields: EnumFields) -> Result<Option<Item>> {
+    try!(fields.next(r));
+    let import = try!(import(r));
+    try!(fields.end(r));
+    Ok(import)
 }
 
-fn glob_import(json: &Value) -> Result<GlobImport> {
-    let mut fields = [("path", None), ("did", None)];
-    try!(collect_object(json, &mut fields, "glob import"));
+fn import<S: Input>(r: &mut S) -> Result<Option<Item>> {
+    let (variant, mut fields) = try!(collect_enum(r, "Import"));
+    let bytes: &[u8] = variant.as_ref();
+    match bytes {
+        b"GlobImport" => {
+            try!(fields.next(r));
+            let source = try!(glob_import(r));
+            try!(fields.end(r));
+            Ok(Some(Item::GlobImport(source)))
+        },
+        b"SimpleImport" | b"ImportList" => {
+            try!(fields.skip(r));
+            Ok(None)
+        },
+        _ => error_in!(r, "unexpected import variant: {:?}", variant),
+    }
+}
 
-    let path = try!(path(fields[0].1.unwrap()));
-    let node = match *fields[1].1.unwrap() {
-        Value::Object(_) => Some(try!(def_id(fields[1].1.unwrap()))),
-        _ => None,
-    };
+/// Reads a `ViewItemItem` of the older layouts, which contains either an import or an
+/// `extern crate`.
+fn item_view<S: Input>(r: &mut S, mut fields: EnumFields) -> Result<Option<Item>> {
+    try!(fields.next(r));
+    let mut item = None;
+    try!(collect_object(r, "ViewItem", |r, key| {
+        match key {
+            b"inner" => {
+                let (variant, mut fields) = try!(collect_enum(r, "ViewItemInner"));
+                let bytes: &[u8] = variant.as_ref();
+                match bytes {
+                    b"Import" => {
+                        try!(fields.next(r));
+                        item = Some(try!(import(r)));
+                        try!(fields.end(r));
+                    },
+                    b"ExternCrate" => {
+                        try!(fields.skip(r));
+                        item = Some(None);
+                    },
+                    _ => error_in!(r, "unexpected view item variant: {:?}", variant),
+                }
+            },
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }));
+    try!(fields.end(r));
+    required(r, item, "ViewItem", "inner")
+}
+
+fn glob_import<S: Input>(r: &mut S) -> Result<GlobImport> {
+    let mut path_ = None;
+    let mut node = None;
+    try!(collect_object(r, "glob import", |r, key| {
+        match key {
+            b"path" => path_ = Some(try!(path(r))),
+            b"did" => {
+                node = Some(match try!(r.peek()) {
+                    Kind::Object => Some(try!(def_id(r))),
+                    _ => { try!(r.skip()); None },
+                });
+            },
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }));
 
-    Ok(GlobImport { path: path, node: node })
+    Ok(GlobImport {
+        path: try!(required(r, path_, "glob import", "path")),
+        node: try!(required(r, node, "glob import", "did")),
+    })
 }
 
-fn path(json: &Value) -> Result<Path> {
-    let mut fields = [("global", None), ("segments", None)];
-    try!(collect_object(json, &mut fields, "path"));
-
-    let global = try!(collect_bool(fields[0].1.unwrap(), "path", "global"));
-    let segments = try!(path_segments(fields[1].1.unwrap()));
+fn path<S: Input>(r: &mut S) -> Result<Path> {
+    let mut global = None;
+    let mut segments = None;
+    try!(collect_object(r, "path", |r, key| {
+        match key {
+            b"global" => global = Some(try!(collect_bool(r, "path", "global"))),
+            b"segments" => segments = Some(try!(path_segments(r))),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }));
 
-    Ok(Path { global: global, segments: segments })
+    Ok(Path {
+        global: try!(required(r, global, "path", "global")),
+        segments: try!(required(r, segments, "path", "segments")),
+    })
 }
 
-fn path_segments(json: &Value) -> Result<Vec<PathSegment>> {
-    let array = try!(collect_array(json, "path", "segments"));
-    let mut vec = try!(Vec::with_capacity(array.len()));
-    for seg in array {
-        vec.push(try!(path_segment(seg)));
-    }
-    Ok(vec)
+fn path_segments<S: Input>(r: &mut S) -> Result<Vec<PathSegment>> {
+    collect_array(r, "path", "segments", path_segment)
 }
 
-fn path_segment(json: &Value) -> Result<PathSegment> {
-    let mut fields = [("name", None), ("params", None)];
-    try!(collect_object(json, &mut fields, "path_segment"));
-
-    let name = try!(collect_string(fields[0].1.unwrap(), "path_segment", "name"));
-    let params = try!(path_params(fields[1].1.unwrap()));
-    let name = try!(name.try_to());
+fn path_segment<S: Input>(r: &mut S) -> Result<PathSegment> {
+    let mut name = None;
+    let mut params = None;
+    try!(collect_object(r, "path_segment", |r, key| {
+        match key {
+            b"name" => name = Some(try!(collect_string(r, "path_segment", "name"))),
+            b"params" => params = Some(try!(path_params(r))),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }));
 
-    Ok(PathSegment { name: name, params: params })
+    Ok(PathSegment {
+        name: try!(required(r, name, "path_segment", "name")),
+        params: try!(required(r, params, "path_segment", "params")),
+    })
 }
 
-fn path_params(params: &Value) -> Result<PathParameters> {
-    let (variant, fields) = try!(collect_enum(params, "PathParameters"));
+fn path_params<S: Input>(r: &mut S) -> Result<PathParameters> {
+    let (variant, mut fields) = try!(collect_enum(r, "PathParameters"));
 
     let bytes: &[u8] = variant.as_ref();
-    match bytes {
+    let params = match bytes {
         b"AngleBracketed" => {
-            if fields.len() != 3 { error!("angle bracketed path params with {} fields",
-                                           fields.len()); }
+            try!(fields.next(r));
+            let lifetimes = try!(lifetimes(r));
+            try!(fields.next(r));
+            let ty_params = try!(types(r));
+            try!(fields.next(r));
+            let bindings = try!(type_bindings(r));
             let abpp = AngleBracketedPathParams {
-                lifetimes: try!(lifetimes(&fields[0])),
-                ty_params: try!(types(&fields[1])),
-                bindings: try!(type_bindings(&fields[2])),
+                lifetimes: lifetimes,
+                ty_params: ty_params,
+                bindings: bindings,
             };
-            Ok(PathParameters::AngleBracketed(abpp))
+            PathParameters::AngleBracketed(abpp)
         },
         b"Parenthesized" => {
-            if fields.len() != 2 { error!("paranthesized path parmas with {} fields",
-                                          fields.len()); }
+            try!(fields.next(r));
+            let args = try!(types(r));
+            try!(fields.next(r));
+            let return_value = match try!(skip_null(r)) {
+                true => None,
+                false => Some(try!(type_(r))),
+            };
             let ppp = ParenthesizedPathParams {
-                args: try!(types(&fields[0])),
-                return_value: match fields[1] {
-                    Value::Null => None,
-                    _ => Some(try!(type_(&fields[1]))),
-                },
+                args: args,
+                return_value: return_value,
             };
-            Ok(PathParameters::Parenthesized(ppp))
+            PathParameters::Parenthesized(ppp)
         },
         _ => {
-            error!("unexpected path_params variant: {:?}", variant);
+            error_in!(r, "unexpected path_params variant: {:?}", variant);
         },
-    }
+    };
+    try!(fields.end(r));
+    Ok(params)
 }
 
-fn lifetimes(json: &Value) -> Result<Vec<Vec<u8>>> {
-    let array = try!(collect_array(json, "?", "lifetimes"));
-    let mut vec = try!(Vec::with_capacity(array.len()));
-    for l in array {
-        vec.push(try!(lifetime(l)));
-    }
-    Ok(vec)
+fn lifetimes<S: Input>(r: &mut S) -> Result<Vec<Vec<u8>>> {
+    collect_array(r, "?", "lifetimes", lifetime)
 }
 
-fn lifetime(json: &Value) -> Result<Vec<u8>> {
-    let mut fields = [("_field0", None)];
-    try!(collect_object(json, &mut fields, "Lifetime"));
-    let s = try!(collect_string(fields[0].1.unwrap(), "Lifetime", "_field0"));
-    let s = try!(s.try_to());
-    Ok(s)
+fn lifetime<S: Input>(r: &mut S) -> Result<Vec<u8>> {
+    let mut s = None;
+    try!(collect_object(r, "Lifetime", |r, key| {
+        match key {
+            b"_field0" => s = Some(try!(collect_string(r, "Lifetime", "_field0"))),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }));
+    required(r, s, "Lifetime", "_field0")
 }
 
-fn types(json: &Value) -> Result<Vec<Type>> {
-    let array = try!(collect_array(json, "?", "types"));
-    let mut vec = try!(Vec::with_capacity(array.len()));
-    for l in array {
-        vec.push(try!(type_(l)));
-    }
-    Ok(vec)
+fn types<S: Input>(r: &mut S) -> Result<Vec<Type>> {
+    collect_array(r, "?", "types", type_)
 }
 
-fn type_(json: &Value) -> Result<Type> {
-    let (variant, fields) = try!(collect_enum(json, "type"));
+fn type_<S: Input>(r: &mut S) -> Result<Type> {
+    let (variant, mut fields) = try!(collect_enum(r, "type"));
 
     let bytes: &[u8] = variant.as_ref();
-    match bytes {
-        b"ResolvedPath" => type_resolved_path(fields),
-        b"Generic"      => type_generic(fields),
-        b"Primitive"    => type_primitive(fields),
-        b"BareFunction" => type_bare_function(fields),
-        b"Tuple"        => type_tuple(fields),
-        b"Vector"       => type_slice(fields),
-        b"FixedVector"  => type_array(fields),
-        b"Bottom"       => type_bottom(fields),
-        b"RawPointer"   => type_pointer(fields),
-        b"BorrowedRef"  => type_ref(fields),
-        b"QPath"        => type_ufcs_path(fields),
-        b"Infer"        => type_infer(fields),
-        b"PolyTraitRef" => type_hklt_bound(fields),
-        _ => error!("Unexpected type: {:?}", variant),
-    }
+    let ty = match bytes {
+        b"ResolvedPath" => try!(type_resolved_path(r, &mut fields)),
+        b"Generic"      => try!(type_generic(r, &mut fields)),
+        b"Primitive"    => try!(type_primitive(r, &mut fields)),
+        b"BareFunction" => try!(type_bare_function(r, &mut fields)),
+        b"Tuple"        => try!(type_tuple(r, &mut fields)),
+        b"Vector"       => try!(type_slice(r, &mut fields)),
+        b"FixedVector"  => try!(type_array(r, &mut fields)),
+        b"Bottom"       => Type::Bottom,
+        b"RawPointer"   => try!(type_pointer(r, &mut fields)),
+        b"BorrowedRef"  => try!(type_ref(r, &mut fields)),
+        b"QPath"        => try!(type_ufcs_path(r, &mut fields)),
+        b"Infer"        => Type::Infer,
+        b"PolyTraitRef" => try!(type_hklt_bound(r, &mut fields)),
+        _ => error_in!(r, "Unexpected type: {:?}", variant),
+    };
+    try!(fields.end(r));
+    Ok(ty)
 }
 
-fn type_resolved_path(fields: &JSlice) -> Result<Type> {
-    if fields.len() != 4 { error!("resolved path type with {} fields", fields.len()); }
+fn type_resolved_path<S: Input>(r: &mut S, fields: &mut EnumFields) -> Result<Type> {
+    try!(fields.next(r));
+    let path = try!(path(r));
+    try!(fields.next(r));
+    let params = match try!(skip_null(r)) {
+        true => None,
+        false => Some(try!(ty_param_bounds(r))),
+    };
+    try!(fields.next(r));
+    let def_id = try!(def_id(r));
+    try!(fields.next(r));
+    let is_generic = try!(collect_bool(r, "ResolvedPath", "is_generic"));
     let rp = ResolvedPath {
-        path: try!(path(&fields[0])),
-        params: match fields[1] {
-            Value::Null => None,
-            _ => Some(try!(ty_param_bounds(&fields[1]))),
-        },
-        def_id: try!(def_id(&fields[2])),
-        is_generic: try!(collect_bool(&fields[3], "ResolvedPath", "is_generic")),
+        path: path,
+        params: params,
+        def_id: def_id,
+        is_generic: is_generic,
         item: RefCell::new(None),
     };
     Ok(Type::ResolvedPath(rp))
 }
 
-fn ty_param_bounds(json: &Value) -> Result<Vec<TyParamBound>> {
-    let array = try!(collect_array(json, "?", "ty_param_bounds"));
-    let mut vec = try!(Vec::with_capacity(array.len()));
-    for l in array {
-        vec.push(try!(ty_param_bound(l)));
-    }
-    Ok(vec)
+fn ty_param_bounds<S: Input>(r: &mut S) -> Result<Vec<TyParamBound>> {
+    collect_array(r, "?", "ty_param_bounds", ty_param_bound)
 }
 
-fn ty_param_bound(json: &Value) -> Result<TyParamBound> {
-    let (variant, fields) = try!(collect_enum(json, "ty_param_bound"));
+fn ty_param_bound<S: Input>(r: &mut S) -> Result<TyParamBound> {
+    let (variant, mut fields) = try!(collect_enum(r, "ty_param_bound"));
 
     let bytes: &[u8] = variant.as_ref();
-    match bytes {
+    let bound = match bytes {
         b"RegionBound" => {
-            if fields.len() != 1 { error!("region bound with {} fields", fields.len()) }
-            let lt = try!(lifetime(&fields[0]));
-            Ok(TyParamBound::Lifetime(lt))
+            try!(fields.next(r));
+            let lt = try!(lifetime(r));
+            TyParamBound::Lifetime(lt)
         },
         b"TraitBound" => {
-            if fields.len() != 2 { error!("trait bound with {} fields", fields.len()) }
-            let ttpb = TraitTyParamBound {
-                trait_: try!(poly_trait(&fields[0])),
-                maybe: try!(trait_bound_modifier(&fields[1])),
-            };
-            Ok(TyParamBound::Trait(ttpb))
+            try!(fields.next(r));
+            let trait_ = try!(poly_trait(r));
+            try!(fields.next(r));
+            let maybe = try!(trait_bound_modifier(r));
+            TyParamBound::Trait(TraitTyParamBound { trait_: trait_, maybe: maybe })
         },
-        _ => error!("unexpected TyParamBound variant: {:?}", variant),
-    }
+        _ => error_in!(r, "unexpected TyParamBound variant: {:?}", variant),
+    };
+    try!(fields.end(r));
+    Ok(bound)
 }
 
-fn trait_bound_modifier(json: &Value) -> Result<bool> {
-    let (variant, _) = try!(collect_enum(json, "TraitBoundModifier"));
+fn trait_bound_modifier<S: Input>(r: &mut S) -> Result<bool> {
+    let (variant, fields) = try!(collect_enum(r, "TraitBoundModifier"));
+    try!(fields.end(r));
     let bytes: &[u8] = variant.as_ref();
     match bytes {
         b"None" => Ok(false),
         b"Maybe" => Ok(true),
-        _ => error!("Unexpected TraitBoundModifier variant: {:?}", variant),
+        _ => error_in!(r, "Unexpected TraitBoundModifier variant: {:?}", variant),
     }
 }
 
-fn poly_trait(json: &Value) -> Result<PolyTrait> {
-    let mut fields = [("trait_", None), ("lifetimes", None)];
-    try!(collect_object(json, &mut fields, "PolyTrait"));
-    let trait_ = try!(type_(fields[0].1.unwrap()));
-    let lifetimes = try!(lifetimes(fields[1].1.unwrap()));
-    Ok(PolyTrait { trait_: trait_, lifetimes: lifetimes })
+fn poly_trait<S: Input>(r: &mut S) -> Result<PolyTrait> {
+    let mut trait_ = None;
+    let mut lifetimes_ = None;
+    try!(collect_object(r, "PolyTrait", |r, key| {
+        match key {
+            b"trait_" => trait_ = Some(try!(type_(r))),
+            b"lifetimes" => lifetimes_ = Some(try!(lifetimes(r))),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }));
+    Ok(PolyTrait {
+        trait_: try!(required(r, trait_, "PolyTrait", "trait_")),
+        lifetimes: try!(required(r, lifetimes_, "PolyTrait", "lifetimes")),
+    })
 }
 
-fn type_generic(fields: &JSlice) -> Result<Type> {
-    if fields.len() != 1 { error!("generic type with {} fields", fields.len()); }
-    let s = try!(collect_string(&fields[0], "generic type", "unnamed"));
-    Ok(Type::Generic(Generic { name: try!(s.try_to()) }))
+fn type_generic<S: Input>(r: &mut S, fields: &mut EnumFields) -> Result<Type> {
+    try!(fields.next(r));
+    let s = try!(collect_string(r, "generic type", "unnamed"));
+    Ok(Type::Generic(Generic { name: s }))
 }
 
-fn type_primitive(fields: &JSlice) -> Result<Type> {
-    if fields.len() != 1 { error!("primitive type with {} fields", fields.len()); }
-    let p = try!(primitive(&fields[0]));
+fn type_primitive<S: Input>(r: &mut S, fields: &mut EnumFields) -> Result<Type> {
+    try!(fields.next(r));
+    let p = try!(primitive(r));
     Ok(Type::Primitive(p))
 }
 
-fn primitive(json: &Value) -> Result<Primitive> {
-    let s = try!(collect_string(json, "?", "primitive"));
+fn primitive<S: Input>(r: &mut S) -> Result<Primitive> {
+    let s = try!(collect_string(r, "?", "primitive"));
 
     let bytes: &[u8] = s.as_ref();
     match bytes {
@@ -424,559 +1013,685 @@ fn primitive(json: &Value) -> Result<Primitive> {
         b"Array"               => Ok(Primitive::Array),
         b"PrimitiveTuple"      => Ok(Primitive::Tuple),
         b"PrimitiveRawPointer" => Ok(Primitive::RawPointer),
-        _ => error!("unexpected primitive variant: {:?}", s),
+        _ => error_in!(r, "unexpected primitive variant: {:?}", s),
     }
 }
 
-fn type_bare_function(fields: &JSlice) -> Result<Type> {
-    if fields.len() != 1 { error!("bare function type with {} fields", fields.len()); }
+fn type_bare_function<S: Input>(r: &mut S, fields: &mut EnumFields) -> Result<Type> {
+    try!(fields.next(r));
+
+    let mut unsafety = None;
+    let mut generics_ = None;
+    let mut decl = None;
+    let mut abi = None;
+    try!(collect_object(r, "bare function type", |r, key| {
+        match key {
+            b"unsafety" => {
+                unsafety = Some(try!(collect_string(r, "bare function type", "unsafety")));
+            },
+            b"generics" => generics_ = Some(try!(generics(r))),
+            b"decl" => decl = Some(try!(fn_decl(r))),
+            b"abi" => abi = Some(try!(collect_string(r, "bare function type", "abi"))),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }));
 
-    let mut fields_ = [("unsafety", None), ("generics", None), ("decl", None),
-                     ("abi", None)];
-    try!(collect_object(&fields[0], &mut fields_, "bare function type"));
-    let unsafety = try!(collect_string(fields_[0].1.unwrap(), "bare function type",
-                                       "unsafety"));
-    let generics = try!(generics(fields_[1].1.unwrap()));
-    let decl = try!(fn_decl(fields_[2].1.unwrap()));
-    let abi = try!(collect_string(fields_[3].1.unwrap(), "bare function type", "abi"));
-    
+    let unsafety = try!(required(r, unsafety, "bare function type", "unsafety"));
     let unsaf = unsafety.as_str() == "Unsafe";
 
     let bare_decl = BareFunctionDecl {
         unsaf: unsaf,
-        generics: generics,
-        decl: decl,
-        abi: try!(abi.try_to()),
+        generics: try!(required(r, generics_, "bare function type", "generics")),
+        decl: try!(required(r, decl, "bare function type", "decl")),
+        abi: try!(required(r, abi, "bare function type", "abi")),
     };
 
     Ok(Type::BareFunction(BareFunction { decl: try!(Box::new()).set(bare_decl) }))
 }
 
-fn type_tuple(fields: &JSlice) -> Result<Type> {
-    if fields.len() != 1 { error!("tuple type with {} fields", fields.len()); }
-    let array = try!(collect_array(&fields[0], "tuple type", "unnamed"));
-    let mut vec = try!(Vec::with_capacity(array.len()));
-    for field in array {
-        vec.push(try!(type_(field)));
-    }
+fn type_tuple<S: Input>(r: &mut S, fields: &mut EnumFields) -> Result<Type> {
+    try!(fields.next(r));
+    let vec = try!(collect_array(r, "tuple type", "unnamed", type_));
     Ok(Type::Tuple(Tuple { fields: vec }))
 }
 
-fn type_slice(fields: &JSlice) -> Result<Type> {
-    if fields.len() != 1 { error!("slice type with {} fields", fields.len()); }
-    let ty = try!(type_(&fields[0]));
+fn type_slice<S: Input>(r: &mut S, fields: &mut EnumFields) -> Result<Type> {
+    try!(fields.next(r));
+    let ty = try!(type_(r));
     Ok(Type::Slice(Slice { ty: try!(Box::new()).set(ty) }))
 }
 
-fn type_array(fields: &JSlice) -> Result<Type> {
-    if fields.len() != 2 { error!("array type with {} fields", fields.len()); }
-    let ty = try!(type_(&fields[0]));
-    let len = try!(collect_string(&fields[1], "array type", "unnamed"));
-    Ok(Type::Array(Array { ty: try!(Box::new()).set(ty), initializer: try!(len.try_to()) }))
-}
-
-fn type_bottom(fields: &JSlice) -> Result<Type> {
-    if fields.len() != 0 { error!("bottom type with {} fields", fields.len()); }
-    Ok(Type::Bottom)
+fn type_array<S: Input>(r: &mut S, fields: &mut EnumFields) -> Result<Type> {
+    try!(fields.next(r));
+    let ty = try!(type_(r));
+    try!(fields.next(r));
+    let len = try!(collect_string(r, "array type", "unnamed"));
+    Ok(Type::Array(Array { ty: try!(Box::new()).set(ty), initializer: len }))
 }
 
-fn type_pointer(fields: &JSlice) -> Result<Type> {
-    if fields.len() != 2 { error!("bottom type with {} fields", fields.len()); }
-    let mutable = try!(mutability(&fields[0]));
-    let ty = try!(type_(&fields[1]));
+fn type_pointer<S: Input>(r: &mut S, fields: &mut EnumFields) -> Result<Type> {
+    try!(fields.next(r));
+    let mutable = try!(mutability(r));
+    try!(fields.next(r));
+    let ty = try!(type_(r));
     Ok(Type::Pointer(Pointer { mutable: mutable, ty: try!(Box::new()).set(ty) }))
 }
 
-fn type_ref(fields: &JSlice) -> Result<Type> {
-    if fields.len() != 3 { error!("ref type with {} fields", fields.len()); }
-    let lifetime = match fields[0] {
-        Value::Null => None,
-        _ => Some(try!(lifetime(&fields[0]))),
+fn type_ref<S: Input>(r: &mut S, fields: &mut EnumFields) -> Result<Type> {
+    try!(fields.next(r));
+    let lifetime = match try!(skip_null(r)) {
+        true => None,
+        false => Some(try!(lifetime(r))),
     };
-    let mutable = try!(mutability(&fields[1]));
-    let ty = try!(type_(&fields[2]));
+    try!(fields.next(r));
+    let mutable = try!(mutability(r));
+    try!(fields.next(r));
+    let ty = try!(type_(r));
     Ok(Type::Ref(Ref { lifetime: lifetime, mutable: mutable, ty: try!(Box::new()).set(ty) }))
 }
 
-fn type_ufcs_path(fields: &JSlice) -> Result<Type> {
-    if fields.len() != 3 { error!("ufcs type with {} fields", fields.len()); }
-    let name = try!(collect_string(&fields[0], "ufcs type", "name"));
+fn type_ufcs_path<S: Input>(r: &mut S, fields: &mut EnumFields) -> Result<Type> {
+    try!(fields.next(r));
+    let name = try!(collect_string(r, "ufcs type", "name"));
+    try!(fields.next(r));
+    let self_ty = try!(type_(r));
+    try!(fields.next(r));
+    let trait_ = try!(type_(r));
     let up = UfcsPath {
-        self_ty: try!(Box::new()).set(try!(type_(&fields[1]))),
-        trait_: try!(Box::new()).set(try!(type_(&fields[2]))),
-        target: try!(name.try_to()),
+        self_ty: try!(Box::new()).set(self_ty),
+        trait_: try!(Box::new()).set(trait_),
+        target: name,
     };
     Ok(Type::UfcsPath(up))
 }
 
-fn type_infer(fields: &JSlice) -> Result<Type> {
-    if fields.len() != 0 { error!("infer type with {} fields", fields.len()); }
-    Ok(Type::Infer)
-}
-
-fn type_hklt_bound(fields: &JSlice) -> Result<Type> {
-    if fields.len() != 1 { error!("hklt bound type with {} fields", fields.len()); }
-    let bounds = try!(ty_param_bounds(&fields[0]));
+fn type_hklt_bound<S: Input>(r: &mut S, fields: &mut EnumFields) -> Result<Type> {
+    try!(fields.next(r));
+    let bounds = try!(ty_param_bounds(r));
     Ok(Type::HkltBound(HkltBound { bounds: bounds }))
 }
 
-fn type_bindings(json: &Value) -> Result<Vec<TypeBinding>> {
-    let array = try!(collect_array(json, "?", "type_bindings"));
-    let mut vec = try!(Vec::with_capacity(array.len()));
-    for l in array {
-        vec.push(try!(type_binding(l)));
-    }
-    Ok(vec)
+fn type_bindings<S: Input>(r: &mut S) -> Result<Vec<TypeBinding>> {
+    collect_array(r, "?", "type_bindings", type_binding)
 }
 
-fn type_binding(json: &Value) -> Result<TypeBinding> {
-    let mut fields = [("name", None), ("ty", None)];
-    try!(collect_object(json, &mut fields, "TypeBinding"));
-    let name = try!(collect_string(fields[0].1.unwrap(), "type_bindings", "name"));
-    let ty = try!(type_(fields[1].1.unwrap()));
-    let name = try!(name.try_to());
-    Ok(TypeBinding { name: name, ty: ty })
+fn type_binding<S: Input>(r: &mut S) -> Result<TypeBinding> {
+    let mut name = None;
+    let mut ty = None;
+    try!(collect_object(r, "TypeBinding", |r, key| {
+        match key {
+            b"name" => name = Some(try!(collect_string(r, "type_bindings", "name"))),
+            b"ty" => ty = Some(try!(type_(r))),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }));
+    Ok(TypeBinding {
+        name: try!(required(r, name, "TypeBinding", "name")),
+        ty: try!(required(r, ty, "TypeBinding", "ty")),
+    })
 }
 
-fn item_struct(fields: &JSlice) -> Result<Item> {
-    if fields.len() != 1 { error!("struct item with {} fields", fields.len()); }
-    let struct_ = try!(struct_(&fields[0]));
+fn item_struct<S: Input>(r: &mut S, fields: &mut EnumFields) -> Result<Item> {
+    try!(fields.next(r));
+    let struct_ = try!(struct_(r));
     Ok(Item::Struct(struct_))
 }
 
-fn struct_(json: &Value) -> Result<Struct> {
-    let mut fields = [("struct_type", None), ("generics", None), ("fields", None),
-                      ("fields_stripped", None)];
-    try!(collect_object(json, &mut fields, "Struct"));
-    
-    let struct_type = try!(struct_type(&fields[0].1.unwrap()));
-    let generics = try!(generics(&fields[1].1.unwrap()));
-    let fields_ = try!(item_datas(&fields[2].1.unwrap()));
-    let stripped = try!(collect_bool(&fields[3].1.unwrap(), "Struct", "fields_stripped"));
-    Ok(Struct { 
-        struct_type: struct_type,
-        generics: generics,
-        fields: fields_,
-        private_fields: stripped,
+fn struct_<S: Input>(r: &mut S) -> Result<Struct> {
+    let mut struct_type_ = None;
+    let mut generics_ = None;
+    let mut fields = None;
+    let mut stripped = None;
+    try!(collect_object(r, "Struct", |r, key| {
+        match key {
+            b"struct_type" => struct_type_ = Some(try!(struct_type(r))),
+            b"generics" => generics_ = Some(try!(generics(r))),
+            b"fields" => fields = Some(try!(item_datas(r))),
+            b"fields_stripped" => {
+                stripped = Some(try!(collect_bool(r, "Struct", "fields_stripped")));
+            },
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }));
+
+    Ok(Struct {
+        struct_type: try!(required(r, struct_type_, "Struct", "struct_type")),
+        generics: try!(required(r, generics_, "Struct", "generics")),
+        fields: try!(required(r, fields, "Struct", "fields")),
+        private_fields: try!(required(r, stripped, "Struct", "fields_stripped")),
     })
 }
 
-fn item_enum(fields: &JSlice) -> Result<Item> {
-    if fields.len() != 1 { error!("enum item with {} fields", fields.len()); }
-    let enum_ = try!(enum_(&fields[0]));
+fn item_enum<S: Input>(r: &mut S, fields: &mut EnumFields) -> Result<Item> {
+    try!(fields.next(r));
+    let enum_ = try!(enum_(r));
     Ok(Item::Enum(enum_))
 }
 
-fn enum_(json: &Value) -> Result<Enum> {
-    let mut fields = [("variants", None), ("generics", None)];
-    try!(collect_object(json, &mut fields, "Enum"));
-    let variants = try!(item_datas(fields[0].1.unwrap()));
-    let generics = try!(generics(&fields[1].1.unwrap()));
+fn enum_<S: Input>(r: &mut S) -> Result<Enum> {
+    let mut variants = None;
+    let mut generics_ = None;
+    try!(collect_object(r, "Enum", |r, key| {
+        match key {
+            b"variants" => variants = Some(try!(item_datas(r))),
+            b"generics" => generics_ = Some(try!(generics(r))),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }));
     Ok(Enum {
-        variants: variants,
-        generics: generics,
+        variants: try!(required(r, variants, "Enum", "variants")),
+        generics: try!(required(r, generics_, "Enum", "generics")),
     })
 }
 
-fn item_func(fields: &JSlice) -> Result<Item> {
-    if fields.len() != 1 { error!("func item with {} fields", fields.len()); }
-    let func = try!(func(&fields[0]));
+fn item_func<S: Input>(r: &mut S, fields: &mut EnumFields) -> Result<Item> {
+    try!(fields.next(r));
+    let func = try!(func(r));
     Ok(Item::Func(func))
 }
 
-fn func(json: &Value) -> Result<Func> {
-    let mut fields = [("decl", None), ("generics", None), ("unsafety", None),
-                      ("abi", None)];
-    try!(collect_object(json, &mut fields, "Func"));
-    let decl = try!(fn_decl(fields[0].1.unwrap()));
-    let generics = try!(generics(fields[1].1.unwrap()));
-    let unsafety = try!(collect_string(fields[2].1.unwrap(), "Func", "unsafety"));
-    let abi = try!(abi(fields[3].1.unwrap()));
+fn func<S: Input>(r: &mut S) -> Result<Func> {
+    let mut decl = None;
+    let mut generics_ = None;
+    let mut unsafety = None;
+    let mut abi_ = None;
+    try!(collect_object(r, "Func", |r, key| {
+        match key {
+            b"decl" => decl = Some(try!(fn_decl(r))),
+            b"generics" => generics_ = Some(try!(generics(r))),
+            b"unsafety" => unsafety = Some(try!(collect_string(r, "Func", "unsafety"))),
+            b"abi" => abi_ = Some(try!(abi(r))),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }));
+    let unsafety = try!(required(r, unsafety, "Func", "unsafety"));
     let unsaf = unsafety.as_str() == "Unsafe";
     Ok(Func {
-        decl: decl,
-        generics: generics,
+        decl: try!(required(r, decl, "Func", "decl")),
+        generics: try!(required(r, generics_, "Func", "generics")),
         unsaf: unsaf,
-        abi: abi,
+        abi: try!(required(r, abi_, "Func", "abi")),
     })
 }
 
-fn item_module(fields: &JSlice) -> Result<Item> {
-    if fields.len() != 1 { error!("module item with {} fields", fields.len()); }
-    let module = try!(module(&fields[0]));
+fn item_module<S: Input>(r: &mut S, fields: &mut EnumFields) -> Result<Item> {
+    try!(fields.next(r));
+    let module = try!(module(r));
     Ok(Item::Module(module))
 }
 
-fn module(json: &Value) -> Result<Module> {
-    let mut fields = [("items", None)];
-    try!(collect_object(json, &mut fields, "Module"));
-    let items = try!(item_datas(&fields[0].1.unwrap()));
-    Ok(Module { items: items })
+fn module<S: Input>(r: &mut S) -> Result<Module> {
+    let mut items = None;
+    try!(collect_object(r, "Module", |r, key| {
+        match key {
+            b"items" => items = Some(try!(item_datas(r))),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }));
+    Ok(Module { items: try!(required(r, items, "Module", "items")) })
 }
 
-fn item_typedef(fields: &JSlice) -> Result<Item> {
-    if fields.len() != 2 { error!("typedef item with {} fields", fields.len()); }
-    let mut typedef = try!(typedef(&fields[0]));
-    typedef.is_assoc = try!(collect_bool(&fields[1], "TypedefItem", "fields[1]"));
+fn item_typedef<S: Input>(r: &mut S, fields: &mut EnumFields) -> Result<Item> {
+    try!(fields.next(r));
+    let mut typedef = try!(typedef(r));
+    if r.schema().assoc_typedefs {
+        try!(fields.next(r));
+        typedef.is_assoc = try!(collect_bool(r, "TypedefItem", "fields[1]"));
+    }
     Ok(Item::Typedef(typedef))
 }
 
-fn typedef(json: &Value) -> Result<Typedef> {
-    let mut fields = [("type_", None), ("generics", None)];
-    try!(collect_object(json, &mut fields, "Typedef"));
-    let type_ = try!(type_(fields[0].1.unwrap()));
-    let generics = try!(generics(fields[1].1.unwrap()));
-    Ok(Typedef { type_: type_, generics: generics, is_assoc: false, })
+fn typedef<S: Input>(r: &mut S) -> Result<Typedef> {
+    let mut ty = None;
+    let mut generics_ = None;
+    try!(collect_object(r, "Typedef", |r, key| {
+        match key {
+            b"type_" => ty = Some(try!(type_(r))),
+            b"generics" => generics_ = Some(try!(generics(r))),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }));
+    Ok(Typedef {
+        type_: try!(required(r, ty, "Typedef", "type_")),
+        generics: try!(required(r, generics_, "Typedef", "generics")),
+        is_assoc: false,
+    })
 }
 
-fn item_static(fields: &JSlice) -> Result<Item> {
-    if fields.len() != 1 { error!("static item with {} fields", fields.len()); }
-    let static_ = try!(static_(&fields[0]));
+fn item_static<S: Input>(r: &mut S, fields: &mut EnumFields) -> Result<Item> {
+    try!(fields.next(r));
+    let static_ = try!(static_(r));
     Ok(Item::Static(static_))
 }
 
-fn static_(json: &Value) -> Result<Static> {
-    let mut fields = [("type_", None), ("mutability", None), ("expr", None)];
-    try!(collect_object(json, &mut fields, "Static"));
-    let type_ = try!(type_(fields[0].1.unwrap()));
-    let mutable = try!(mutability(fields[1].1.unwrap()));
-    let expr = try!(collect_string(fields[2].1.unwrap(), "Static", "expr"));
-    let expr = try!(expr.try_to());
-    Ok(Static { type_: type_, mutable: mutable, expr: expr })
+fn static_<S: Input>(r: &mut S) -> Result<Static> {
+    let mut ty = None;
+    let mut mutable = None;
+    let mut expr = None;
+    try!(collect_object(r, "Static", |r, key| {
+        match key {
+            b"type_" => ty = Some(try!(type_(r))),
+            b"mutability" => mutable = Some(try!(mutability(r))),
+            b"expr" => expr = Some(try!(collect_string(r, "Static", "expr"))),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }));
+    Ok(Static {
+        type_: try!(required(r, ty, "Static", "type_")),
+        mutable: try!(required(r, mutable, "Static", "mutability")),
+        expr: try!(required(r, expr, "Static", "expr")),
+    })
 }
 
-fn item_constant(fields: &JSlice) -> Result<Item> {
-    if fields.len() != 1 { error!("constant item with {} fields", fields.len()); }
-    let constant = try!(constant(&fields[0]));
+fn item_constant<S: Input>(r: &mut S, fields: &mut EnumFields) -> Result<Item> {
+    try!(fields.next(r));
+    let constant = try!(constant(r));
     Ok(Item::Constant(constant))
 }
 
-fn constant(json: &Value) -> Result<Constant> {
-    let mut fields = [("type_", None), ("expr", None)];
-    try!(collect_object(json, &mut fields, "Constant"));
-    let type_ = try!(type_(fields[0].1.unwrap()));
-    let expr = try!(collect_string(fields[1].1.unwrap(), "Constant", "expr"));
-    let expr = try!(expr.try_to());
-    Ok(Constant { type_: type_, expr: expr })
+fn constant<S: Input>(r: &mut S) -> Result<Constant> {
+    let mut ty = None;
+    let mut expr = None;
+    try!(collect_object(r, "Constant", |r, key| {
+        match key {
+            b"type_" => ty = Some(try!(type_(r))),
+            b"expr" => expr = Some(try!(collect_string(r, "Constant", "expr"))),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }));
+    Ok(Constant {
+        type_: try!(required(r, ty, "Constant", "type_")),
+        expr: try!(required(r, expr, "Constant", "expr")),
+    })
 }
 
-fn item_trait(fields: &JSlice) -> Result<Item> {
-    if fields.len() != 1 { error!("trait item with {} fields", fields.len()); }
-    let trait_ = try!(trait_(&fields[0]));
+fn item_trait<S: Input>(r: &mut S, fields: &mut EnumFields) -> Result<Item> {
+    try!(fields.next(r));
+    let trait_ = try!(trait_(r));
     Ok(Item::Trait(trait_))
 }
 
-fn trait_(json: &Value) -> Result<Trait> {
-    let mut fields = [("unsafety", None), ("items", None), ("generics", None),
-                      ("bounds", None)];
-    try!(collect_object(json, &mut fields, "Trait"));
-    let unsaf = try!(unsafety(&fields[0].1.unwrap()));
-    let items = try!(item_datas(&fields[1].1.unwrap()));
-    let generics = try!(generics(&fields[2].1.unwrap()));
-    let bounds = try!(ty_param_bounds(&fields[3].1.unwrap()));
+fn trait_<S: Input>(r: &mut S) -> Result<Trait> {
+    let mut unsaf = None;
+    let mut items = None;
+    let mut generics_ = None;
+    let mut bounds = None;
+    try!(collect_object(r, "Trait", |r, key| {
+        match key {
+            b"unsafety" => unsaf = Some(try!(unsafety(r))),
+            b"items" => items = Some(try!(item_datas(r))),
+            b"generics" => generics_ = Some(try!(generics(r))),
+            b"bounds" => bounds = Some(try!(ty_param_bounds(r))),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }));
     Ok(Trait {
-        unsaf: unsaf,
-        items: items,
-        generics: generics,
-        bounds: bounds,
+        unsaf: try!(required(r, unsaf, "Trait", "unsafety")),
+        items: try!(required(r, items, "Trait", "items")),
+        generics: try!(required(r, generics_, "Trait", "generics")),
+        bounds: try!(required(r, bounds, "Trait", "bounds")),
     })
 }
 
-fn item_impl(fields: &JSlice) -> Result<Item> {
-    if fields.len() != 1 { error!("impl item with {} fields", fields.len()); }
-    let impl_ = try!(impl_(&fields[0]));
+fn item_impl<S: Input>(r: &mut S, fields: &mut EnumFields) -> Result<Item> {
+    try!(fields.next(r));
+    let impl_ = try!(impl_(r));
     Ok(Item::Impl(impl_))
 }
 
-fn impl_(json: &Value) -> Result<Impl> {
-    let mut fields = [("unsafety", None), ("generics", None), ("trait_", None),
-                      ("for_", None), ("items", None), ("derived", None),
-                      ("polarity", None)];
-    try!(collect_object(json, &mut fields, "Impl"));
-    let unsaf = try!(unsafety(fields[0].1.unwrap()));
-    let generics = try!(generics(fields[1].1.unwrap()));
-    let trait_ = match *fields[2].1.unwrap() {
-        Value::Null => None,
-        _ => Some(try!(type_(fields[2].1.unwrap()))),
-    };
-    let for_ = try!(type_(fields[3].1.unwrap()));
-    let items = try!(item_datas(fields[4].1.unwrap()));
-    let derived = try!(collect_bool(fields[5].1.unwrap(), "Impl", "derived"));
-    let negative = match *fields[6].1.unwrap() {
-        Value::Null => None,
-        _ => Some(try!(polarity(fields[6].1.unwrap()))),
-    };
+fn impl_<S: Input>(r: &mut S) -> Result<Impl> {
+    let mut unsaf = None;
+    let mut generics_ = None;
+    let mut trait_ = None;
+    let mut for_ = None;
+    let mut items = None;
+    let mut derived = None;
+    let mut negative = None;
+    try!(collect_object(r, "Impl", |r, key| {
+        match key {
+            b"unsafety" => unsaf = Some(try!(unsafety(r))),
+            b"generics" => generics_ = Some(try!(generics(r))),
+            b"trait_" => {
+                trait_ = Some(match try!(skip_null(r)) {
+                    true => None,
+                    false => Some(try!(type_(r))),
+                });
+            },
+            b"for_" => for_ = Some(try!(type_(r))),
+            b"items" => items = Some(try!(item_datas(r))),
+            b"derived" => derived = Some(try!(collect_bool(r, "Impl", "derived"))),
+            b"polarity" => {
+                negative = Some(match try!(skip_null(r)) {
+                    true => None,
+                    false => Some(try!(polarity(r))),
+                });
+            },
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }));
     Ok(Impl {
-        unsaf:    unsaf,
-        generics: generics,
-        trait_:   trait_,
-        for_:     for_,
-        items:    items,
-        derived:  derived,
-        negative: negative,
+        unsaf:    try!(required(r, unsaf, "Impl", "unsafety")),
+        generics: try!(required(r, generics_, "Impl", "generics")),
+        trait_:   try!(required(r, trait_, "Impl", "trait_")),
+        for_:     try!(required(r, for_, "Impl", "for_")),
+        items:    try!(required(r, items, "Impl", "items")),
+        derived:  try!(required(r, derived, "Impl", "derived")),
+        negative: try!(required(r, negative, "Impl", "polarity")),
     })
 }
 
-fn item_method_decl(fields: &JSlice) -> Result<Item> {
-    if fields.len() != 1 { error!("method decl item with {} fields", fields.len()); }
-    let method = try!(method(&fields[0]));
+fn item_method_decl<S: Input>(r: &mut S, fields: &mut EnumFields) -> Result<Item> {
+    try!(fields.next(r));
+    let method = try!(method(r));
     Ok(Item::MethodDecl(method))
 }
 
-fn item_method(fields: &JSlice) -> Result<Item> {
-    if fields.len() != 1 { error!("method item with {} fields", fields.len()); }
-    let method = try!(method(&fields[0]));
+fn item_method<S: Input>(r: &mut S, fields: &mut EnumFields) -> Result<Item> {
+    try!(fields.next(r));
+    let method = try!(method(r));
     Ok(Item::Method(method))
 }
 
-fn method(json: &Value) -> Result<Method> {
-    let mut fields = [("unsafety", None), ("decl", None), ("generics", None),
-                      ("self_", None), ("abi", None)];
-    try!(collect_object(json, &mut fields, "Method"));
-    let unsaf = try!(unsafety(fields[0].1.unwrap()));
-    let decl = try!(fn_decl(fields[1].1.unwrap()));
-    let generics = try!(generics(fields[2].1.unwrap()));
-    let self_ = try!(self_ty(fields[3].1.unwrap()));
-    let abi = try!(abi(fields[4].1.unwrap()));
+fn method<S: Input>(r: &mut S) -> Result<Method> {
+    let mut unsaf = None;
+    let mut decl = None;
+    let mut generics_ = None;
+    let mut self_ = None;
+    let mut abi_ = None;
+    try!(collect_object(r, "Method", |r, key| {
+        match key {
+            b"unsafety" => unsaf = Some(try!(unsafety(r))),
+            b"decl" => decl = Some(try!(fn_decl(r))),
+            b"generics" => generics_ = Some(try!(generics(r))),
+            b"self_" => self_ = Some(try!(self_ty(r))),
+            b"abi" => abi_ = Some(try!(abi(r))),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }));
     Ok(Method {
-        unsaf:    unsaf,
-        decl:     decl,
-        generics: generics,
-        self_:    self_,
-        abi:      abi,
+        unsaf:    try!(required(r, unsaf, "Method", "unsafety")),
+        decl:     try!(required(r, decl, "Method", "decl")),
+        generics: try!(required(r, generics_, "Method", "generics")),
+        self_:    try!(required(r, self_, "Method", "self_")),
+        abi:      try!(required(r, abi_, "Method", "abi")),
     })
 }
 
-fn item_struct_field(fields: &JSlice) -> Result<Item> {
-    if fields.len() != 1 { error!("struct field item with {} fields", fields.len()); }
-    let struct_field = try!(struct_field(&fields[0]));
+fn item_struct_field<S: Input>(r: &mut S, fields: &mut EnumFields) -> Result<Item> {
+    try!(fields.next(r));
+    let struct_field = try!(struct_field(r));
     Ok(Item::StructField(struct_field))
 }
 
-fn struct_field(json: &Value) -> Result<StructField> {
-    let (variant, fields) = try!(collect_enum(json, "StructField"));
+fn struct_field<S: Input>(r: &mut S) -> Result<StructField> {
+    let (variant, mut fields) = try!(collect_enum(r, "StructField"));
     let bytes: &[u8] = variant.as_ref();
-    match bytes {
-        b"HiddenStructField" => {
-            if fields.len() != 0 { error!("HiddenStructField with {} fields",
-                                          fields.len()); }
-            Ok(StructField::Hidden)
-        },
+    let field = match bytes {
+        b"HiddenStructField" => StructField::Hidden,
         b"TypedStructField" => {
-            if fields.len() != 1 { error!("TypedStructField with {} fields",
-                                          fields.len()); }
-            let type_ = try!(type_(&fields[0]));
-            Ok(StructField::Typed(type_))
+            try!(fields.next(r));
+            let type_ = try!(type_(r));
+            StructField::Typed(type_)
         },
-        _ => error!("Unexpected struct field variant: {:?}", variant),
-    }
+        _ => error_in!(r, "Unexpected struct field variant: {:?}", variant),
+    };
+    try!(fields.end(r));
+    Ok(field)
 }
 
-fn item_variant(fields: &JSlice) -> Result<Item> {
-    if fields.len() != 1 { error!("variant item with {} fields", fields.len()); }
-    let variant = try!(variant(&fields[0]));
+fn item_variant<S: Input>(r: &mut S, fields: &mut EnumFields) -> Result<Item> {
+    try!(fields.next(r));
+    let variant = try!(variant(r));
     Ok(Item::Variant(variant))
 }
 
-fn variant(json: &Value) -> Result<Variant> {
-    let mut fields = [("kind", None)];
-    try!(collect_object(json, &mut fields, "Variant"));
-    let kind = try!(variant_kind(fields[0].1.unwrap()));
-    Ok(Variant { kind: kind })
+fn variant<S: Input>(r: &mut S) -> Result<Variant> {
+    let mut kind = None;
+    try!(collect_object(r, "Variant", |r, key| {
+        match key {
+            b"kind" => kind = Some(try!(variant_kind(r))),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }));
+    Ok(Variant { kind: try!(required(r, kind, "Variant", "kind")) })
 }
 
-fn variant_kind(json: &Value) -> Result<VariantKind> {
-    let (variant, fields) = try!(collect_enum(json, "VariantKind"));
+fn variant_kind<S: Input>(r: &mut S) -> Result<VariantKind> {
+    let (variant, mut fields) = try!(collect_enum(r, "VariantKind"));
     let bytes: &[u8] = variant.as_ref();
-    match bytes {
-        b"CLikeVariant" => {
-            if fields.len() != 0 { error!("CLikeVariant with {} fields", fields.len()); }
-            Ok(VariantKind::CLike)
-        },
+    let kind = match bytes {
+        b"CLikeVariant" => VariantKind::CLike,
         b"TupleVariant" => {
-            if fields.len() != 1 { error!("TupleVariant with {} fields", fields.len()); }
-            let types = try!(types(&fields[0]));
-            Ok(VariantKind::Tuple(types))
+            try!(fields.next(r));
+            let types = try!(types(r));
+            VariantKind::Tuple(types)
         },
         b"StructVariant" => {
-            if fields.len() != 1 { error!("StructVariant with {} fields", fields.len()); }
-            let variant_struct = try!(variant_struct(&fields[0]));
-            Ok(VariantKind::Struct(variant_struct))
+            try!(fields.next(r));
+            let variant_struct = try!(variant_struct(r));
+            VariantKind::Struct(variant_struct)
         },
         _ => {
-            error!("Unexpected VariantKind variant: {:?}", variant);
+            error_in!(r, "Unexpected VariantKind variant: {:?}", variant);
         },
-    }
-}
-
-fn variant_struct(json: &Value) -> Result<VariantStruct> {
-    let mut fields = [("struct_type", None), ("fields", None), ("fields_stripped", None)];
-    try!(collect_object(json, &mut fields, "VariantStruct"));
-    let struct_type = try!(struct_type(fields[0].1.unwrap()));
-    let fields_ = try!(item_datas(fields[1].1.unwrap()));
-    let private_fields = try!(collect_bool(fields[2].1.unwrap(), "VariantStruct",
-                                           "fields_stripped"));
+    };
+    try!(fields.end(r));
+    Ok(kind)
+}
+
+fn variant_struct<S: Input>(r: &mut S) -> Result<VariantStruct> {
+    let mut struct_type_ = None;
+    let mut fields = None;
+    let mut private_fields = None;
+    try!(collect_object(r, "VariantStruct", |r, key| {
+        match key {
+            b"struct_type" => struct_type_ = Some(try!(struct_type(r))),
+            b"fields" => fields = Some(try!(item_datas(r))),
+            b"fields_stripped" => {
+                private_fields = Some(try!(collect_bool(r, "VariantStruct",
+                                                        "fields_stripped")));
+            },
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }));
     Ok(VariantStruct {
-        struct_type: struct_type,
-        fields: fields_,
-        private_fields: private_fields,
+        struct_type: try!(required(r, struct_type_, "VariantStruct", "struct_type")),
+        fields: try!(required(r, fields, "VariantStruct", "fields")),
+        private_fields: try!(required(r, private_fields, "VariantStruct",
+                                      "fields_stripped")),
     })
 }
 
-fn struct_type(json: &Value) -> Result<StructType> {
-    let s = try!(collect_string(json, "?", "struct_type"));
+fn struct_type<S: Input>(r: &mut S) -> Result<StructType> {
+    let s = try!(collect_string(r, "?", "struct_type"));
     let bytes: &[u8] = s.as_ref();
     match bytes {
         b"Plain" => Ok(StructType::Plain),
         b"Tuple" | b"Newtype" => Ok(StructType::Tuple),
         b"Unit" => Ok(StructType::Unit),
-        _ => error!("Unexpected StructType variant: {:?}", s),
+        _ => error_in!(r, "Unexpected StructType variant: {:?}", s),
     }
 }
 
-fn item_extern_func(fields: &JSlice) -> Result<Item> {
-    if fields.len() != 1 { error!("extern func item with {} fields", fields.len()); }
-    let func = try!(func(&fields[0]));
+fn item_extern_func<S: Input>(r: &mut S, fields: &mut EnumFields) -> Result<Item> {
+    try!(fields.next(r));
+    let func = try!(func(r));
     Ok(Item::ExternFunc(func))
 }
 
-fn item_extern_static(fields: &JSlice) -> Result<Item> {
-    if fields.len() != 1 { error!("extern static item with {} fields", fields.len()); }
-    let static_ = try!(static_(&fields[0]));
+fn item_extern_static<S: Input>(r: &mut S, fields: &mut EnumFields) -> Result<Item> {
+    try!(fields.next(r));
+    let static_ = try!(static_(r));
     Ok(Item::ExternStatic(static_))
 }
 
-fn item_macro(fields: &JSlice) -> Result<Item> {
-    if fields.len() != 1 { error!("macro item with {} fields", fields.len()); }
-    let macro_ = try!(macro_(&fields[0]));
+fn item_macro<S: Input>(r: &mut S, fields: &mut EnumFields) -> Result<Item> {
+    try!(fields.next(r));
+    let macro_ = try!(macro_(r));
     Ok(Item::Macro(macro_))
 }
 
-fn macro_(json: &Value) -> Result<Macro> {
-    let mut fields = [("source", None)];
-    try!(collect_object(json, &mut fields, "Macro"));
-    let source = try!(collect_string(fields[0].1.unwrap(), "Macro", "source"));
-    let source = try!(source.try_to());
-    Ok(Macro { source: source })
+fn macro_<S: Input>(r: &mut S) -> Result<Macro> {
+    let mut source = None;
+    try!(collect_object(r, "Macro", |r, key| {
+        match key {
+            b"source" => source = Some(try!(collect_string(r, "Macro", "source"))),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }));
+    Ok(Macro { source: try!(required(r, source, "Macro", "source")) })
 }
 
-fn item_primitive(fields: &JSlice) -> Result<Item> {
-    if fields.len() != 1 { error!("primitive item with {} fields", fields.len()); }
-    let primitive = try!(primitive(&fields[0]));
+fn item_primitive<S: Input>(r: &mut S, fields: &mut EnumFields) -> Result<Item> {
+    try!(fields.next(r));
+    let primitive = try!(primitive(r));
     Ok(Item::Primitive(primitive))
 }
 
-fn item_assoc_type(fields: &JSlice) -> Result<Item> {
-    if fields.len() != 2 { error!("associated type item with {} fields", fields.len()); }
-    let at = AssocType {
-        bounds: try!(ty_param_bounds(&fields[0])),
-        default: match fields[1] {
-            Value::Null => None,
-            _ => Some(try!(type_(&fields[1]))),
-        },
+fn item_assoc_type<S: Input>(r: &mut S, fields: &mut EnumFields) -> Result<Item> {
+    try!(fields.next(r));
+    let bounds = try!(ty_param_bounds(r));
+    try!(fields.next(r));
+    let default = match try!(skip_null(r)) {
+        true => None,
+        false => Some(try!(type_(r))),
     };
-    Ok(Item::AssocType(at))
+    Ok(Item::AssocType(AssocType { bounds: bounds, default: default }))
 }
 
-fn item_default_impl(fields: &JSlice) -> Result<Item> {
-    if fields.len() != 1 { error!("default impl item with {} fields", fields.len()); }
-    let default_impl = try!(default_impl(&fields[0]));
+fn item_default_impl<S: Input>(r: &mut S, fields: &mut EnumFields) -> Result<Item> {
+    try!(fields.next(r));
+    let default_impl = try!(default_impl(r));
     Ok(Item::DefaultImpl(default_impl))
 }
 
-fn default_impl(json: &Value) -> Result<DefaultImpl> {
-    let mut fields = [("unsafety", None), ("trait_", None)];
-    try!(collect_object(json, &mut fields, "DefaultImpl"));
-    let unsaf = try!(unsafety(fields[0].1.unwrap()));
-    let ty = try!(type_(fields[1].1.unwrap()));
-    Ok(DefaultImpl { unsaf: unsaf, trait_: ty })
+fn default_impl<S: Input>(r: &mut S) -> Result<DefaultImpl> {
+    let mut unsaf = None;
+    let mut ty = None;
+    try!(collect_object(r, "DefaultImpl", |r, key| {
+        match key {
+            b"unsafety" => unsaf = Some(try!(unsafety(r))),
+            b"trait_" => ty = Some(try!(type_(r))),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }));
+    Ok(DefaultImpl {
+        unsaf: try!(required(r, unsaf, "DefaultImpl", "unsafety")),
+        trait_: try!(required(r, ty, "DefaultImpl", "trait_")),
+    })
 }
 
-fn unsafety(json: &Value) -> Result<bool> {
-    let string = try!(collect_string(json, "?", "unsafety"));
+fn unsafety<S: Input>(r: &mut S) -> Result<bool> {
+    let string = try!(collect_string(r, "?", "unsafety"));
     Ok(string.as_str() == "Unsafe")
 }
 
-fn generics(json: &Value) -> Result<Generics> {
-    let mut fields = [("lifetimes", None), ("type_params", None),
-                      ("where_predicates", None)];
-    try!(collect_object(json, &mut fields, "Generics"));
+fn generics<S: Input>(r: &mut S) -> Result<Generics> {
+    let mut lifetimes_ = None;
+    let mut type_params = None;
+    let mut where_predicates_ = None;
+    try!(collect_object(r, "Generics", |r, key| {
+        match key {
+            b"lifetimes" => lifetimes_ = Some(try!(lifetimes(r))),
+            b"type_params" => type_params = Some(try!(ty_params(r))),
+            b"where_predicates" => where_predicates_ = Some(try!(where_predicates(r))),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }));
     Ok(Generics {
-        lifetimes:        try!(lifetimes(fields[0].1.unwrap())),
-        type_params:      try!(ty_params(fields[1].1.unwrap())),
-        where_predicates: try!(where_predicates(fields[2].1.unwrap())),
+        lifetimes:        try!(required(r, lifetimes_, "Generics", "lifetimes")),
+        type_params:      try!(required(r, type_params, "Generics", "type_params")),
+        where_predicates: try!(required(r, where_predicates_, "Generics",
+                                        "where_predicates")),
     })
 }
 
-fn ty_params(json: &Value) -> Result<Vec<TyParam>> {
-    let array = try!(collect_array(json, "?", "ty_params"));
-    let mut vec = try!(Vec::with_capacity(array.len()));
-    for l in array {
-        vec.push(try!(ty_param(l)));
-    }
-    Ok(vec)
-}
-
-fn ty_param(json: &Value) -> Result<TyParam> {
-    let mut fields = [("name", None), ("did", None), ("bounds", None),
-                      ("default", None)];
-    try!(collect_object(json, &mut fields, "TyParam"));
-    let name = try!(collect_string(fields[0].1.unwrap(), "TyParam", "name"));
-    let default = match *fields[3].1.unwrap() {
-        Value::Null => None,
-        _ => Some(try!(type_(fields[3].1.unwrap()))),
-    };
+fn ty_params<S: Input>(r: &mut S) -> Result<Vec<TyParam>> {
+    collect_array(r, "?", "ty_params", ty_param)
+}
+
+fn ty_param<S: Input>(r: &mut S) -> Result<TyParam> {
+    let mut name = None;
+    let mut definition = None;
+    let mut bounds = None;
+    let mut default = None;
+    try!(collect_object(r, "TyParam", |r, key| {
+        match key {
+            b"name" => name = Some(try!(collect_string(r, "TyParam", "name"))),
+            b"did" => definition = Some(try!(def_id(r))),
+            b"bounds" => bounds = Some(try!(ty_param_bounds(r))),
+            b"default" => {
+                default = Some(match try!(skip_null(r)) {
+                    true => None,
+                    false => Some(try!(type_(r))),
+                });
+            },
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }));
     Ok(TyParam {
-        name: try!(name.try_to()),
-        definition: try!(def_id(fields[1].1.unwrap())),
-        bounds: try!(ty_param_bounds(fields[2].1.unwrap())),
-        default: default,
+        name: try!(required(r, name, "TyParam", "name")),
+        definition: try!(required(r, definition, "TyParam", "did")),
+        bounds: try!(required(r, bounds, "TyParam", "bounds")),
+        default: try!(required(r, default, "TyParam", "default")),
     })
 }
 
-fn where_predicates(json: &Value) -> Result<Vec<WherePredicate>> {
-    let array = try!(collect_array(json, "?", "where_predicates"));
-    let mut vec = try!(Vec::with_capacity(array.len()));
-    for l in array {
-        vec.push(try!(where_predicate(l)));
-    }
-    Ok(vec)
+fn where_predicates<S: Input>(r: &mut S) -> Result<Vec<WherePredicate>> {
+    collect_array(r, "?", "where_predicates", where_predicate)
 }
 
-fn where_predicate(json: &Value) -> Result<WherePredicate> {
-    let (variant, fields) = try!(collect_enum(json, "WherePredicate"));
+fn where_predicate<S: Input>(r: &mut S) -> Result<WherePredicate> {
+    let (variant, mut fields) = try!(collect_enum(r, "WherePredicate"));
     let bytes: &[u8] = variant.as_ref();
-    match bytes {
+    let pred = match bytes {
         b"BoundPredicate" => {
-            if fields.len() != 2 { error!("BoundPredicate with {} fields", fields.len()); }
-            let bwp = BoundWherePredicate {
-                ty: try!(type_(&fields[0])),
-                bounds: try!(ty_param_bounds(&fields[1])),
-            };
-            Ok(WherePredicate::Bound(bwp))
+            try!(fields.next(r));
+            let ty = try!(type_(r));
+            try!(fields.next(r));
+            let bounds = try!(ty_param_bounds(r));
+            WherePredicate::Bound(BoundWherePredicate { ty: ty, bounds: bounds })
         },
         b"RegionPredicate" => {
-            if fields.len() != 2 { error!("RegionPredicate with {} fields", fields.len()); }
-            let rwp = RegionWherePredicate {
-                lt: try!(lifetime(&fields[0])),
-                bounds: try!(lifetimes(&fields[1])),
-            };
-            Ok(WherePredicate::Region(rwp))
+            try!(fields.next(r));
+            let lt = try!(lifetime(r));
+            try!(fields.next(r));
+            let bounds = try!(lifetimes(r));
+            WherePredicate::Region(RegionWherePredicate { lt: lt, bounds: bounds })
         },
         b"EqPredicate" => {
-            if fields.len() != 2 { error!("EqPredicate with {} fields", fields.len()); }
-            let ewp = EqWherePredicate {
-                lhs: try!(type_(&fields[0])),
-                rhs: try!(type_(&fields[1])),
-            };
-            Ok(WherePredicate::Eq(ewp))
+            try!(fields.next(r));
+            let lhs = try!(type_(r));
+            try!(fields.next(r));
+            let rhs = try!(type_(r));
+            WherePredicate::Eq(EqWherePredicate { lhs: lhs, rhs: rhs })
         },
-        _ => error!("Unexpected WherePredicate variant: {:?}", variant),
-    }
+        _ => error_in!(r, "Unexpected WherePredicate variant: {:?}", variant),
+    };
+    try!(fields.end(r));
+    Ok(pred)
 }
 
-fn abi(json: &Value) -> Result<Abi> {
-    let s = try!(collect_string(json, "?", "abi"));
+fn abi<S: Input>(r: &mut S) -> Result<Abi> {
+    let s = try!(collect_string(r, "?", "abi"));
     let bytes: &[u8] = s.as_ref();
     match bytes {
         b"Rust"          => Ok(Abi::Rust),
@@ -984,164 +1699,267 @@ fn abi(json: &Value) -> Result<Abi> {
         b"System"        => Ok(Abi::System),
         b"RustIntrinsic" => Ok(Abi::RustIntrinsic),
         b"RustCall"      => Ok(Abi::RustCall),
-        _ => error!("Unexpected Abi variant: {:?}", s.as_str()),
+        _ => error_in!(r, "Unexpected Abi variant: {:?}", s.as_str()),
     }
 }
 
-fn fn_decl(json: &Value) -> Result<FnDecl> {
-    let mut fields = [("inputs", None), ("output", None), ("attrs", None)];
-    try!(collect_object(json, &mut fields, "FnDecl"));
+fn fn_decl<S: Input>(r: &mut S) -> Result<FnDecl> {
+    let mut inputs = None;
+    let mut output = None;
+    let mut attrs = None;
+    try!(collect_object(r, "FnDecl", |r, key| {
+        match key {
+            b"inputs" => inputs = Some(try!(arguments(r))),
+            b"output" => output = Some(try!(func_ret_ty(r))),
+            b"attrs" => attrs = Some(try!(attributes(r))),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }));
     Ok(FnDecl {
-        inputs: try!(arguments(fields[0].1.unwrap())),
-        output: try!(func_ret_ty(fields[1].1.unwrap())),
-        attrs: try!(attributes(fields[2].1.unwrap())),
+        inputs: try!(required(r, inputs, "FnDecl", "inputs")),
+        output: try!(required(r, output, "FnDecl", "output")),
+        attrs: try!(required(r, attrs, "FnDecl", "attrs")),
     })
 }
 
-fn func_ret_ty(json: &Value) -> Result<FuncRetTy> {
-    let (variant, fields) = try!(collect_enum(json, "FuncRetTy"));
+fn func_ret_ty<S: Input>(r: &mut S) -> Result<FuncRetTy> {
+    let (variant, mut fields) = try!(collect_enum(r, "FuncRetTy"));
     let bytes: &[u8] = variant.as_ref();
-    match bytes {
+    let ret = match bytes {
         b"Return" => {
-            if fields.len() != 1 { error!("FuncRetTy::Return with {} fields",
-                                          fields.len()); }
-            let ty = try!(type_(&fields[0]));
-            Ok(FuncRetTy::Return(ty))
-        },
-        b"DefaultReturn" => {
-            if fields.len() != 0 { error!("DefaultReturn with {} fields", fields.len()); }
-            Ok(FuncRetTy::Unit)
+            try!(fields.next(r));
+            let ty = try!(type_(r));
+            FuncRetTy::Return(ty)
         },
-        b"NoReturn" => {
-            if fields.len() != 0 { error!("NoReturn with {} fields", fields.len()); }
-            Ok(FuncRetTy::NoReturn)
-        },
-        _ => error!("Unexpected FuncRetTy variant: {:?}", variant),
-    }
+        b"DefaultReturn" => FuncRetTy::Unit,
+        b"NoReturn" => FuncRetTy::NoReturn,
+        _ => error_in!(r, "Unexpected FuncRetTy variant: {:?}", variant),
+    };
+    try!(fields.end(r));
+    Ok(ret)
 }
 
-fn self_ty(json: &Value) -> Result<SelfTy> {
-    let (variant, fields) = try!(collect_enum(json, "SelfTy"));
+fn self_ty<S: Input>(r: &mut S) -> Result<SelfTy> {
+    let (variant, mut fields) = try!(collect_enum(r, "SelfTy"));
     let bytes: &[u8] = variant.as_ref();
-    match bytes {
-        b"SelfStatic" => {
-            if fields.len() != 0 { error!("SelfStatic with {} fields", fields.len()); }
-            Ok(SelfTy::Static)
-        },
-        b"SelfValue" => {
-            if fields.len() != 0 { error!("SelfValue with {} fields", fields.len()); }
-            Ok(SelfTy::Value)
-        },
+    let self_ = match bytes {
+        b"SelfStatic" => SelfTy::Static,
+        b"SelfValue" => SelfTy::Value,
         b"SelfBorrowed" => {
-            if fields.len() != 2 { error!("SelfBorrowed with {} fields", fields.len()); }
-            let lt = match fields[0] {
-                Value::Null => None,
-                _ => Some(try!(lifetime(&fields[0]))),
+            try!(fields.next(r));
+            let lt = match try!(skip_null(r)) {
+                true => None,
+                false => Some(try!(lifetime(r))),
             };
-            let mutable = try!(mutability(&fields[1]));
-            Ok(SelfTy::Borrowed(lt, mutable))
+            try!(fields.next(r));
+            let mutable = try!(mutability(r));
+            SelfTy::Borrowed(lt, mutable)
         },
         b"SelfExplicit" => {
-            if fields.len() != 1 { error!("SelfExplicit with {} fields", fields.len()); }
-            let ty = try!(type_(&fields[0]));
-            Ok(SelfTy::Explicit(ty))
+            try!(fields.next(r));
+            let ty = try!(type_(r));
+            SelfTy::Explicit(ty)
         },
-        _ => error!("Unexpected SelfTy variant: {:?}", variant),
-    }
+        _ => error_in!(r, "Unexpected SelfTy variant: {:?}", variant),
+    };
+    try!(fields.end(r));
+    Ok(self_)
 }
 
-fn mutability(json: &Value) -> Result<bool> {
-    let s = try!(collect_string(json, "?", "mutability"));
+fn mutability<S: Input>(r: &mut S) -> Result<bool> {
+    let s = try!(collect_string(r, "?", "mutability"));
     Ok(s.as_str() == "Mutable")
 }
 
-fn polarity(json: &Value) -> Result<bool> {
-    let s = try!(collect_string(json, "?", "polarity"));
+fn polarity<S: Input>(r: &mut S) -> Result<bool> {
+    let s = try!(collect_string(r, "?", "polarity"));
     Ok(s.as_str() == "Negative")
 }
 
-fn arguments(json: &Value) -> Result<Vec<Argument>> {
-    let mut fields = [("values", None)];
-    try!(collect_object(json, &mut fields, "Arguments"));
-    let array = try!(collect_array(fields[0].1.unwrap(), "?", "arguments"));
-    let mut vec = try!(Vec::with_capacity(array.len()));
-    for l in array {
-        vec.push(try!(argument(l)));
-    }
-    Ok(vec)
+fn arguments<S: Input>(r: &mut S) -> Result<Vec<Argument>> {
+    let mut values = None;
+    try!(collect_object(r, "Arguments", |r, key| {
+        match key {
+            b"values" => values = Some(try!(collect_array(r, "?", "arguments", argument))),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }));
+    required(r, values, "Arguments", "values")
+}
+
+fn argument<S: Input>(r: &mut S) -> Result<Argument> {
+    let mut ty = None;
+    let mut name = None;
+    let mut id = None;
+    try!(collect_object(r, "Argument", |r, key| {
+        match key {
+            b"type_" => ty = Some(try!(type_(r))),
+            b"name" => name = Some(try!(collect_string(r, "Argument", "name"))),
+            b"id" => id = Some(try!(collect_int(r, "Argument", "id"))),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }));
+    Ok(Argument {
+        type_: try!(required(r, ty, "Argument", "type_")),
+        name: try!(required(r, name, "Argument", "name")),
+        id: try!(required(r, id, "Argument", "id")) as u64,
+    })
 }
 
-fn argument(json: &Value) -> Result<Argument> {
-    let mut fields = [("type_", None), ("name", None), ("id", None)];
-    try!(collect_object(json, &mut fields, "Argument"));
-    let ty = try!(type_(fields[0].1.unwrap()));
-    let name = try!(collect_string(fields[1].1.unwrap(), "Argument", "name"));
-    let id = try!(collect_int(fields[2].1.unwrap(), "Argument", "id"));
-    let name = try!(name.try_to());
-    Ok(Argument { type_: ty, name: name, id: id as u64 })
+fn collect_string<S: Input>(r: &mut S, obj: &str, field: &str) -> Result<Vec<u8>> {
+    if try!(r.peek()) != Kind::String {
+        error_in!(r, "field {} on {} is not a string", field, obj);
+    }
+    r.string()
 }
 
-fn collect_string<'a>(json: &'a Value, obj: &str, field: &str) -> Result<&'a Vec<u8>> {
-    match *json {
-        Value::String(ref s) => Ok(s),
-        _ => error!("field {} on {} is not a string", field, obj),
+fn collect_int<S: Input>(r: &mut S, obj: &str, field: &str) -> Result<i64> {
+    if try!(r.peek()) != Kind::Number {
+        error_in!(r, "field {} on {} is not an integer", field, obj);
     }
+    r.integer()
 }
 
-fn collect_int(json: &Value, obj: &str, field: &str) -> Result<i64> {
-    match *json {
-        Value::Integer(s) => Ok(s),
-        _ => error!("field {} on {} is not an integer", field, obj),
+fn collect_bool<S: Input>(r: &mut S, obj: &str, field: &str) -> Result<bool> {
+    if try!(r.peek()) != Kind::Boolean {
+        error_in!(r, "field {} on {} is not a boolean", field, obj);
     }
+    r.boolean()
 }
 
-fn collect_object<'a>(obj: &'a Value, fields: &mut [(&str, Option<&'a Value>)],
-                      name: &str) -> Result {
-    let obj = match *obj {
-        Value::Object(ref o) => o,
-        _ => error!("tried to collect fields on non-object {:?}", name),
-    };
-
-    for field in obj {
-        for rfield in &mut *fields {
-            if field.0.as_str() == rfield.0 {
-                rfield.1 = Some(&field.1);
-            }
-        }
+/// Consumes a `null` if it's the next value and returns whether it was there.
+fn skip_null<S: Input>(r: &mut S) -> Result<bool> {
+    if try!(r.peek()) == Kind::Null {
+        try!(r.null());
+        Ok(true)
+    } else {
+        Ok(false)
     }
+}
 
-    for field in fields {
-        if field.1.is_none() {
-            error!("did not find field {} on {}", field.0, name);
+/// Reads an object.
+///
+/// [argument, f]
+/// Called with each key. Returns `false` if the key is unknown, in which case the
+/// value is skipped with a warning.
+fn collect_object<S, F>(r: &mut S, name: &'static str, mut f: F) -> Result
+    where S: Input,
+          F: FnMut(&mut S, &[u8]) -> Result<bool>,
+{
+    if try!(r.peek()) != Kind::Object {
+        error_in!(r, "tried to collect fields on non-object {:?}", name);
+    }
+    try!(r.object_start());
+    while let Some(key) = try!(r.key()) {
+        if !try!(f(r, &key)) {
+            try!(r.unknown_field(name, &key));
+            try!(r.skip());
         }
     }
-
     Ok(())
 }
 
-fn collect_array<'a>(json: &'a Value, obj: &str, field: &str) -> Result<&'a JSlice> {
-    match *json {
-        Value::Array(ref s) => Ok(&s[..]),
-        _ => error!("field {} on {} is not an array", field, obj),
+fn required<S: Input, T>(r: &S, val: Option<T>, obj: &str, field: &str) -> Result<T> {
+    match val {
+        Some(v) => Ok(v),
+        _ => error_in!(r, "did not find field {} on {}", field, obj),
     }
 }
 
-fn collect_bool<'a>(json: &'a Value, obj: &str, field: &str) -> Result<bool> {
-    match *json {
-        Value::Boolean(s) => Ok(s),
-        _ => error!("field {} on {} is not a boolean", field, obj),
+fn collect_array<S, T, F>(r: &mut S, obj: &str, field: &str, mut f: F) -> Result<Vec<T>>
+    where S: Input,
+          F: FnMut(&mut S) -> Result<T>,
+{
+    if try!(r.peek()) != Kind::Array {
+        error_in!(r, "field {} on {} is not an array", field, obj);
+    }
+    let mut vec = Vec::new();
+    try!(r.array_start());
+    while try!(r.array_next()) {
+        let val = try!(f(r));
+        try!(vec.reserve(1));
+        vec.push(val);
     }
+    Ok(vec)
+}
+
+/// The fields of an enum that is currently being read.
+struct EnumFields<'a> {
+    obj: &'a str,
+    /// Whether the enum has a `fields` array that is still open.
+    array: bool,
+    /// The number of fields that have been read.
+    count: usize,
 }
 
-fn collect_enum<'a>(json: &'a Value, obj: &str) -> Result<(&'a Vec<u8>, &'a JSlice)> {
-    match *json {
-        Value::String(ref s) => return Ok((s, &[])),
-        _ => { },
+impl<'a> EnumFields<'a> {
+    /// Moves to the next field.
+    fn next<S: Input>(&mut self, r: &mut S) -> Result {
+        if !self.array || !try!(r.array_next()) {
+            self.array = false;
+            error_in!(r, "{} with fewer than {} fields", self.obj, self.count + 1);
+        }
+        self.count += 1;
+        Ok(())
+    }
+
+    /// Skips the remaining fields and closes the enum.
+    fn skip<S: Input>(self, r: &mut S) -> Result {
+        if self.array {
+            while try!(r.array_next()) {
+                try!(r.skip());
+            }
+            while try!(r.key()).is_some() {
+                try!(r.skip());
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that all fields have been read and closes the enum.
+    fn end<S: Input>(self, r: &mut S) -> Result {
+        if self.array {
+            if try!(r.array_next()) {
+                error_in!(r, "{} with more than {} fields", self.obj, self.count);
+            }
+            while try!(r.key()).is_some() {
+                try!(r.skip());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reads the start of an enum, which is either a string or an object of the form
+/// `{ "variant": .., "fields": [..] }` with the fields in this order.
+fn collect_enum<'a, S: Input>(r: &mut S, obj: &'a str) -> Result<(Vec<u8>, EnumFields<'a>)> {
+    let mut fields = EnumFields { obj: obj, array: false, count: 0 };
+
+    if try!(r.peek()) == Kind::String {
+        return Ok((try!(r.string()), fields));
+    }
+
+    if try!(r.peek()) != Kind::Object {
+        error_in!(r, "tried to collect fields on non-object {:?}", obj);
+    }
+    try!(r.object_start());
+    match try!(r.key()) {
+        Some(ref k) if k.as_str() == "variant" => { },
+        _ => error_in!(r, "{} does not start with a variant", obj),
+    }
+    let variant = try!(collect_string(r, obj, "variant"));
+    match try!(r.key()) {
+        Some(ref k) if k.as_str() == "fields" => { },
+        _ => error_in!(r, "{} variant is not followed by fields", obj),
+    }
+    if try!(r.peek()) != Kind::Array {
+        error_in!(r, "field fields on {} is not an array", obj);
     }
+    try!(r.array_start());
+    fields.array = true;
 
-    let mut fields = [("variant", None), ("fields", None)];
-    try!(collect_object(json, &mut fields, obj));
-    let variant = try!(collect_string(fields[0].1.unwrap(), obj, "variant"));
-    let fields = try!(collect_array(fields[1].1.unwrap(), obj, "fields"));
     Ok((variant, fields))
 }
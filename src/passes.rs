@@ -4,25 +4,47 @@
 
 use std::rc::{Arc};
 use std::vec::{Vec};
-use tree::{self, Walker, ItemData, ResolvedPath, Crate, Type, Item};
+use std::io::{Write};
+use tree::{self, Walker, ItemData, ResolvedPath, Crate, Type, Item, Trait, FuncRetTy,
+           Primitive, PathParameters, DefId};
 use hashmap::{ItemMap};
+use markup::{Part};
+use auto_trait;
 
-pub fn run(krate: &Crate) {
+/// Runs the passes over the crate and returns the number of errors in the documentation.
+pub fn run(krate: &Crate) -> Result<usize> {
     let mut map = ItemMap::new();
+    let mut primitives = Vec::new();
 
-    (CollectItems { map: &mut map }).walk_crate(krate);
+    (CollectItems { map: &mut map, primitives: &mut primitives }).walk_crate(krate);
     (AddParents { parents: Vec::new() }).walk_crate(krate);
     (LinkTypes { map: &map }).walk_crate(krate);
-    (CollectImpls).walk_crate(krate);
+    (CollectImpls { primitives: &primitives }).walk_crate(krate);
+    (InheritDocs).walk_crate(krate);
+    let mut deref = FindDerefTrait { deref: None };
+    deref.walk_crate(krate);
+    (FindDeref { primitives: &primitives, deref: deref.deref }).walk_crate(krate);
+    auto_trait::run(krate);
+    let mut uses = TypeUses { res: Ok(()) };
+    uses.walk_crate(krate);
+    try!(uses.res);
+    let mut diags = DocDiagnostics { errors: 0 };
+    diags.walk_crate(krate);
+    Ok(diags.errors)
 }
 
 struct CollectItems<'a> {
     map: &'a mut ItemMap,
+    /// The items that document primitive types.
+    primitives: &'a mut Vec<Arc<ItemData>>,
 }
 
 impl<'a> Walker for CollectItems<'a> {
     fn walk_item_data(&mut self, val: &Arc<ItemData>) {
         self.map.add(val.node, val.add_ref());
+        if let Item::Primitive(_) = val.inner {
+            self.primitives.push(val.add_ref());
+        }
         tree::walk_item_data(self, val);
     }
 }
@@ -51,17 +73,456 @@ impl<'a> Walker for LinkTypes<'a> {
     }
 }
 
-struct CollectImpls;
+/// Adds each impl to the item of its type.
+///
+/// = Remarks
+///
+/// Impls for slices, arrays, and other primitive types are added to the primitive items.
+struct CollectImpls<'a> {
+    primitives: &'a [Arc<ItemData>],
+}
+
+impl<'a> Walker for CollectImpls<'a> {
+    fn walk_item_data(&mut self, val: &Arc<ItemData>) {
+        if let Item::Impl(ref i) = val.inner {
+            if let Some(i) = type_item(&i.for_, self.primitives) {
+                i.impls.borrow_mut().push(val.add_ref());
+            }
+        }
+        tree::walk_item_data(self, val);
+    }
+}
+
+/// Returns the item that documents a type.
+///
+/// [argument, primitives]
+/// The items that document primitive types.
+fn type_item(t: &Type, primitives: &[Arc<ItemData>]) -> Option<Arc<ItemData>> {
+    let prim = match *t {
+        Type::ResolvedPath(ref r) => return match *r.item.borrow() {
+            Some(ref i) => Some(i.add_ref()),
+            _ => None,
+        },
+        Type::Primitive(p) => p,
+        Type::Slice(_) => Primitive::Slice,
+        Type::Array(_) => Primitive::Array,
+        Type::Tuple(_) => Primitive::Tuple,
+        Type::Pointer(_) => Primitive::RawPointer,
+        _ => return None,
+    };
+    for item in primitives {
+        if let Item::Primitive(p) = item.inner {
+            if p == prim {
+                return Some(item.add_ref());
+            }
+        }
+    }
+    None
+}
+
+/// Links undocumented items of trait impls to the corresponding items of the trait.
+struct InheritDocs;
 
-impl Walker for CollectImpls {
+impl Walker for InheritDocs {
     fn walk_item_data(&mut self, val: &Arc<ItemData>) {
         if let Item::Impl(ref i) = val.inner {
+            if let Some(Type::ResolvedPath(ref r)) = i.trait_ {
+                if let Some(ref t) = *r.item.borrow() {
+                    if let Item::Trait(ref trait_) = t.inner {
+                        for item in &i.items {
+                            if item.docs.parts.len() == 0 {
+                                *item.inherited.borrow_mut() = trait_item(trait_, item);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        tree::walk_item_data(self, val);
+    }
+}
+
+/// Returns the item of a trait that has the same name as an item of an impl.
+fn trait_item(trait_: &Trait, item: &ItemData) -> Option<Arc<ItemData>> {
+    let name = match item.name {
+        Some(ref n) => n,
+        _ => return None,
+    };
+    for t in &trait_.items {
+        if let Some(ref n) = t.name {
+            if n[..] == name[..] {
+                return Some(t.add_ref());
+            }
+        }
+    }
+    None
+}
+
+/// Finds the `ops::Deref` trait if it is part of the crate.
+struct FindDerefTrait {
+    deref: Option<DefId>,
+}
+
+impl Walker for FindDerefTrait {
+    fn walk_item_data(&mut self, val: &Arc<ItemData>) {
+        if let Item::Trait(_) = val.inner {
+            let parent = match *val.parent.borrow() {
+                Some(ref p) => match p.name {
+                    Some(ref n) => &n[..] == b"ops",
+                    _ => false,
+                },
+                _ => false,
+            };
+            if parent && val.name.as_ref().map(|n| &n[..] == b"Deref").unwrap_or(false) {
+                self.deref = Some(val.node);
+            }
+        }
+        tree::walk_item_data(self, val);
+    }
+}
+
+/// Stores the `Target` of the `Deref` impl of each type.
+///
+/// = Remarks
+///
+/// If the `Deref` trait is not part of the crate, traits from other crates that are
+/// called `Deref` are used instead.
+struct FindDeref<'a> {
+    primitives: &'a [Arc<ItemData>],
+    /// The `ops::Deref` trait.
+    deref: Option<DefId>,
+}
+
+impl<'a> Walker for FindDeref<'a> {
+    fn walk_item_data(&mut self, val: &Arc<ItemData>) {
+        for impl_item in &*val.impls.borrow() {
+            let impl_ = match impl_item.inner {
+                Item::Impl(ref i) => i,
+                _ => continue,
+            };
+            let is_deref = match impl_.trait_ {
+                Some(Type::ResolvedPath(ref r)) => match self.deref {
+                    Some(d) => r.def_id == d,
+                    _ => r.item.borrow().is_none() && match r.path.segments.last() {
+                        Some(s) => &s.name[..] == b"Deref",
+                        _ => false,
+                    },
+                },
+                _ => false,
+            };
+            if !is_deref {
+                continue;
+            }
+            for item in &impl_.items {
+                if let Item::Typedef(ref t) = item.inner {
+                    if let Some(ref n) = item.name {
+                        if &n[..] == b"Target" {
+                            if let Some(target) = type_item(&t.type_, self.primitives) {
+                                *val.deref.borrow_mut() = Some((item.add_ref(), target));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        tree::walk_item_data(self, val);
+    }
+}
+
+/// Records the public functions and methods whose signatures contain each type.
+///
+/// = Remarks
+///
+/// Methods of trait impls are skipped because they don't have their own pages. Only the
+/// outermost type of each argument and of the return value counts, except that
+/// references, slices, `Option`, and `Result` count as their contained types.
+struct TypeUses {
+    res: Result,
+}
+
+impl Walker for TypeUses {
+    fn walk_item_data(&mut self, val: &Arc<ItemData>) {
+        if self.res.is_err() {
+            return;
+        }
+        let decl = match val.inner {
+            Item::Func(ref f) | Item::ExternFunc(ref f) => Some(&f.decl),
+            Item::Method(ref m) | Item::MethodDecl(ref m) => Some(&m.decl),
+            _ => None,
+        };
+        if let Some(decl) = decl {
+            if val.public && has_page(val) {
+                for arg in &decl.inputs {
+                    self.res = add_uses(val, false, &arg.type_);
+                    if self.res.is_err() {
+                        return;
+                    }
+                }
+                if let FuncRetTy::Return(ref t) = decl.output {
+                    self.res = add_uses(val, true, t);
+                }
+            }
+        }
+        tree::walk_item_data(self, val);
+    }
+}
+
+/// Adds a function to the items of the types that a type in its signature stands for.
+fn add_uses(func: &Arc<ItemData>, returned: bool, t: &Type) -> Result {
+    match *t {
+        Type::ResolvedPath(ref r) => {
+            let item = match *r.item.borrow() {
+                Some(ref item) => item.add_ref(),
+                _ => return Ok(()),
+            };
+            if is_wrapper(&item) {
+                if let Some(seg) = r.path.segments.last() {
+                    if let PathParameters::AngleBracketed(ref a) = seg.params {
+                        for t in &a.ty_params {
+                            try!(add_uses(func, returned, t));
+                        }
+                    }
+                }
+                return Ok(());
+            }
+            let mut uses = match returned {
+                true => item.returned_by.borrow_mut(),
+                false => item.accepted_by.borrow_mut(),
+            };
+            if !uses.iter().any(|f| f.node == func.node) {
+                try!(uses.reserve(1));
+                uses.push(func.add_ref());
+            }
+            Ok(())
+        },
+        Type::Ref(ref r) => add_uses(func, returned, &r.ty),
+        Type::Slice(ref s) => add_uses(func, returned, &s.ty),
+        _ => Ok(()),
+    }
+}
+
+/// Returns whether an item is the `Option` or `Result` enum.
+fn is_wrapper(item: &ItemData) -> bool {
+    if let Item::Enum(_) = item.inner {
+        if let Some(ref name) = item.name {
+            return &name[..] == b"Option" || &name[..] == b"Result";
+        }
+    }
+    false
+}
+
+/// Returns whether a function or method has its own page.
+pub fn has_page(item: &ItemData) -> bool {
+    if let Some(ref parent) = *item.parent.borrow() {
+        if let Item::Impl(ref i) = parent.inner {
+            if i.trait_.is_some() {
+                return false;
+            }
+            return match i.for_ {
+                Type::ResolvedPath(ref r) => r.item.borrow().is_some(),
+                _ => false,
+            };
+        }
+    }
+    true
+}
+
+/// Returns the `::` separated path of the page of a function or method.
+///
+/// = Remarks
+///
+/// The pages of methods in inherent impls are placed below the type of the impl.
+pub fn fn_page(item: &ItemData) -> Result<Vec<u8>> {
+    if let Some(ref parent) = *item.parent.borrow() {
+        if let Item::Impl(ref i) = parent.inner {
             if let Type::ResolvedPath(ref r) = i.for_ {
-                if let Some(ref i) = *r.item.borrow() {
-                    i.impls.borrow_mut().push(val.add_ref());
+                if let Some(ref t) = *r.item.borrow() {
+                    let mut path = try!(full_path(t));
+                    try!(path.push_all(b"::"));
+                    if let Some(ref name) = item.name {
+                        try!(path.push_all(name));
+                    }
+                    return Ok(path);
                 }
             }
         }
+    }
+    full_path(item)
+}
+
+/// Prints the problems found while parsing the documentation of each item.
+struct DocDiagnostics {
+    /// The number of diagnostics that are errors.
+    errors: usize,
+}
+
+impl Walker for DocDiagnostics {
+    fn walk_item_data(&mut self, val: &Arc<ItemData>) {
+        for diag in &val.docs.diagnostics {
+            let loc = tryerr!(doc_location(val, diag.line, diag.column),
+                              "Could not format path");
+            if diag.error {
+                errln!("lrs_doc: Error: {}: {}", loc.as_str(), diag.msg);
+                self.errors += 1;
+            } else {
+                warning!("{}: {}", loc.as_str(), diag.msg);
+            }
+        }
+
+        let decl = match val.inner {
+            Item::Func(ref f) | Item::ExternFunc(ref f) => Some(&f.decl),
+            Item::Method(ref m) | Item::MethodDecl(ref m) => Some(&m.decl),
+            _ => None,
+        };
+        let generics = match val.inner {
+            Item::Struct(ref s) => Some(&s.generics),
+            Item::Enum(ref e) => Some(&e.generics),
+            Item::Func(ref f) | Item::ExternFunc(ref f) => Some(&f.generics),
+            Item::Method(ref m) | Item::MethodDecl(ref m) => Some(&m.generics),
+            Item::Trait(ref t) => Some(&t.generics),
+            Item::Typedef(ref t) => Some(&t.generics),
+            // Only trait impls have a page that shows the type parameters.
+            Item::Impl(ref i) if i.trait_.is_some() => Some(&i.generics),
+            _ => None,
+        };
+        for part in &val.docs.parts {
+            let data = match *part {
+                Part::Block(ref data) => data,
+                _ => continue,
+            };
+            for attr in &data.attributes {
+                let kind = attr.name.as_str().trim();
+                let name = match attr.args {
+                    Some(ref a) => a.as_str().trim(),
+                    _ => continue,
+                };
+                // Items without a declaration or generics are not checked.
+                let (known, what) = if kind == "argument" {
+                    let known = match decl {
+                        Some(d) => d.inputs.iter().any(|i| i.name.as_str() == name),
+                        _ => continue,
+                    };
+                    (known, "argument")
+                } else if kind == "type_param" {
+                    let known = match generics {
+                        Some(g) => g.type_params.iter().any(|t| t.name.as_str() == name),
+                        _ => continue,
+                    };
+                    (known, "type parameter")
+                } else if kind == "lifetime" {
+                    let known = match generics {
+                        Some(g) => g.lifetimes.iter().any(|l| l.as_str() == name),
+                        _ => continue,
+                    };
+                    (known, "lifetime")
+                } else {
+                    continue;
+                };
+                if !known {
+                    let loc = tryerr!(doc_location(val, attr.line, 1),
+                                      "Could not format path");
+                    warning!("{}: unknown {} {:?}", loc.as_str(), what, name);
+                }
+            }
+        }
+
         tree::walk_item_data(self, val);
     }
 }
+
+/// Formats a position in the documentation of an item.
+///
+/// = Remarks
+///
+/// If the item has a span, the position is also mapped to the source file under the
+/// assumption that the doc comment directly precedes the item. The column is relative to
+/// the start of the comment text.
+fn doc_location(item: &ItemData, line: usize, column: usize) -> Result<Vec<u8>> {
+    let mut loc = try!(full_path(item));
+    try!(write!(&mut loc, ": line {}, column {}", line, column));
+    if let Some(ref span) = item.span {
+        if span.loline > item.docs.lines {
+            try!(write!(&mut loc, " ({}:{})", span.filename.as_str(),
+                        span.loline - item.docs.lines + line - 1));
+        }
+    }
+    Ok(loc)
+}
+
+/// Returns the number of items in the crate, including the crate module.
+pub fn count_items(krate: &Crate) -> usize {
+    let mut count = CountItems { count: 0 };
+    count.walk_crate(krate);
+    count.count
+}
+
+struct CountItems {
+    count: usize,
+}
+
+impl Walker for CountItems {
+    fn walk_item_data(&mut self, val: &Arc<ItemData>) {
+        self.count += 1;
+        tree::walk_item_data(self, val);
+    }
+}
+
+/// Prints every use of a deprecated item in the public signature of another item.
+pub fn deprecated_report(krate: &Crate) {
+    (DeprecatedUses { items: Vec::new() }).walk_crate(krate);
+}
+
+struct DeprecatedUses {
+    /// The enclosing items and whether they are reachable from the crate root.
+    items: Vec<(Arc<ItemData>, bool)>,
+}
+
+impl Walker for DeprecatedUses {
+    fn walk_item_data(&mut self, val: &Arc<ItemData>) {
+        let public = val.public && self.items.last().map(|l| l.1).unwrap_or(true);
+        self.items.push((val.add_ref(), public));
+        tree::walk_item_data(self, val);
+        self.items.pop();
+    }
+
+    fn walk_resolved_path(&mut self, val: &ResolvedPath) {
+        if let Some(ref target) = *val.item.borrow() {
+            if let Some(ref dep) = target.stability.deprecated {
+                if let Some(&(ref user, true)) = self.items.last() {
+                    if user.stability.deprecated.is_none() {
+                        let user_path = tryerr!(full_path(user), "Could not format path");
+                        let target_path = tryerr!(full_path(target),
+                                                  "Could not format path");
+                        match dep.since {
+                            Some(ref s) => println!("{}: uses {} (deprecated since {})",
+                                                    user_path.as_str(),
+                                                    target_path.as_str(), s.as_str()),
+                            _ => println!("{}: uses {} (deprecated)", user_path.as_str(),
+                                          target_path.as_str()),
+                        }
+                    }
+                }
+            }
+        }
+        tree::walk_resolved_path(self, val);
+    }
+}
+
+/// Returns the `::` separated path of an item.
+pub fn full_path(item: &ItemData) -> Result<Vec<u8>> {
+    let mut path = Vec::new();
+    try!(write_full_path(&mut path, item));
+    Ok(path)
+}
+
+/// Writes the `::` separated path of an item.
+pub fn write_full_path<W: Write>(dst: &mut W, item: &ItemData) -> Result {
+    if let Some(ref parent) = *item.parent.borrow() {
+        try!(write_full_path(dst, parent));
+        try!(dst.write_all(b"::"));
+    }
+    if let Some(ref name) = item.name {
+        try!(dst.write_all(name.as_ref()));
+    }
+    Ok(())
+}
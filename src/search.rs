@@ -0,0 +1,639 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Search by type signature
+//!
+//! The signatures of all functions and methods that have their own page are normalised
+//! into a list of argument types and a return type:
+//!
+//! * Paths are reduced to their last segment, e.g., `std::fd::Fd` becomes `Fd`.
+//! * References are removed, e.g., `&mut [u8]` becomes `[u8]`, and arrays become slices.
+//! * Type parameters, including `Self` in traits, become variables that are numbered in
+//!   the order of their first appearance.
+//! * The receiver of a method is its first argument.
+//! * Types that cannot be searched for, e.g., function pointers, become `_`.
+//!
+//! Queries have the form `args -> ret` where `args` is a comma separated list of types
+//! and `-> ret` is optional. Queries are normalised in the same way. `_` matches every
+//! type and single uppercase letters are variables. The arguments of the query have to
+//! match distinct arguments of the signature in the same order. A type with fewer type
+//! arguments than the corresponding type in the signature only constrains the given
+//! arguments, e.g., `Result` matches `Result<usize>`.
+
+use std::io::{Write};
+use std::rc::{Arc};
+use std::vec::{Vec};
+use std::bx::{Box};
+use std::file::{File};
+use std::file::flags::{FILE_WRITE_ONLY, FILE_TRUNCATE, FILE_CREATE};
+use std::file::mode::{MODE_FILE};
+use std::string::{ByteStr};
+
+use json::{Value};
+use tree::*;
+use export::{field, primitive_name};
+use passes::{has_page, fn_page};
+use config::{Config};
+use layout::{Docs, TextSink, render, item_fn_sig};
+
+/// A normalised type.
+pub enum SigType {
+    /// The last segment of a path and its type arguments.
+    Named(Vec<u8>, Vec<SigType>),
+    /// A type parameter.
+    Var(usize),
+    Slice(Box<SigType>),
+    Tuple(Vec<SigType>),
+    /// Any type.
+    Any,
+}
+
+/// A normalised function signature.
+pub struct Signature {
+    pub args: Vec<SigType>,
+    pub ret: SigType,
+}
+
+/// A function or method in the signature index.
+pub struct Entry {
+    pub item: Arc<ItemData>,
+    /// The `::` separated path of the page of the function.
+    pub path: Vec<u8>,
+    pub sig: Signature,
+}
+
+/// Prints the functions and methods whose signatures match a query.
+///
+/// = Remarks
+///
+/// Each match is printed as its path followed by its declaration, which is indented and
+/// laid out at the configured width.
+pub fn run(krate: &Crate, query: &[u8], config: &Config) -> Result {
+    let query = try!(parse_query(query));
+    for entry in &try!(index(krate)) {
+        if try!(matches(&query, &entry.sig)) {
+            let mut decl = Docs::new();
+            try!(item_fn_sig(&mut decl, &entry.item));
+            let mut doc = Docs::new();
+            try!(doc.lit(b"    "));
+            try!(doc.nest(4, decl));
+            try!(doc.hard_line());
+
+            let mut out = Vec::new();
+            try!(render(&mut TextSink { file: &mut out }, &doc.done(), config.width));
+            println!("{}:\n{}", entry.path.as_str(), out.as_str());
+        }
+    }
+    Ok(())
+}
+
+/// Writes the signature index to `search_index.json` in the output directory.
+///
+/// = Remarks
+///
+/// The file contains an array of objects with the fields `path`, `sig`, `args`, and
+/// `ret`. `sig` is the normalised signature as text. In `args` and `ret`, named types
+/// are objects with `name` and `args` fields, variables have a `var` field, slices a
+/// `slice` field, tuples a `tuple` field, and `_` is `null`.
+pub fn write_index(krate: &Crate, config: &Config) -> Result {
+    let index = try!(index(krate));
+    let mut entries = try!(Vec::with_capacity(index.len()));
+    for entry in &index {
+        let mut sig = Vec::new();
+        try!(write_signature(&mut sig, &entry.sig));
+        let mut obj = Vec::new();
+        try!(field(&mut obj, "path", Value::String(try!(entry.path.try_to()))));
+        try!(field(&mut obj, "sig", Value::String(sig)));
+        try!(field(&mut obj, "args", try!(types_json(&entry.sig.args))));
+        try!(field(&mut obj, "ret", try!(type_json(&entry.sig.ret))));
+        entries.push(Value::Object(obj));
+    }
+
+    let path = try!(config.output_file("search_index.json"));
+    let path: &ByteStr = path.as_ref();
+    let flags = FILE_WRITE_ONLY | FILE_TRUNCATE | FILE_CREATE;
+    let mut file = try!(File::open(path, flags, MODE_FILE));
+    Value::Array(entries).write(&mut file)
+}
+
+fn type_json(ty: &SigType) -> Result<Value> {
+    let val = match *ty {
+        SigType::Named(ref name, ref args) => {
+            let mut obj = Vec::new();
+            try!(field(&mut obj, "name", Value::String(try!(name.try_to()))));
+            try!(field(&mut obj, "args", try!(types_json(args))));
+            Value::Object(obj)
+        },
+        SigType::Var(i) => {
+            let mut obj = Vec::new();
+            try!(field(&mut obj, "var", Value::Integer(i as i64)));
+            Value::Object(obj)
+        },
+        SigType::Slice(ref t) => {
+            let mut obj = Vec::new();
+            try!(field(&mut obj, "slice", try!(type_json(t))));
+            Value::Object(obj)
+        },
+        SigType::Tuple(ref ts) => {
+            let mut obj = Vec::new();
+            try!(field(&mut obj, "tuple", try!(types_json(ts))));
+            Value::Object(obj)
+        },
+        SigType::Any => Value::Null,
+    };
+    Ok(val)
+}
+
+fn types_json(tys: &[SigType]) -> Result<Value> {
+    let mut array = try!(Vec::with_capacity(tys.len()));
+    for ty in tys {
+        array.push(try!(type_json(ty)));
+    }
+    Ok(Value::Array(array))
+}
+
+/// Builds the signature index of a crate.
+pub fn index(krate: &Crate) -> Result<Vec<Entry>> {
+    let mut index = Index { entries: Vec::new() };
+    index.walk_crate(krate);
+    Ok(index.entries)
+}
+
+struct Index {
+    entries: Vec<Entry>,
+}
+
+impl Walker for Index {
+    fn walk_item_data(&mut self, val: &Arc<ItemData>) {
+        let sig = tryerr!(signature(val), "Could not build the signature index");
+        if let Some(sig) = sig {
+            let path = tryerr!(fn_page(val), "Could not format path");
+            tryerr!(self.entries.reserve(1), "Out of memory");
+            self.entries.push(Entry { item: val.add_ref(), path: path, sig: sig });
+        }
+        tree::walk_item_data(self, val);
+    }
+}
+
+/// The type parameters of a signature in the order of their first appearance.
+struct Vars {
+    names: Vec<Vec<u8>>,
+}
+
+impl Vars {
+    fn var(&mut self, name: &[u8]) -> Result<SigType> {
+        let pos = match self.names.find(|n| &n[..] == name) {
+            Some(pos) => pos,
+            _ => {
+                try!(self.names.reserve(1));
+                self.names.push(try!(name.try_to()));
+                self.names.len() - 1
+            },
+        };
+        Ok(SigType::Var(pos))
+    }
+}
+
+/// Returns the normalised signature of a function or method with its own page.
+fn signature(item: &ItemData) -> Result<Option<Signature>> {
+    let (decl, self_) = match item.inner {
+        Item::Func(ref f) | Item::ExternFunc(ref f) => (&f.decl, None),
+        Item::Method(ref m) | Item::MethodDecl(ref m) => (&m.decl, Some(&m.self_)),
+        _ => return Ok(None),
+    };
+    if !has_page(item) {
+        return Ok(None);
+    }
+
+    let mut vars = Vars { names: Vec::new() };
+    let mut args = try!(Vec::with_capacity(decl.inputs.len() + 1));
+
+    match self_ {
+        Some(&SelfTy::Value) | Some(&SelfTy::Borrowed(..)) => {
+            args.push(try!(self_type(item, &mut vars)));
+        },
+        Some(&SelfTy::Explicit(ref t)) => args.push(try!(normalize(t, &mut vars))),
+        _ => { },
+    }
+    for arg in &decl.inputs {
+        args.push(try!(normalize(&arg.type_, &mut vars)));
+    }
+
+    let ret = match decl.output {
+        FuncRetTy::Return(ref t) => try!(normalize(t, &mut vars)),
+        FuncRetTy::Unit => SigType::Tuple(Vec::new()),
+        FuncRetTy::NoReturn => SigType::Named(try!(b"!".try_to()), Vec::new()),
+    };
+
+    Ok(Some(Signature { args: args, ret: ret }))
+}
+
+/// Returns the type of `self` in a method.
+fn self_type(item: &ItemData, vars: &mut Vars) -> Result<SigType> {
+    if let Some(ref parent) = *item.parent.borrow() {
+        if let Item::Impl(ref i) = parent.inner {
+            return normalize(&i.for_, vars);
+        }
+    }
+    vars.var(b"Self")
+}
+
+fn normalize(ty: &Type, vars: &mut Vars) -> Result<SigType> {
+    let ty = match *ty {
+        Type::ResolvedPath(ref r) => {
+            let segment = match r.path.segments.last() {
+                Some(s) => s,
+                _ => return Ok(SigType::Any),
+            };
+            if r.is_generic {
+                return vars.var(&segment.name);
+            }
+            let mut args = Vec::new();
+            if let PathParameters::AngleBracketed(ref a) = segment.params {
+                args = try!(Vec::with_capacity(a.ty_params.len()));
+                for t in &a.ty_params {
+                    args.push(try!(normalize(t, vars)));
+                }
+            }
+            SigType::Named(try!(segment.name.try_to()), args)
+        },
+        Type::Generic(ref g) => return vars.var(&g.name),
+        Type::Primitive(p) => {
+            SigType::Named(try!(primitive_name(p).try_to()), Vec::new())
+        },
+        Type::Tuple(ref t) => {
+            let mut fields = try!(Vec::with_capacity(t.fields.len()));
+            for t in &t.fields {
+                fields.push(try!(normalize(t, vars)));
+            }
+            SigType::Tuple(fields)
+        },
+        Type::Slice(ref s) => {
+            let ty = try!(normalize(&s.ty, vars));
+            SigType::Slice(try!(Box::new()).set(ty))
+        },
+        Type::Array(ref a) => {
+            let ty = try!(normalize(&a.ty, vars));
+            SigType::Slice(try!(Box::new()).set(ty))
+        },
+        Type::Ref(ref r) => return normalize(&r.ty, vars),
+        Type::Pointer(ref p) => {
+            let name: &[u8] = match p.mutable {
+                true => b"*mut",
+                false => b"*const",
+            };
+            let mut args = try!(Vec::with_capacity(1));
+            args.push(try!(normalize(&p.ty, vars)));
+            SigType::Named(try!(name.try_to()), args)
+        },
+        Type::Bottom => SigType::Named(try!(b"!".try_to()), Vec::new()),
+        Type::BareFunction(_) | Type::UfcsPath(_) | Type::Infer | Type::HkltBound(_) => {
+            SigType::Any
+        },
+    };
+    Ok(ty)
+}
+
+pub fn write_signature<W: Write>(w: &mut W, sig: &Signature) -> Result {
+    try!(write_types(w, &sig.args));
+    try!(w.write_all(b" -> "));
+    write_type(w, &sig.ret)
+}
+
+fn write_types<W: Write>(w: &mut W, tys: &[SigType]) -> Result {
+    for (i, ty) in tys.iter().enumerate() {
+        if i > 0 {
+            try!(w.write_all(b", "));
+        }
+        try!(write_type(w, ty));
+    }
+    Ok(())
+}
+
+fn write_type<W: Write>(w: &mut W, ty: &SigType) -> Result {
+    match *ty {
+        SigType::Named(ref name, ref args) => {
+            try!(w.write_all(name));
+            if args.len() > 0 {
+                try!(w.write_all(b"<"));
+                try!(write_types(w, args));
+                try!(w.write_all(b">"));
+            }
+        },
+        SigType::Var(i) => try!(write!(w, "T{}", i)),
+        SigType::Slice(ref t) => {
+            try!(w.write_all(b"["));
+            try!(write_type(w, t));
+            try!(w.write_all(b"]"));
+        },
+        SigType::Tuple(ref ts) => {
+            try!(w.write_all(b"("));
+            try!(write_types(w, ts));
+            try!(w.write_all(b")"));
+        },
+        SigType::Any => try!(w.write_all(b"_")),
+    }
+    Ok(())
+}
+
+/// Parses a signature query.
+pub fn parse_query(src: &[u8]) -> Result<Signature> {
+    let mut parser = Parser { src: src, pos: 0, vars: Vars { names: Vec::new() } };
+    let args = try!(parser.list());
+    let ret = match parser.eat(b"->") {
+        true => try!(parser.ty()),
+        false => SigType::Any,
+    };
+    parser.skip_ws();
+    if parser.pos < src.len() {
+        error!("unexpected character at byte {} of the query", parser.pos);
+    }
+    Ok(Signature { args: args, ret: ret })
+}
+
+struct Parser<'a> {
+    src: &'a [u8],
+    pos: usize,
+    vars: Vars,
+}
+
+impl<'a> Parser<'a> {
+    fn skip_ws(&mut self) {
+        while self.pos < self.src.len() && self.src[self.pos] == b' ' {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&mut self, s: &[u8]) -> bool {
+        self.skip_ws();
+        self.src[self.pos..].starts_with(s)
+    }
+
+    fn eat(&mut self, s: &[u8]) -> bool {
+        let found = self.peek(s);
+        if found {
+            self.pos += s.len();
+        }
+        found
+    }
+
+    /// Consumes a keyword if it is not the prefix of a longer identifier.
+    fn eat_word(&mut self, word: &[u8]) -> bool {
+        let start = self.pos;
+        if self.ident() == word {
+            return true;
+        }
+        self.pos = start;
+        false
+    }
+
+    fn ident(&mut self) -> &'a [u8] {
+        self.skip_ws();
+        let start = self.pos;
+        while self.pos < self.src.len() && is_ident(self.src[self.pos]) {
+            self.pos += 1;
+        }
+        &self.src[start..self.pos]
+    }
+
+    /// Returns the last segment of a `::` separated path.
+    fn path(&mut self) -> &'a [u8] {
+        let mut name = self.ident();
+        while name.len() > 0 && self.eat(b"::") {
+            name = self.ident();
+        }
+        name
+    }
+
+    fn list(&mut self) -> Result<Vec<SigType>> {
+        let mut list = Vec::new();
+        loop {
+            if self.pos == self.src.len() || self.peek(b"->") || self.peek(b")")
+                    || self.peek(b">") {
+                break;
+            }
+            if self.eat(b"'") {
+                // Lifetimes are not part of normalised signatures.
+                self.ident();
+            } else {
+                try!(list.reserve(1));
+                list.push(try!(self.ty()));
+            }
+            if !self.eat(b",") {
+                break;
+            }
+        }
+        Ok(list)
+    }
+
+    fn ty(&mut self) -> Result<SigType> {
+        if self.eat(b"&") {
+            if self.eat(b"'") {
+                self.ident();
+            }
+            self.eat_word(b"mut");
+            return self.ty();
+        }
+
+        if self.eat(b"*") {
+            let name: &[u8] = if self.eat_word(b"mut") {
+                b"*mut"
+            } else if self.eat_word(b"const") {
+                b"*const"
+            } else {
+                error!("expected `const` or `mut` at byte {} of the query", self.pos);
+            };
+            let mut args = try!(Vec::with_capacity(1));
+            args.push(try!(self.ty()));
+            return Ok(SigType::Named(try!(name.try_to()), args));
+        }
+
+        if self.eat(b"[") {
+            let ty = try!(self.ty());
+            if self.eat(b";") {
+                while self.pos < self.src.len() && self.src[self.pos] != b']' {
+                    self.pos += 1;
+                }
+            }
+            if !self.eat(b"]") {
+                error!("expected `]` at byte {} of the query", self.pos);
+            }
+            return Ok(SigType::Slice(try!(Box::new()).set(ty)));
+        }
+
+        if self.eat(b"(") {
+            let mut list = try!(self.list());
+            if !self.eat(b")") {
+                error!("expected `)` at byte {} of the query", self.pos);
+            }
+            if list.len() == 1 {
+                return Ok(list.pop().unwrap());
+            }
+            return Ok(SigType::Tuple(list));
+        }
+
+        if self.eat(b"!") {
+            return Ok(SigType::Named(try!(b"!".try_to()), Vec::new()));
+        }
+
+        let name = self.path();
+        match name {
+            b"" => error!("expected a type at byte {} of the query", self.pos),
+            b"_" => return Ok(SigType::Any),
+            _ => { },
+        }
+        if name.len() == 1 && b'A' <= name[0] && name[0] <= b'Z' {
+            return self.vars.var(name);
+        }
+
+        let mut args = Vec::new();
+        if self.eat(b"<") {
+            args = try!(self.list());
+            if !self.eat(b">") {
+                error!("expected `>` at byte {} of the query", self.pos);
+            }
+        }
+        Ok(SigType::Named(try!(name.try_to()), args))
+    }
+}
+
+fn is_ident(c: u8) -> bool {
+    match c {
+        b'a'...b'z' | b'A'...b'Z' | b'0'...b'9' | b'_' => true,
+        _ => false,
+    }
+}
+
+/// Returns whether a signature matches a query.
+pub fn matches(query: &Signature, sig: &Signature) -> Result<bool> {
+    let mut unifier = Unifier { bindings: Vec::new() };
+    unifier.args(&query.args, &sig.args, &query.ret, &sig.ret)
+}
+
+/// The signature whose variables a type refers to.
+#[derive(Copy, Eq)]
+enum Side {
+    Query,
+    Sig,
+}
+
+/// A substitution for the variables of a query and a signature.
+///
+/// = Remarks
+///
+/// Types are resolved through the bindings before they are compared and an unbound
+/// variable on either side is bound to the other type. Variables can therefore be
+/// bound to variables of the other side, e.g., `T, T` matches `fn(U, usize)`.
+struct Unifier<'a> {
+    bindings: Vec<((Side, usize), (Side, &'a SigType))>,
+}
+
+impl<'a> Unifier<'a> {
+    /// Matches the remaining arguments and the return types.
+    ///
+    /// = Remarks
+    ///
+    /// Every argument of the query is tried against every remaining argument of the
+    /// signature. The bindings of a failed attempt are discarded.
+    fn args(&mut self, query: &'a [SigType], sig: &'a [SigType], query_ret: &'a SigType,
+            sig_ret: &'a SigType) -> Result<bool> {
+        if query.len() == 0 {
+            return self.unify((Side::Query, query_ret), (Side::Sig, sig_ret));
+        }
+        for i in 0..sig.len() {
+            if sig.len() - i < query.len() {
+                break;
+            }
+            let num_bindings = self.bindings.len();
+            if try!(self.unify((Side::Query, &query[0]), (Side::Sig, &sig[i])))
+                    && try!(self.args(&query[1..], &sig[i+1..], query_ret, sig_ret)) {
+                return Ok(true);
+            }
+            self.bindings.truncate(num_bindings);
+        }
+        Ok(false)
+    }
+
+    /// Follows the bindings of a variable.
+    fn resolve(&self, mut ty: (Side, &'a SigType)) -> (Side, &'a SigType) {
+        while let SigType::Var(i) = *ty.1 {
+            match self.bindings.find(|b| (b.0).0 == ty.0 && (b.0).1 == i) {
+                Some(pos) => ty = self.bindings[pos].1,
+                _ => break,
+            }
+        }
+        ty
+    }
+
+    /// Returns whether a variable appears in a type.
+    fn occurs(&self, var: (Side, usize), ty: (Side, &'a SigType)) -> bool {
+        let (side, ty) = self.resolve(ty);
+        match *ty {
+            SigType::Var(i) => side == var.0 && i == var.1,
+            SigType::Named(_, ref args) | SigType::Tuple(ref args) => {
+                args.iter().any(|a| self.occurs(var, (side, a)))
+            },
+            SigType::Slice(ref t) => self.occurs(var, (side, &**t)),
+            SigType::Any => false,
+        }
+    }
+
+    fn bind(&mut self, var: (Side, usize), ty: (Side, &'a SigType)) -> Result<bool> {
+        if self.occurs(var, ty) {
+            return Ok(false);
+        }
+        try!(self.bindings.reserve(1));
+        self.bindings.push((var, ty));
+        Ok(true)
+    }
+
+    /// Unifies two types.
+    ///
+    /// = Remarks
+    ///
+    /// A named type of the query can have fewer type arguments than the corresponding
+    /// type of the signature. The other arguments are not constrained.
+    fn unify(&mut self, a: (Side, &'a SigType), b: (Side, &'a SigType)) -> Result<bool> {
+        let (a, b) = (self.resolve(a), self.resolve(b));
+        let ((sa, ta), (sb, tb)) = (a, b);
+        match (ta, tb) {
+            (&SigType::Any, _) | (_, &SigType::Any) => Ok(true),
+            (&SigType::Var(i), &SigType::Var(j)) if sa == sb && i == j => Ok(true),
+            (&SigType::Var(i), _) => self.bind((sa, i), b),
+            (_, &SigType::Var(j)) => self.bind((sb, j), a),
+            (&SigType::Named(ref n1, ref a1), &SigType::Named(ref n2, ref a2)) => {
+                let fewer = match (sa, sb) {
+                    (Side::Query, Side::Sig) => a1.len() <= a2.len(),
+                    (Side::Sig, Side::Query) => a2.len() <= a1.len(),
+                    _ => a1.len() == a2.len(),
+                };
+                if n1[..] != n2[..] || !fewer {
+                    return Ok(false);
+                }
+                let len = if a1.len() < a2.len() { a1.len() } else { a2.len() };
+                for i in 0..len {
+                    if !try!(self.unify((sa, &a1[i]), (sb, &a2[i]))) {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            },
+            (&SigType::Slice(ref t1), &SigType::Slice(ref t2)) => {
+                self.unify((sa, &**t1), (sb, &**t2))
+            },
+            (&SigType::Tuple(ref t1), &SigType::Tuple(ref t2)) => {
+                if t1.len() != t2.len() {
+                    return Ok(false);
+                }
+                for i in 0..t1.len() {
+                    if !try!(self.unify((sa, &t1[i]), (sb, &t2[i]))) {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            },
+            _ => Ok(false),
+        }
+    }
+}
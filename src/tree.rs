@@ -8,6 +8,7 @@ use std::vec::{Vec};
 use std::bx::{Box};
 
 use markup::{Document};
+use cfg::{Cfg};
 
 pub struct Crate {
     pub item: Arc<ItemData>,
@@ -22,6 +23,63 @@ pub struct ItemData {
     pub node: DefId,
     pub parent: RefCell<Option<Arc<ItemData>>>,
     pub impls: RefCell<Vec<Arc<ItemData>>>,
+    /// The trait item whose documentation is used because this item has none.
+    pub inherited: RefCell<Option<Arc<ItemData>>>,
+    /// The `Target` typedef of the `Deref` impl of this item and the item it points to.
+    pub deref: RefCell<Option<(Arc<ItemData>, Arc<ItemData>)>>,
+    /// The auto traits and whether this item implements them.
+    pub auto_traits: RefCell<Vec<(Arc<ItemData>, AutoImpl)>>,
+    /// The public functions and methods that return this item, see `passes::TypeUses`.
+    pub returned_by: RefCell<Vec<Arc<ItemData>>>,
+    /// The public functions and methods that take this item as an argument.
+    pub accepted_by: RefCell<Vec<Arc<ItemData>>>,
+    pub stability: Stability,
+    pub cfg: Option<Cfg>,
+    /// The targets this item is available on if multiple `doc.json` files were merged.
+    pub targets: Vec<Vec<u8>>,
+    pub span: Option<Span>,
+}
+
+/// Whether a type implements an auto trait.
+pub enum AutoImpl {
+    /// The type has a positive impl of the trait.
+    Explicit,
+    /// The trait is implemented if the listed type parameters implement it.
+    Bounded(Vec<Vec<u8>>),
+    /// The trait is not implemented.
+    Negative,
+    /// The implementation depends on private fields or types that are not documented.
+    Unknown,
+}
+
+/// The location of an item in the source code.
+pub struct Span {
+    pub filename: Vec<u8>,
+    pub loline: usize,
+    pub locol: usize,
+    pub hiline: usize,
+    pub hicol: usize,
+}
+
+/// Stability and deprecation information collected from the attributes of an item.
+pub struct Stability {
+    pub level: StabilityLevel,
+    pub feature: Option<Vec<u8>>,
+    pub since: Option<Vec<u8>>,
+    pub issue: Option<Vec<u8>>,
+    pub deprecated: Option<Deprecation>,
+}
+
+#[derive(Copy, Eq)]
+pub enum StabilityLevel {
+    Unmarked,
+    Stable,
+    Unstable,
+}
+
+pub struct Deprecation {
+    pub since: Option<Vec<u8>>,
+    pub note: Option<Vec<u8>>,
 }
 
 pub enum Attribute {
@@ -244,7 +302,7 @@ pub struct DefaultImpl {
     pub trait_: Type,
 }
 
-#[derive(Copy)]
+#[derive(Copy, Eq)]
 pub enum Primitive {
     Isize, I8, I16, I32, I64,
     Usize, U8, U16, U32, U64,